@@ -0,0 +1,112 @@
+//! Code generation errors.
+
+/// An error produced when reading an ESI file or generating code from it.
+#[derive(Debug)]
+pub enum Error {
+    /// Failed to read or write a file.
+    Io(std::io::Error),
+    /// The ESI file is not valid XML.
+    Xml(roxmltree::Error),
+    /// A required element or attribute is missing from the ESI file.
+    Missing {
+        /// The name of the missing element or attribute.
+        item: &'static str,
+        /// The element the item was expected in.
+        parent: String,
+    },
+    /// A number in the ESI file could not be parsed.
+    InvalidNumber(String),
+    /// No device with the given product code (and revision, if given) was found in the ESI file.
+    DeviceNotFound {
+        /// Product code.
+        product_code: u32,
+        /// Revision, if one was requested.
+        revision: Option<u32>,
+    },
+    /// A selected PDO was not found in the device description.
+    PdoNotFound {
+        /// Product code.
+        product_code: u32,
+        /// PDO index, e.g. `0x1a00`.
+        index: u16,
+    },
+    /// A PDO entry cannot be represented with `#[wire(bits = N)]` at its position in the process
+    /// data.
+    ///
+    /// Fields 8 bits or longer must be byte-aligned, and fields shorter than 8 bits may not cross
+    /// a byte boundary.
+    Unaligned {
+        /// Generated field name.
+        field: String,
+        /// Bit offset of the entry from the start of the process data.
+        bit_offset: usize,
+        /// Entry length in bits.
+        bit_len: usize,
+    },
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Xml(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "io: {}", e),
+            Error::Xml(e) => write!(f, "xml: {}", e),
+            Error::Missing { item, parent } => write!(f, "missing {} in {}", item, parent),
+            Error::InvalidNumber(s) => write!(f, "invalid number {:?}", s),
+            Error::DeviceNotFound {
+                product_code,
+                revision: Some(revision),
+            } => write!(
+                f,
+                "device with product code {:#010x} revision {:#010x} not found",
+                product_code, revision
+            ),
+            Error::DeviceNotFound {
+                product_code,
+                revision: None,
+            } => write!(
+                f,
+                "device with product code {:#010x} not found",
+                product_code
+            ),
+            Error::PdoNotFound {
+                product_code,
+                index,
+            } => write!(
+                f,
+                "PDO {:#06x} not found in device {:#010x}",
+                index, product_code
+            ),
+            Error::Unaligned {
+                field,
+                bit_offset,
+                bit_len,
+            } => write!(
+                f,
+                "field {} of {} bits at bit offset {} is not aligned",
+                field, bit_len, bit_offset
+            ),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<roxmltree::Error> for Error {
+    fn from(e: roxmltree::Error) -> Self {
+        Self::Xml(e)
+    }
+}
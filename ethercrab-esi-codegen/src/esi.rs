@@ -0,0 +1,228 @@
+//! A minimal model of the parts of an EtherCAT SubDevice Information (ESI) file needed to generate
+//! process data structs.
+
+use crate::Error;
+use roxmltree::Node;
+
+/// Parsed ESI file.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Esi {
+    pub vendor_id: u32,
+    pub devices: Vec<Device>,
+}
+
+/// A single `<Device>` description.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Device {
+    /// Device type name, e.g. `EL2004`.
+    pub type_name: String,
+    /// Longer device name, if present.
+    pub name: Option<String>,
+    pub product_code: u32,
+    pub revision: u32,
+    /// MainDevice outputs.
+    pub rx_pdos: Vec<Pdo>,
+    /// MainDevice inputs.
+    pub tx_pdos: Vec<Pdo>,
+}
+
+/// A `<RxPdo>` or `<TxPdo>`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Pdo {
+    pub index: u16,
+    pub name: String,
+    /// The sync manager this PDO is assigned to by default. PDOs without a sync manager are not
+    /// assigned unless explicitly selected.
+    pub sync_manager: Option<u8>,
+    pub entries: Vec<PdoEntry>,
+}
+
+/// A single PDO `<Entry>`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PdoEntry {
+    /// Object index. A value of `0` denotes a padding entry.
+    pub index: u16,
+    pub sub_index: u8,
+    pub bit_len: usize,
+    pub name: String,
+    pub data_type: Option<String>,
+}
+
+impl PdoEntry {
+    pub fn is_padding(&self) -> bool {
+        self.index == 0
+    }
+}
+
+impl Esi {
+    /// Parse ESI file contents.
+    pub fn parse(xml: &str) -> Result<Self, Error> {
+        let doc = roxmltree::Document::parse(xml)?;
+
+        let root = doc.root_element();
+
+        let vendor_id = child(root, "Vendor").and_then(|vendor| number(vendor, "Id"))?;
+
+        let devices = child(root, "Descriptions")
+            .and_then(|d| child(d, "Devices"))?
+            .children()
+            .filter(|n| n.has_tag_name("Device"))
+            .map(Device::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { vendor_id, devices })
+    }
+}
+
+impl Device {
+    fn parse(node: Node) -> Result<Self, Error> {
+        let ty = child(node, "Type")?;
+
+        let type_name = ty.text().unwrap_or_default().trim().to_string();
+        let product_code = parse_number(attribute(ty, "ProductCode")?)?;
+        let revision = parse_number(attribute(ty, "RevisionNo")?)?;
+
+        // Prefer English (LCID 1033) names where there are multiple languages.
+        let name = node
+            .children()
+            .filter(|n| n.has_tag_name("Name"))
+            .max_by_key(|n| n.attribute("LcId") == Some("1033"))
+            .and_then(|n| n.text())
+            .map(|name| name.trim().to_string());
+
+        let pdos = |tag: &str| {
+            node.children()
+                .filter(|n| n.has_tag_name(tag))
+                .map(Pdo::parse)
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        Ok(Self {
+            type_name,
+            name,
+            product_code,
+            revision,
+            rx_pdos: pdos("RxPdo")?,
+            tx_pdos: pdos("TxPdo")?,
+        })
+    }
+}
+
+impl Pdo {
+    fn parse(node: Node) -> Result<Self, Error> {
+        let index = u16::try_from(number(node, "Index")?)
+            .map_err(|_| Error::InvalidNumber(text(node, "Index")))?;
+
+        let sync_manager = node
+            .attribute("Sm")
+            .map(|sm| {
+                parse_number(sm).and_then(|sm| {
+                    u8::try_from(sm).map_err(|_| Error::InvalidNumber(sm.to_string()))
+                })
+            })
+            .transpose()?;
+
+        let entries = node
+            .children()
+            .filter(|n| n.has_tag_name("Entry"))
+            .map(PdoEntry::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            index,
+            name: text(node, "Name"),
+            sync_manager,
+            entries,
+        })
+    }
+}
+
+impl PdoEntry {
+    fn parse(node: Node) -> Result<Self, Error> {
+        let index = u16::try_from(number(node, "Index")?)
+            .map_err(|_| Error::InvalidNumber(text(node, "Index")))?;
+
+        // Padding entries have no subindex
+        let sub_index = child(node, "SubIndex")
+            .ok()
+            .and_then(|n| n.text())
+            .map(|n| {
+                parse_number(n)
+                    .and_then(|n| u8::try_from(n).map_err(|_| Error::InvalidNumber(n.to_string())))
+            })
+            .transpose()?
+            .unwrap_or(0);
+
+        let bit_len = number(node, "BitLen")? as usize;
+
+        let data_type = child(node, "DataType")
+            .ok()
+            .and_then(|n| n.text())
+            .map(|t| t.trim().to_string());
+
+        Ok(Self {
+            index,
+            sub_index,
+            bit_len,
+            name: text(node, "Name"),
+            data_type,
+        })
+    }
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, tag: &'static str) -> Result<Node<'a, 'input>, Error> {
+    node.children()
+        .find(|n| n.has_tag_name(tag))
+        .ok_or_else(|| Error::Missing {
+            item: tag,
+            parent: node.tag_name().name().to_string(),
+        })
+}
+
+fn attribute<'a>(node: Node<'a, '_>, name: &'static str) -> Result<&'a str, Error> {
+    node.attribute(name).ok_or_else(|| Error::Missing {
+        item: name,
+        parent: node.tag_name().name().to_string(),
+    })
+}
+
+/// Text of a child element, or an empty string if it doesn't exist.
+fn text(node: Node, tag: &'static str) -> String {
+    child(node, tag)
+        .ok()
+        .and_then(|n| n.text())
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+fn number(node: Node, tag: &'static str) -> Result<u32, Error> {
+    let n = child(node, tag)?;
+
+    parse_number(n.text().unwrap_or_default())
+}
+
+/// Parse an ESI `HexDecValue`, e.g. `#x1a00` or `6656`.
+fn parse_number(s: &str) -> Result<u32, Error> {
+    let s = s.trim();
+
+    let result = match s.strip_prefix("#x").or_else(|| s.strip_prefix("#X")) {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+
+    result.map_err(|_| Error::InvalidNumber(s.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers() {
+        assert_eq!(parse_number("#x1A00").unwrap(), 0x1a00);
+        assert_eq!(parse_number("#x07d43052").unwrap(), 0x07d43052);
+        assert_eq!(parse_number(" 16 ").unwrap(), 16);
+        assert!(parse_number("#xzz").is_err());
+    }
+}
@@ -0,0 +1,366 @@
+//! Rust source generation from parsed PDOs.
+
+use crate::{
+    esi::{Device, Pdo, PdoEntry},
+    Error,
+};
+use std::fmt::Write;
+
+/// Which way data flows for a generated struct.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Direction {
+    /// SubDevice -> MainDevice (`TxPdo`).
+    Inputs,
+    /// MainDevice -> SubDevice (`RxPdo`).
+    Outputs,
+}
+
+impl Direction {
+    fn suffix(self) -> &'static str {
+        match self {
+            Direction::Inputs => "Inputs",
+            Direction::Outputs => "Outputs",
+        }
+    }
+}
+
+/// A single field of a generated struct.
+#[derive(Debug, Clone, PartialEq)]
+struct Field {
+    name: String,
+    ty: String,
+    bit_len: usize,
+    pre_skip: usize,
+    post_skip: usize,
+    doc: String,
+}
+
+/// Generate a struct for the given PDOs, in assignment order.
+///
+/// Returns `Ok(false)` and writes nothing if the PDOs contain no data.
+pub(crate) fn generate_struct(
+    out: &mut String,
+    device: &Device,
+    struct_name: &str,
+    direction: Direction,
+    pdos: &[&Pdo],
+) -> Result<bool, Error> {
+    let mut fields = Vec::<Field>::new();
+
+    // Current bit position in the process data
+    let mut position = 0usize;
+    // Padding that has not yet been applied to a field
+    let mut skip = 0usize;
+
+    for pdo in pdos {
+        for entry in pdo.entries.iter() {
+            if entry.is_padding() {
+                skip += entry.bit_len;
+
+                continue;
+            }
+
+            let name = unique_name(&fields, field_name(&pdo.name, &entry.name));
+            let bit_offset = position + skip;
+
+            check_alignment(&name, bit_offset, entry.bit_len)?;
+
+            let ty = rust_type(entry);
+
+            fields.push(Field {
+                doc: entry_doc(entry, bit_offset),
+                name,
+                ty,
+                bit_len: entry.bit_len,
+                pre_skip: skip,
+                post_skip: 0,
+            });
+
+            position = bit_offset + entry.bit_len;
+            skip = 0;
+        }
+    }
+
+    // Trailing padding
+    let Some(last) = fields.last_mut() else {
+        return Ok(false);
+    };
+
+    last.post_skip = skip;
+
+    let total_bits = position + skip;
+
+    writeln!(
+        out,
+        "/// {} of {} (product code `{:#010x}`, revision `{:#010x}`).",
+        direction.suffix(),
+        device.type_name,
+        device.product_code,
+        device.revision
+    )
+    .unwrap();
+
+    if let Some(name) = device.name.as_ref().filter(|name| !name.is_empty()) {
+        writeln!(out, "///\n/// {}", doc_line(name)).unwrap();
+    }
+
+    writeln!(
+        out,
+        "///\n/// Assigned PDOs, {} bits in total:\n///",
+        total_bits
+    )
+    .unwrap();
+
+    for pdo in pdos {
+        writeln!(out, "/// - `{:#06x}` {}", pdo.index, doc_line(&pdo.name)).unwrap();
+    }
+
+    writeln!(
+        out,
+        "#[derive(Debug, Default, Copy, Clone, PartialEq, ethercrab_wire::EtherCrabWireReadWrite)]"
+    )
+    .unwrap();
+    writeln!(out, "#[wire(bits = {})]", total_bits).unwrap();
+    writeln!(out, "pub struct {}{} {{", struct_name, direction.suffix()).unwrap();
+
+    for field in fields {
+        writeln!(out, "    /// {}", field.doc).unwrap();
+
+        write!(out, "    #[wire(bits = {}", field.bit_len).unwrap();
+
+        if field.pre_skip > 0 {
+            write!(out, ", pre_skip = {}", field.pre_skip).unwrap();
+        }
+
+        if field.post_skip > 0 {
+            write!(out, ", post_skip = {}", field.post_skip).unwrap();
+        }
+
+        writeln!(out, ")]").unwrap();
+        writeln!(out, "    pub {}: {},", field.name, field.ty).unwrap();
+    }
+
+    writeln!(out, "}}").unwrap();
+
+    Ok(true)
+}
+
+fn entry_doc(entry: &PdoEntry, bit_offset: usize) -> String {
+    let mut doc = format!("`{:#06x}:{:02x}`", entry.index, entry.sub_index);
+
+    if !entry.name.is_empty() {
+        write!(doc, " {}", doc_line(&entry.name)).unwrap();
+    }
+
+    if let Some(data_type) = entry.data_type.as_ref() {
+        write!(doc, " (`{}`)", data_type).unwrap();
+    }
+
+    write!(doc, ", bit offset {}", bit_offset).unwrap();
+
+    doc
+}
+
+/// Collapse whitespace so arbitrary ESI text fits on a single doc comment line.
+fn doc_line(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Check that an entry can be expressed with `ethercrab-wire-derive` field attributes.
+fn check_alignment(name: &str, bit_offset: usize, bit_len: usize) -> Result<(), Error> {
+    let aligned = if bit_len >= 8 {
+        bit_offset % 8 == 0 && bit_len % 8 == 0
+    } else {
+        bit_offset % 8 + bit_len <= 8
+    };
+
+    if aligned {
+        Ok(())
+    } else {
+        Err(Error::Unaligned {
+            field: name.to_string(),
+            bit_offset,
+            bit_len,
+        })
+    }
+}
+
+/// Map an ESI data type to a Rust type.
+///
+/// Entries must already be checked for alignment, so all entries longer than 8 bits are a whole
+/// number of bytes.
+fn rust_type(entry: &PdoEntry) -> String {
+    let data_type = entry.data_type.as_deref().unwrap_or_default();
+
+    let ty = match (data_type, entry.bit_len) {
+        ("BOOL" | "BIT", 1) => "bool",
+        ("SINT", 8) => "i8",
+        ("INT", 16) => "i16",
+        ("DINT", 32) => "i32",
+        ("LINT", 64) => "i64",
+        ("REAL", 32) => "f32",
+        ("LREAL", 64) => "f64",
+        // USINT, BYTE, BIT2, BITARR8, etc
+        (_, 1..=8) => "u8",
+        (_, 16) => "u16",
+        (_, 32) => "u32",
+        (_, 64) => "u64",
+        // Strings, octet strings, etc
+        (_, len) => return format!("[u8; {}]", len / 8),
+    };
+
+    ty.to_string()
+}
+
+/// Convert a device type name like `EL2004-0000` into a struct name prefix like `El20040000`.
+pub(crate) fn struct_name(type_name: &str) -> String {
+    let mut name = String::new();
+
+    for word in type_name.split(|c: char| !c.is_ascii_alphanumeric()) {
+        let mut chars = word.chars();
+
+        if let Some(first) = chars.next() {
+            name.push(first.to_ascii_uppercase());
+            name.extend(chars.map(|c| c.to_ascii_lowercase()));
+        }
+    }
+
+    if name.starts_with(|c: char| c.is_ascii_digit()) || name.is_empty() {
+        name.insert(0, 'D');
+    }
+
+    name
+}
+
+/// Create a snake case field name from a PDO and entry name.
+fn field_name(pdo_name: &str, entry_name: &str) -> String {
+    let mut name = String::new();
+
+    let mut push_words = |s: &str| {
+        let mut prev_lower = false;
+
+        for c in s.chars() {
+            if c.is_ascii_alphanumeric() {
+                // Split camel case words
+                if c.is_ascii_uppercase() && prev_lower && !name.ends_with('_') {
+                    name.push('_');
+                }
+
+                prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+
+                name.push(c.to_ascii_lowercase());
+            } else {
+                prev_lower = false;
+
+                if !name.is_empty() && !name.ends_with('_') {
+                    name.push('_');
+                }
+            }
+        }
+
+        if !name.is_empty() && !name.ends_with('_') {
+            name.push('_');
+        }
+    };
+
+    push_words(pdo_name);
+    push_words(entry_name);
+
+    let mut name = name.trim_end_matches('_').to_string();
+
+    if name.is_empty() {
+        name.push_str("value");
+    }
+
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+
+    if is_keyword(&name) {
+        name.push('_');
+    }
+
+    name
+}
+
+fn unique_name(fields: &[Field], name: String) -> String {
+    if !fields.iter().any(|f| f.name == name) {
+        return name;
+    }
+
+    (2..)
+        .map(|i| format!("{}_{}", name, i))
+        .find(|candidate| !fields.iter().any(|f| &f.name == candidate))
+        .unwrap()
+}
+
+fn is_keyword(s: &str) -> bool {
+    matches!(
+        s,
+        "as" | "async"
+            | "await"
+            | "break"
+            | "const"
+            | "continue"
+            | "crate"
+            | "dyn"
+            | "else"
+            | "enum"
+            | "extern"
+            | "false"
+            | "fn"
+            | "for"
+            | "if"
+            | "impl"
+            | "in"
+            | "let"
+            | "loop"
+            | "match"
+            | "mod"
+            | "move"
+            | "mut"
+            | "pub"
+            | "ref"
+            | "return"
+            | "self"
+            | "static"
+            | "struct"
+            | "super"
+            | "trait"
+            | "true"
+            | "type"
+            | "unsafe"
+            | "use"
+            | "where"
+            | "while"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        assert_eq!(field_name("Channel 1", "Input"), "channel_1_input");
+        assert_eq!(
+            field_name("AI Standard Channel 1", "Status__Underrange"),
+            "ai_standard_channel_1_status_underrange"
+        );
+        assert_eq!(field_name("", "StatusWord"), "status_word");
+        assert_eq!(field_name("1st", ""), "_1st");
+        assert_eq!(field_name("", "Type"), "type_");
+        assert_eq!(struct_name("EL2004"), "El2004");
+        assert_eq!(struct_name("EL3004-0000"), "El30040000");
+        assert_eq!(struct_name("7 axis"), "D7Axis");
+    }
+
+    #[test]
+    fn alignment() {
+        assert!(check_alignment("a", 0, 16).is_ok());
+        assert!(check_alignment("a", 4, 4).is_ok());
+        assert!(check_alignment("a", 6, 4).is_err());
+        assert!(check_alignment("a", 4, 8).is_err());
+        assert!(check_alignment("a", 8, 12).is_err());
+    }
+}
@@ -0,0 +1,263 @@
+//! Generate typed process data structs for [`ethercrab`](https://docs.rs/ethercrab) from
+//! EtherCAT SubDevice Information (ESI) XML files.
+//!
+//! For each selected device, a `<Name>Inputs` and `<Name>Outputs` struct is generated containing
+//! one field per PDO entry, in the order the PDOs are assigned. The structs derive
+//! [`EtherCrabWireReadWrite`](https://docs.rs/ethercrab-wire-derive) with `#[wire(bits = N)]`
+//! attributes matching each entry's position in the process data image, so they can be packed to
+//! and unpacked from a SubDevice's PDI directly.
+//!
+//! This crate is intended to be used from a build script. The crate using the generated code must
+//! depend on [`ethercrab-wire`](https://docs.rs/ethercrab-wire) (re-exported by `ethercrab`).
+//!
+//! # Experimental
+//!
+//! This crate is in its early stages and may contain bugs or publish breaking changes at any time.
+//!
+//! # Examples
+//!
+//! In `build.rs`:
+//!
+//! ```rust,no_run
+//! use ethercrab_esi_codegen::{DeviceSelection, Generator};
+//! use std::path::PathBuf;
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     println!("cargo:rerun-if-changed=esi/Beckhoff EL2xxx.xml");
+//!
+//!     let out = PathBuf::from(std::env::var("OUT_DIR")?).join("el2xxx.rs");
+//!
+//!     Generator::from_file("esi/Beckhoff EL2xxx.xml")?
+//!         // EL2004 with its default PDO assignment
+//!         .device(DeviceSelection::new(0x07d43052))
+//!         // EL2828 revision 0x00120000, named `Outputs8`, with an explicit PDO assignment
+//!         .device(
+//!             DeviceSelection::new(0x0b0c3052)
+//!                 .revision(0x00120000)
+//!                 .name("Outputs8")
+//!                 .pdos(&[0x1600, 0x1601, 0x1602, 0x1603]),
+//!         )
+//!         .write_to(out)?;
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Then in the crate itself:
+//!
+//! ```rust,ignore
+//! include!(concat!(env!("OUT_DIR"), "/el2xxx.rs"));
+//!
+//! let outputs = El2004Outputs {
+//!     channel_1_output: true,
+//!     ..El2004Outputs::default()
+//! };
+//! ```
+
+#![deny(missing_docs)]
+#![deny(missing_copy_implementations)]
+#![deny(trivial_casts)]
+#![deny(trivial_numeric_casts)]
+#![deny(unused_import_braces)]
+#![deny(unused_qualifications)]
+#![deny(rustdoc::broken_intra_doc_links)]
+#![deny(rustdoc::private_intra_doc_links)]
+
+mod error;
+mod esi;
+mod generate;
+
+pub use error::Error;
+
+use esi::{Device, Esi, Pdo};
+use generate::Direction;
+use std::path::Path;
+
+/// Choose a device from an ESI file to generate process data structs for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceSelection {
+    product_code: u32,
+    revision: Option<u32>,
+    name: Option<String>,
+    pdos: Option<Vec<u16>>,
+}
+
+impl DeviceSelection {
+    /// Select a device by product code.
+    ///
+    /// If the ESI file contains multiple revisions of the device, the highest revision is used.
+    pub fn new(product_code: u32) -> Self {
+        Self {
+            product_code,
+            revision: None,
+            name: None,
+            pdos: None,
+        }
+    }
+
+    /// Select a specific revision of the device.
+    pub fn revision(self, revision: u32) -> Self {
+        Self {
+            revision: Some(revision),
+            ..self
+        }
+    }
+
+    /// Set the prefix of the generated struct names.
+    ///
+    /// By default, the prefix is derived from the device type, e.g. `EL2004` generates
+    /// `El2004Inputs` and `El2004Outputs`.
+    pub fn name(self, name: &str) -> Self {
+        Self {
+            name: Some(name.to_string()),
+            ..self
+        }
+    }
+
+    /// Select which PDOs are assigned, by index.
+    ///
+    /// RxPDOs (`0x16xx`) are added to the outputs struct and TxPDOs (`0x1axx`) to the inputs
+    /// struct, in the order given here. This order must match the order the PDOs are assigned to
+    /// the SubDevice's sync managers.
+    ///
+    /// By default, all PDOs with a default sync manager assignment in the ESI file are used, in
+    /// the order they appear in the file.
+    pub fn pdos(self, pdos: &[u16]) -> Self {
+        Self {
+            pdos: Some(pdos.to_vec()),
+            ..self
+        }
+    }
+
+    fn find_device<'esi>(&self, esi: &'esi Esi) -> Result<&'esi Device, Error> {
+        esi.devices
+            .iter()
+            .filter(|device| {
+                device.product_code == self.product_code
+                    && self.revision.map_or(true, |rev| rev == device.revision)
+            })
+            .max_by_key(|device| device.revision)
+            .ok_or(Error::DeviceNotFound {
+                product_code: self.product_code,
+                revision: self.revision,
+            })
+    }
+
+    /// Get the assigned PDOs from the given list, in assignment order.
+    fn select<'dev>(&self, available: &'dev [Pdo]) -> Vec<&'dev Pdo> {
+        match self.pdos.as_ref() {
+            Some(selected) => selected
+                .iter()
+                .filter_map(|index| available.iter().find(|pdo| pdo.index == *index))
+                .collect(),
+            None => available
+                .iter()
+                .filter(|pdo| pdo.sync_manager.is_some())
+                .collect(),
+        }
+    }
+}
+
+/// Process data struct generator.
+#[derive(Debug, Clone)]
+pub struct Generator {
+    esi: Esi,
+    devices: Vec<DeviceSelection>,
+}
+
+impl Generator {
+    /// Read an ESI file from disk.
+    ///
+    /// ESI files are commonly encoded as ISO-8859-1. Files that are not valid UTF-8 are decoded
+    /// as such.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let bytes = std::fs::read(path)?;
+
+        let xml = match String::from_utf8(bytes) {
+            Ok(xml) => xml,
+            // ISO-8859-1 maps directly to the first 256 Unicode code points
+            Err(e) => e.into_bytes().into_iter().map(char::from).collect(),
+        };
+
+        Self::from_xml(&xml)
+    }
+
+    /// Parse ESI XML from a string.
+    pub fn from_xml(xml: &str) -> Result<Self, Error> {
+        Ok(Self {
+            esi: Esi::parse(xml)?,
+            devices: Vec::new(),
+        })
+    }
+
+    /// The vendor ID from the ESI file.
+    pub fn vendor_id(&self) -> u32 {
+        self.esi.vendor_id
+    }
+
+    /// Add a device to generate structs for.
+    pub fn device(mut self, selection: DeviceSelection) -> Self {
+        self.devices.push(selection);
+
+        self
+    }
+
+    /// Generate Rust source code for all selected devices.
+    pub fn generate(&self) -> Result<String, Error> {
+        let mut out = String::from(
+            "// This file was generated by ethercrab-esi-codegen. Do not edit it by hand.\n",
+        );
+
+        for selection in self.devices.iter() {
+            let device = selection.find_device(&self.esi)?;
+
+            if let Some(missing) = selection.pdos.iter().flatten().find(|index| {
+                !device
+                    .rx_pdos
+                    .iter()
+                    .chain(device.tx_pdos.iter())
+                    .any(|pdo| pdo.index == **index)
+            }) {
+                return Err(Error::PdoNotFound {
+                    product_code: device.product_code,
+                    index: *missing,
+                });
+            }
+
+            let struct_name = selection
+                .name
+                .clone()
+                .unwrap_or_else(|| generate::struct_name(&device.type_name));
+
+            for (direction, pdos) in [
+                (Direction::Inputs, &device.tx_pdos),
+                (Direction::Outputs, &device.rx_pdos),
+            ] {
+                let pdos = selection.select(pdos);
+
+                let mut item = String::new();
+
+                if generate::generate_struct(&mut item, device, &struct_name, direction, &pdos)? {
+                    out.push('\n');
+                    out.push_str(&item);
+                }
+            }
+        }
+
+        Ok(out)
+    }
+
+    /// Generate Rust source code for all selected devices and write it to a file.
+    ///
+    /// The file is only written if its contents have changed.
+    pub fn write_to(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        let code = self.generate()?;
+
+        if std::fs::read_to_string(path).ok().as_deref() != Some(code.as_str()) {
+            std::fs::write(path, code)?;
+        }
+
+        Ok(())
+    }
+}
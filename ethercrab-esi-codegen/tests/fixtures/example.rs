@@ -0,0 +1,96 @@
+// This file was generated by ethercrab-esi-codegen. Do not edit it by hand.
+
+/// Outputs of EL2004 (product code `0x07d43052`, revision `0x00100000`).
+///
+/// EL2004 4Ch. Dig. Output 24V, 0.5A
+///
+/// Assigned PDOs, 4 bits in total:
+///
+/// - `0x1600` Channel 1
+/// - `0x1601` Channel 2
+/// - `0x1602` Channel 3
+/// - `0x1603` Channel 4
+#[derive(Debug, Default, Copy, Clone, PartialEq, ethercrab_wire::EtherCrabWireReadWrite)]
+#[wire(bits = 4)]
+pub struct El2004Outputs {
+    /// `0x7000:01` Output (`BOOL`), bit offset 0
+    #[wire(bits = 1)]
+    pub channel_1_output: bool,
+    /// `0x7010:01` Output (`BOOL`), bit offset 1
+    #[wire(bits = 1)]
+    pub channel_2_output: bool,
+    /// `0x7020:01` Output (`BOOL`), bit offset 2
+    #[wire(bits = 1)]
+    pub channel_3_output: bool,
+    /// `0x7030:01` Output (`BOOL`), bit offset 3
+    #[wire(bits = 1)]
+    pub channel_4_output: bool,
+}
+
+/// Inputs of EL3102 (product code `0x0c1e3052`, revision `0x00130000`).
+///
+/// EL3102 2Ch. Ana. Input +/-10V, Diff.
+///
+/// Assigned PDOs, 64 bits in total:
+///
+/// - `0x1a00` AI Standard Channel 1
+/// - `0x1a02` AI Standard Channel 2
+#[derive(Debug, Default, Copy, Clone, PartialEq, ethercrab_wire::EtherCrabWireReadWrite)]
+#[wire(bits = 64)]
+pub struct El3102Inputs {
+    /// `0x6000:01` Status__Underrange (`BOOL`), bit offset 0
+    #[wire(bits = 1)]
+    pub ai_standard_channel_1_status_underrange: bool,
+    /// `0x6000:02` Status__Overrange (`BOOL`), bit offset 1
+    #[wire(bits = 1)]
+    pub ai_standard_channel_1_status_overrange: bool,
+    /// `0x6000:03` Status__Limit 1 (`BIT2`), bit offset 2
+    #[wire(bits = 2)]
+    pub ai_standard_channel_1_status_limit_1: u8,
+    /// `0x6000:0f` Status__TxPDO State (`BOOL`), bit offset 14
+    #[wire(bits = 1, pre_skip = 10)]
+    pub ai_standard_channel_1_status_tx_pdo_state: bool,
+    /// `0x6000:10` Status__TxPDO Toggle (`BOOL`), bit offset 15
+    #[wire(bits = 1)]
+    pub ai_standard_channel_1_status_tx_pdo_toggle: bool,
+    /// `0x6000:11` Value (`INT`), bit offset 16
+    #[wire(bits = 16)]
+    pub ai_standard_channel_1_value: i16,
+    /// `0x6010:01` Status__Underrange (`BOOL`), bit offset 32
+    #[wire(bits = 1)]
+    pub ai_standard_channel_2_status_underrange: bool,
+    /// `0x6010:02` Status__Overrange (`BOOL`), bit offset 33
+    #[wire(bits = 1)]
+    pub ai_standard_channel_2_status_overrange: bool,
+    /// `0x6010:03` Status__Limit 1 (`BIT2`), bit offset 34
+    #[wire(bits = 2)]
+    pub ai_standard_channel_2_status_limit_1: u8,
+    /// `0x6010:0f` Status__TxPDO State (`BOOL`), bit offset 46
+    #[wire(bits = 1, pre_skip = 10)]
+    pub ai_standard_channel_2_status_tx_pdo_state: bool,
+    /// `0x6010:10` Status__TxPDO Toggle (`BOOL`), bit offset 47
+    #[wire(bits = 1)]
+    pub ai_standard_channel_2_status_tx_pdo_toggle: bool,
+    /// `0x6010:11` Value (`INT`), bit offset 48
+    #[wire(bits = 16)]
+    pub ai_standard_channel_2_value: i16,
+}
+
+/// Inputs of EL3102 (product code `0x0c1e3052`, revision `0x00130000`).
+///
+/// EL3102 2Ch. Ana. Input +/-10V, Diff.
+///
+/// Assigned PDOs, 32 bits in total:
+///
+/// - `0x1a01` AI Compact Channel 1
+/// - `0x1a03` AI Compact Channel 2
+#[derive(Debug, Default, Copy, Clone, PartialEq, ethercrab_wire::EtherCrabWireReadWrite)]
+#[wire(bits = 32)]
+pub struct El3102CompactInputs {
+    /// `0x6000:11` Value (`INT`), bit offset 0
+    #[wire(bits = 16)]
+    pub ai_compact_channel_1_value: i16,
+    /// `0x6010:11` Value (`INT`), bit offset 16
+    #[wire(bits = 16)]
+    pub ai_compact_channel_2_value: i16,
+}
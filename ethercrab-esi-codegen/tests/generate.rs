@@ -0,0 +1,100 @@
+use ethercrab_esi_codegen::{DeviceSelection, Error, Generator};
+use ethercrab_wire::{EtherCrabWireRead, EtherCrabWireWriteSized};
+use pretty_assertions::assert_eq;
+
+mod generated {
+    include!("fixtures/example.rs");
+}
+
+fn generator() -> Generator {
+    Generator::from_file("tests/fixtures/example.xml")
+        .expect("Parse ESI")
+        .device(DeviceSelection::new(0x07d43052))
+        .device(DeviceSelection::new(0x0c1e3052))
+        .device(
+            DeviceSelection::new(0x0c1e3052)
+                .name("El3102Compact")
+                .pdos(&[0x1a01, 0x1a03]),
+        )
+}
+
+#[test]
+fn matches_fixture() {
+    let generated = generator().generate().expect("Generate");
+
+    assert_eq!(generated, include_str!("fixtures/example.rs"));
+}
+
+#[test]
+fn pack_outputs() {
+    let outputs = generated::El2004Outputs {
+        channel_2_output: true,
+        channel_4_output: true,
+        ..Default::default()
+    };
+
+    assert_eq!(outputs.pack(), [0b1010]);
+}
+
+#[test]
+fn unpack_inputs() {
+    let inputs = generated::El3102Inputs::unpack_from_slice(&[
+        0b0000_1001,
+        0b1000_0000,
+        0x34,
+        0x12,
+        0b0000_0010,
+        0b0100_0000,
+        0xfe,
+        0xff,
+    ])
+    .expect("Unpack");
+
+    assert_eq!(
+        inputs,
+        generated::El3102Inputs {
+            ai_standard_channel_1_status_underrange: true,
+            ai_standard_channel_1_status_overrange: false,
+            ai_standard_channel_1_status_limit_1: 0b10,
+            ai_standard_channel_1_status_tx_pdo_state: false,
+            ai_standard_channel_1_status_tx_pdo_toggle: true,
+            ai_standard_channel_1_value: 0x1234,
+            ai_standard_channel_2_status_underrange: false,
+            ai_standard_channel_2_status_overrange: true,
+            ai_standard_channel_2_status_limit_1: 0,
+            ai_standard_channel_2_status_tx_pdo_state: true,
+            ai_standard_channel_2_status_tx_pdo_toggle: false,
+            ai_standard_channel_2_value: -2,
+        }
+    );
+}
+
+#[test]
+fn unknown_device_and_pdo() {
+    assert!(matches!(
+        Generator::from_file("tests/fixtures/example.xml")
+            .unwrap()
+            .device(DeviceSelection::new(0x07d43052).revision(0x00110000))
+            .generate(),
+        Err(Error::DeviceNotFound {
+            product_code: 0x07d43052,
+            revision: Some(0x00110000)
+        })
+    ));
+
+    assert!(matches!(
+        Generator::from_file("tests/fixtures/example.xml")
+            .unwrap()
+            .device(DeviceSelection::new(0x07d43052).pdos(&[0x1a00]))
+            .generate(),
+        Err(Error::PdoNotFound {
+            product_code: 0x07d43052,
+            index: 0x1a00
+        })
+    ));
+}
+
+#[test]
+fn vendor_id() {
+    assert_eq!(generator().vendor_id(), 0x2);
+}
@@ -7,10 +7,9 @@ use env_logger::Env;
 use ethercrab::{
     ds402::{Ds402, Ds402Sm, StatusWord},
     std::{ethercat_now, tx_rx_task},
-    MainDevice, MainDeviceConfig, PduStorage, Timeouts,
+    MainDevice, MainDeviceConfig, PdoMapping, PduStorage, Timeouts,
 };
 use std::{
-    array::from_ref,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
@@ -73,7 +72,7 @@ async fn main() -> anyhow::Result<()> {
         .await
         .expect("Init");
 
-    for subdevice in group.iter(&maindevice) {
+    for mut subdevice in group.iter(&maindevice) {
         // Assuming all connected SubDevices are C5-Es here
 
         // Manual section 4.8 Setting the motor data
@@ -120,57 +119,37 @@ async fn main() -> anyhow::Result<()> {
 
         // CSV described a bit better in section 7.6.2.2 Related Objects of the manual
         subdevice
-            .sdo_write_array(
-                0x1600,
-                &[
+            .configure_pdos(
+                &PdoMapping::new()
+                    .rx_pdo(0x1600)
                     // Control word, u16
-                    // NOTE: The lower word specifies the field length
-                    0x6040_0010u32,
+                    .entry(0x6040, 0, 16)
                     // Target velocity, i32
-                    0x60ff_0020,
-                ],
+                    .entry(0x60ff, 0, 32)
+                    .tx_pdo(0x1a00)
+                    // Status word, u16
+                    .entry(0x6041, 0, 16)
+                    // Actual position, i32
+                    .entry(0x6064, 0, 32)
+                    // Actual velocity, i32
+                    .entry(0x606c, 0, 32),
             )
             .await?;
 
         // The above code is equivalent to:
+        // subdevice.sdo_write(0x1c12, 0, 0u8).await?;
+        // subdevice.sdo_write(0x1c13, 0, 0u8).await?;
         // subdevice.sdo_write(0x1600, 0, 0u8).await?;
         // subdevice.sdo_write(0x1600, 1, 0x6040_0010u32).await?;
         // subdevice.sdo_write(0x1600, 2, 0x60ff_0020u32).await?;
         // subdevice.sdo_write(0x1600, 0, 2u8).await?;
-
-        subdevice
-            .sdo_write_array(
-                0x1a00,
-                &[
-                    // Status word, u16
-                    0x6041_0010u32,
-                    // Actual position, i32
-                    0x6064_0020,
-                    // Actual velocity, i32
-                    0x606c_0020,
-                ],
-            )
-            .await?;
-
-        // Above code is equivalent to:
         // subdevice.sdo_write(0x1a00, 0, 0u8).await?;
         // subdevice.sdo_write(0x1a00, 1, 0x6041_0010u32).await?;
         // subdevice.sdo_write(0x1a00, 2, 0x6064_0020u32).await?;
         // subdevice.sdo_write(0x1a00, 3, 0x606c_0020u32).await?;
         // subdevice.sdo_write(0x1a00, 0, 0x03u8).await?;
-
-        subdevice
-            .sdo_write_array(0x1c12, from_ref(&0x1600u16))
-            .await?;
-        subdevice
-            .sdo_write_array(0x1c13, from_ref(&0x1a00u16))
-            .await?;
-
-        // Above code is equivalent to:
-        // subdevice.sdo_write(0x1c12, 0, 0u8).await?;
         // subdevice.sdo_write(0x1c12, 1, 0x1600u16).await?;
         // subdevice.sdo_write(0x1c12, 0, 1u8).await?;
-        // subdevice.sdo_write(0x1c13, 0, 0u8).await?;
         // subdevice.sdo_write(0x1c13, 1, 0x1a00u16).await?;
         // subdevice.sdo_write(0x1c13, 0, 1u8).await?;
 
@@ -7,7 +7,7 @@ use env_logger::Env;
 use ethercrab::{
     error::Error,
     std::{ethercat_now, tx_rx_task},
-    subdevice_group::{CycleInfo, DcConfiguration},
+    subdevice_group::{CycleInfo, CycleLock, CycleLockConfig, DcConfiguration},
     DcSync, MainDevice, MainDeviceConfig, PduStorage, RegisterAddress, Timeouts,
 };
 use futures_lite::StreamExt;
@@ -307,20 +307,17 @@ fn main() -> Result<(), Error> {
 
         log::info!("Alignment done");
 
+        let dc_config = DcConfiguration {
+            // Start SYNC0 100ms in the future
+            start_delay: Duration::from_millis(100),
+            // SYNC0 period should be the same as the process data loop in most cases
+            sync0_period: TICK_INTERVAL,
+            // Send process data half way through cycle
+            sync0_shift: TICK_INTERVAL / 2,
+        };
+
         // SubDevice clocks are aligned. We can turn DC on now.
-        let group = group
-            .configure_dc_sync(
-                &maindevice,
-                DcConfiguration {
-                    // Start SYNC0 100ms in the future
-                    start_delay: Duration::from_millis(100),
-                    // SYNC0 period should be the same as the process data loop in most cases
-                    sync0_period: TICK_INTERVAL,
-                    // Send process data half way through cycle
-                    sync0_shift: TICK_INTERVAL / 2,
-                },
-            )
-            .await?;
+        let group = group.configure_dc_sync(&maindevice, dc_config).await?;
 
         let group = group
             .into_safe_op(&maindevice)
@@ -382,18 +379,22 @@ fn main() -> Result<(), Error> {
         signal_hook::flag::register(signal_hook::consts::SIGINT, Arc::clone(&term))
             .expect("Register hook");
 
+        // Smooths out host timer jitter when aligning the process data cycle with SYNC0
+        let mut cycle_lock = CycleLock::new(dc_config, CycleLockConfig::default());
+
         // Main application process data cycle
         loop {
             let now = Instant::now();
 
-            let (
-                _wkc,
-                CycleInfo {
-                    dc_system_time,
-                    next_cycle_wait,
-                    cycle_start_offset,
-                },
-            ) = group.tx_rx_dc(&maindevice).await.expect("TX/RX");
+            let (_wkc, cycle_info) = group.tx_rx_dc(&maindevice).await.expect("TX/RX");
+
+            let CycleInfo {
+                dc_system_time,
+                cycle_start_offset,
+                ..
+            } = cycle_info;
+
+            let next_cycle_wait = cycle_lock.update(&cycle_info);
 
             // Debug logging
             {
@@ -27,6 +27,24 @@ impl AlControl {
         }
     }
 
+    /// Request a state and acknowledge any AL status error.
+    pub fn acknowledge(state: SubDeviceState) -> Self {
+        Self {
+            state,
+            error: true,
+            ..Default::default()
+        }
+    }
+
+    /// Stay in `state` and request the SubDevice's Explicit Device ID.
+    pub fn id_request(state: SubDeviceState) -> Self {
+        Self {
+            state,
+            id_request: true,
+            ..Default::default()
+        }
+    }
+
     pub fn reset() -> Self {
         Self {
             state: SubDeviceState::Init,
@@ -55,6 +73,22 @@ mod tests {
         assert_eq!(packed, [0x04 | 0x10, 0x00]);
     }
 
+    #[test]
+    fn acknowledge() {
+        assert_eq!(
+            AlControl::acknowledge(SubDeviceState::PreOp).pack(),
+            [0x02 | 0x10, 0x00]
+        );
+    }
+
+    #[test]
+    fn id_request() {
+        assert_eq!(
+            AlControl::id_request(SubDeviceState::PreOp).pack(),
+            [0x02 | 0x20, 0x00]
+        );
+    }
+
     #[test]
     fn unpack() {
         let value = AlControl {
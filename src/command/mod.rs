@@ -219,6 +219,11 @@ impl Command {
         WrappedRead::new(Reads::Frmw { address, register })
     }
 
+    /// Logical Read (LRD).
+    pub fn lrd(address: u32) -> WrappedRead {
+        WrappedRead::new(Reads::Lrd { address })
+    }
+
     /// Logical Read Write (LRW), used mainly for sending and receiving PDI.
     pub fn lrw(address: u32) -> WrappedWrite {
         WrappedWrite::new(Writes::Lrw { address })
@@ -294,4 +299,12 @@ mod tests {
             expected
         );
     }
+
+    #[test]
+    fn pack_logical_read() {
+        let command = Command::from(Command::lrd(0x0001_2345));
+
+        assert_eq!(command.code(), LRD);
+        assert_eq!(command.pack(), [0x45, 0x23, 0x01, 0x00]);
+    }
 }
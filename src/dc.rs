@@ -420,6 +420,15 @@ pub(crate) async fn configure_dc<'subdevices>(
 ) -> Result<Option<&'subdevices SubDevice>, Error> {
     latch_dc_times(maindevice, subdevices).await?;
 
+    // In a redundant ring, the last SubDevice's outgoing port is connected back to the secondary
+    // interface instead of another SubDevice. Frames don't return through it so it must not count
+    // towards propagation delays.
+    if maindevice.pdu_loop.is_redundant() {
+        if let Some(last) = subdevices.last_mut() {
+            last.ports.close_downstream_ports();
+        }
+    }
+
     assign_parent_relationships(subdevices)?;
 
     let first_dc_subdevice = subdevices
@@ -451,24 +460,20 @@ pub(crate) async fn configure_dc<'subdevices>(
 /// designated DC SubDevice.
 pub(crate) async fn run_dc_static_sync(
     maindevice: &MainDevice<'_>,
-    dc_reference_subdevice: &SubDevice,
+    dc_reference: u16,
     iterations: u32,
 ) -> Result<(), Error> {
     fmt::debug!(
-        "Performing static drift compensation using SubDevice {:#06x} {} as reference. This can take some time...",
-        dc_reference_subdevice.configured_address(),
-        dc_reference_subdevice.name
+        "Performing static drift compensation using SubDevice {:#06x} as reference. This can take some time...",
+        dc_reference
     );
 
     // Static drift compensation - distribute reference clock through network until SubDevice clocks
     // settle
     for _ in 0..iterations {
-        Command::frmw(
-            dc_reference_subdevice.configured_address(),
-            RegisterAddress::DcSystemTime.into(),
-        )
-        .receive_wkc::<u64>(maindevice)
-        .await?;
+        Command::frmw(dc_reference, RegisterAddress::DcSystemTime.into())
+            .receive_wkc::<u64>(maindevice)
+            .await?;
     }
 
     fmt::debug!("Static drift compensation complete");
@@ -1,7 +1,7 @@
 //! EtherCrab error types.
 
 pub use crate::coe::abort_code::CoeAbortCode;
-use crate::{command::Command, fmt, AlStatusCode, SubDeviceState};
+use crate::{command::Command, fmt, AlStatusCode, SubDeviceState, TopologyMismatch};
 use core::{cell::BorrowError, num::TryFromIntError};
 
 /// An EtherCrab error.
@@ -60,6 +60,15 @@ pub enum Error {
         /// Actual PDI length.
         desired_length: usize,
     },
+    /// The packed length of a type does not match the length of a SubDevice's inputs or outputs
+    /// in the PDI.
+    PdiLength {
+        /// The length of the SubDevice's PDI segment in bytes.
+        segment_length: usize,
+
+        /// The packed length of the type in bytes.
+        type_length: usize,
+    },
     /// An item in a list could not be found.
     NotFound {
         /// Item kind.
@@ -72,6 +81,15 @@ pub enum Error {
     Internal,
     /// There is a problem with the discovered EtherCAT SubDevice topology.
     Topology,
+    /// The discovered SubDevices do not match the expected network topology.
+    ///
+    /// All mismatches are logged at error level.
+    TopologyMismatch {
+        /// The first mismatch found.
+        first: TopologyMismatch,
+        /// The total number of mismatches.
+        count: usize,
+    },
     /// An error was read back from one or more SubDevices when attempting to transition to a new
     /// state.
     StateTransition,
@@ -88,6 +106,21 @@ pub enum Error {
         /// SubDevice address.
         configured_address: u16,
     },
+    /// A SubDevice's process data layout changed when it was reconfigured, e.g. because its PDO
+    /// mapping was not restored after it lost power.
+    PdiLayoutChanged {
+        /// SubDevice address.
+        configured_address: u16,
+    },
+    /// A SubDevice's Explicit Device ID does not match the value expected by its group.
+    DeviceIdMismatch {
+        /// SubDevice address.
+        configured_address: u16,
+        /// The expected Explicit Device ID.
+        expected: u16,
+        /// The Explicit Device ID read from the SubDevice.
+        actual: u16,
+    },
 
     /// An error occurred encoding or decoding an item.
     Wire(ethercrab_wire::WireError),
@@ -136,11 +169,22 @@ impl core::fmt::Display for Error {
                 "Process Data Image is too long ({} bytes), max length is {}",
                 desired_length, max_length
             ),
+            Error::PdiLength {
+                segment_length,
+                type_length,
+            } => write!(
+                f,
+                "type of {} bytes does not match PDI segment of {} bytes",
+                type_length, segment_length
+            ),
             Error::NotFound { item, index } => {
                 write!(f, "item kind {:?} not found (index: {:?})", item, index)
             }
             Error::Internal => f.write_str("internal error"),
             Error::Topology => f.write_str("topology"),
+            Error::TopologyMismatch { first, count } => {
+                write!(f, "{} topology mismatch(es), first: {}", count, first)
+            }
             Error::StateTransition => {
                 f.write_str("a SubDevice failed to transition to a new state")
             }
@@ -154,6 +198,20 @@ impl core::fmt::Display for Error {
                 "SubDevice {:#06x} state is invalid: {}, expected {}",
                 configured_address, actual, expected
             ),
+            Error::PdiLayoutChanged { configured_address } => write!(
+                f,
+                "SubDevice {:#06x} process data layout changed after reconfiguration",
+                configured_address
+            ),
+            Error::DeviceIdMismatch {
+                configured_address,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "SubDevice {:#06x} has Explicit Device ID {:#06x}, expected {:#06x}",
+                configured_address, actual, expected
+            ),
             Error::Wire(e) => write!(f, "wire encode/decode error: {}", e),
             Error::SubDevice(e) => write!(f, "subdevice error: {}", e),
             Error::DistributedClock(e) => write!(f, "distributed clock: {}", e),
@@ -0,0 +1,174 @@
+//! ESC error counters, registers `0x0300` to `0x0313`.
+
+use ethercrab_wire::{EtherCrabWireRead, EtherCrabWireSized, WireError};
+
+/// Error counters of a single ESC port.
+///
+/// Every counter saturates at 255.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PortErrorCounters {
+    /// Frames received on this port with an invalid CRC or length, e.g. because of interference
+    /// on the cable.
+    pub invalid_frames: u8,
+    /// Physical layer errors detected while receiving on this port.
+    pub rx_errors: u8,
+    /// Frames received on this port that were already marked as invalid by a previous SubDevice.
+    pub forwarded_rx_errors: u8,
+    /// The number of times the link on this port was lost.
+    pub lost_links: u8,
+}
+
+impl PortErrorCounters {
+    /// Errors detected by this port itself, i.e. errors on the cable connected to it.
+    ///
+    /// Forwarded errors are not counted as they were detected by a previous SubDevice.
+    pub fn local_errors(&self) -> u16 {
+        u16::from(self.invalid_frames) + u16::from(self.rx_errors) + u16::from(self.lost_links)
+    }
+}
+
+/// ESC error counters read by
+/// [`SubDeviceRef::error_counters`](crate::SubDeviceRef::error_counters).
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ErrorCounters {
+    /// Per-port counters, indexed by EtherCAT port number 0 to 3.
+    pub ports: [PortErrorCounters; 4],
+    /// Frames that were invalid when processed by the ESC's EtherCAT processing unit.
+    pub processing_unit_errors: u8,
+    /// Errors on the ESC's process data interface, e.g. the SPI bus to the SubDevice's
+    /// microcontroller.
+    pub pdi_errors: u8,
+}
+
+impl ErrorCounters {
+    /// The increase of every counter since `previous`.
+    ///
+    /// If a counter was reset after `previous` was read, its current value is used.
+    pub fn since(&self, previous: &Self) -> Self {
+        let delta = |current: u8, previous: u8| current.checked_sub(previous).unwrap_or(current);
+
+        let mut ports = self.ports;
+
+        for (port, previous) in ports.iter_mut().zip(previous.ports.iter()) {
+            *port = PortErrorCounters {
+                invalid_frames: delta(port.invalid_frames, previous.invalid_frames),
+                rx_errors: delta(port.rx_errors, previous.rx_errors),
+                forwarded_rx_errors: delta(port.forwarded_rx_errors, previous.forwarded_rx_errors),
+                lost_links: delta(port.lost_links, previous.lost_links),
+            };
+        }
+
+        Self {
+            ports,
+            processing_unit_errors: delta(
+                self.processing_unit_errors,
+                previous.processing_unit_errors,
+            ),
+            pdi_errors: delta(self.pdi_errors, previous.pdi_errors),
+        }
+    }
+
+    /// The port with the most [local errors](PortErrorCounters::local_errors), if any errors were
+    /// counted.
+    pub fn worst_port(&self) -> Option<(u8, u16)> {
+        self.ports
+            .iter()
+            .zip(0u8..)
+            .map(|(port, number)| (number, port.local_errors()))
+            .filter(|(_, errors)| *errors > 0)
+            .max_by_key(|(_, errors)| *errors)
+    }
+}
+
+impl EtherCrabWireSized for ErrorCounters {
+    // 0x0300 to 0x0313 inclusive
+    const PACKED_LEN: usize = 20;
+
+    type Buffer = [u8; Self::PACKED_LEN];
+
+    fn buffer() -> Self::Buffer {
+        [0u8; Self::PACKED_LEN]
+    }
+}
+
+impl EtherCrabWireRead for ErrorCounters {
+    fn unpack_from_slice(buf: &[u8]) -> Result<Self, WireError> {
+        let buf = buf
+            .get(0..Self::PACKED_LEN)
+            .ok_or(WireError::ReadBufferTooShort)?;
+
+        let port = |n: usize| PortErrorCounters {
+            // 0x0300 + n * 2
+            invalid_frames: buf[n * 2],
+            // 0x0301 + n * 2
+            rx_errors: buf[n * 2 + 1],
+            // 0x0308 + n
+            forwarded_rx_errors: buf[0x08 + n],
+            // 0x0310 + n
+            lost_links: buf[0x10 + n],
+        };
+
+        Ok(Self {
+            ports: [port(0), port(1), port(2), port(3)],
+            processing_unit_errors: buf[0x0c],
+            pdi_errors: buf[0x0d],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unpack() {
+        let buf = [
+            // Invalid frame, RX error counters, ports 0 to 3
+            1, 2, 3, 4, 5, 6, 7, 8, //
+            // Forwarded RX errors
+            9, 10, 11, 12, //
+            // Processing unit, PDI errors, PDI error code, reserved
+            13, 14, 0xff, 0xff, //
+            // Lost links
+            15, 16, 17, 18,
+        ];
+
+        let counters = ErrorCounters::unpack_from_slice(&buf).unwrap();
+
+        assert_eq!(
+            counters.ports[1],
+            PortErrorCounters {
+                invalid_frames: 3,
+                rx_errors: 4,
+                forwarded_rx_errors: 10,
+                lost_links: 16
+            }
+        );
+        assert_eq!(counters.ports[3].lost_links, 18);
+        assert_eq!(counters.processing_unit_errors, 13);
+        assert_eq!(counters.pdi_errors, 14);
+    }
+
+    #[test]
+    fn since() {
+        let mut previous = ErrorCounters::default();
+        previous.ports[0].invalid_frames = 10;
+        previous.ports[1].rx_errors = 5;
+
+        let mut current = previous;
+        current.ports[0].invalid_frames = 4;
+        current.ports[1].rx_errors = 8;
+        current.ports[1].forwarded_rx_errors = 20;
+
+        let delta = current.since(&previous);
+
+        // Reset in between
+        assert_eq!(delta.ports[0].invalid_frames, 4);
+        assert_eq!(delta.ports[1].rx_errors, 3);
+        // Forwarded errors don't count towards the worst port
+        assert_eq!(delta.worst_port(), Some((0, 4)));
+        assert_eq!(ErrorCounters::default().worst_port(), None);
+    }
+}
@@ -0,0 +1,232 @@
+//! Per-SubDevice configuration hooks run during state transitions.
+
+#[cfg(feature = "std")]
+use crate::subdevice::SubDevice;
+
+/// An async hook registered with [`MainDevice::add_hook`](crate::MainDevice::add_hook).
+#[cfg(feature = "std")]
+pub type HookFuture<'a> = core::pin::Pin<
+    Box<dyn core::future::Future<Output = Result<(), crate::error::Error>> + Send + 'a>,
+>;
+
+/// The SubDevice(s) a hook applies to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum HookTarget {
+    /// Every SubDevice with the given vendor and product ID, e.g. a particular drive model.
+    Identity {
+        /// Vendor ID.
+        vendor_id: u32,
+        /// Product ID.
+        product_id: u32,
+    },
+    /// The SubDevice with the given configured station alias.
+    Alias(u16),
+    /// The SubDevice with the given configured station address.
+    ConfiguredAddress(u16),
+}
+
+#[cfg(feature = "std")]
+impl HookTarget {
+    pub(crate) fn matches(&self, subdevice: &SubDevice) -> bool {
+        match *self {
+            HookTarget::Identity {
+                vendor_id,
+                product_id,
+            } => {
+                let identity = subdevice.identity();
+
+                identity.vendor_id == vendor_id && identity.product_id == product_id
+            }
+            HookTarget::Alias(alias) => subdevice.alias_address() == alias,
+            HookTarget::ConfiguredAddress(address) => subdevice.configured_address() == address,
+        }
+    }
+}
+
+/// The point in the SubDevice state machine a hook runs at.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum HookTransition {
+    /// Run once the SubDevice has reached PRE-OP from INIT and its mailbox is available.
+    InitToPreOp,
+    /// Run in PRE-OP before the group's process data is mapped, like SOEM's `PO2SOconfig`.
+    ///
+    /// PDO assignment with [`SubDeviceRef::configure_pdos`](crate::SubDeviceRef::configure_pdos)
+    /// and other SDO configuration should be done here.
+    PreOpToSafeOp,
+    /// Run in SAFE-OP before the SubDevice is requested to go to OP.
+    SafeOpToOp,
+}
+
+#[cfg(feature = "std")]
+pub(crate) type HookFn<'sto> = Box<
+    dyn for<'a> Fn(crate::SubDeviceRef<'a, &'a mut SubDevice>) -> HookFuture<'a>
+        + Send
+        + Sync
+        + 'sto,
+>;
+
+/// A hook registered on a [`MainDevice`](crate::MainDevice).
+#[cfg(feature = "std")]
+pub(crate) struct Hook<'sto> {
+    pub target: HookTarget,
+    pub transition: HookTransition,
+    pub hook: HookFn<'sto>,
+}
+
+#[cfg(feature = "std")]
+impl core::fmt::Debug for Hook<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Hook")
+            .field("target", &self.target)
+            .field("transition", &self.transition)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{
+        sim::{SimNetwork, SimSubDevice},
+        subdevice_group::Op,
+        PduStorage, SubDeviceGroup, SubDeviceIdentity, SubDeviceState,
+    };
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    enum Event {
+        /// A hook ran for the given SubDevice, which was in the given state.
+        Hook(HookTransition, u16, SubDeviceState),
+        /// The `configure` closure passed to `recover_subdevice` ran.
+        Configure(u16),
+    }
+
+    #[test]
+    fn run_during_transitions() {
+        let storage = PduStorage::<16, { PduStorage::element_size(1100) }>::new();
+        let (mut maindevice, mut network) = SimNetwork::new(
+            &storage,
+            vec![
+                SimSubDevice::new(1, 0).inputs(8),
+                // Not matched by the hooks' target
+                SimSubDevice::new(2, 1).inputs(8),
+                SimSubDevice::new(1, 2).outputs(8),
+            ],
+        );
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        for transition in [
+            HookTransition::InitToPreOp,
+            HookTransition::PreOpToSafeOp,
+            HookTransition::SafeOpToOp,
+        ] {
+            let events = events.clone();
+
+            maindevice.add_hook(
+                HookTarget::Identity {
+                    vendor_id: 0x0000_0002,
+                    product_id: 1,
+                },
+                transition,
+                move |subdevice| {
+                    let events = events.clone();
+
+                    Box::pin(async move {
+                        let state = subdevice.state().await?;
+
+                        events.lock().unwrap().push(Event::Hook(
+                            transition,
+                            subdevice.configured_address(),
+                            state,
+                        ));
+
+                        Ok(())
+                    })
+                },
+            );
+        }
+
+        let take = || core::mem::take(&mut *events.lock().unwrap());
+
+        let group = network
+            .run(maindevice.init_single_group::<4, 64>(|| 0))
+            .expect("init");
+
+        assert_eq!(
+            take(),
+            [
+                Event::Hook(HookTransition::InitToPreOp, 0x1000, SubDeviceState::PreOp),
+                Event::Hook(HookTransition::InitToPreOp, 0x1002, SubDeviceState::PreOp),
+            ]
+        );
+
+        let group: SubDeviceGroup<4, 64, Op> = network.run(group.into_op(&maindevice)).expect("OP");
+
+        assert_eq!(
+            take(),
+            [
+                Event::Hook(HookTransition::PreOpToSafeOp, 0x1000, SubDeviceState::PreOp),
+                Event::Hook(HookTransition::PreOpToSafeOp, 0x1002, SubDeviceState::PreOp),
+                Event::Hook(HookTransition::SafeOpToOp, 0x1000, SubDeviceState::SafeOp),
+                Event::Hook(HookTransition::SafeOpToOp, 0x1002, SubDeviceState::SafeOp),
+            ]
+        );
+
+        // All hooks run again when a SubDevice is recovered, with InitToPreOp before `configure`
+        network.subdevices[2].power_cycle();
+
+        network
+            .run(group.recover_subdevice(&maindevice, 2, |subdevice| async {
+                events
+                    .lock()
+                    .unwrap()
+                    .push(Event::Configure(subdevice.configured_address()));
+
+                Ok(subdevice)
+            }))
+            .expect("recover");
+
+        assert_eq!(
+            take(),
+            [
+                Event::Hook(HookTransition::InitToPreOp, 0x1002, SubDeviceState::PreOp),
+                Event::Configure(0x1002),
+                Event::Hook(HookTransition::PreOpToSafeOp, 0x1002, SubDeviceState::PreOp),
+                Event::Hook(HookTransition::SafeOpToOp, 0x1002, SubDeviceState::SafeOp),
+            ]
+        );
+    }
+
+    #[test]
+    fn target_matches() {
+        let subdevice = SubDevice {
+            configured_address: 0x1001,
+            alias_address: 0xabcd,
+            identity: SubDeviceIdentity {
+                vendor_id: 0x2,
+                product_id: 0x1234,
+                revision: 1,
+                serial: 0,
+            },
+            ..SubDevice::default()
+        };
+
+        assert!(HookTarget::Identity {
+            vendor_id: 0x2,
+            product_id: 0x1234
+        }
+        .matches(&subdevice));
+        assert!(!HookTarget::Identity {
+            vendor_id: 0x2,
+            product_id: 0x4321
+        }
+        .matches(&subdevice));
+        assert!(HookTarget::Alias(0xabcd).matches(&subdevice));
+        assert!(!HookTarget::Alias(0x1001).matches(&subdevice));
+        assert!(HookTarget::ConfiguredAddress(0x1001).matches(&subdevice));
+        assert!(!HookTarget::ConfiguredAddress(0x1002).matches(&subdevice));
+    }
+}
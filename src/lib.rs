@@ -173,21 +173,27 @@ mod dl_status;
 pub mod ds402;
 mod eeprom;
 pub mod error;
+mod error_counters;
 mod ethernet;
 mod fmmu;
 mod generate;
+mod hooks;
 mod mailbox;
 mod maindevice;
 mod maindevice_config;
 mod pdi;
 mod pdu_loop;
 mod register;
+#[cfg(test)]
+mod sim;
 mod subdevice;
 pub mod subdevice_group;
 mod subdevice_state;
 mod sync_manager_channel;
 mod timer_factory;
+mod topology;
 mod vendors;
+mod watchdog;
 
 #[cfg(feature = "__internals")]
 pub mod internals;
@@ -198,19 +204,30 @@ pub mod std;
 pub use al_status_code::AlStatusCode;
 pub use coe::SubIndex;
 pub use command::{Command, Reads, WrappedRead, WrappedWrite, Writes};
+pub use error_counters::{ErrorCounters, PortErrorCounters};
 pub use ethercrab_wire::{
     EtherCrabWireRead, EtherCrabWireReadSized, EtherCrabWireReadWrite, EtherCrabWireSized,
     EtherCrabWireWrite, EtherCrabWireWriteSized,
 };
 use ethernet::EthernetAddress;
+#[cfg(feature = "std")]
+pub use hooks::HookFuture;
+pub use hooks::{HookTarget, HookTransition};
 pub use maindevice::MainDevice;
-pub use maindevice_config::{MainDeviceConfig, RetryBehaviour};
+pub use maindevice_config::{MainDeviceConfig, RetryBehaviour, TransitionRetry};
 pub use pdu_loop::{PduLoop, PduRx, PduStorage, PduTx, SendableFrame};
 pub use register::{DcSupport, RegisterAddress};
-pub use subdevice::{DcSync, SubDevice, SubDeviceIdentity, SubDevicePdi, SubDeviceRef};
+pub use subdevice::{
+    DcSync, InputEntry, OutputEntryMut, OutputsGuard, PdiBits, PdiBitsMut, PdoMapping, SubDevice,
+    SubDeviceIdentity, SubDevicePdi, SubDeviceRef, TransitionFailure,
+};
+#[cfg(feature = "std")]
+pub use subdevice_group::HeapSubDeviceGroup;
 pub use subdevice_group::{GroupId, GroupSubDeviceIterator, SubDeviceGroup, SubDeviceGroupHandle};
 pub use subdevice_state::SubDeviceState;
 pub use timer_factory::Timeouts;
+pub use topology::{ExpectedSubDevice, TopologyMismatch};
+pub use watchdog::{WatchdogConfig, WatchdogStatus};
 
 const LEN_MASK: u16 = 0b0000_0111_1111_1111;
 const ETHERCAT_ETHERTYPE: u16 = 0x88a4;
@@ -5,21 +5,28 @@ use crate::{
     dc,
     error::{Error, Item},
     fmt,
+    hooks::HookTransition,
     pdi::PdiOffset,
     pdu_loop::{PduLoop, ReceivedPdu},
     register::RegisterAddress,
-    subdevice::SubDevice,
-    subdevice_group::{self, SubDeviceGroupHandle},
+    subdevice::{SubDevice, SubDeviceRef},
+    subdevice_group::{self, GroupList, ShutdownReport, SubDeviceGroupHandle, SubDeviceGroupRef},
     subdevice_state::SubDeviceState,
     timer_factory::IntoTimeout,
-    MainDeviceConfig, SubDeviceGroup, Timeouts, BASE_SUBDEVICE_ADDRESS,
+    topology::{self, DiscoveredSubDevice},
+    ExpectedSubDevice, MainDeviceConfig, SubDeviceGroup, Timeouts, TopologyMismatch,
+    TransitionRetry, BASE_SUBDEVICE_ADDRESS,
+};
+#[cfg(feature = "std")]
+use crate::{
+    hooks::{HookFuture, HookTarget},
+    subdevice_group::HeapSubDeviceGroup,
 };
 use core::{
     ops::Range,
-    sync::atomic::{AtomicU16, Ordering},
+    sync::atomic::{AtomicU16, AtomicU32, Ordering},
 };
 use ethercrab_wire::EtherCrabWireWrite;
-use heapless::FnvIndexMap;
 
 /// The main EtherCAT controller.
 ///
@@ -39,8 +46,18 @@ pub struct MainDevice<'sto> {
     ///
     /// If no DC subdevices are found, this will be `0`.
     dc_reference_configured_address: AtomicU16,
+    /// The configured station address to assign to the next SubDevice found by
+    /// [`rescan`](MainDevice::rescan).
+    next_configured_address: AtomicU16,
+    /// The end of the PDI segments reserved for all groups so far.
+    pdi_end: AtomicU32,
     pub(crate) timeouts: Timeouts,
     pub(crate) config: MainDeviceConfig,
+    /// What to do when a SubDevice refuses a state transition.
+    pub(crate) transition_retry: TransitionRetry,
+    /// Hooks registered with [`add_hook`](MainDevice::add_hook).
+    #[cfg(feature = "std")]
+    hooks: std::vec::Vec<crate::hooks::Hook<'sto>>,
 }
 
 unsafe impl<'sto> Sync for MainDevice<'sto> {}
@@ -56,9 +73,148 @@ impl<'sto> MainDevice<'sto> {
             pdu_loop,
             num_subdevices: AtomicU16::new(0),
             dc_reference_configured_address: AtomicU16::new(0),
+            next_configured_address: AtomicU16::new(BASE_SUBDEVICE_ADDRESS),
+            pdi_end: AtomicU32::new(0),
             timeouts,
             config,
+            transition_retry: TransitionRetry::None,
+            #[cfg(feature = "std")]
+            hooks: std::vec::Vec::new(),
+        }
+    }
+
+    /// Set what to do when a SubDevice refuses a state transition.
+    ///
+    /// Defaults to [`TransitionRetry::None`], returning the SubDevice's AL status code as an error
+    /// straight away.
+    ///
+    /// # Examples
+    ///
+    /// Acknowledge refused transitions and retry them up to three times.
+    ///
+    /// ```rust
+    /// use ethercrab::{MainDevice, MainDeviceConfig, PduStorage, Timeouts, TransitionRetry};
+    ///
+    /// static PDU_STORAGE: PduStorage<16, { PduStorage::element_size(1100) }> = PduStorage::new();
+    ///
+    /// let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    ///
+    /// let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default())
+    ///     .with_transition_retry(TransitionRetry::Count(3));
+    /// ```
+    pub const fn with_transition_retry(mut self, transition_retry: TransitionRetry) -> Self {
+        self.transition_retry = transition_retry;
+
+        self
+    }
+
+    /// Register an async hook to run for the matching SubDevice(s) during the given state
+    /// transition.
+    ///
+    /// Hooks are run during [`init`](MainDevice::init), group state transitions,
+    /// [`rescan`](MainDevice::rescan) and
+    /// [`recover_subdevice`](crate::SubDeviceGroup::recover_subdevice), so configuration for a
+    /// particular device model can live in one place and is applied again whenever the device is
+    /// reconfigured. Hooks are run in the order they were registered. An error returned from a hook
+    /// aborts the transition.
+    ///
+    /// Hooks run during a group transition are run after any configuration done by the
+    /// application, e.g. in PRE-OP before calling
+    /// [`into_safe_op`](crate::SubDeviceGroup::into_safe_op).
+    ///
+    /// # Examples
+    ///
+    /// Assign PDOs for every SubDevice of a particular drive model.
+    ///
+    /// ```rust,no_run
+    /// use ethercrab::{
+    ///     std::ethercat_now, HookTarget, HookTransition, MainDevice, MainDeviceConfig, PduStorage,
+    ///     PdoMapping, Timeouts,
+    /// };
+    ///
+    /// static PDU_STORAGE: PduStorage<16, { PduStorage::element_size(1100) }> = PduStorage::new();
+    ///
+    /// let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    ///
+    /// let mut maindevice =
+    ///     MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+    ///
+    /// let mapping = PdoMapping::new()
+    ///     .rx_pdo(0x1600)
+    ///     .entry(0x6040, 0, 16)
+    ///     .tx_pdo(0x1a00)
+    ///     .entry(0x6041, 0, 16);
+    ///
+    /// maindevice.add_hook(
+    ///     HookTarget::Identity {
+    ///         vendor_id: 0x0000_0002,
+    ///         product_id: 0x1b7e_3052,
+    ///     },
+    ///     HookTransition::PreOpToSafeOp,
+    ///     move |mut subdevice| {
+    ///         let mapping = mapping.clone();
+    ///
+    ///         Box::pin(async move { subdevice.configure_pdos(&mapping).await })
+    ///     },
+    /// );
+    ///
+    /// # async {
+    /// // The hook is run for every matching SubDevice before the PDI is configured
+    /// let group = maindevice
+    ///     .init_single_group::<16, 64>(ethercat_now)
+    ///     .await
+    ///     .expect("Init")
+    ///     .into_op(&maindevice)
+    ///     .await
+    ///     .expect("PRE-OP -> OP");
+    /// # };
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn add_hook<F>(&mut self, target: HookTarget, transition: HookTransition, hook: F)
+    where
+        F: for<'a> Fn(SubDeviceRef<'a, &'a mut SubDevice>) -> HookFuture<'a> + Send + Sync + 'sto,
+    {
+        self.hooks.push(crate::hooks::Hook {
+            target,
+            transition,
+            hook: Box::new(hook),
+        });
+    }
+
+    /// Run all hooks registered for the given SubDevice and transition.
+    #[cfg(feature = "std")]
+    pub(crate) async fn run_hooks(
+        &self,
+        transition: HookTransition,
+        subdevice: &mut SubDevice,
+    ) -> Result<(), Error> {
+        let configured_address = subdevice.configured_address();
+
+        for hook in self.hooks.iter() {
+            if hook.transition != transition || !hook.target.matches(subdevice) {
+                continue;
+            }
+
+            fmt::debug!(
+                "Running {:?} hook for SubDevice {:#06x}",
+                transition,
+                configured_address
+            );
+
+            (hook.hook)(SubDeviceRef::new(self, configured_address, &mut *subdevice)).await?;
         }
+
+        Ok(())
+    }
+
+    /// Hooks can only be registered with `std`, so there is nothing to run.
+    #[cfg(not(feature = "std"))]
+    pub(crate) async fn run_hooks(
+        &self,
+        _transition: HookTransition,
+        _subdevice: &mut SubDevice,
+    ) -> Result<(), Error> {
+        Ok(())
     }
 
     /// Write zeroes to every SubDevice's memory in chunks.
@@ -150,7 +306,8 @@ impl<'sto> MainDevice<'sto> {
         // Loop Parameter 1" to 3.
         //
         // According to ETG1020, we'll use the mode where the DC reference clock is adjusted to the
-        // master clock.
+        // master clock. The reference clock is only steered once a group has a host clock set with
+        // `SubDeviceGroup::set_host_clock`.
         Command::bwr(RegisterAddress::DcControlLoopParam3.into())
             .ignore_wkc()
             .send(self, 0x0c00u16)
@@ -234,7 +391,87 @@ impl<'sto> MainDevice<'sto> {
     pub async fn init<const MAX_SUBDEVICES: usize, G>(
         &self,
         now: impl Fn() -> u64 + Copy,
-        mut group_filter: impl for<'g> FnMut(
+        group_filter: impl for<'g> FnMut(
+            &'g G,
+            &SubDevice,
+        ) -> Result<&'g dyn SubDeviceGroupHandle, Error>,
+    ) -> Result<G, Error>
+    where
+        G: Default,
+    {
+        self.init_inner::<MAX_SUBDEVICES, G>(
+            now,
+            None::<(_, fn(TopologyMismatch))>,
+            G::default(),
+            GroupList::default(),
+            group_filter,
+        )
+        .await
+    }
+
+    /// The same as [`MainDevice::init`], but checks the discovered SubDevices against a list of
+    /// expected SubDevices before any SubDevice is configured.
+    ///
+    /// Every expected SubDevice must be present at its given position with a matching vendor and
+    /// product ID, as well as revision and alias address if specified. Any SubDevice found at a
+    /// position not in `expected` is also a mismatch.
+    ///
+    /// If the network does not match, `on_mismatch` is called with every mismatch found, each
+    /// mismatch is logged at error level, and [`Error::TopologyMismatch`] is returned containing
+    /// the first mismatch and the total mismatch count.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ethercrab::{
+    ///     error::Error, std::ethercat_now, ExpectedSubDevice, MainDevice, MainDeviceConfig,
+    ///     PduStorage, SubDeviceGroup, Timeouts,
+    /// };
+    ///
+    /// const MAX_SUBDEVICES: usize = 2;
+    /// const MAX_PDU_DATA: usize = PduStorage::element_size(1100);
+    /// const MAX_FRAMES: usize = 16;
+    ///
+    /// static PDU_STORAGE: PduStorage<MAX_FRAMES, MAX_PDU_DATA> = PduStorage::new();
+    ///
+    /// const EXPECTED: [ExpectedSubDevice; 2] = [
+    ///     // EK1100
+    ///     ExpectedSubDevice::new(0, 0x2, 0x044c2c52),
+    ///     // EL2004
+    ///     ExpectedSubDevice::new(1, 0x2, 0x07d43052),
+    /// ];
+    ///
+    /// let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    ///
+    /// let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+    ///
+    /// # async {
+    /// let mut mismatches = Vec::new();
+    ///
+    /// let group = maindevice
+    ///     .init_with_topology::<MAX_SUBDEVICES, _>(
+    ///         ethercat_now,
+    ///         &EXPECTED,
+    ///         |mismatch| mismatches.push(mismatch),
+    ///         |group: &SubDeviceGroup<MAX_SUBDEVICES, 1>, _subdevice| Ok(group),
+    ///     )
+    ///     .await;
+    ///
+    /// if let Err(Error::TopologyMismatch { count, .. }) = group {
+    ///     eprintln!("Network does not match: {} mismatches", count);
+    ///
+    ///     for mismatch in mismatches {
+    ///         eprintln!("- {}", mismatch);
+    ///     }
+    /// }
+    /// # };
+    /// ```
+    pub async fn init_with_topology<const MAX_SUBDEVICES: usize, G>(
+        &self,
+        now: impl Fn() -> u64 + Copy,
+        expected: &[ExpectedSubDevice],
+        on_mismatch: impl FnMut(TopologyMismatch),
+        group_filter: impl for<'g> FnMut(
             &'g G,
             &SubDevice,
         ) -> Result<&'g dyn SubDeviceGroupHandle, Error>,
@@ -242,8 +479,31 @@ impl<'sto> MainDevice<'sto> {
     where
         G: Default,
     {
-        let groups = G::default();
+        self.init_inner::<MAX_SUBDEVICES, G>(
+            now,
+            Some((expected, on_mismatch)),
+            G::default(),
+            GroupList::default(),
+            group_filter,
+        )
+        .await
+    }
 
+    /// Discover and configure SubDevices, assigning them to `groups`.
+    ///
+    /// `subdevices` is the empty list discovered SubDevices are stored in. Other lists used during
+    /// initialisation are created with the same kind of storage.
+    async fn init_inner<const MAX_SUBDEVICES: usize, G>(
+        &self,
+        now: impl Fn() -> u64 + Copy,
+        expected: Option<(&[ExpectedSubDevice], impl FnMut(TopologyMismatch))>,
+        groups: G,
+        mut subdevices: GroupList<SubDevice, MAX_SUBDEVICES>,
+        mut group_filter: impl for<'g> FnMut(
+            &'g G,
+            &SubDevice,
+        ) -> Result<&'g dyn SubDeviceGroupHandle, Error>,
+    ) -> Result<G, Error> {
         // Each SubDevice increments working counter, so we can use it as a total count of
         // SubDevices
         let num_subdevices = self.count_subdevices().await?;
@@ -253,6 +513,10 @@ impl<'sto> MainDevice<'sto> {
         if num_subdevices == 0 {
             fmt::warn!("No SubDevices were discovered. Check NIC device, connections and PDU response timeouts");
 
+            if let Some((expected, on_mismatch)) = expected {
+                topology::verify(expected, &[], on_mismatch)?;
+            }
+
             return Ok(groups);
         }
 
@@ -261,8 +525,10 @@ impl<'sto> MainDevice<'sto> {
         // This is the only place we store the number of SubDevices, so the ordering can be
         // pretty much anything.
         self.num_subdevices.store(num_subdevices, Ordering::Relaxed);
-
-        let mut subdevices = heapless::Deque::<SubDevice, MAX_SUBDEVICES>::new();
+        self.next_configured_address.store(
+            BASE_SUBDEVICE_ADDRESS.wrapping_add(num_subdevices),
+            Ordering::Relaxed,
+        );
 
         // Set configured address for all discovered SubDevices
         for subdevice_idx in 0..num_subdevices {
@@ -278,40 +544,70 @@ impl<'sto> MainDevice<'sto> {
             let subdevice = SubDevice::new(self, subdevice_idx, configured_address).await?;
 
             subdevices
-                .push_back(subdevice)
+                .push(subdevice)
                 .map_err(|_| Error::Capacity(Item::SubDevice))?;
         }
 
+        if let Some((expected, mut on_mismatch)) = expected {
+            fmt::debug!(
+                "Verifying network against {} expected SubDevices",
+                expected.len()
+            );
+
+            let mut found = subdevices.empty_like();
+
+            for subdevice in subdevices.iter() {
+                // Holds as many items as the SubDevice list so will never be full
+                let _ = found.push(DiscoveredSubDevice {
+                    position: subdevice.index,
+                    identity: subdevice.identity,
+                    alias: subdevice.alias_address,
+                });
+            }
+
+            topology::verify(expected, &found, &mut on_mismatch)?;
+        }
+
         fmt::debug!("Configuring topology/distributed clocks");
 
         // Configure distributed clock offsets/propagation delays, perform static drift
         // compensation. We need the SubDevices in a single list so we can read the topology.
-        let dc_master = dc::configure_dc(self, subdevices.as_mut_slices().0, now).await?;
+        let dc_master = dc::configure_dc(self, &mut subdevices, now).await?;
 
         // If there are SubDevices that support distributed clocks, run static drift compensation
         if let Some(dc_master) = dc_master {
             self.dc_reference_configured_address
                 .store(dc_master.configured_address(), Ordering::Relaxed);
 
-            dc::run_dc_static_sync(self, dc_master, self.config.dc_static_sync_iterations).await?;
+            dc::run_dc_static_sync(
+                self,
+                dc_master.configured_address(),
+                self.config.dc_static_sync_iterations,
+            )
+            .await?;
         }
 
         // This block is to reduce the lifetime of the groups map references
         {
             // A unique list of groups so we can iterate over them and assign consecutive PDIs to each
             // one.
-            let mut group_map = FnvIndexMap::<_, _, MAX_SUBDEVICES>::new();
+            let mut group_map = subdevices.empty_like::<(usize, SubDeviceGroupRef<'_>)>();
 
-            while let Some(subdevice) = subdevices.pop_front() {
+            for subdevice in subdevices {
                 let group = group_filter(&groups, &subdevice)?;
 
                 // SAFETY: This mutates the internal SubDevice list, so a reference to `group` may not be
                 // held over this line.
                 unsafe { group.push(subdevice)? };
 
-                group_map
-                    .insert(usize::from(group.id()), group.as_ref())
-                    .map_err(|_| Error::Capacity(Item::Group))?;
+                let id = usize::from(group.id());
+
+                match group_map.iter_mut().find(|(group_id, _)| *group_id == id) {
+                    Some((_, group_ref)) => *group_ref = group.as_ref(),
+                    None => group_map
+                        .push((id, group.as_ref()))
+                        .map_err(|_| Error::Capacity(Item::Group))?,
+                }
             }
 
             let mut offset = PdiOffset::default();
@@ -323,6 +619,8 @@ impl<'sto> MainDevice<'sto> {
             }
 
             fmt::debug!("Total PDI {} bytes", offset.start_address);
+
+            self.pdi_end.store(offset.start_address, Ordering::Relaxed);
         }
 
         // Check that all SubDevices reached PRE-OP
@@ -420,8 +718,424 @@ impl<'sto> MainDevice<'sto> {
             .await
     }
 
+    /// Like [`init`](MainDevice::init), but with heap-backed groups whose number and sizes are
+    /// chosen at runtime.
+    ///
+    /// Each group in `groups` is identified by a user-defined key `K`. The `group_filter` closure
+    /// returns the key of the group to add each SubDevice to, or
+    /// [`Err(Error::UnknownSubDevice)`](Error::UnknownSubDevice) if the SubDevice should not be
+    /// added to a group. [`Error::NotFound`] is returned if no group has the returned key.
+    ///
+    /// The groups are returned in the same order as they were passed in, in PRE-OP.
+    ///
+    /// # Examples
+    ///
+    /// Create groups from a configuration loaded at runtime.
+    ///
+    /// ```rust,no_run
+    /// use ethercrab::{
+    ///     std::ethercat_now, HeapSubDeviceGroup, MainDevice, MainDeviceConfig, PduStorage,
+    ///     Timeouts,
+    /// };
+    ///
+    /// static PDU_STORAGE: PduStorage<16, { PduStorage::element_size(1100) }> = PduStorage::new();
+    ///
+    /// let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    ///
+    /// let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+    ///
+    /// // E.g. read from a config file: group name, max SubDevices, max PDI bytes
+    /// let config = [("io", 32, 256), ("axes", 8, 512)];
+    ///
+    /// let groups = config
+    ///     .iter()
+    ///     .map(|(name, max_subdevices, max_pdi)| {
+    ///         (
+    ///             name.to_string(),
+    ///             HeapSubDeviceGroup::with_capacity(*max_subdevices, *max_pdi),
+    ///         )
+    ///     })
+    ///     .collect();
+    ///
+    /// # async {
+    /// let groups = maindevice
+    ///     .init_heap(ethercat_now, groups, |subdevice| {
+    ///         if subdevice.name().starts_with("EL7") {
+    ///             Ok("axes".to_string())
+    ///         } else {
+    ///             Ok("io".to_string())
+    ///         }
+    ///     })
+    ///     .await
+    ///     .expect("Init");
+    ///
+    /// for (name, group) in groups {
+    ///     let group = group.into_op(&maindevice).await.expect("PRE-OP -> OP");
+    ///
+    ///     println!("Group {} has {} SubDevices", name, group.len());
+    /// }
+    /// # };
+    /// ```
+    #[cfg(feature = "std")]
+    pub async fn init_heap<K>(
+        &self,
+        now: impl Fn() -> u64 + Copy,
+        groups: std::vec::Vec<(K, HeapSubDeviceGroup)>,
+        mut group_filter: impl FnMut(&SubDevice) -> Result<K, Error>,
+    ) -> Result<std::vec::Vec<(K, HeapSubDeviceGroup)>, Error>
+    where
+        K: PartialEq,
+    {
+        self.init_inner::<0, _>(
+            now,
+            None::<(_, fn(TopologyMismatch))>,
+            groups,
+            GroupList::heap(usize::MAX),
+            |groups: &std::vec::Vec<(K, HeapSubDeviceGroup)>, subdevice| {
+                let key = group_filter(subdevice)?;
+
+                let (_, group) = groups
+                    .iter()
+                    .find(|(group_key, _)| *group_key == key)
+                    .ok_or(Error::NotFound {
+                        item: Item::Group,
+                        index: None,
+                    })?;
+
+                Ok(group)
+            },
+        )
+        .await
+    }
+
+    /// Detect SubDevices connected since initialisation or the previous rescan, and attach them to
+    /// their [hot-connect groups](SubDeviceGroup::hot_connect).
+    ///
+    /// SubDevices that have already been configured are not touched, so groups not passed in
+    /// `hot_connect_groups` may continue to exchange process data while this method runs. A
+    /// SubDevice is considered new if it has no configured station address, i.e. it was powered on
+    /// since it was last configured.
+    ///
+    /// A SubDevice with no configured station address may also be a known SubDevice that lost
+    /// power. If a SubDevice in `groups` or `hot_connect_groups` was discovered at the same network
+    /// position, its configured station address is not in use and its identity matches, the
+    /// SubDevice is given its configured station address back and left in INIT. It can then be
+    /// brought back into its group with [`SubDeviceGroup::recover_subdevice`].
+    ///
+    /// Each new SubDevice is given a configured station address and read from EEPROM like during
+    /// [`init`](MainDevice::init). A new SubDevice whose configured station alias matches one of
+    /// the empty `hot_connect_groups` starts a segment, and every new SubDevice after it up to the
+    /// next segment or already configured SubDevice is added to the same group. The SubDevices in
+    /// each attached group are then put into PRE-OP. The group can be configured and transitioned
+    /// further like any other group, e.g. with [`SubDeviceGroup::into_op`].
+    ///
+    /// A group that was empty during initialisation is given a new segment of the PDI after all
+    /// other groups. The segment of a [detached](SubDeviceGroup::detach) group is reused.
+    ///
+    /// New SubDevices with DC support have their system time offset set relative to the DC
+    /// reference SubDevice, but propagation delays are not measured as this requires sending
+    /// commands to every SubDevice.
+    ///
+    /// New SubDevices that are not part of a hot-connect segment are left in INIT.
+    ///
+    /// Note that EtherCAT positions of SubDevices after a newly connected segment change. Groups
+    /// address their SubDevices with configured station addresses so are not affected, but
+    /// [`SubDeviceGroup::recover_subdevice`] and matching SubDevices that lost power rely on the
+    /// positions found when each SubDevice was discovered.
+    ///
+    /// Returns the number of SubDevices added to hot-connect groups.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ethercrab::{
+    ///     error::Error, std::ethercat_now, MainDevice, MainDeviceConfig, PduStorage,
+    ///     SubDeviceGroup, Timeouts,
+    /// };
+    ///
+    /// const TOOL_ALIAS: u16 = 0x0100;
+    ///
+    /// static PDU_STORAGE: PduStorage<16, { PduStorage::element_size(1100) }> = PduStorage::new();
+    ///
+    /// struct Groups {
+    ///     machine: SubDeviceGroup<8, 64>,
+    ///     tool: SubDeviceGroup<4, 16>,
+    /// }
+    ///
+    /// impl Default for Groups {
+    ///     fn default() -> Self {
+    ///         Self {
+    ///             machine: SubDeviceGroup::default(),
+    ///             tool: SubDeviceGroup::hot_connect(TOOL_ALIAS),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    ///
+    /// let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+    ///
+    /// # async {
+    /// // The tool may or may not be connected at startup
+    /// let Groups { machine, mut tool } = maindevice
+    ///     .init::<16, _>(ethercat_now, |groups: &Groups, subdevice| {
+    ///         if subdevice.alias_address() == TOOL_ALIAS {
+    ///             Ok(&groups.tool)
+    ///         } else {
+    ///             Ok(&groups.machine)
+    ///         }
+    ///     })
+    ///     .await
+    ///     .expect("Init");
+    ///
+    /// let machine = machine.into_op(&maindevice).await.expect("PRE-OP -> OP");
+    ///
+    /// // Later, once the tool has been plugged in
+    /// if tool.is_empty() && maindevice.rescan(&[&machine], &mut [&mut tool]).await? > 0 {
+    ///     let tool = tool.into_op(&maindevice).await.expect("Tool PRE-OP -> OP");
+    ///
+    ///     // ...
+    ///
+    ///     // The tool was unplugged
+    ///     let tool = tool.detach();
+    /// }
+    /// # Ok::<(), Error>(())
+    /// # };
+    /// ```
+    pub async fn rescan(
+        &self,
+        groups: &[&dyn SubDeviceGroupHandle],
+        hot_connect_groups: &mut [&mut dyn SubDeviceGroupHandle],
+    ) -> Result<usize, Error> {
+        let num_subdevices = self.count_subdevices().await?;
+
+        fmt::debug!("Rescan found {} SubDevices", num_subdevices);
+
+        let mut added = 0;
+        // Index of the hot-connect group new SubDevices are currently added to
+        let mut segment: Option<usize> = None;
+
+        for position in 0..num_subdevices {
+            let address = Command::aprd(position, RegisterAddress::ConfiguredStationAddress.into())
+                .receive::<u16>(self)
+                .await?;
+
+            let is_known = (BASE_SUBDEVICE_ADDRESS
+                ..self.next_configured_address.load(Ordering::Relaxed))
+                .contains(&address)
+                || self
+                    .restore_address(position, groups, hot_connect_groups)
+                    .await?;
+
+            if is_known {
+                if let Some(index) = segment.take() {
+                    self.attach(&mut *hot_connect_groups[index]).await?;
+                }
+
+                continue;
+            }
+
+            let configured_address = self.next_configured_address.fetch_add(1, Ordering::Relaxed);
+
+            fmt::debug!(
+                "New SubDevice at position {}, assigning address {:#06x}",
+                position,
+                configured_address
+            );
+
+            Command::apwr(position, RegisterAddress::ConfiguredStationAddress.into())
+                .send(self, configured_address)
+                .await?;
+
+            let mut subdevice = SubDevice::new(self, position, configured_address).await?;
+
+            let head = hot_connect_groups.iter_mut().position(|group| {
+                group.as_mut().hot_connect_alias() == Some(subdevice.alias_address())
+            });
+
+            if let Some(head) = head {
+                if let Some(index) = segment.take() {
+                    self.attach(&mut *hot_connect_groups[index]).await?;
+                }
+
+                if hot_connect_groups[head].as_mut().is_empty() {
+                    segment = Some(head);
+                } else {
+                    fmt::warn!(
+                        "Hot-connect group with alias {:#06x} is already attached",
+                        subdevice.alias_address()
+                    );
+                }
+            }
+
+            let Some(index) = segment else {
+                fmt::warn!(
+                    "New SubDevice {:#06x} is not part of a hot-connect segment and will be left in INIT",
+                    configured_address
+                );
+
+                continue;
+            };
+
+            if subdevice.dc_support().any() {
+                SubDeviceRef::new(self, configured_address, &mut subdevice)
+                    .restore_dc(self.dc_ref_address())
+                    .await?;
+            }
+
+            hot_connect_groups[index].push_mut(subdevice)?;
+
+            added += 1;
+        }
+
+        if let Some(index) = segment.take() {
+            self.attach(&mut *hot_connect_groups[index]).await?;
+        }
+
+        self.num_subdevices.store(num_subdevices, Ordering::Relaxed);
+
+        Ok(added)
+    }
+
+    /// Give a SubDevice with no configured station address its previous address back if it is a
+    /// known SubDevice that lost power.
+    ///
+    /// Returns `true` if the address was restored.
+    async fn restore_address(
+        &self,
+        position: u16,
+        groups: &[&dyn SubDeviceGroupHandle],
+        hot_connect_groups: &[&mut dyn SubDeviceGroupHandle],
+    ) -> Result<bool, Error> {
+        let mut known = None;
+
+        for group in groups
+            .iter()
+            .copied()
+            .chain(hot_connect_groups.iter().map(|group| &**group))
+        {
+            known = group.find_position(position)?;
+
+            if known.is_some() {
+                break;
+            }
+        }
+
+        let Some((configured_address, identity)) = known else {
+            return Ok(false);
+        };
+
+        // The SubDevice may have moved to another position instead of losing power
+        let in_use = Command::fprd(
+            configured_address,
+            RegisterAddress::ConfiguredStationAddress.into(),
+        )
+        .receive_wkc::<u16>(self)
+        .await?
+            > 0;
+
+        if in_use {
+            return Ok(false);
+        }
+
+        Command::apwr(position, RegisterAddress::ConfiguredStationAddress.into())
+            .send(self, configured_address)
+            .await?;
+
+        let found = SubDeviceRef::new(self, configured_address, ())
+            .read_identity()
+            .await?;
+
+        if found != identity {
+            fmt::debug!(
+                "SubDevice at position {} is not {:#06x}: expected {}, found {}",
+                position,
+                configured_address,
+                identity,
+                found
+            );
+
+            return Ok(false);
+        }
+
+        fmt::info!(
+            "SubDevice {:#06x} at position {} lost power, restored configured address",
+            configured_address,
+            position
+        );
+
+        Ok(true)
+    }
+
+    /// Reserve a segment of the PDI for a newly populated hot-connect group and put its
+    /// SubDevices into PRE-OP.
+    async fn attach(&self, group: &mut dyn SubDeviceGroupHandle) -> Result<(), Error> {
+        let mut group = group.as_mut();
+
+        let pdi_start = match group.reserved_pdi_start() {
+            Some(start) => start,
+            None => PdiOffset {
+                start_address: self
+                    .pdi_end
+                    .fetch_add(group.max_pdi_len() as u32, Ordering::Relaxed),
+                start_bit: 0,
+            },
+        };
+
+        fmt::debug!(
+            "Attaching hot-connect group with alias {:?}, PDI start {:#010x}",
+            group.hot_connect_alias(),
+            pdi_start.start_address
+        );
+
+        group.into_pre_op(pdi_start, self).await?;
+
+        Ok(())
+    }
+
+    /// Step every SubDevice on the network down to INIT.
+    ///
+    /// This goes through SAFE-OP and PRE-OP, deactivating DC SYNC signal generation for all
+    /// SubDevices once they have reached PRE-OP. Each SubDevice is only requested to go to states
+    /// below the one it is in, and addresses with no SubDevice, e.g. those of a disconnected
+    /// hot-connect segment, are skipped. A SubDevice that fails any step is recorded in the
+    /// returned [`ShutdownReport`](crate::subdevice_group::ShutdownReport), but the sequence
+    /// continues for every SubDevice.
+    ///
+    /// Unlike [`SubDeviceGroup::shutdown`], no process data is exchanged and outputs are not set to
+    /// safe values first, so groups in OP should be shut down with that method. This method can be
+    /// used afterwards to make sure every SubDevice, including those not in any group, is in INIT.
+    ///
+    /// `MAX_SUBDEVICES` must be at least the number of SubDevices on the network.
+    pub async fn shutdown<const MAX_SUBDEVICES: usize>(&self) -> ShutdownReport<MAX_SUBDEVICES> {
+        let mut report = ShutdownReport::new();
+
+        let addresses =
+            BASE_SUBDEVICE_ADDRESS..self.next_configured_address.load(Ordering::Relaxed);
+
+        for state in [SubDeviceState::SafeOp, SubDeviceState::PreOp] {
+            report
+                .step_down(self, addresses.clone(), state, true, || async {})
+                .await;
+        }
+
+        if let Err(e) = Command::bwr(RegisterAddress::DcSyncActive.into())
+            .ignore_wkc()
+            .send(self, 0u8)
+            .await
+        {
+            fmt::error!("Failed to deactivate DC sync: {}", e);
+        }
+
+        report
+            .step_down(self, addresses, SubDeviceState::Init, true, || async {})
+            .await;
+
+        report
+    }
+
     /// Count the number of SubDevices on the network.
-    async fn count_subdevices(&self) -> Result<u16, Error> {
+    pub(crate) async fn count_subdevices(&self) -> Result<u16, Error> {
         Command::brd(RegisterAddress::Type.into())
             .receive_wkc::<u8>(self)
             .await
@@ -496,10 +1210,6 @@ impl<'sto> MainDevice<'sto> {
         .await
     }
 
-    pub(crate) fn max_frame_data(&self) -> usize {
-        self.pdu_loop.max_frame_data()
-    }
-
     /// Send a single PDU in a frame.
     pub(crate) async fn single_pdu(
         &'sto self,
@@ -549,6 +1259,217 @@ fn blank_mem_iter(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        sim::{SimNetwork, SimSubDevice},
+        PduStorage,
+    };
+    use ::std::vec;
+
+    const TOOL_ALIAS: u16 = 0x0100;
+
+    const STORAGE_LEN: usize = PduStorage::element_size(1100);
+
+    struct Groups {
+        machine: SubDeviceGroup<4, 16>,
+        tool: SubDeviceGroup<4, 16>,
+    }
+
+    impl Default for Groups {
+        fn default() -> Self {
+            Self {
+                machine: SubDeviceGroup::default(),
+                tool: SubDeviceGroup::hot_connect(TOOL_ALIAS),
+            }
+        }
+    }
+
+    fn tool_segment() -> [SimSubDevice; 2] {
+        [
+            SimSubDevice::new(3, 3).alias(TOOL_ALIAS).outputs(8),
+            SimSubDevice::new(4, 4).inputs(16),
+        ]
+    }
+
+    fn init<'sto>(maindevice: &'sto MainDevice<'sto>, network: &mut SimNetwork<'_>) -> Groups {
+        network
+            .run(maindevice.init::<8, _>(
+                || 0,
+                |groups: &Groups, subdevice| {
+                    if subdevice.alias_address() == TOOL_ALIAS {
+                        Ok(&groups.tool)
+                    } else {
+                        Ok(&groups.machine)
+                    }
+                },
+            ))
+            .expect("init")
+    }
+
+    /// The logical start address of a simulated SubDevice's first FMMU.
+    fn logical_start(subdevice: &SimSubDevice) -> u32 {
+        u32::from_le_bytes(subdevice.memory[0x0600..0x0604].try_into().unwrap())
+    }
+
+    #[test]
+    fn rescan_attaches_hot_connect_segment() {
+        let storage = PduStorage::<16, STORAGE_LEN>::new();
+        let (maindevice, mut network) =
+            SimNetwork::new(&storage, vec![SimSubDevice::new(1, 1).outputs(8)]);
+
+        let Groups { machine, mut tool } = init(&maindevice, &mut network);
+
+        assert!(tool.is_empty());
+
+        let machine = network.run(machine.into_op(&maindevice)).expect("OP");
+
+        network.subdevices.extend(tool_segment());
+        network.subdevices[2].set_inputs(&[0xaa, 0xbb]);
+
+        let added = network
+            .run(maindevice.rescan(&[&machine], &mut [&mut tool]))
+            .expect("rescan");
+
+        assert_eq!(added, 2);
+        assert_eq!(tool.len(), 2);
+        assert_eq!(network.subdevices[1].station_address(), 0x1001);
+        assert_eq!(network.subdevices[2].station_address(), 0x1002);
+        // PRE-OP
+        assert_eq!(network.subdevices[1].state(), 0x02);
+        assert_eq!(network.subdevices[2].state(), 0x02);
+
+        let tool = network.run(tool.into_op(&maindevice)).expect("tool OP");
+
+        // The tool's PDI segment is after the machine's
+        assert!(logical_start(&network.subdevices[1]) >= 16);
+
+        machine.subdevice(&maindevice, 0).unwrap().outputs_raw_mut()[0] = 0x11;
+        tool.subdevice(&maindevice, 0).unwrap().outputs_raw_mut()[0] = 0x22;
+
+        assert_eq!(network.run(machine.tx_rx(&maindevice)), Ok(2));
+        assert_eq!(network.run(tool.tx_rx(&maindevice)), Ok(3));
+
+        assert_eq!(network.subdevices[0].outputs_memory(), &[0x11]);
+        assert_eq!(network.subdevices[1].outputs_memory(), &[0x22]);
+        assert_eq!(
+            tool.subdevice(&maindevice, 1).unwrap().inputs_raw(),
+            &[0xaa, 0xbb]
+        );
+    }
+
+    #[test]
+    fn rescan_leaves_subdevices_outside_segments_in_init() {
+        let storage = PduStorage::<16, STORAGE_LEN>::new();
+        let (maindevice, mut network) = SimNetwork::new(&storage, vec![SimSubDevice::new(1, 1)]);
+
+        let Groups { mut tool, .. } = init(&maindevice, &mut network);
+
+        network.subdevices.push(SimSubDevice::new(2, 2));
+
+        let added = network
+            .run(maindevice.rescan(&[], &mut [&mut tool]))
+            .expect("rescan");
+
+        assert_eq!(added, 0);
+        assert!(tool.is_empty());
+        assert_eq!(network.subdevices[1].station_address(), 0x1001);
+        assert_eq!(network.subdevices[1].state(), 0x01);
+    }
+
+    #[test]
+    fn detach_and_attach_again() {
+        let storage = PduStorage::<16, STORAGE_LEN>::new();
+        let (maindevice, mut network) = SimNetwork::new(&storage, vec![SimSubDevice::new(1, 1)]);
+
+        let Groups { mut tool, .. } = init(&maindevice, &mut network);
+
+        network.subdevices.extend(tool_segment());
+
+        network
+            .run(maindevice.rescan(&[], &mut [&mut tool]))
+            .expect("rescan");
+
+        let tool = network.run(tool.into_op(&maindevice)).expect("tool OP");
+
+        let start = logical_start(&network.subdevices[1]);
+
+        // Unplug the tool
+        network.subdevices.truncate(1);
+
+        let mut tool = tool.detach();
+
+        assert!(tool.is_empty());
+
+        // Plug it back in
+        network.subdevices.extend(tool_segment());
+
+        let added = network
+            .run(maindevice.rescan(&[], &mut [&mut tool]))
+            .expect("rescan again");
+
+        assert_eq!(added, 2);
+        assert_eq!(tool.len(), 2);
+        let tool = network
+            .run(tool.into_op(&maindevice))
+            .expect("tool OP again");
+
+        // New addresses are assigned, but the PDI segment is reused
+        assert_eq!(network.subdevices[1].station_address(), 0x1003);
+        assert_eq!(logical_start(&network.subdevices[1]), start);
+
+        assert_eq!(network.run(tool.tx_rx(&maindevice)), Ok(3));
+    }
+
+    #[test]
+    fn rescan_restores_power_cycled_subdevice() {
+        let storage = PduStorage::<16, STORAGE_LEN>::new();
+        let (maindevice, mut network) = SimNetwork::new(
+            &storage,
+            vec![SimSubDevice::new(1, 1), SimSubDevice::new(2, 2).outputs(8)],
+        );
+
+        let Groups { machine, mut tool } = init(&maindevice, &mut network);
+
+        let machine = network.run(machine.into_op(&maindevice)).expect("OP");
+
+        network.subdevices[1].power_cycle();
+        network.subdevices.push(SimSubDevice::new(5, 5));
+
+        let added = network
+            .run(maindevice.rescan(&[&machine], &mut [&mut tool]))
+            .expect("rescan");
+
+        assert_eq!(added, 0);
+        // The power cycled SubDevice gets its address back and the new one the next address
+        assert_eq!(network.subdevices[1].station_address(), 0x1001);
+        assert_eq!(network.subdevices[2].station_address(), 0x1002);
+
+        network
+            .run(machine.recover_subdevice(&maindevice, 1, |subdevice| async { Ok(subdevice) }))
+            .expect("recover");
+
+        // OP
+        assert_eq!(network.subdevices[1].state(), 0x08);
+    }
+
+    #[test]
+    fn rescan_replaced_subdevice_is_new() {
+        let storage = PduStorage::<16, STORAGE_LEN>::new();
+        let (maindevice, mut network) = SimNetwork::new(
+            &storage,
+            vec![SimSubDevice::new(1, 1), SimSubDevice::new(2, 2)],
+        );
+
+        let Groups { machine, .. } = init(&maindevice, &mut network);
+
+        // A different SubDevice at the same position
+        network.subdevices[1] = SimSubDevice::new(2, 3);
+
+        network
+            .run(maindevice.rescan(&[&machine], &mut []))
+            .expect("rescan");
+
+        assert_eq!(network.subdevices[1].station_address(), 0x1002);
+    }
 
     #[test]
     #[cfg_attr(miri, ignore)]
@@ -61,6 +61,27 @@ impl RetryBehaviour {
     }
 }
 
+/// State transition retry policy.
+///
+/// When a SubDevice refuses a state transition, it sets the error flag in its AL status register
+/// and an AL status code describing the reason. The flag stays set until it is acknowledged.
+///
+/// Set with [`MainDevice::with_transition_retry`](crate::MainDevice::with_transition_retry).
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum TransitionRetry {
+    /// Return an [`Error::SubDevice`](crate::error::Error::SubDevice) containing the AL status
+    /// code straight away, leaving the error flag set (default).
+    #[default]
+    None,
+
+    /// Acknowledge the error, then request the transition again up to `N` times before returning
+    /// an [`Error::SubDevice`](crate::error::Error::SubDevice) with the AL status code of the last
+    /// attempt.
+    ///
+    /// `Count(0)` acknowledges the error without retrying.
+    Count(usize),
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
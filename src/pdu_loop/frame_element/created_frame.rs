@@ -8,9 +8,9 @@ use crate::{
         pdu_flags::PduFlags,
         pdu_header::PduHeader,
     },
-    Command, PduLoop,
+    Command, PduLoop, LEN_MASK,
 };
-use core::{ptr::NonNull, sync::atomic::AtomicU8, time::Duration};
+use core::{ops::Range, ptr::NonNull, sync::atomic::AtomicU8, time::Duration};
 use ethercrab_wire::{
     EtherCrabWireRead, EtherCrabWireSized, EtherCrabWireWrite, EtherCrabWireWriteSized,
 };
@@ -74,6 +74,20 @@ impl<'sto> CreatedFrame<'sto> {
         }
     }
 
+    /// The maximum data payload length of the next PDU pushed into this frame.
+    pub fn max_pdu_data_len(&self) -> usize {
+        let remaining = self
+            .inner
+            .pdu_buf()
+            .len()
+            .saturating_sub(self.inner.pdu_payload_len());
+
+        // PDU header and working counter
+        remaining
+            .saturating_sub(PduHeader::PACKED_LEN + 2)
+            .min(usize::from(LEN_MASK))
+    }
+
     /// Push a PDU into this frame.
     ///
     /// # Errors
@@ -169,6 +183,38 @@ impl<'sto> CreatedFrame<'sto> {
             command_code: command.code(),
         })
     }
+
+    /// Push a PDU with a zeroed payload of `len` bytes into this frame, to be written in place
+    /// with [`pdu_payload_mut`](CreatedFrame::pdu_payload_mut).
+    ///
+    /// Returns the position of the payload in the frame's PDU buffer along with the response
+    /// handle.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PduError::TooLong`] if the remaining space in the frame is not enough to hold the
+    /// new PDU.
+    pub fn push_pdu_zeroed(
+        &mut self,
+        command: Command,
+        len: usize,
+    ) -> Result<(PduResponseHandle, Range<usize>), PduError> {
+        if len > self.max_pdu_data_len() {
+            return Err(PduError::TooLong);
+        }
+
+        let start = self.inner.pdu_payload_len() + PduHeader::PACKED_LEN;
+
+        let handle = self.push_pdu(command, (), Some(len as u16))?;
+
+        Ok((handle, start..(start + len)))
+    }
+
+    /// Get part of the frame's PDU buffer, e.g. a payload pushed with
+    /// [`push_pdu_zeroed`](CreatedFrame::push_pdu_zeroed).
+    pub fn pdu_payload_mut(&mut self, range: Range<usize>) -> Option<&mut [u8]> {
+        self.inner.pdu_buf_mut().get_mut(range)
+    }
 }
 
 // SAFETY: This unsafe impl is required due to `FrameBox` containing a `NonNull`, however this impl
@@ -180,7 +226,7 @@ impl<'sto> CreatedFrame<'sto> {
 // a 'static bound.
 unsafe impl<'sto> Send for CreatedFrame<'sto> {}
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone)]
 pub struct PduResponseHandle {
     pub index_in_frame: u8,
 
@@ -281,4 +327,103 @@ mod tests {
             PduFlags::new(0, false).pack()
         );
     }
+
+    #[test]
+    fn max_pdu_data_len() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        const BUF_LEN: usize = 64;
+
+        let pdu_idx = AtomicU8::new(0);
+
+        let frames = UnsafeCell::new([FrameElement {
+            frame_index: 0xab,
+            status: AtomicFrameState::new(FrameState::None),
+            waker: AtomicWaker::default(),
+            ethernet_frame: [0u8; BUF_LEN],
+            pdu_payload_len: 0,
+            first_pdu: AtomicU16::new(FIRST_PDU_EMPTY),
+        }]);
+
+        let mut created = CreatedFrame::claim_created(
+            unsafe { NonNull::new_unchecked(frames.get().cast()) },
+            0xab,
+            &pdu_idx,
+            BUF_LEN,
+        )
+        .expect("Claim created");
+
+        let max = created.max_pdu_data_len();
+
+        // Fill the first PDU so only a zero-length PDU will fit afterwards
+        let len = max - (PduHeader::PACKED_LEN + 2);
+
+        assert!(created
+            .push_pdu(Command::lrw(0).into(), (), Some(len as u16))
+            .is_ok());
+
+        assert_eq!(created.max_pdu_data_len(), 0);
+
+        assert!(created.push_pdu(Command::brd(0).into(), (), None).is_ok());
+
+        assert_eq!(
+            created
+                .push_pdu(Command::brd(0).into(), (), None)
+                .unwrap_err(),
+            PduError::TooLong
+        );
+    }
+
+    #[test]
+    fn push_zeroed_write_in_place() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        const BUF_LEN: usize = 64;
+
+        let pdu_idx = AtomicU8::new(0);
+
+        let frames = UnsafeCell::new([FrameElement {
+            frame_index: 0xab,
+            status: AtomicFrameState::new(FrameState::None),
+            waker: AtomicWaker::default(),
+            ethernet_frame: [0u8; BUF_LEN],
+            pdu_payload_len: 0,
+            first_pdu: AtomicU16::new(FIRST_PDU_EMPTY),
+        }]);
+
+        let mut created = CreatedFrame::claim_created(
+            unsafe { NonNull::new_unchecked(frames.get().cast()) },
+            0xab,
+            &pdu_idx,
+            BUF_LEN,
+        )
+        .expect("Claim created");
+
+        let (_handle, first) = created
+            .push_pdu_zeroed(Command::lrd(0).into(), 2)
+            .expect("First PDU");
+        let (_handle, second) = created
+            .push_pdu_zeroed(Command::lwr(2).into(), 3)
+            .expect("Second PDU");
+
+        assert_eq!(first, PduHeader::PACKED_LEN..(PduHeader::PACKED_LEN + 2));
+        // Previous PDU's data and working counter, then the next header
+        assert_eq!(second.start, first.end + 2 + PduHeader::PACKED_LEN);
+        assert_eq!(second.len(), 3);
+
+        created
+            .pdu_payload_mut(second.clone())
+            .expect("Payload")
+            .copy_from_slice(&[0xaa, 0xbb, 0xcc]);
+
+        assert_eq!(created.inner.pdu_buf()[first], [0x00, 0x00]);
+        assert_eq!(created.inner.pdu_buf()[second], [0xaa, 0xbb, 0xcc]);
+
+        assert_eq!(
+            created
+                .push_pdu_zeroed(Command::lwr(5).into(), BUF_LEN)
+                .unwrap_err(),
+            PduError::TooLong
+        );
+    }
 }
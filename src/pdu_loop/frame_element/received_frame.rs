@@ -123,6 +123,13 @@ impl<'sto> ReceivedFrame<'sto> {
         })
     }
 
+    /// The raw PDU buffer of the received frame.
+    ///
+    /// Received PDUs are at the same positions in the buffer as when they were sent.
+    pub fn pdu_buf(&self) -> &[u8] {
+        self.inner.pdu_buf()
+    }
+
     pub fn into_iter(self) -> ReceivedPduIter<'sto> {
         ReceivedPduIter {
             frame: self,
@@ -144,6 +151,10 @@ impl<'sto> Drop for ReceivedFrame<'sto> {
     }
 }
 
+// SAFETY: This is ok because we respect the lifetime of the underlying data by carrying the 'sto
+// lifetime.
+unsafe impl<'sto> Send for ReceivedFrame<'sto> {}
+
 // NOTE: Takes ownership of frame so we can't do double reads with handles
 pub struct ReceivedPduIter<'sto> {
     frame: ReceivedFrame<'sto>,
@@ -8,13 +8,14 @@ mod pdu_tx;
 pub mod storage;
 
 use crate::{command::Command, error::Error, pdu_loop::storage::PduStorageRef};
-use core::time::Duration;
+use core::{sync::atomic::Ordering, time::Duration};
 pub use pdu_rx::PduRx;
 pub use pdu_tx::PduTx;
 pub use storage::PduStorage;
 
-pub(crate) use self::frame_element::created_frame::CreatedFrame;
-pub(crate) use frame_element::received_frame::ReceivedPdu;
+pub(crate) use self::frame_element::created_frame::{CreatedFrame, PduResponseHandle};
+pub(crate) use frame_element::received_frame::{ReceivedFrame, ReceivedPdu};
+pub(crate) use frame_element::receiving_frame::ReceiveFrameFut;
 
 pub use frame_element::sendable_frame::SendableFrame;
 
@@ -68,6 +69,11 @@ impl<'sto> PduLoop<'sto> {
         self.storage.frame_data_len
     }
 
+    /// Whether frames are sent around a redundant ring by `tx_rx_task_redundant`.
+    pub(crate) fn is_redundant(&self) -> bool {
+        self.storage.redundant.load(Ordering::Relaxed)
+    }
+
     /// Tell the packet sender there are PDUs ready to send.
     pub(crate) fn wake_sender(&self) {
         self.storage.tx_waker.wake();
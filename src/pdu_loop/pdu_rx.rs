@@ -49,8 +49,15 @@ impl<'sto> PduRx<'sto> {
             return Ok(());
         }
 
-        let i = raw_packet.payload();
+        self.receive_ethercat_frame(raw_packet.payload())
+    }
 
+    /// Parse a response PDU from the EtherCAT payload of an Ethernet frame, i.e. everything after
+    /// the Ethernet header, and wake the future that sent the frame.
+    ///
+    /// Unlike [`receive_frame`](PduRx::receive_frame), the source address of the frame is not
+    /// checked.
+    pub(crate) fn receive_ethercat_frame(&mut self, i: &[u8]) -> Result<(), Error> {
         let frame_header = EthercatFrameHeader::unpack_from_slice(i).map_err(|e| {
             fmt::error!("Failed to parse frame header: {}", e);
 
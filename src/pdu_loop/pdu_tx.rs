@@ -1,5 +1,5 @@
 use super::{frame_element::sendable_frame::SendableFrame, storage::PduStorageRef};
-use core::task::Waker;
+use core::{sync::atomic::Ordering, task::Waker};
 
 /// EtherCAT frame transmit adapter.
 pub struct PduTx<'sto> {
@@ -16,6 +16,12 @@ impl<'sto> PduTx<'sto> {
         self.storage.num_frames
     }
 
+    /// Mark the network as a redundant ring, with every frame sent from two network interfaces.
+    #[cfg_attr(not(all(target_os = "linux", feature = "std")), allow(unused))]
+    pub(crate) fn set_redundant(&self) {
+        self.storage.redundant.store(true, Ordering::Relaxed);
+    }
+
     /// Get the next sendable frame, if any are available.
     // NOTE: Mutable so it can only be used in one task.
     pub fn next_sendable_frame(&mut self) -> Option<SendableFrame<'sto>> {
@@ -41,6 +41,8 @@ pub struct PduStorage<const N: usize, const DATA: usize> {
     frame_idx: AtomicU8,
     pdu_idx: AtomicU8,
     is_split: AtomicBool,
+    /// Set when every frame is sent around a redundant ring from two network interfaces.
+    redundant: AtomicBool,
     /// A waker used to wake up the TX task when a new frame is ready to be sent.
     pub(in crate::pdu_loop) tx_waker: AtomicWaker,
 }
@@ -117,6 +119,7 @@ impl<const N: usize, const DATA: usize> PduStorage<N, DATA> {
             frame_idx: AtomicU8::new(0),
             pdu_idx: AtomicU8::new(0),
             is_split: AtomicBool::new(false),
+            redundant: AtomicBool::new(false),
             tx_waker: AtomicWaker::new(),
         }
     }
@@ -155,6 +158,7 @@ impl<const N: usize, const DATA: usize> PduStorage<N, DATA> {
             frame_data_len: DATA,
             frame_idx: &self.frame_idx,
             pdu_idx: &self.pdu_idx,
+            redundant: &self.redundant,
             tx_waker: &self.tx_waker,
             _lifetime: PhantomData,
         }
@@ -170,6 +174,7 @@ pub(crate) struct PduStorageRef<'sto> {
     pub frame_data_len: usize,
     frame_idx: &'sto AtomicU8,
     pub pdu_idx: &'sto AtomicU8,
+    pub redundant: &'sto AtomicBool,
     pub tx_waker: &'sto AtomicWaker,
     _lifetime: PhantomData<&'sto ()>,
 }
@@ -40,6 +40,27 @@ pub enum RegisterAddress {
     /// Application Layer (AL) status code register.
     AlStatusCode = 0x0134,
 
+    /// RX error counters, `[u8; 8]`. An invalid frame counter followed by an RX error counter for
+    /// each port from 0 to 3.
+    ///
+    /// Writing to any of the counters up to and including
+    /// [`ProcessingUnitErrorCounter`](Self::ProcessingUnitErrorCounter) resets all of them.
+    RxErrorCounter = 0x0300,
+
+    /// Forwarded RX error counters for ports 0 to 3, `[u8; 4]`.
+    ForwardedRxErrorCounter = 0x0308,
+
+    /// EtherCAT processing unit error counter, `u8`.
+    ProcessingUnitErrorCounter = 0x030C,
+
+    /// PDI error counter, `u8`. Reset by writing to it.
+    PdiErrorCounter = 0x030D,
+
+    /// Lost link counters for ports 0 to 3, `[u8; 4]`.
+    ///
+    /// Writing to any of the counters resets all of them.
+    LostLinkCounter = 0x0310,
+
     /// Watchdog divider, `u16`.
     ///
     /// See ETG1000.4 section 6.3 Watchdogs.
@@ -291,8 +312,11 @@ pub enum PortType {
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[wire(bytes = 2)]
 pub struct SupportFlags {
+    /// Set if the SubDevice's FMMUs only support byte-oriented mappings.
+    ///
+    /// ETG1000.4 Table 31 – DL information, "FMMU bit operation not supported".
     #[wire(bits = 1)]
-    pub fmmu_supports_bit_ops: bool,
+    pub fmmu_bit_ops_not_supported: bool,
     #[wire(bits = 1)]
     pub reserved_register_support: bool,
     /// This parameter is set to 1 if at least distributed clock receive times are supported.
@@ -423,7 +447,7 @@ mod tests {
         pretty_assertions::assert_eq!(
             unpacked,
             SupportFlags {
-                fmmu_supports_bit_ops: false,
+                fmmu_bit_ops_not_supported: false,
                 reserved_register_support: false,
                 dc_supported: true,
                 has_64bit_dc: true,
@@ -448,7 +472,7 @@ mod tests {
         pretty_assertions::assert_eq!(
             unpacked,
             SupportFlags {
-                fmmu_supports_bit_ops: true,
+                fmmu_bit_ops_not_supported: true,
                 reserved_register_support: true,
                 dc_supported: true,
                 has_64bit_dc: false,
@@ -0,0 +1,598 @@
+//! A register level simulation of a network of SubDevices, used to test sequences that configure
+//! SubDevices without real hardware.
+//!
+//! Each simulated SubDevice has a flat register and process data memory, an AL state machine that
+//! changes state immediately unless told to refuse a transition and optionally answers Explicit
+//! Device ID requests, an SII EEPROM generated from the SubDevice's identity and process
+//! data sizes, and FMMUs that map logical addresses to process data memory. Mailboxes are not
+//! supported, and DC registers are plain memory that only SubDevices with `dc` set respond to.
+//!
+//! Every PDU sent by the MainDevice is logged so tests can check which commands were used and how
+//! they were split into frames.
+
+use crate::{MainDevice, MainDeviceConfig, PduRx, PduStorage, PduTx, Timeouts};
+use core::{
+    future::{poll_fn, Future},
+    ops::Range,
+    task::{Context, Poll},
+    time::Duration,
+};
+use futures_lite::future;
+use std::{collections::VecDeque, vec, vec::Vec};
+
+pub(crate) const APRD: u8 = 0x01;
+pub(crate) const APWR: u8 = 0x02;
+pub(crate) const APRW: u8 = 0x03;
+pub(crate) const FPRD: u8 = 0x04;
+pub(crate) const FPWR: u8 = 0x05;
+pub(crate) const FPRW: u8 = 0x06;
+pub(crate) const BRD: u8 = 0x07;
+pub(crate) const BWR: u8 = 0x08;
+pub(crate) const BRW: u8 = 0x09;
+pub(crate) const LRD: u8 = 0x0a;
+pub(crate) const LWR: u8 = 0x0b;
+pub(crate) const LRW: u8 = 0x0c;
+pub(crate) const ARMW: u8 = 0x0d;
+pub(crate) const FRMW: u8 = 0x0e;
+
+const STATION_ADDRESS: usize = 0x0010;
+const STATION_ALIAS: usize = 0x0012;
+const DL_STATUS: usize = 0x0110;
+const AL_CONTROL: usize = 0x0120;
+const AL_STATUS: usize = 0x0130;
+const AL_STATUS_CODE: usize = 0x0134;
+/// Error flag in AL status, and acknowledge flag in AL control.
+const AL_ERROR: u8 = 0x10;
+/// ID loaded flag in AL status, and ID request flag in AL control.
+const AL_ID: u8 = 0x20;
+const SII_CONTROL: usize = 0x0502;
+const SII_ADDRESS: usize = 0x0504;
+const SII_DATA: usize = 0x0508;
+const FMMU0: usize = 0x0600;
+const DC_START: usize = 0x0900;
+const DC_END: usize = 0x0a00;
+
+/// Registers that are read only from EtherCAT.
+const READ_ONLY: [core::ops::Range<usize>; 3] = [0x0000..0x0010, 0x0110..0x0112, 0x0130..0x0136];
+
+/// Start of the outputs sync manager buffer.
+const OUTPUTS_START: u16 = 0x1000;
+/// Start of the inputs sync manager buffer.
+const INPUTS_START: u16 = 0x1100;
+
+const MEMORY_LEN: usize = 0x1200;
+
+/// A simulated SubDevice.
+pub(crate) struct SimSubDevice {
+    pub(crate) vendor_id: u32,
+    pub(crate) product_id: u32,
+    pub(crate) serial: u32,
+    /// Station alias loaded from EEPROM at power on.
+    pub(crate) alias: u16,
+    /// Number of output bits mapped by the SubDevice's EEPROM PDOs.
+    pub(crate) output_bits: u8,
+    /// Number of input bits mapped by the SubDevice's EEPROM PDOs.
+    pub(crate) input_bits: u8,
+    /// When true, the SubDevice does not respond to AL state change requests.
+    pub(crate) stuck: bool,
+    /// Transitions to refuse, as the requested state and the AL status code to refuse it with.
+    ///
+    /// A request for the state at the front of the list sets the AL status error flag and code, and
+    /// removes it from the list. Further requests are ignored until the error is acknowledged.
+    pub(crate) refusals: VecDeque<(u8, u16)>,
+    /// When true, the SubDevice responds to DC registers.
+    pub(crate) dc: bool,
+    /// Explicit Device ID returned in the AL status code register when requested with the AL
+    /// control ID request flag, or `None` if the SubDevice does not support ID requests.
+    pub(crate) device_id: Option<u16>,
+    /// Every state requested by writing to the AL control register.
+    pub(crate) requested_states: Vec<u8>,
+    /// ESC registers and process data memory.
+    pub(crate) memory: Vec<u8>,
+}
+
+impl SimSubDevice {
+    pub(crate) fn new(product_id: u32, serial: u32) -> Self {
+        let mut subdevice = Self {
+            vendor_id: 0x0000_0002,
+            product_id,
+            serial,
+            alias: 0,
+            output_bits: 0,
+            input_bits: 0,
+            stuck: false,
+            refusals: VecDeque::new(),
+            dc: false,
+            device_id: None,
+            requested_states: Vec::new(),
+            memory: Vec::new(),
+        };
+
+        subdevice.power_cycle();
+
+        subdevice
+    }
+
+    pub(crate) fn alias(mut self, alias: u16) -> Self {
+        self.alias = alias;
+        self.power_cycle();
+
+        self
+    }
+
+    pub(crate) fn device_id(mut self, id: u16) -> Self {
+        self.device_id = Some(id);
+
+        self
+    }
+
+    pub(crate) fn outputs(mut self, bits: u8) -> Self {
+        self.output_bits = bits;
+
+        self
+    }
+
+    pub(crate) fn inputs(mut self, bits: u8) -> Self {
+        self.input_bits = bits;
+
+        self
+    }
+
+    /// Reset all registers and process data memory to their power on values.
+    pub(crate) fn power_cycle(&mut self) {
+        self.memory = vec![0u8; MEMORY_LEN];
+
+        // Type, FMMU and SM count
+        self.memory[0x0000] = 0x11;
+        self.memory[0x0004] = 8;
+        self.memory[0x0005] = 8;
+
+        self.memory[STATION_ALIAS..STATION_ALIAS + 2].copy_from_slice(&self.alias.to_le_bytes());
+
+        // INIT
+        self.memory[AL_STATUS] = 0x01;
+    }
+
+    pub(crate) fn station_address(&self) -> u16 {
+        self.register(STATION_ADDRESS)
+    }
+
+    /// Current AL state, without the error flag.
+    pub(crate) fn state(&self) -> u8 {
+        self.memory[AL_STATUS] & 0x0f
+    }
+
+    /// Whether the AL status error flag is set.
+    pub(crate) fn al_error(&self) -> bool {
+        self.memory[AL_STATUS] & AL_ERROR != 0
+    }
+
+    /// Set input process data as the SubDevice's application would.
+    pub(crate) fn set_inputs(&mut self, inputs: &[u8]) {
+        let start = usize::from(INPUTS_START);
+
+        self.memory[start..start + inputs.len()].copy_from_slice(inputs);
+    }
+
+    /// Output process data written by the MainDevice.
+    pub(crate) fn outputs_memory(&self) -> &[u8] {
+        let start = usize::from(OUTPUTS_START);
+
+        &self.memory[start..start + usize::from(self.output_bits).div_ceil(8)]
+    }
+
+    /// The logical address range of the first enabled FMMU that reads inputs (`kind` 0x01) or
+    /// writes outputs (`kind` 0x02).
+    pub(crate) fn fmmu(&self, kind: u8) -> Option<Range<u32>> {
+        (0..8).find_map(|fmmu| {
+            let config = &self.memory[FMMU0 + fmmu * 16..FMMU0 + fmmu * 16 + 16];
+
+            if config[12] & 0x01 == 0 || config[11] & kind == 0 {
+                return None;
+            }
+
+            let start = u32::from_le_bytes(config[0..4].try_into().unwrap());
+            let len = u32::from(u16::from_le_bytes([config[4], config[5]]));
+
+            Some(start..(start + len))
+        })
+    }
+
+    fn register(&self, address: usize) -> u16 {
+        u16::from_le_bytes([self.memory[address], self.memory[address + 1]])
+    }
+
+    /// Generate the SII EEPROM contents.
+    fn eeprom(&self) -> Vec<u8> {
+        // Fixed fields up to the first category at word 0x40
+        let mut eeprom = vec![0u8; 0x80];
+
+        eeprom[0x08..0x0a].copy_from_slice(&self.alias.to_le_bytes());
+        eeprom[0x10..0x14].copy_from_slice(&self.vendor_id.to_le_bytes());
+        eeprom[0x14..0x18].copy_from_slice(&self.product_id.to_le_bytes());
+        eeprom[0x1c..0x20].copy_from_slice(&self.serial.to_le_bytes());
+
+        let mut category = |ty: u16, data: &[u8]| {
+            eeprom.extend_from_slice(&ty.to_le_bytes());
+            eeprom.extend_from_slice(&(data.len() as u16 / 2).to_le_bytes());
+            eeprom.extend_from_slice(data);
+        };
+
+        // Names of the input and output PDO entries
+        category(10, b"\x02\x05Input\x06Output");
+
+        // No CoE, FoE or EoE
+        let mut general = [0u8; 32];
+
+        // Explicit Device ID is read with an AL control ID request
+        if self.device_id.is_some() {
+            general[11] = 0x08;
+        }
+
+        category(30, &general);
+
+        // FMMU0 for outputs, FMMU1 for inputs
+        category(40, &[0x01, 0x02]);
+
+        let output_len = u16::from(self.output_bits).div_ceil(8);
+        let input_len = u16::from(self.input_bits).div_ceil(8);
+
+        let mut sms = Vec::new();
+
+        for (start, len, control, ty) in [
+            (OUTPUTS_START, output_len, 0x64u8, 3u8),
+            (INPUTS_START, input_len, 0x20, 4),
+        ] {
+            sms.extend_from_slice(&start.to_le_bytes());
+            sms.extend_from_slice(&len.to_le_bytes());
+            sms.extend_from_slice(&[control, 0x00, u8::from(len > 0), ty]);
+        }
+
+        category(41, &sms);
+
+        for (ty, bits, pdo_index, entry_index, sm, name) in [
+            (50u16, self.input_bits, 0x1a00u16, 0x6000u16, 1u8, 1u8),
+            (51, self.output_bits, 0x1600, 0x7000, 0, 2),
+        ] {
+            if bits == 0 {
+                continue;
+            }
+
+            let mut pdo = Vec::new();
+
+            pdo.extend_from_slice(&pdo_index.to_le_bytes());
+            pdo.extend_from_slice(&[1, sm, 0, 0, 0, 0]);
+            pdo.extend_from_slice(&entry_index.to_le_bytes());
+            pdo.extend_from_slice(&[1, name, 0x1e, bits, 0, 0]);
+
+            category(ty, &pdo);
+        }
+
+        eeprom.extend_from_slice(&[0xff, 0xff]);
+
+        eeprom
+    }
+
+    fn write(&mut self, address: usize, data: &[u8]) {
+        for (address, byte) in (address..).zip(data) {
+            if !READ_ONLY.iter().any(|range| range.contains(&address)) {
+                self.memory[address] = *byte;
+            }
+        }
+
+        let written = address..(address + data.len());
+
+        if written.contains(&AL_CONTROL) {
+            let requested = self.memory[AL_CONTROL] & 0x0f;
+            let acknowledge = self.memory[AL_CONTROL] & AL_ERROR != 0;
+            let id_request = self.memory[AL_CONTROL] & AL_ID != 0;
+
+            self.requested_states.push(requested);
+
+            if acknowledge {
+                self.memory[AL_STATUS] &= !AL_ERROR;
+                self.memory[AL_STATUS_CODE..AL_STATUS_CODE + 2].fill(0);
+            }
+
+            let refused = self
+                .refusals
+                .front()
+                .is_some_and(|(state, _code)| *state == requested);
+
+            if self.memory[AL_STATUS] & AL_ERROR != 0 || self.stuck {
+                // Requests are ignored until the error is acknowledged
+            } else if refused {
+                let (_state, code) = self.refusals.pop_front().expect("refusal");
+
+                self.memory[AL_STATUS] |= AL_ERROR;
+                self.memory[AL_STATUS_CODE..AL_STATUS_CODE + 2]
+                    .copy_from_slice(&code.to_le_bytes());
+            } else if let Some(id) = self.device_id.filter(|_| id_request) {
+                // The ID replaces the AL status code until the request is cleared
+                self.memory[AL_STATUS] = requested | AL_ID;
+                self.memory[AL_STATUS_CODE..AL_STATUS_CODE + 2].copy_from_slice(&id.to_le_bytes());
+            } else {
+                self.memory[AL_STATUS] = requested;
+                self.memory[AL_STATUS_CODE..AL_STATUS_CODE + 2].fill(0);
+            }
+        }
+
+        if written.contains(&(SII_CONTROL + 1)) && self.memory[SII_CONTROL + 1] & 0x01 != 0 {
+            let word = usize::from(self.register(SII_ADDRESS));
+            let eeprom = self.eeprom();
+
+            for (i, byte) in self.memory[SII_DATA..SII_DATA + 8].iter_mut().enumerate() {
+                *byte = eeprom.get(word * 2 + i).copied().unwrap_or(0xff);
+            }
+
+            // 8 byte reads, not busy
+            self.memory[SII_CONTROL] |= 0x40;
+            self.memory[SII_CONTROL + 1] = 0x00;
+        }
+    }
+
+    /// Apply a logical command to this SubDevice's FMMUs, returning whether any were read from and
+    /// written to.
+    fn logical(&mut self, command: u8, address: u32, data: &mut [u8]) -> (bool, bool) {
+        let mut read = false;
+        let mut written = false;
+
+        // Write outputs first so overlapping inputs replace them in the frame
+        for kind in [0x02, 0x01] {
+            for fmmu in 0..8 {
+                let base = FMMU0 + fmmu * 16;
+                let config = &self.memory[base..base + 16];
+
+                if config[12] & 0x01 == 0 || config[11] & kind == 0 {
+                    continue;
+                }
+
+                let logical_start = u32::from_le_bytes(config[0..4].try_into().unwrap());
+                let len = u32::from(u16::from_le_bytes([config[4], config[5]]));
+                let physical_start = usize::from(u16::from_le_bytes([config[8], config[9]]));
+
+                if len == 0 {
+                    continue;
+                }
+
+                // Bit-oriented mappings start and end part way through a byte
+                let start_bit = u64::from(logical_start) * 8 + u64::from(config[6]);
+                let end_bit = u64::from(logical_start + len - 1) * 8 + u64::from(config[7]) + 1;
+                let physical_start_bit = physical_start as u64 * 8 + u64::from(config[10]);
+
+                let frame_start_bit = u64::from(address) * 8;
+                let frame_end_bit = frame_start_bit + data.len() as u64 * 8;
+
+                for logical in start_bit.max(frame_start_bit)..end_bit.min(frame_end_bit) {
+                    let frame_bit = logical - frame_start_bit;
+                    let physical = physical_start_bit + (logical - start_bit);
+
+                    let byte = &mut data[(frame_bit / 8) as usize];
+                    let frame_mask = 1u8 << (frame_bit % 8);
+                    let memory = &mut self.memory[(physical / 8) as usize];
+                    let memory_mask = 1u8 << (physical % 8);
+
+                    match (kind, command) {
+                        (0x02, LWR | LRW) => {
+                            if *byte & frame_mask != 0 {
+                                *memory |= memory_mask;
+                            } else {
+                                *memory &= !memory_mask;
+                            }
+
+                            written = true;
+                        }
+                        (0x01, LRD | LRW) => {
+                            if *memory & memory_mask != 0 {
+                                *byte |= frame_mask;
+                            } else {
+                                *byte &= !frame_mask;
+                            }
+
+                            read = true;
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        }
+
+        (read, written)
+    }
+}
+
+/// A PDU received by the simulated network.
+#[derive(Debug, Clone)]
+pub(crate) struct SimPdu {
+    pub(crate) command: u8,
+    /// Logical address, or ADP in the lower and ADO in the upper 16 bits.
+    pub(crate) address: u32,
+    /// Data as sent by the MainDevice.
+    pub(crate) data: Vec<u8>,
+    /// The number of the frame the PDU was sent in, counted from the last
+    /// [`clear_log`](SimNetwork::clear_log).
+    pub(crate) frame: usize,
+}
+
+/// A chain of simulated SubDevices connected to a MainDevice's PDU loop.
+pub(crate) struct SimNetwork<'sto> {
+    tx: PduTx<'sto>,
+    rx: PduRx<'sto>,
+    /// SubDevices in network order. SubDevices can be added or removed to simulate them being
+    /// connected or disconnected.
+    pub(crate) subdevices: Vec<SimSubDevice>,
+    /// Every PDU sent by the MainDevice, in order.
+    pub(crate) pdus: Vec<SimPdu>,
+    /// The number of frames sent by the MainDevice.
+    pub(crate) frames: usize,
+}
+
+impl<'sto> SimNetwork<'sto> {
+    /// Create a MainDevice connected to a network of `subdevices`.
+    pub(crate) fn new<const MAX_FRAMES: usize, const MAX_PDU_DATA: usize>(
+        storage: &'sto PduStorage<MAX_FRAMES, MAX_PDU_DATA>,
+        subdevices: Vec<SimSubDevice>,
+    ) -> (MainDevice<'sto>, Self) {
+        let (tx, rx, pdu_loop) = storage.try_split().expect("split");
+
+        // Simulated SubDevices respond immediately, so only time out SubDevices that are stuck
+        let timeouts = Timeouts {
+            state_transition: Duration::from_millis(50),
+            ..Timeouts::default()
+        };
+
+        let maindevice = MainDevice::new(pdu_loop, timeouts, MainDeviceConfig::default());
+
+        (
+            maindevice,
+            Self {
+                tx,
+                rx,
+                subdevices,
+                pdus: Vec::new(),
+                frames: 0,
+            },
+        )
+    }
+
+    /// Forget all PDUs and frames sent so far.
+    pub(crate) fn clear_log(&mut self) {
+        self.pdus.clear();
+        self.frames = 0;
+    }
+
+    /// The logical address ranges of every PDU with the given command, in order.
+    pub(crate) fn ranges(&self, command: u8) -> Vec<Range<u32>> {
+        self.pdus
+            .iter()
+            .filter(|pdu| pdu.command == command)
+            .map(|pdu| pdu.address..(pdu.address + pdu.data.len() as u32))
+            .collect()
+    }
+
+    /// Run `fut` to completion, responding to every frame it sends.
+    pub(crate) fn run<T>(&mut self, fut: impl Future<Output = T>) -> T {
+        future::block_on(future::or(fut, poll_fn(|cx| self.poll(cx))))
+    }
+
+    fn poll<T>(&mut self, cx: &mut Context<'_>) -> Poll<T> {
+        self.tx.replace_waker(cx.waker());
+
+        while let Some(frame) = self.tx.next_sendable_frame() {
+            let mut response = Vec::new();
+
+            frame
+                .send_blocking(|bytes| {
+                    response = bytes.to_vec();
+
+                    Ok(bytes.len())
+                })
+                .expect("send");
+
+            self.respond(&mut response);
+
+            self.rx.receive_frame(&response).expect("receive");
+        }
+
+        Poll::Pending
+    }
+
+    fn respond(&mut self, frame: &mut [u8]) {
+        // Source address as modified by the first SubDevice
+        frame[6] = 0x12;
+
+        // Skip Ethernet and EtherCAT frame headers
+        let mut pos = 16;
+
+        loop {
+            let command = frame[pos];
+            let adp = u16::from_le_bytes([frame[pos + 2], frame[pos + 3]]);
+            let ado = usize::from(u16::from_le_bytes([frame[pos + 4], frame[pos + 5]]));
+            let flags = u16::from_le_bytes([frame[pos + 6], frame[pos + 7]]);
+            let len = usize::from(flags & 0x07ff);
+            let data = pos + 10;
+
+            let address = u32::from_le_bytes(frame[pos + 2..pos + 6].try_into().unwrap());
+
+            let mut wkc = u16::from_le_bytes([frame[data + len], frame[data + len + 1]]);
+
+            self.pdus.push(SimPdu {
+                command,
+                address,
+                data: frame[data..data + len].to_vec(),
+                frame: self.frames,
+            });
+
+            let last = self.subdevices.len().saturating_sub(1);
+
+            for (position, subdevice) in self.subdevices.iter_mut().enumerate() {
+                // Link on port 0, and port 1 if another SubDevice follows
+                subdevice.memory[DL_STATUS] = if position < last { 0x30 } else { 0x10 };
+
+                let payload = &mut frame[data..data + len];
+
+                if matches!(command, LRD | LWR | LRW) {
+                    let (read, written) = subdevice.logical(command, address, payload);
+
+                    wkc += u16::from(read);
+
+                    if written {
+                        wkc += if command == LRW { 2 } else { 1 };
+                    }
+
+                    continue;
+                }
+
+                // DC registers are only implemented as plain memory for DC SubDevices
+                if (DC_START..DC_END).contains(&ado) && !subdevice.dc {
+                    continue;
+                }
+
+                let addressed = match command {
+                    APRD | APWR | APRW | ARMW => 0u16.wrapping_sub(adp) == position as u16,
+                    FPRD | FPWR | FPRW | FRMW => subdevice.station_address() == adp,
+                    _ => true,
+                };
+
+                let (read, write) = match command {
+                    APRD | FPRD | BRD => (addressed, false),
+                    APWR | FPWR | BWR => (false, addressed),
+                    APRW | FPRW | BRW => (addressed, addressed),
+                    ARMW | FRMW => (addressed, !addressed),
+                    _ => (false, false),
+                };
+
+                let previous = subdevice.memory[ado..ado + len].to_vec();
+
+                if write {
+                    subdevice.write(ado, payload);
+
+                    wkc += if read { 2 } else { 1 };
+                }
+
+                if read {
+                    for (byte, value) in payload.iter_mut().zip(previous) {
+                        if command == BRD {
+                            *byte |= value;
+                        } else {
+                            *byte = value;
+                        }
+                    }
+
+                    wkc += 1;
+                }
+            }
+
+            frame[data + len..data + len + 2].copy_from_slice(&wkc.to_le_bytes());
+
+            // More PDUs follow flag
+            if flags & 0x8000 == 0 {
+                break;
+            }
+
+            pos = data + len + 2;
+        }
+
+        self.frames += 1;
+    }
+}
@@ -2,6 +2,8 @@
 
 #[cfg(target_os = "linux")]
 mod io_uring;
+#[cfg(target_os = "linux")]
+mod redundancy;
 #[cfg(unix)]
 mod unix;
 #[cfg(target_os = "windows")]
@@ -14,3 +16,8 @@ pub use unix::{ethercat_now, tx_rx_task};
 // io_uring is Linux-only
 #[cfg(target_os = "linux")]
 pub use io_uring::tx_rx_task_io_uring;
+// Redundancy is Linux-only
+#[cfg(target_os = "linux")]
+pub use redundancy::{LineState, RedundancyStatus};
+#[cfg(target_os = "linux")]
+pub use unix::tx_rx_task_redundant;
@@ -0,0 +1,765 @@
+//! Cable redundancy: send every frame out of two network interfaces and merge the returned copies.
+//!
+//! The primary interface is connected to the first SubDevice as usual, and the secondary
+//! interface to the last port of the last SubDevice, closing the line into a ring.
+//!
+//! Each frame is sent from both interfaces, with the secondary copy marked with
+//! [`SECONDARY_ADDR`] as its source address. SubDevices only process frames travelling in the
+//! forward direction, so:
+//!
+//! - With the ring intact, the primary copy is processed by every SubDevice and is received on
+//!   the secondary interface, and the secondary copy passes through the network unprocessed and
+//!   is received on the primary interface.
+//! - With the ring broken, the primary copy is processed by every SubDevice before the break and
+//!   is received back on the primary interface. The secondary copy is processed by every
+//!   SubDevice after the break and is received back on the secondary interface.
+//!
+//! In both cases, merging the data the two copies changed and summing their working counters gives
+//! the same result as a single frame passing through an intact line.
+
+use crate::{
+    ethernet::{EthernetAddress, EthernetFrame},
+    fmt, MASTER_ADDR,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Source address used for frames sent from the secondary interface.
+pub(crate) const SECONDARY_ADDR: EthernetAddress =
+    EthernetAddress([0x10, 0x10, 0x10, 0x10, 0x10, 0x11]);
+
+/// How long to wait for the second copy of a frame once it has been sent, before the copies
+/// received so far are used on their own.
+///
+/// A copy is lost when a cable between the MainDevice and the network is broken.
+pub(crate) const COPY_TIMEOUT: Duration = Duration::from_millis(2);
+
+/// Offset of the first PDU in an Ethernet frame, after the Ethernet and EtherCAT frame headers.
+const FIRST_PDU: usize = 14 + 2;
+
+/// Length of a PDU header.
+const PDU_HEADER_LEN: usize = 10;
+
+const APRD: u8 = 0x01;
+const APWR: u8 = 0x02;
+const APRW: u8 = 0x03;
+const BRD: u8 = 0x07;
+const BWR: u8 = 0x08;
+const ARMW: u8 = 0x0d;
+const FRMW: u8 = 0x0e;
+
+/// The state of a redundant line.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LineState {
+    /// No frames have been received yet.
+    Unknown,
+    /// Frames sent from the primary interface are received on the secondary interface.
+    Intact,
+    /// The line is broken, or one of the interfaces has lost its link.
+    ///
+    /// The number of SubDevices reachable from each interface is counted from the working
+    /// counter of the last broadcast PDU, e.g. a `BRD` sent by
+    /// [`MainDevice::wait_for_state`](crate::MainDevice::wait_for_state). The break is after
+    /// `primary_subdevices` SubDevices counted from the primary interface. Counts are `None` until
+    /// a broadcast PDU has been sent since the line broke.
+    Broken {
+        /// The number of SubDevices reachable from the primary interface.
+        primary_subdevices: Option<u16>,
+        /// The number of SubDevices reachable from the secondary interface.
+        secondary_subdevices: Option<u16>,
+    },
+}
+
+/// A handle to the line state of a redundant network, returned by
+/// [`tx_rx_task_redundant`](crate::std::tx_rx_task_redundant).
+#[derive(Debug, Clone)]
+pub struct RedundancyStatus {
+    state: Arc<Mutex<LineState>>,
+}
+
+impl RedundancyStatus {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(LineState::Unknown)),
+        }
+    }
+
+    /// The current state of the line.
+    pub fn line_state(&self) -> LineState {
+        *self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn update(&self, new: LineState) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        let new = match (*state, new) {
+            // Keep the last known break position until a new broadcast PDU is seen
+            (
+                LineState::Broken {
+                    primary_subdevices,
+                    secondary_subdevices,
+                },
+                LineState::Broken {
+                    primary_subdevices: None,
+                    secondary_subdevices: None,
+                },
+            ) => LineState::Broken {
+                primary_subdevices,
+                secondary_subdevices,
+            },
+            (_, new) => new,
+        };
+
+        if *state != new {
+            match new {
+                LineState::Broken { .. } => fmt::warn!("Redundant line state {:?}", new),
+                _ => fmt::info!("Redundant line state {:?}", new),
+            }
+        }
+
+        *state = new;
+    }
+}
+
+/// One of the two network interfaces of a redundant line.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Interface {
+    Primary,
+    Secondary,
+}
+
+/// Something the TX/RX task must do as a result of sending or receiving a frame.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Action {
+    /// Send an Ethernet frame out of the given interface.
+    Send(Interface, Vec<u8>),
+    /// Pass the merged EtherCAT payload of a frame to the PDU loop.
+    Receive(Vec<u8>),
+}
+
+#[derive(Debug)]
+struct Pending {
+    /// The frame as it was sent.
+    sent: Vec<u8>,
+    /// The primary copy and the interface it was received on.
+    primary: Option<(Interface, Vec<u8>)>,
+    /// The secondary copy and the interface it was received on.
+    secondary: Option<(Interface, Vec<u8>)>,
+    /// The secondary copy will be sent once the primary copy is received.
+    deferred: bool,
+    /// The secondary copy was sent with the data of the received primary copy.
+    relayed: bool,
+    deadline: Instant,
+}
+
+/// Merges the two copies of every frame sent on a redundant line.
+#[derive(Debug)]
+pub(crate) struct FrameMerger {
+    /// Frames waiting for their copies, keyed by the index of their first PDU.
+    pending: HashMap<u8, Pending>,
+    /// Whether the line was broken when the last frame completed.
+    broken: bool,
+    status: RedundancyStatus,
+}
+
+impl FrameMerger {
+    pub fn new(status: RedundancyStatus) -> Self {
+        Self {
+            pending: HashMap::new(),
+            broken: false,
+            status,
+        }
+    }
+
+    /// The earliest time a pending frame times out.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pending.values().map(|pending| pending.deadline).min()
+    }
+
+    /// Prepare a frame to be sent, returning the copies to send.
+    ///
+    /// When the line is broken, frames containing `ARMW` or `FRMW` PDUs are sent from the
+    /// secondary interface only once the primary copy has been received, with the data read by
+    /// the primary copy. This distributes e.g. the DC reference time to the SubDevices on both
+    /// sides of the break, instead of writing stale data to the SubDevices after it.
+    ///
+    /// Frames containing `APRD`, `APWR` or `APRW` PDUs are deferred in the same way. The primary
+    /// copy returns with its position incremented by every SubDevice before the break, so the
+    /// relayed secondary copy addresses the same SubDevice instead of one counted from the break.
+    pub fn send(&mut self, frame: &[u8], now: Instant) -> Vec<Action> {
+        let Some(index) = first_pdu_index(frame) else {
+            return vec![Action::Send(Interface::Primary, frame.to_vec())];
+        };
+
+        let deferred = self.broken
+            && pdus(frame).any(|pdu| matches!(pdu.command, APRD | APWR | APRW | ARMW | FRMW));
+
+        if self
+            .pending
+            .insert(
+                index,
+                Pending {
+                    sent: frame.to_vec(),
+                    primary: None,
+                    secondary: None,
+                    deferred,
+                    relayed: false,
+                    deadline: now + COPY_TIMEOUT,
+                },
+            )
+            .is_some()
+        {
+            fmt::warn!("Frame with first PDU {:#04x} was never completed", index);
+        }
+
+        let mut actions = vec![Action::Send(Interface::Primary, frame.to_vec())];
+
+        if !deferred {
+            actions.push(Action::Send(Interface::Secondary, secondary_copy(frame)));
+        }
+
+        actions
+    }
+
+    /// Process a frame received on the given interface.
+    pub fn receive(&mut self, interface: Interface, frame: &[u8], now: Instant) -> Vec<Action> {
+        let Ok(ethernet) = EthernetFrame::new_checked(frame) else {
+            return Vec::new();
+        };
+
+        let source = ethernet.src_addr();
+
+        // Ignore our own frames echoed back by the raw socket
+        match interface {
+            Interface::Primary if source == MASTER_ADDR => return Vec::new(),
+            Interface::Secondary if source == SECONDARY_ADDR => return Vec::new(),
+            _ => (),
+        }
+
+        let Some(index) = first_pdu_index(frame) else {
+            return Vec::new();
+        };
+
+        let Some(pending) = self.pending.get_mut(&index) else {
+            fmt::trace!("Ignoring late copy of frame with first PDU {:#04x}", index);
+
+            return Vec::new();
+        };
+
+        // The first SubDevice sets the U/L bit of the source address, so mask it out
+        let mut origin = source;
+        origin.0[0] &= !0x02;
+
+        if origin == SECONDARY_ADDR {
+            pending.secondary = Some((interface, frame.to_vec()));
+        } else {
+            pending.primary = Some((interface, frame.to_vec()));
+
+            if pending.deferred {
+                pending.deferred = false;
+                pending.relayed = true;
+                pending.deadline = now + COPY_TIMEOUT;
+
+                // Send the secondary copy on with the data read by the primary copy
+                return vec![Action::Send(Interface::Secondary, secondary_copy(frame))];
+            }
+        }
+
+        if pending.primary.is_some() && pending.secondary.is_some() {
+            return self.complete(index).into_iter().collect();
+        }
+
+        Vec::new()
+    }
+
+    /// Complete frames that have not received both copies in time.
+    pub fn timeout(&mut self, now: Instant) -> Vec<Action> {
+        let expired = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| pending.deadline <= now)
+            .map(|(index, _)| *index)
+            .collect::<Vec<_>>();
+
+        let mut actions = Vec::new();
+
+        for index in expired {
+            let Some(pending) = self.pending.get_mut(&index) else {
+                continue;
+            };
+
+            // The primary copy was lost, so send the secondary copy as it is
+            if pending.deferred {
+                pending.deferred = false;
+                pending.deadline = now + COPY_TIMEOUT;
+
+                actions.push(Action::Send(
+                    Interface::Secondary,
+                    secondary_copy(&pending.sent),
+                ));
+
+                continue;
+            }
+
+            fmt::trace!("Frame with first PDU {:#04x} timed out", index);
+
+            actions.extend(self.complete(index));
+        }
+
+        actions
+    }
+
+    /// Merge the received copies of a frame, update the line state and return the merged payload.
+    fn complete(&mut self, index: u8) -> Option<Action> {
+        let pending = self.pending.remove(&index)?;
+
+        let (state, merged) = match (pending.primary, pending.secondary) {
+            (None, None) => return None,
+            // The primary copy travelled the whole line
+            (Some((Interface::Secondary, primary)), secondary) => {
+                let merged = match secondary {
+                    Some((_, secondary)) => merge(&pending.sent, &primary, &secondary),
+                    None => primary,
+                };
+
+                (LineState::Intact, merged)
+            }
+            // The line is broken, or the primary interface has lost its link
+            (Some((_, primary)), Some((_, secondary))) => {
+                let merged = if pending.relayed {
+                    secondary
+                } else {
+                    merge(&pending.sent, &primary, &secondary)
+                };
+
+                let state = broken_state(&pending.sent, Some(&primary), Some(&merged));
+
+                (state, merged)
+            }
+            (Some((_, primary)), None) => {
+                let state = broken_state(&pending.sent, Some(&primary), None);
+
+                (state, primary)
+            }
+            (None, Some((_, secondary))) => {
+                let state = broken_state(&pending.sent, None, Some(&secondary));
+
+                (state, secondary)
+            }
+        };
+
+        self.broken = matches!(state, LineState::Broken { .. });
+        self.status.update(state);
+
+        Some(Action::Receive(merged.get(14..)?.to_vec()))
+    }
+}
+
+/// The broken line state, counting reachable SubDevices from the first broadcast PDU in the frame.
+///
+/// `total` is the frame that has been processed by every reachable SubDevice.
+fn broken_state(sent: &[u8], primary: Option<&[u8]>, total: Option<&[u8]>) -> LineState {
+    let Some(position) = pdus(sent).position(|pdu| matches!(pdu.command, BRD | BWR)) else {
+        return LineState::Broken {
+            primary_subdevices: None,
+            secondary_subdevices: None,
+        };
+    };
+
+    let wkc = |frame: Option<&[u8]>| {
+        frame
+            .and_then(|frame| pdus(frame).nth(position))
+            .map_or(0, |pdu| pdu.wkc)
+    };
+
+    let primary_subdevices = wkc(primary);
+    let total = wkc(total).max(primary_subdevices);
+
+    LineState::Broken {
+        primary_subdevices: Some(primary_subdevices),
+        secondary_subdevices: Some(total - primary_subdevices),
+    }
+}
+
+/// Merge the two returned copies of a frame.
+///
+/// Each bit changed by either copy is taken from that copy, and the working counters of each PDU
+/// are summed.
+fn merge(sent: &[u8], primary: &[u8], secondary: &[u8]) -> Vec<u8> {
+    let mut merged = sent.to_vec();
+
+    for (i, byte) in merged.iter_mut().enumerate().skip(14) {
+        let (Some(p), Some(s)) = (primary.get(i), secondary.get(i)) else {
+            break;
+        };
+
+        *byte ^= (p ^ *byte) | (s ^ *byte);
+    }
+
+    for (p, s) in pdus(primary).zip(pdus(secondary)) {
+        if let Some(wkc) = merged.get_mut(p.wkc_offset..p.wkc_offset + 2) {
+            wkc.copy_from_slice(&p.wkc.wrapping_add(s.wkc).to_le_bytes());
+        }
+    }
+
+    merged
+}
+
+/// A copy of `frame` to send from the secondary interface.
+fn secondary_copy(frame: &[u8]) -> Vec<u8> {
+    let mut copy = frame.to_vec();
+
+    if let Ok(mut ethernet) = EthernetFrame::new_checked(copy.as_mut_slice()) {
+        ethernet.set_src_addr(SECONDARY_ADDR);
+    }
+
+    copy
+}
+
+fn first_pdu_index(frame: &[u8]) -> Option<u8> {
+    frame.get(FIRST_PDU + 1).copied()
+}
+
+struct Pdu {
+    command: u8,
+    wkc_offset: usize,
+    wkc: u16,
+}
+
+/// Iterate over the PDUs in an Ethernet frame.
+fn pdus(frame: &[u8]) -> impl Iterator<Item = Pdu> + '_ {
+    let mut offset = Some(FIRST_PDU);
+
+    core::iter::from_fn(move || {
+        let start = offset.take()?;
+
+        let header = frame.get(start..start + PDU_HEADER_LEN)?;
+
+        let command = header[0];
+        let flags = u16::from_le_bytes([header[6], header[7]]);
+        let len = usize::from(flags & 0x07ff);
+        let more_follows = flags & 0x8000 != 0;
+
+        let wkc_offset = start + PDU_HEADER_LEN + len;
+        let wkc = frame.get(wkc_offset..wkc_offset + 2)?;
+
+        if more_follows {
+            offset = Some(wkc_offset + 2);
+        }
+
+        Some(Pdu {
+            command,
+            wkc_offset,
+            wkc: u16::from_le_bytes([wkc[0], wkc[1]]),
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build an Ethernet frame with one PDU per `(command, index, data, wkc)`.
+    fn frame(source: EthernetAddress, pdus: &[(u8, u8, &[u8], u16)]) -> Vec<u8> {
+        let mut frame = vec![0xff; 6];
+
+        frame.extend_from_slice(&source.0);
+        frame.extend_from_slice(&0x88a4u16.to_be_bytes());
+
+        let payload_len: usize = pdus.iter().map(|(_, _, data, _)| 12 + data.len()).sum();
+
+        frame.extend_from_slice(&(payload_len as u16 | 0x1000).to_le_bytes());
+
+        for (i, (command, index, data, wkc)) in pdus.iter().enumerate() {
+            let more_follows = if i + 1 < pdus.len() { 0x8000 } else { 0 };
+
+            frame.extend_from_slice(&[*command, *index, 0, 0, 0, 0]);
+            frame.extend_from_slice(&(data.len() as u16 | more_follows).to_le_bytes());
+            frame.extend_from_slice(&[0, 0]);
+            frame.extend_from_slice(data);
+            frame.extend_from_slice(&wkc.to_le_bytes());
+        }
+
+        frame
+    }
+
+    /// Set the ADP of the first PDU in `frame`.
+    fn with_adp(mut frame: Vec<u8>, adp: u16) -> Vec<u8> {
+        frame[FIRST_PDU + 2..FIRST_PDU + 4].copy_from_slice(&adp.to_le_bytes());
+
+        frame
+    }
+
+    const PROCESSED: EthernetAddress = EthernetAddress([0x12, 0x10, 0x10, 0x10, 0x10, 0x10]);
+    const PROCESSED_SECONDARY: EthernetAddress =
+        EthernetAddress([0x12, 0x10, 0x10, 0x10, 0x10, 0x11]);
+
+    fn received(actions: Vec<Action>) -> Option<Vec<u8>> {
+        actions.into_iter().find_map(|action| match action {
+            Action::Receive(payload) => Some(payload),
+            Action::Send(..) => None,
+        })
+    }
+
+    #[test]
+    fn sends_both_copies() {
+        let mut merger = FrameMerger::new(RedundancyStatus::new());
+
+        let sent = frame(MASTER_ADDR, &[(BRD, 0x01, &[0, 0], 0)]);
+
+        let actions = merger.send(&sent, Instant::now());
+
+        assert_eq!(
+            actions,
+            vec![
+                Action::Send(Interface::Primary, sent.clone()),
+                Action::Send(
+                    Interface::Secondary,
+                    frame(SECONDARY_ADDR, &[(BRD, 0x01, &[0, 0], 0)])
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn intact() {
+        let status = RedundancyStatus::new();
+        let mut merger = FrameMerger::new(status.clone());
+        let now = Instant::now();
+
+        let sent = frame(MASTER_ADDR, &[(BRD, 0x01, &[0, 0], 0)]);
+
+        merger.send(&sent, now);
+
+        // Own echo is ignored
+        assert_eq!(merger.receive(Interface::Primary, &sent, now), vec![]);
+
+        // Unprocessed secondary copy arrives on the primary interface
+        assert_eq!(
+            merger.receive(
+                Interface::Primary,
+                &frame(SECONDARY_ADDR, &[(BRD, 0x01, &[0, 0], 0)]),
+                now
+            ),
+            vec![]
+        );
+
+        let processed = frame(PROCESSED, &[(BRD, 0x01, &[0x08, 0], 5)]);
+
+        let payload = received(merger.receive(Interface::Secondary, &processed, now));
+
+        assert_eq!(payload.as_deref(), processed.get(14..));
+        assert_eq!(status.line_state(), LineState::Intact);
+    }
+
+    #[test]
+    fn broken() {
+        let status = RedundancyStatus::new();
+        let mut merger = FrameMerger::new(status.clone());
+        let now = Instant::now();
+
+        let sent = frame(
+            MASTER_ADDR,
+            &[
+                (BRD, 0x01, &[0, 0], 0),
+                (0x0c, 0x02, &[0xaa, 0x00, 0x00], 0),
+            ],
+        );
+
+        merger.send(&sent, now);
+
+        // First 2 SubDevices set bits in the BRD, and the first byte of the LRW is read
+        let primary = frame(
+            PROCESSED,
+            &[
+                (BRD, 0x01, &[0x01, 0], 2),
+                (0x0c, 0x02, &[0x12, 0x00, 0x00], 3),
+            ],
+        );
+        // Last 3 SubDevices
+        let secondary = frame(
+            PROCESSED_SECONDARY,
+            &[
+                (BRD, 0x01, &[0x02, 0], 3),
+                (0x0c, 0x02, &[0xaa, 0x34, 0x56], 2),
+            ],
+        );
+
+        assert_eq!(merger.receive(Interface::Primary, &primary, now), vec![]);
+
+        let payload = received(merger.receive(Interface::Secondary, &secondary, now));
+
+        let expected = frame(
+            MASTER_ADDR,
+            &[
+                (BRD, 0x01, &[0x03, 0], 5),
+                (0x0c, 0x02, &[0x12, 0x34, 0x56], 5),
+            ],
+        );
+
+        assert_eq!(payload.as_deref(), expected.get(14..));
+        assert_eq!(
+            status.line_state(),
+            LineState::Broken {
+                primary_subdevices: Some(2),
+                secondary_subdevices: Some(3)
+            }
+        );
+    }
+
+    /// Complete a frame with one SubDevice on either side of a break in the line.
+    fn break_line(merger: &mut FrameMerger, now: Instant) {
+        merger.send(&frame(MASTER_ADDR, &[(BRD, 0x01, &[0], 0)]), now);
+        merger.receive(
+            Interface::Primary,
+            &frame(PROCESSED, &[(BRD, 0x01, &[0], 1)]),
+            now,
+        );
+        merger.receive(
+            Interface::Secondary,
+            &frame(PROCESSED_SECONDARY, &[(BRD, 0x01, &[0], 1)]),
+            now,
+        );
+    }
+
+    #[test]
+    fn broken_defers_frmw() {
+        let status = RedundancyStatus::new();
+        let mut merger = FrameMerger::new(status.clone());
+        let now = Instant::now();
+
+        break_line(&mut merger, now);
+
+        let sent = frame(MASTER_ADDR, &[(FRMW, 0x02, &[0, 0], 0)]);
+
+        // Only the primary copy is sent
+        assert_eq!(
+            merger.send(&sent, now),
+            vec![Action::Send(Interface::Primary, sent.clone())]
+        );
+
+        // Reference is before the break
+        let primary = frame(PROCESSED, &[(FRMW, 0x02, &[0x12, 0x34], 1)]);
+
+        assert_eq!(
+            merger.receive(Interface::Primary, &primary, now),
+            vec![Action::Send(
+                Interface::Secondary,
+                frame(SECONDARY_ADDR, &[(FRMW, 0x02, &[0x12, 0x34], 1)])
+            )]
+        );
+
+        let secondary = frame(PROCESSED_SECONDARY, &[(FRMW, 0x02, &[0x12, 0x34], 1)]);
+
+        let payload = received(merger.receive(Interface::Secondary, &secondary, now));
+
+        assert_eq!(payload.as_deref(), secondary.get(14..));
+    }
+
+    #[test]
+    fn broken_defers_apwr() {
+        let status = RedundancyStatus::new();
+        let mut merger = FrameMerger::new(status.clone());
+        let now = Instant::now();
+
+        break_line(&mut merger, now);
+
+        // Write to the SubDevice at position 1, after the break
+        let sent = with_adp(
+            frame(MASTER_ADDR, &[(APWR, 0x02, &[0x34, 0x12], 0)]),
+            0u16.wrapping_sub(1),
+        );
+
+        // Only the primary copy is sent
+        assert_eq!(
+            merger.send(&sent, now),
+            vec![Action::Send(Interface::Primary, sent.clone())]
+        );
+
+        // Position incremented by the SubDevice before the break
+        let primary = with_adp(frame(PROCESSED, &[(APWR, 0x02, &[0x34, 0x12], 0)]), 0);
+
+        assert_eq!(
+            merger.receive(Interface::Primary, &primary, now),
+            vec![Action::Send(
+                Interface::Secondary,
+                with_adp(frame(SECONDARY_ADDR, &[(APWR, 0x02, &[0x34, 0x12], 0)]), 0)
+            )]
+        );
+
+        // Written by the first SubDevice after the break
+        let secondary = with_adp(
+            frame(PROCESSED_SECONDARY, &[(APWR, 0x02, &[0x34, 0x12], 1)]),
+            1,
+        );
+
+        let payload = received(merger.receive(Interface::Secondary, &secondary, now));
+
+        assert_eq!(payload.as_deref(), secondary.get(14..));
+        assert_eq!(
+            status.line_state(),
+            LineState::Broken {
+                primary_subdevices: Some(1),
+                secondary_subdevices: Some(1)
+            }
+        );
+    }
+
+    #[test]
+    fn primary_link_lost() {
+        let status = RedundancyStatus::new();
+        let mut merger = FrameMerger::new(status.clone());
+        let now = Instant::now();
+
+        merger.send(&frame(MASTER_ADDR, &[(BRD, 0x01, &[0], 0)]), now);
+
+        let secondary = frame(PROCESSED_SECONDARY, &[(BRD, 0x01, &[0], 4)]);
+
+        assert_eq!(
+            merger.receive(Interface::Secondary, &secondary, now),
+            vec![]
+        );
+        assert_eq!(merger.timeout(now), vec![]);
+
+        let payload = received(merger.timeout(now + COPY_TIMEOUT));
+
+        assert_eq!(payload.as_deref(), secondary.get(14..));
+        assert_eq!(
+            status.line_state(),
+            LineState::Broken {
+                primary_subdevices: Some(0),
+                secondary_subdevices: Some(4)
+            }
+        );
+        assert_eq!(merger.next_deadline(), None);
+    }
+
+    #[test]
+    fn keep_break_position() {
+        let status = RedundancyStatus::new();
+
+        status.update(LineState::Broken {
+            primary_subdevices: Some(1),
+            secondary_subdevices: Some(2),
+        });
+        status.update(LineState::Broken {
+            primary_subdevices: None,
+            secondary_subdevices: None,
+        });
+
+        assert_eq!(
+            status.line_state(),
+            LineState::Broken {
+                primary_subdevices: Some(1),
+                secondary_subdevices: Some(2),
+            }
+        );
+
+        status.update(LineState::Intact);
+
+        assert_eq!(status.line_state(), LineState::Intact);
+    }
+}
@@ -4,11 +4,15 @@
 mod bpf;
 #[cfg(target_os = "linux")]
 mod linux;
+#[cfg(target_os = "linux")]
+mod redundant;
 
 #[cfg(all(not(target_os = "linux"), unix))]
 use self::bpf::BpfDevice as RawSocketDesc;
 #[cfg(target_os = "linux")]
 pub(in crate::std) use self::linux::RawSocketDesc;
+#[cfg(target_os = "linux")]
+pub use self::redundant::tx_rx_task_redundant;
 
 use crate::{
     error::{Error, PduError},
@@ -0,0 +1,548 @@
+//! A TX/RX task for redundant networks with two network interfaces.
+
+use crate::{
+    error::{Error, PduError},
+    fmt,
+    pdu_loop::{PduRx, PduTx},
+    std::{
+        redundancy::{Action, FrameMerger, Interface, RedundancyStatus},
+        unix::RawSocketDesc,
+    },
+};
+use async_io::{Async, Timer};
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use futures_lite::{AsyncRead, AsyncWrite};
+use std::{thread, time::Instant};
+
+struct RedundantTxRxFut<'a, S> {
+    primary: S,
+    secondary: S,
+    mtu: usize,
+    tx: PduTx<'a>,
+    rx: PduRx<'a>,
+    merger: FrameMerger,
+    timer: Timer,
+}
+
+impl<'a, S> RedundantTxRxFut<'a, S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn new(
+        primary: S,
+        secondary: S,
+        mtu: usize,
+        tx: PduTx<'a>,
+        rx: PduRx<'a>,
+    ) -> (Self, RedundancyStatus) {
+        // DC configuration must know the last SubDevice is connected back to the secondary
+        // interface
+        tx.set_redundant();
+
+        let status = RedundancyStatus::new();
+
+        let task = Self {
+            primary,
+            secondary,
+            mtu,
+            tx,
+            rx,
+            merger: FrameMerger::new(status.clone()),
+            timer: Timer::never(),
+        };
+
+        (task, status)
+    }
+
+    fn write(&mut self, ctx: &mut Context<'_>, interface: Interface, data: &[u8]) -> bool {
+        let socket = match interface {
+            Interface::Primary => &mut self.primary,
+            Interface::Secondary => &mut self.secondary,
+        };
+
+        match Pin::new(socket).poll_write(ctx, data) {
+            Poll::Ready(Ok(bytes_written)) if bytes_written == data.len() => true,
+            Poll::Ready(Ok(bytes_written)) => {
+                fmt::error!(
+                    "Only wrote {} of {} bytes on {:?} interface",
+                    bytes_written,
+                    data.len(),
+                    interface
+                );
+
+                false
+            }
+            Poll::Ready(Err(e)) => {
+                fmt::error!("Send PDU on {:?} interface failed: {}", interface, e);
+
+                false
+            }
+            Poll::Pending => false,
+        }
+    }
+
+    /// Perform the given actions, returning whether any frame was sent successfully.
+    fn handle(&mut self, ctx: &mut Context<'_>, actions: Vec<Action>) -> Result<bool, Error> {
+        let mut sent = false;
+
+        for action in actions {
+            match action {
+                Action::Send(interface, data) => {
+                    sent |= self.write(ctx, interface, &data);
+                }
+                Action::Receive(payload) => loop {
+                    match self.rx.receive_ethercat_frame(&payload) {
+                        // Wait for frame RX future waker to be registered
+                        Err(Error::Pdu(PduError::NoWaker)) => thread::yield_now(),
+                        Err(e) => {
+                            fmt::error!("Failed to receive frame: {}", e);
+
+                            return Err(Error::ReceiveFrame);
+                        }
+                        Ok(()) => break,
+                    }
+                },
+            }
+        }
+
+        Ok(sent)
+    }
+
+    /// Read a frame from the given interface, if one is available.
+    fn read(&mut self, ctx: &mut Context<'_>, interface: Interface) -> Option<Vec<u8>> {
+        let mut buf = vec![0; self.mtu];
+
+        let socket = match interface {
+            Interface::Primary => &mut self.primary,
+            Interface::Secondary => &mut self.secondary,
+        };
+
+        match Pin::new(socket).poll_read(ctx, &mut buf) {
+            Poll::Ready(Ok(n)) => {
+                // Wake again in case there are more frames to consume
+                ctx.waker().wake_by_ref();
+
+                buf.truncate(n);
+
+                Some(buf)
+            }
+            Poll::Ready(Err(e)) => {
+                fmt::error!("Receive PDU on {:?} interface failed: {}", interface, e);
+
+                None
+            }
+            Poll::Pending => None,
+        }
+    }
+}
+
+impl<S> Future for RedundantTxRxFut<'_, S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    type Output = Result<(), Error>;
+
+    fn poll(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+
+        // Re-register waker to make sure this future is polled again
+        this.tx.replace_waker(ctx.waker());
+
+        while let Some(frame) = this.tx.next_sendable_frame() {
+            let res = frame.send_blocking(|data| {
+                let actions = this.merger.send(data, Instant::now());
+
+                // The frame is sent if it made it out of at least one interface
+                if this.handle(ctx, actions)? {
+                    Ok(data.len())
+                } else {
+                    Err(Error::SendFrame)
+                }
+            });
+
+            if let Err(e) = res {
+                fmt::error!("Send PDU failed: {}", e);
+
+                return Poll::Ready(Err(e));
+            }
+        }
+
+        for interface in [Interface::Primary, Interface::Secondary] {
+            if let Some(frame) = this.read(ctx, interface) {
+                let actions = this.merger.receive(interface, &frame, Instant::now());
+
+                if let Err(e) = this.handle(ctx, actions) {
+                    return Poll::Ready(Err(e));
+                }
+            }
+        }
+
+        let actions = this.merger.timeout(Instant::now());
+
+        if let Err(e) = this.handle(ctx, actions) {
+            return Poll::Ready(Err(e));
+        }
+
+        // Poll again when the next frame times out
+        if let Some(deadline) = this.merger.next_deadline() {
+            this.timer.set_at(deadline);
+
+            if Pin::new(&mut this.timer).poll(ctx).is_ready() {
+                ctx.waker().wake_by_ref();
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+/// Spawn a TX and RX task for a redundant network.
+///
+/// Every frame is sent from both the `primary` interface, connected to the first SubDevice, and
+/// the `secondary` interface, connected to the last free port of the last SubDevice. The network
+/// keeps working if a single cable in the ring is broken, or if one of the interfaces loses its
+/// link. The returned [`RedundancyStatus`] can be used to monitor the line.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ethercrab::{
+///     std::{ethercat_now, tx_rx_task_redundant, LineState},
+///     MainDevice, MainDeviceConfig, PduStorage, Timeouts,
+/// };
+///
+/// const MAX_SUBDEVICES: usize = 16;
+/// const MAX_PDU_DATA: usize = PduStorage::element_size(1100);
+/// const MAX_FRAMES: usize = 16;
+///
+/// static PDU_STORAGE: PduStorage<MAX_FRAMES, MAX_PDU_DATA> = PduStorage::new();
+///
+/// # async fn example() -> Result<(), ethercrab::error::Error> {
+/// let (tx, rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+///
+/// let (task, status) =
+///     tx_rx_task_redundant("eth0", "eth1", tx, rx).expect("spawn TX/RX task");
+///
+/// smol::spawn(task).detach();
+///
+/// let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+///
+/// let group = maindevice
+///     .init_single_group::<MAX_SUBDEVICES, 64>(ethercat_now)
+///     .await?;
+///
+/// if let LineState::Broken {
+///     primary_subdevices, ..
+/// } = status.line_state()
+/// {
+///     eprintln!("Line broken after {:?} SubDevices", primary_subdevices);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn tx_rx_task_redundant<'sto>(
+    primary: &str,
+    secondary: &str,
+    pdu_tx: PduTx<'sto>,
+    pdu_rx: PduRx<'sto>,
+) -> Result<
+    (
+        impl Future<Output = Result<(), Error>> + 'sto,
+        RedundancyStatus,
+    ),
+    std::io::Error,
+> {
+    let mut primary_socket = RawSocketDesc::new(primary)?;
+    let mut secondary_socket = RawSocketDesc::new(secondary)?;
+
+    let mtu = primary_socket
+        .interface_mtu()?
+        .max(secondary_socket.interface_mtu()?);
+
+    fmt::debug!(
+        "Opening {} and {} for redundancy with MTU {}",
+        primary,
+        secondary,
+        mtu
+    );
+
+    Ok(RedundantTxRxFut::new(
+        Async::new(primary_socket)?,
+        Async::new(secondary_socket)?,
+        mtu,
+        pdu_tx,
+        pdu_rx,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        command::Command, std::redundancy::LineState, MainDevice, MainDeviceConfig, PduStorage,
+        Timeouts,
+    };
+    use futures_lite::future;
+    use std::{
+        collections::VecDeque,
+        io,
+        ops::Range,
+        sync::{Arc, Mutex},
+        task::Waker,
+    };
+
+    const APRD: u8 = 0x01;
+    const APWR: u8 = 0x02;
+    const BRD: u8 = 0x07;
+
+    /// A line of SubDevices with both ends connected to simulated network interfaces.
+    struct Ring {
+        /// The single 16 bit register of each SubDevice, read by `APRD` and written by `APWR`.
+        registers: Vec<u16>,
+        /// The line is broken after this many SubDevices.
+        break_after: Option<usize>,
+        primary: VecDeque<Vec<u8>>,
+        secondary: VecDeque<Vec<u8>>,
+        waker: Option<Waker>,
+    }
+
+    impl Ring {
+        fn new(registers: Vec<u16>, break_after: Option<usize>) -> Arc<Mutex<Self>> {
+            Arc::new(Mutex::new(Self {
+                registers,
+                break_after,
+                primary: VecDeque::new(),
+                secondary: VecDeque::new(),
+                waker: None,
+            }))
+        }
+
+        /// Send a frame from the given interface and queue it on the interface it returns to.
+        fn send(&mut self, from: Interface, data: &[u8]) {
+            let mut frame = data.to_vec();
+            let end = self.registers.len();
+
+            match (from, self.break_after) {
+                (Interface::Primary, None) => {
+                    self.process(&mut frame, 0..end);
+                    self.secondary.push_back(frame);
+                }
+                (Interface::Primary, Some(at)) => {
+                    self.process(&mut frame, 0..at);
+                    self.primary.push_back(frame);
+                }
+                // SubDevices don't process frames travelling in the reverse direction
+                (Interface::Secondary, None) => self.primary.push_back(frame),
+                (Interface::Secondary, Some(at)) => {
+                    self.process(&mut frame, at..end);
+                    self.secondary.push_back(frame);
+                }
+            }
+
+            if let Some(waker) = self.waker.take() {
+                waker.wake();
+            }
+        }
+
+        /// Process every PDU in `frame` by the SubDevices at `positions`.
+        fn process(&mut self, frame: &mut [u8], positions: Range<usize>) {
+            // Source address as modified by the first SubDevice
+            frame[6] |= 0x02;
+
+            // Skip Ethernet and EtherCAT frame headers
+            let mut pos = 16;
+
+            loop {
+                let command = frame[pos];
+                let mut adp = u16::from_le_bytes([frame[pos + 2], frame[pos + 3]]);
+                let flags = u16::from_le_bytes([frame[pos + 6], frame[pos + 7]]);
+                let len = usize::from(flags & 0x07ff);
+                let data = pos + 10;
+                let mut wkc = u16::from_le_bytes([frame[data + len], frame[data + len + 1]]);
+
+                for position in positions.clone() {
+                    match command {
+                        BRD => wkc += 1,
+                        APRD if adp == 0 => {
+                            frame[data..data + 2]
+                                .copy_from_slice(&self.registers[position].to_le_bytes());
+
+                            wkc += 1;
+                        }
+                        APWR if adp == 0 => {
+                            self.registers[position] =
+                                u16::from_le_bytes([frame[data], frame[data + 1]]);
+
+                            wkc += 1;
+                        }
+                        _ => (),
+                    }
+
+                    if matches!(command, APRD | APWR) {
+                        adp = adp.wrapping_add(1);
+                    }
+                }
+
+                frame[pos + 2..pos + 4].copy_from_slice(&adp.to_le_bytes());
+                frame[data + len..data + len + 2].copy_from_slice(&wkc.to_le_bytes());
+
+                // More PDUs follow flag
+                if flags & 0x8000 == 0 {
+                    break;
+                }
+
+                pos = data + len + 2;
+            }
+        }
+    }
+
+    /// One of the network interfaces connected to a [`Ring`].
+    struct FakeSocket {
+        ring: Arc<Mutex<Ring>>,
+        interface: Interface,
+    }
+
+    impl AsyncWrite for FakeSocket {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.ring.lock().unwrap().send(self.interface, buf);
+
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    impl AsyncRead for FakeSocket {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            let mut ring = self.ring.lock().unwrap();
+
+            let queue = match self.interface {
+                Interface::Primary => &mut ring.primary,
+                Interface::Secondary => &mut ring.secondary,
+            };
+
+            match queue.pop_front() {
+                Some(frame) => {
+                    buf[..frame.len()].copy_from_slice(&frame);
+
+                    Poll::Ready(Ok(frame.len()))
+                }
+                None => {
+                    ring.waker = Some(cx.waker().clone());
+
+                    Poll::Pending
+                }
+            }
+        }
+    }
+
+    /// Create a MainDevice and redundant TX/RX task connected to `ring`.
+    fn network<'sto>(
+        storage: &'sto PduStorage<4, { PduStorage::element_size(32) }>,
+        ring: &Arc<Mutex<Ring>>,
+    ) -> (
+        MainDevice<'sto>,
+        RedundantTxRxFut<'sto, FakeSocket>,
+        RedundancyStatus,
+    ) {
+        let (tx, rx, pdu_loop) = storage.try_split().expect("split");
+
+        let socket = |interface| FakeSocket {
+            ring: ring.clone(),
+            interface,
+        };
+
+        let (task, status) = RedundantTxRxFut::new(
+            socket(Interface::Primary),
+            socket(Interface::Secondary),
+            1500,
+            tx,
+            rx,
+        );
+
+        let maindevice =
+            MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+
+        (maindevice, task, status)
+    }
+
+    /// Run `fut` to completion while `task` sends and receives its frames.
+    fn run<T>(task: RedundantTxRxFut<'_, FakeSocket>, fut: impl Future<Output = T>) -> T {
+        future::block_on(future::or(fut, async {
+            task.await.expect("TX/RX task");
+
+            unreachable!("TX/RX task stopped")
+        }))
+    }
+
+    #[test]
+    fn intact() {
+        let storage = PduStorage::new();
+        let ring = Ring::new(vec![0x1000, 0x1001, 0x1002], None);
+        let (maindevice, task, status) = network(&storage, &ring);
+
+        assert!(maindevice.pdu_loop.is_redundant());
+
+        run(task, async {
+            assert_eq!(maindevice.count_subdevices().await, Ok(3));
+            assert_eq!(status.line_state(), LineState::Intact);
+
+            assert_eq!(
+                Command::aprd(2, 0x0010).receive::<u16>(&maindevice).await,
+                Ok(0x1002)
+            );
+        });
+    }
+
+    #[test]
+    fn broken_apwr() {
+        let storage = PduStorage::new();
+        let ring = Ring::new(vec![0x1000, 0x1001, 0x1002], Some(1));
+        let (maindevice, task, status) = network(&storage, &ring);
+
+        run(task, async {
+            assert_eq!(maindevice.count_subdevices().await, Ok(3));
+            assert_eq!(
+                status.line_state(),
+                LineState::Broken {
+                    primary_subdevices: Some(1),
+                    secondary_subdevices: Some(2)
+                }
+            );
+
+            // Positions are counted from the primary interface on both sides of the break
+            assert_eq!(
+                Command::apwr(2, 0x0010).send(&maindevice, 0xabcdu16).await,
+                Ok(())
+            );
+            assert_eq!(
+                Command::aprd(0, 0x0010).receive::<u16>(&maindevice).await,
+                Ok(0x1000)
+            );
+            assert_eq!(
+                Command::aprd(1, 0x0010).receive::<u16>(&maindevice).await,
+                Ok(0x1001)
+            );
+        });
+
+        assert_eq!(ring.lock().unwrap().registers, [0x1000, 0x1001, 0xabcd]);
+    }
+}
@@ -1,6 +1,9 @@
 use super::{SubDevice, SubDeviceRef};
 use crate::{
+    al_control::AlControl,
     coe::{SdoExpedited, SubIndex},
+    command::Command,
+    dc,
     eeprom::types::{
         CoeDetails, FmmuUsage, MailboxProtocols, SiiOwner, SyncManager, SyncManagerEnable,
         SyncManagerType,
@@ -8,13 +11,29 @@ use crate::{
     error::{Error, Item},
     fmmu::Fmmu,
     fmt,
+    hooks::HookTransition,
     pdi::{PdiOffset, PdiSegment},
     register::RegisterAddress,
-    subdevice::types::{Mailbox, MailboxConfig},
+    subdevice::{
+        pdo_entries::{GroupPdoEntry, PdoEntries, MAX_PDO_ENTRIES},
+        types::{Mailbox, MailboxConfig, SubDeviceConfig},
+    },
     subdevice_state::SubDeviceState,
     sync_manager_channel::{Enable, Status, SyncManagerChannel, SM_BASE_ADDRESS, SM_TYPE_ADDRESS},
+    ExpectedSubDevice, SubDeviceIdentity, TopologyMismatch,
 };
-use core::ops::DerefMut;
+use core::ops::{Deref, DerefMut};
+use ethercrab_wire::EtherCrabWireRead;
+
+/// DC times read by [`SubDeviceRef::read_dc_times`].
+struct DcTimes {
+    /// This SubDevice's system time, with no offset applied.
+    local_time: u64,
+    /// The time source's system time.
+    system_time: u64,
+    /// The time source's propagation delay from the DC reference.
+    source_delay: u32,
+}
 
 /// Configuation from EEPROM methods.
 impl<'a, S> SubDeviceRef<'a, S>
@@ -96,17 +115,205 @@ where
 
         self.set_eeprom_mode(SiiOwner::Master).await?;
 
+        self.maindevice
+            .run_hooks(HookTransition::InitToPreOp, &mut self.state)
+            .await
+    }
+
+    /// Reset a SubDevice that has dropped out of the network, e.g. after losing power, and run
+    /// first stage configuration again (INIT -> PRE-OP).
+    ///
+    /// The SubDevice must already have its configured station address. Its identity is checked
+    /// against the identity read during initialisation, and its DC system time offset is restored
+    /// relative to the SubDevice at `dc_time_source`.
+    ///
+    /// All configuration read from the SubDevice, including its process data layout, is cleared.
+    pub(crate) async fn reinit(&mut self, dc_time_source: Option<u16>) -> Result<(), Error> {
+        self.write(RegisterAddress::AlControl)
+            .send(self.maindevice, AlControl::reset())
+            .await?;
+
+        self.wait_for_state(SubDeviceState::Init).await?;
+
+        // Clear FMMUs and SMs. See ETG1000.4 Table 57 and Table 59 for lengths.
+        self.write(RegisterAddress::Fmmu0)
+            .send(self.maindevice, [0u8; 0xff])
+            .await?;
+        self.write(RegisterAddress::Sm0)
+            .send(self.maindevice, [0u8; 0x7f])
+            .await?;
+
+        self.set_eeprom_mode(SiiOwner::Master).await?;
+
+        let expected = self.state.identity;
+        let found = self.eeprom().identity().await?;
+
+        if found.vendor_id != expected.vendor_id
+            || found.product_id != expected.product_id
+            || found.revision != expected.revision
+        {
+            fmt::error!(
+                "SubDevice {:#06x} identity changed from {} to {}",
+                self.configured_address,
+                expected,
+                found
+            );
+
+            return Err(identity_mismatch(self.state.index, expected, found));
+        }
+
+        self.state.config = SubDeviceConfig::default();
+
+        if self.state.flags.dc_support().any() {
+            self.restore_dc(dc_time_source).await?;
+        }
+
+        self.configure_mailboxes().await
+    }
+
+    /// Restore DC control loop parameters, system time offset and propagation delay after a
+    /// SubDevice has been reset, or set them for a SubDevice connected after initialisation.
+    pub(crate) async fn restore_dc(&self, dc_time_source: Option<u16>) -> Result<(), Error> {
+        // Same initial parameters as set for all SubDevices during initialisation. Parameter 3
+        // must be written first so the DC control unit is reset.
+        self.write(RegisterAddress::DcControlLoopParam3)
+            .send(self.maindevice, 0x0c00u16)
+            .await?;
+        self.write(RegisterAddress::DcControlLoopParam1)
+            .send(self.maindevice, 0x1000u16)
+            .await?;
+
+        let Some(dc_time_source) = dc_time_source else {
+            fmt::warn!(
+                "No DC time source available to restore SubDevice {:#06x} system time offset",
+                self.configured_address
+            );
+
+            return Ok(());
+        };
+
+        // Local time is read with no offset applied
+        self.write(RegisterAddress::DcSystemTimeOffset)
+            .send(self.maindevice, 0u64)
+            .await?;
+
+        let DcTimes {
+            local_time,
+            system_time,
+            source_delay,
+        } = self.read_dc_times(dc_time_source).await?;
+
+        // The frame passes the time source, then reaches this SubDevice after the difference in
+        // their propagation delays.
+        let system_time_offset = system_time
+            .wrapping_add(u64::from(self.state.propagation_delay))
+            .wrapping_sub(u64::from(source_delay))
+            .wrapping_sub(local_time);
+
+        fmt::debug!(
+            "Restoring SubDevice {:#06x} system time offset to {} ns, propagation delay {} ns",
+            self.configured_address,
+            system_time_offset as i64,
+            self.state.propagation_delay
+        );
+
+        self.write(RegisterAddress::DcSystemTimeOffset)
+            .send(self.maindevice, system_time_offset)
+            .await?;
+
+        self.write(RegisterAddress::DcSystemTimeTransmissionDelay)
+            .send(self.maindevice, self.state.propagation_delay)
+            .await?;
+
+        // Let the SubDevice's drift compensation settle. `FRMW` only reaches SubDevices after the
+        // one it reads from, so this is only possible when the time source is the DC reference.
+        if self.maindevice.dc_ref_address() == Some(dc_time_source) {
+            dc::run_dc_static_sync(
+                self.maindevice,
+                dc_time_source,
+                self.maindevice.config.dc_static_sync_iterations,
+            )
+            .await?;
+        }
+
         Ok(())
     }
 
+    /// Read this SubDevice's DC system time, and the system time and propagation delay of
+    /// `dc_time_source`, in a single frame so both times are sampled as the frame passes through
+    /// the network.
+    async fn read_dc_times(&self, dc_time_source: u16) -> Result<DcTimes, Error> {
+        let maindevice = self.maindevice;
+
+        let mut frame = maindevice.pdu_loop.alloc_frame()?;
+
+        let system_time = frame.push_pdu(
+            Command::fprd(dc_time_source, RegisterAddress::DcSystemTime.into()).into(),
+            0u64,
+            None,
+        )?;
+        let source_delay = frame.push_pdu(
+            Command::fprd(
+                dc_time_source,
+                RegisterAddress::DcSystemTimeTransmissionDelay.into(),
+            )
+            .into(),
+            0u32,
+            None,
+        )?;
+        let local_time = frame.push_pdu(
+            Command::fprd(
+                self.configured_address,
+                RegisterAddress::DcSystemTime.into(),
+            )
+            .into(),
+            0u64,
+            None,
+        )?;
+
+        let frame = frame.mark_sendable(
+            &maindevice.pdu_loop,
+            maindevice.timeouts.pdu,
+            maindevice.config.retry_behaviour.retry_count(),
+        );
+
+        maindevice.pdu_loop.wake_sender();
+
+        let received = frame.await?;
+
+        let read = |handle| {
+            let pdu = received.pdu(handle)?;
+
+            if pdu.working_counter != 1 {
+                return Err(Error::WorkingCounter {
+                    expected: 1,
+                    received: pdu.working_counter,
+                });
+            }
+
+            Ok::<_, Error>(pdu)
+        };
+
+        Ok(DcTimes {
+            system_time: u64::unpack_from_slice(&read(system_time)?)?,
+            source_delay: u32::unpack_from_slice(&read(source_delay)?)?,
+            local_time: u64::unpack_from_slice(&read(local_time)?)?,
+        })
+    }
+
     /// Second state configuration (PRE-OP -> SAFE-OP).
     ///
     /// PDOs must be configured in the PRE-OP state.
+    ///
+    /// If `bit_packing` is true and the SubDevice's FMMUs support bit operations, process data
+    /// shorter than 8 bits is mapped at `global_offset`'s bit position instead of being aligned to
+    /// the next byte.
     pub(crate) async fn configure_fmmus(
         &mut self,
         mut global_offset: PdiOffset,
         group_start_address: u32,
         direction: PdoDirection,
+        bit_packing: bool,
     ) -> Result<PdiOffset, Error> {
         let sync_managers = self.eeprom().sync_managers().await?;
         let fmmu_usage = self.eeprom().fmmus().await?;
@@ -136,12 +343,26 @@ where
             has_coe
         );
 
+        let bit_packing = bit_packing && !self.state.flags.fmmu_bit_ops_not_supported;
+
         let range = if has_coe {
-            self.configure_pdos_coe(&sync_managers, &fmmu_usage, direction, &mut global_offset)
-                .await?
+            self.configure_pdos_coe(
+                &sync_managers,
+                &fmmu_usage,
+                direction,
+                &mut global_offset,
+                bit_packing,
+            )
+            .await?
         } else {
-            self.configure_pdos_eeprom(&sync_managers, &fmmu_usage, direction, &mut global_offset)
-                .await?
+            self.configure_pdos_eeprom(
+                &sync_managers,
+                &fmmu_usage,
+                direction,
+                &mut global_offset,
+                bit_packing,
+            )
+            .await?
         };
 
         match direction {
@@ -292,6 +513,7 @@ where
         fmmu_usage: &[FmmuUsage],
         direction: PdoDirection,
         global_offset: &mut PdiOffset,
+        bit_packing: bool,
     ) -> Result<PdiSegment, Error> {
         if !self.state.config.mailbox.has_coe {
             fmt::warn!("Invariant: attempting to configure PDOs from COE with no SOE support");
@@ -308,7 +530,19 @@ where
         //     .sdo_read::<u8>(SM_TYPE_ADDRESS, SubIndex::Index(0))
         //     .await?;
 
-        let start_offset = *global_offset;
+        // Bit-packed mappings can't share an FMMU, so only pack if a single SM is used
+        let bit_packing = bit_packing
+            && self
+                .state
+                .config
+                .mailbox
+                .coe_sync_manager_types
+                .iter()
+                .filter(|sm_type| **sm_type == desired_sm_type)
+                .count()
+                == 1;
+
+        let mut start_offset = None;
         let mut total_bit_len = 0;
 
         for (sync_manager_index, sm_type) in self
@@ -335,72 +569,15 @@ where
                 continue;
             }
 
-            // Total number of PDO assignments for this sync manager
-            let num_sm_assignments = self
-                .sdo_read_expedited::<u8>(sm_address, SubIndex::Index(0))
-                .await?;
-
-            fmt::trace!(
-                "SDO sync manager {}  {:#06x} {:?}, sub indices: {}",
-                sync_manager_index,
-                sm_address,
-                sm_type,
-                num_sm_assignments
-            );
-
-            let mut sm_bit_len = 0u16;
-
-            for i in 1..=num_sm_assignments {
-                let pdo = self
-                    .sdo_read_expedited::<u16>(sm_address, SubIndex::Index(i))
-                    .await?;
-                let num_mappings = self
-                    .sdo_read_expedited::<u8>(pdo, SubIndex::Index(0))
-                    .await?;
-
-                fmt::trace!(
-                    "--> {:#04x} data: {:#06x} ({} mappings):",
-                    i,
-                    pdo,
-                    num_mappings
-                );
-
-                for i in 1..=num_mappings {
-                    /// Defined in ETG1000.6 Table 74/Table 75 Receive PDO Mapping.
-                    ///
-                    /// Note that this struct order is opposite to the specification as the data is
-                    /// big-endian in EEPROM, but little endian on the wire.
-                    #[derive(ethercrab_wire::EtherCrabWireRead)]
-                    #[wire(bytes = 4)]
-                    struct Mapping {
-                        #[wire(bytes = 1)]
-                        mapping_bit_len: u8,
-                        #[wire(bytes = 1)]
-                        sub_index: u8,
-                        #[wire(bytes = 2)]
-                        index: u16,
-                    }
-
-                    impl SdoExpedited for Mapping {}
-
-                    let Mapping {
-                        index,
-                        sub_index,
-                        mapping_bit_len,
-                    } = self
-                        .sdo_read_expedited::<Mapping>(pdo, SubIndex::Index(i))
-                        .await?;
-
-                    fmt::trace!(
-                        "----> index {:#06x}, sub index {}, bit length {}",
-                        index,
-                        sub_index,
-                        mapping_bit_len,
-                    );
-
-                    sm_bit_len += u16::from(mapping_bit_len);
+            // PDOs assigned by `SubDeviceRef::configure_pdos` have a known length so don't need to
+            // be read back from the SubDevice.
+            let sm_bit_len = match self.state.config.assigned_pdos.bit_len(sm_address) {
+                Some(sm_bit_len) => sm_bit_len,
+                None => {
+                    self.read_sm_bit_len(sync_manager_index, direction, |_| ())
+                        .await?
                 }
-            }
+            };
 
             fmt::trace!(
                 "----= total SM bit length {} ({} bytes)",
@@ -421,25 +598,31 @@ where
                         index: None,
                     })?;
 
-                self.write_fmmu_config(
-                    sm_bit_len,
-                    fmmu_index,
-                    global_offset,
-                    desired_sm_type,
-                    &sm_config,
-                )
-                .await?;
+                let mapping_start = self
+                    .write_fmmu_config(
+                        sm_bit_len,
+                        fmmu_index,
+                        global_offset,
+                        desired_sm_type,
+                        &sm_config,
+                        bit_packing,
+                    )
+                    .await?;
+
+                start_offset.get_or_insert(mapping_start);
             }
 
             total_bit_len += sm_bit_len;
         }
 
-        Ok(PdiSegment {
-            bit_len: total_bit_len.into(),
-            bytes: start_offset.up_to(*global_offset),
-        })
+        Ok(segment(
+            start_offset.unwrap_or(*global_offset),
+            *global_offset,
+            total_bit_len,
+        ))
     }
 
+    /// Write an FMMU config for a sync manager, returning the PDI offset the mapping starts at.
     async fn write_fmmu_config(
         &self,
         sm_bit_len: u16,
@@ -447,13 +630,23 @@ where
         global_offset: &mut PdiOffset,
         desired_sm_type: SyncManagerType,
         sm_config: &SyncManagerChannel,
-    ) -> Result<(), Error> {
+        bit_packing: bool,
+    ) -> Result<PdiOffset, Error> {
         // Multiple SMs may use the same FMMU, so we'll read the existing config from the SubDevice
         let mut fmmu_config = self
             .read(RegisterAddress::fmmu(fmmu_index as u8))
             .receive::<Fmmu>(self.maindevice)
             .await?;
 
+        // Zero length mappings are also packed so they don't move the offset to the next byte
+        let bit_packed = bit_packing && !fmmu_config.enable && sm_bit_len < 8;
+
+        let mapping_start = if bit_packed {
+            global_offset.packed_start(sm_bit_len)
+        } else {
+            global_offset.align()
+        };
+
         // We can use the enable flag as a sentinel for existing config because EtherCrab inits
         // FMMUs to all zeroes on startup.
         let fmmu_config = if fmmu_config.enable {
@@ -462,12 +655,14 @@ where
             fmmu_config
         } else {
             Fmmu {
-                logical_start_address: global_offset.start_address,
+                logical_start_address: mapping_start.start_address,
                 length_bytes: sm_config.length_bytes,
-                // Mapping into PDI is byte-aligned until/if we support bit-oriented SubDevices
-                logical_start_bit: 0,
-                // Always byte-aligned
-                logical_end_bit: 7,
+                logical_start_bit: mapping_start.start_bit,
+                logical_end_bit: if bit_packed {
+                    mapping_start.end_bit(sm_bit_len)
+                } else {
+                    7
+                },
                 physical_start_address: sm_config.physical_start_address,
                 physical_start_bit: 0x0,
                 read_enable: desired_sm_type == SyncManagerType::ProcessDataRead,
@@ -487,9 +682,13 @@ where
             fmmu_config
         );
 
-        *global_offset = global_offset.increment_byte_aligned(sm_bit_len);
+        *global_offset = if bit_packed {
+            mapping_start.increment_bits(sm_bit_len)
+        } else {
+            mapping_start.increment_byte_aligned(sm_bit_len)
+        };
 
-        Ok(())
+        Ok(mapping_start)
     }
 
     /// Configure PDOs from EEPROM
@@ -499,6 +698,7 @@ where
         fmmu_usage: &[FmmuUsage],
         direction: PdoDirection,
         offset: &mut PdiOffset,
+        bit_packing: bool,
     ) -> Result<PdiSegment, Error> {
         let pdos = match direction {
             PdoDirection::MasterRead => {
@@ -519,11 +719,19 @@ where
 
         let fmmu_sm_mappings = self.eeprom().fmmu_mappings().await?;
 
-        let start_offset = *offset;
+        let mut start_offset = None;
         let mut total_bit_len = 0;
 
         let (sm_type, fmmu_type) = direction.filter_terms();
 
+        // Bit-packed mappings can't share an FMMU, so only pack if a single SM is used
+        let bit_packing = bit_packing
+            && sync_managers
+                .iter()
+                .filter(|sm| sm.usage_type == sm_type)
+                .count()
+                == 1;
+
         for (sync_manager_index, sync_manager) in sync_managers
             .iter()
             .enumerate()
@@ -567,24 +775,280 @@ where
                 .write_sm_config(sync_manager_index, sync_manager, (bit_len + 7) / 8)
                 .await?;
 
-            self.write_fmmu_config(
-                bit_len,
-                usize::from(fmmu_index),
-                offset,
-                sm_type,
-                &sm_config,
-            )
+            let mapping_start = self
+                .write_fmmu_config(
+                    bit_len,
+                    usize::from(fmmu_index),
+                    offset,
+                    sm_type,
+                    &sm_config,
+                    bit_packing,
+                )
+                .await?;
+
+            start_offset.get_or_insert(mapping_start);
+        }
+
+        Ok(segment(
+            start_offset.unwrap_or(*offset),
+            *offset,
+            total_bit_len,
+        ))
+    }
+}
+
+/// PDO entry layout methods.
+impl<'a, S> SubDeviceRef<'a, S>
+where
+    S: Deref<Target = SubDevice>,
+{
+    /// Read the layout of the PDO entries mapped into the SubDevice's inputs or outputs, adding
+    /// each entry to `pdo_entries`.
+    ///
+    /// The layout is read back from the SubDevice's object dictionary if it supports CoE, or from
+    /// its EEPROM otherwise, so this sends the same requests as configuring the SubDevice's PDI
+    /// would if no PDO lengths were known.
+    pub(crate) async fn read_pdo_entries(
+        &self,
+        direction: PdoDirection,
+        pdo_entries: &mut PdoEntries,
+    ) -> Result<(), Error> {
+        let (sm_type, _) = direction.filter_terms();
+
+        // Each sync manager's process data starts on a new byte, as mapped by
+        // `write_fmmu_config`. A bit-packed mapping only ever uses a single sync manager.
+        let mut sm_offset = 0u16;
+
+        if self.state.config.mailbox.has_coe {
+            for (sync_manager_index, _) in self
+                .state
+                .config
+                .mailbox
+                .coe_sync_manager_types
+                .iter()
+                .enumerate()
+                .filter(|(_idx, ty)| **ty == sm_type)
+            {
+                let sm_bit_len = self
+                    .read_sm_bit_len(sync_manager_index as u8, direction, |entry| {
+                        pdo_entries.push(GroupPdoEntry {
+                            bit_offset: sm_offset + entry.bit_offset,
+                            ..entry
+                        })
+                    })
+                    .await?;
+
+                sm_offset += sm_bit_len.next_multiple_of(8);
+            }
+
+            return Ok(());
+        }
+
+        let mut entries = heapless::Vec::<_, MAX_PDO_ENTRIES>::new();
+
+        let pdos = match direction {
+            PdoDirection::MasterRead => {
+                self.eeprom()
+                    .maindevice_read_pdo_entries(&mut entries)
+                    .await?
+            }
+            PdoDirection::MasterWrite => {
+                self.eeprom()
+                    .maindevice_write_pdo_entries(&mut entries)
+                    .await?
+            }
+        };
+
+        let sync_managers = self.eeprom().sync_managers().await?;
+
+        for (sync_manager_index, _) in sync_managers
+            .iter()
+            .enumerate()
+            .filter(|(_idx, sm)| sm.usage_type == sm_type)
+        {
+            let sync_manager_index = sync_manager_index as u8;
+
+            let mut sm_bit_len = 0u16;
+
+            // Entries are stored in PDO order, so find each PDO's entries by skipping past those of
+            // previous PDOs.
+            for (pdo, pdo_entries_start) in pdos.iter().scan(0usize, |start, pdo| {
+                let pdo_start = *start;
+
+                *start += usize::from(pdo.num_entries);
+
+                Some((pdo, pdo_start))
+            }) {
+                if pdo.sync_manager != sync_manager_index {
+                    continue;
+                }
+
+                let pdo_entries_end = pdo_entries_start + usize::from(pdo.num_entries);
+
+                for entry in entries
+                    .get(pdo_entries_start..pdo_entries_end.min(entries.len()))
+                    .unwrap_or(&[])
+                {
+                    pdo_entries.push(GroupPdoEntry {
+                        configured_address: self.configured_address,
+                        direction,
+                        index: entry.index,
+                        sub_index: entry.sub_index,
+                        bit_len: entry.data_length_bits,
+                        bit_offset: sm_offset + sm_bit_len,
+                    });
+
+                    sm_bit_len += u16::from(entry.data_length_bits);
+                }
+            }
+
+            sm_offset += sm_bit_len.next_multiple_of(8);
+        }
+
+        Ok(())
+    }
+
+    /// Read the total bit length of all PDOs assigned to a sync manager from the SubDevice's
+    /// object dictionary.
+    ///
+    /// `on_entry` is called with each mapped entry, offset from the start of the sync manager's
+    /// process data.
+    async fn read_sm_bit_len(
+        &self,
+        sync_manager_index: u8,
+        direction: PdoDirection,
+        mut on_entry: impl FnMut(GroupPdoEntry),
+    ) -> Result<u16, Error> {
+        let sm_address = SM_BASE_ADDRESS + u16::from(sync_manager_index);
+        let (sm_type, _) = direction.filter_terms();
+
+        // Total number of PDO assignments for this sync manager
+        let num_sm_assignments = self
+            .sdo_read_expedited::<u8>(sm_address, SubIndex::Index(0))
             .await?;
+
+        fmt::trace!(
+            "SDO sync manager {}  {:#06x} {:?}, sub indices: {}",
+            sync_manager_index,
+            sm_address,
+            sm_type,
+            num_sm_assignments
+        );
+
+        let mut sm_bit_len = 0u16;
+
+        for i in 1..=num_sm_assignments {
+            let pdo = self
+                .sdo_read_expedited::<u16>(sm_address, SubIndex::Index(i))
+                .await?;
+            let num_mappings = self
+                .sdo_read_expedited::<u8>(pdo, SubIndex::Index(0))
+                .await?;
+
+            fmt::trace!(
+                "--> {:#04x} data: {:#06x} ({} mappings):",
+                i,
+                pdo,
+                num_mappings
+            );
+
+            for i in 1..=num_mappings {
+                /// Defined in ETG1000.6 Table 74/Table 75 Receive PDO Mapping.
+                ///
+                /// Note that this struct order is opposite to the specification as the data is
+                /// big-endian in EEPROM, but little endian on the wire.
+                #[derive(ethercrab_wire::EtherCrabWireRead)]
+                #[wire(bytes = 4)]
+                struct Mapping {
+                    #[wire(bytes = 1)]
+                    mapping_bit_len: u8,
+                    #[wire(bytes = 1)]
+                    sub_index: u8,
+                    #[wire(bytes = 2)]
+                    index: u16,
+                }
+
+                impl SdoExpedited for Mapping {}
+
+                let Mapping {
+                    index,
+                    sub_index,
+                    mapping_bit_len,
+                } = self
+                    .sdo_read_expedited::<Mapping>(pdo, SubIndex::Index(i))
+                    .await?;
+
+                fmt::trace!(
+                    "----> index {:#06x}, sub index {}, bit length {}",
+                    index,
+                    sub_index,
+                    mapping_bit_len,
+                );
+
+                on_entry(GroupPdoEntry {
+                    configured_address: self.configured_address,
+                    direction,
+                    index,
+                    sub_index,
+                    bit_len: mapping_bit_len,
+                    bit_offset: sm_bit_len,
+                });
+
+                sm_bit_len += u16::from(mapping_bit_len);
+            }
         }
 
-        Ok(PdiSegment {
-            bit_len: total_bit_len.into(),
-            bytes: start_offset.up_to(*offset),
-        })
+        Ok(sm_bit_len)
+    }
+}
+
+/// The error returned when a SubDevice found at `position` is not the SubDevice seen there during
+/// initialisation.
+pub(crate) fn identity_mismatch(
+    position: u16,
+    expected: SubDeviceIdentity,
+    found: SubDeviceIdentity,
+) -> Error {
+    let expected = ExpectedSubDevice {
+        position,
+        vendor_id: expected.vendor_id,
+        product_id: expected.product_id,
+        revision: Some(expected.revision),
+        alias: None,
+    };
+
+    let first = if expected.is_product(&found) {
+        TopologyMismatch::WrongRevision {
+            expected,
+            found: found.revision,
+        }
+    } else {
+        TopologyMismatch::WrongProduct { expected, found }
+    };
+
+    Error::TopologyMismatch { first, count: 1 }
+}
+
+/// Create a PDI segment spanning from the first mapping's start to the end of the last mapping.
+fn segment(start: PdiOffset, end: PdiOffset, bit_len: u16) -> PdiSegment {
+    if bit_len == 0 {
+        return PdiSegment {
+            bytes: start.start_address as usize..start.start_address as usize,
+            ..PdiSegment::default()
+        };
+    }
+
+    PdiSegment {
+        bytes: start.up_to(end),
+        bit_len: bit_len.into(),
+        start_bit: start.start_bit,
+        // Byte-aligned mappings always start and end on a byte boundary
+        bit_packed: start.start_bit != 0 || end.start_bit != 0,
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum PdoDirection {
     MasterRead,
     MasterWrite,
@@ -213,7 +213,12 @@ where
         Ok(mappings)
     }
 
-    async fn pdos(&self, direction: PdoType) -> Result<heapless::Vec<Pdo, 64>, Error> {
+    /// Read all PDOs of the given type, calling `on_entry` with each PDO's entries in order.
+    async fn pdos(
+        &self,
+        direction: PdoType,
+        mut on_entry: impl FnMut(PdoEntry),
+    ) -> Result<heapless::Vec<Pdo, 64>, Error> {
         let mut pdos = heapless::Vec::new();
 
         fmt::trace!("Get {:?} PDOs", direction);
@@ -234,6 +239,8 @@ where
                 fmt::debug!("--> PDO entry:\n{:#?}", entry);
 
                 pdo.bit_len += u16::from(entry.data_length_bits);
+
+                on_entry(entry);
             }
 
             pdos.push(pdo).map_err(|_| {
@@ -246,14 +253,51 @@ where
         Ok(pdos)
     }
 
+    /// Read all PDOs of the given type, adding each PDO's entries to `entries` in order.
+    ///
+    /// Entries that don't fit in `entries` are logged and discarded.
+    async fn pdos_with_entries<const N: usize>(
+        &self,
+        direction: PdoType,
+        entries: &mut heapless::Vec<PdoEntry, N>,
+    ) -> Result<heapless::Vec<Pdo, 64>, Error> {
+        self.pdos(direction, |entry| {
+            if let Err(entry) = entries.push(entry) {
+                fmt::warn!(
+                    "Too many PDO entries, max {}. Entry {:#06x}:{} will not be available",
+                    N,
+                    entry.index,
+                    entry.sub_index
+                );
+            }
+        })
+        .await
+    }
+
     /// Transmit PDOs (from device's perspective) - inputs
     pub(crate) async fn maindevice_read_pdos(&self) -> Result<heapless::Vec<Pdo, 64>, Error> {
-        self.pdos(PdoType::Tx).await
+        self.pdos(PdoType::Tx, |_| ()).await
     }
 
     /// Receive PDOs (from device's perspective) - outputs
     pub(crate) async fn maindevice_write_pdos(&self) -> Result<heapless::Vec<Pdo, 64>, Error> {
-        self.pdos(PdoType::Rx).await
+        self.pdos(PdoType::Rx, |_| ()).await
+    }
+
+    /// Transmit PDOs (from device's perspective) and their entries - inputs
+    pub(crate) async fn maindevice_read_pdo_entries<const N: usize>(
+        &self,
+        entries: &mut heapless::Vec<PdoEntry, N>,
+    ) -> Result<heapless::Vec<Pdo, 64>, Error> {
+        self.pdos_with_entries(PdoType::Tx, entries).await
+    }
+
+    /// Receive PDOs (from device's perspective) and their entries - outputs
+    pub(crate) async fn maindevice_write_pdo_entries<const N: usize>(
+        &self,
+        entries: &mut heapless::Vec<PdoEntry, N>,
+    ) -> Result<heapless::Vec<Pdo, 64>, Error> {
+        self.pdos_with_entries(PdoType::Rx, entries).await
     }
 
     /// Find a string in the device EEPROM.
@@ -649,8 +693,17 @@ mod tests {
     async fn subdevice_no_pdos() {
         let e = SubDeviceEeprom::new(EepromFile::new("dumps/eeprom/ek1100.hex"));
 
-        assert_eq!(e.maindevice_read_pdos().await, Ok(heapless::Vec::new()));
-        assert_eq!(e.maindevice_write_pdos().await, Ok(heapless::Vec::new()));
+        let mut entries = heapless::Vec::<PdoEntry, 16>::new();
+
+        assert_eq!(
+            e.maindevice_read_pdo_entries(&mut entries).await,
+            Ok(heapless::Vec::new())
+        );
+        assert_eq!(
+            e.maindevice_write_pdo_entries(&mut entries).await,
+            Ok(heapless::Vec::new())
+        );
+        assert!(entries.is_empty());
     }
 
     #[tokio::test]
@@ -709,11 +762,32 @@ mod tests {
             pdo(0x1607, 13, 0x7070),
         ];
 
-        assert_eq!(e.maindevice_read_pdos().await, Ok(heapless::Vec::new()));
+        let mut entries = heapless::Vec::<PdoEntry, 16>::new();
+
+        assert_eq!(
+            e.maindevice_read_pdo_entries(&mut entries).await,
+            Ok(heapless::Vec::new())
+        );
         pretty_assertions::assert_eq!(
-            e.maindevice_write_pdos().await,
+            e.maindevice_write_pdo_entries(&mut entries).await,
             Ok(heapless::Vec::from_slice(&output_pdos).unwrap())
         );
+        pretty_assertions::assert_eq!(
+            entries
+                .iter()
+                .map(|entry| (entry.index, entry.sub_index, entry.data_length_bits))
+                .collect::<heapless::Vec<_, 16>>(),
+            [
+                (0x7000, 1, 1),
+                (0x7010, 1, 1),
+                (0x7020, 1, 1),
+                (0x7030, 1, 1),
+                (0x7040, 1, 1),
+                (0x7050, 1, 1),
+                (0x7060, 1, 1),
+                (0x7070, 1, 1),
+            ]
+        );
     }
 
     // This exercises the "read from a specific address" codepath as opposed to the "find a category
@@ -2,6 +2,8 @@ pub(crate) mod configuration;
 mod dc;
 mod eeprom;
 pub mod pdi;
+pub(crate) mod pdo_entries;
+mod pdo_mapping;
 pub mod ports;
 mod types;
 
@@ -14,8 +16,12 @@ use crate::{
     },
     command::Command,
     dl_status::DlStatus,
-    eeprom::{device_reader::DeviceEeprom, types::SiiOwner},
-    error::{Error, MailboxError, PduError},
+    eeprom::{
+        device_reader::DeviceEeprom,
+        types::{Flags, SiiOwner},
+    },
+    error::{EepromError, Error, MailboxError, PduError},
+    error_counters::ErrorCounters,
     fmt,
     mailbox::{MailboxHeader, MailboxType},
     maindevice::MainDevice,
@@ -24,7 +30,8 @@ use crate::{
     subdevice::{ports::Ports, types::SubDeviceConfig},
     subdevice_state::SubDeviceState,
     timer_factory::IntoTimeout,
-    WrappedRead, WrappedWrite,
+    watchdog::{WatchdogConfig, WatchdogStatus},
+    TransitionRetry, WrappedRead, WrappedWrite,
 };
 use core::{
     any::type_name,
@@ -37,10 +44,12 @@ use ethercrab_wire::{
     EtherCrabWireWrite,
 };
 
-pub use self::pdi::SubDevicePdi;
+pub use self::pdi::{InputEntry, OutputEntryMut, OutputsGuard, PdiBits, PdiBitsMut, SubDevicePdi};
+pub use self::pdo_mapping::PdoMapping;
 pub use self::types::IoRanges;
 pub use self::types::SubDeviceIdentity;
-use self::{eeprom::SubDeviceEeprom, types::Mailbox};
+pub use self::types::TransitionFailure;
+use self::{eeprom::SubDeviceEeprom, pdo_entries::MAX_PDO_ENTRIES, types::Mailbox};
 pub use dc::DcSync;
 
 /// SubDevice device metadata. See [`SubDeviceRef`] for richer behaviour.
@@ -366,6 +375,34 @@ where
         SubDevice::description(&self.state, &self.maindevice).await
     }
 
+    /// Get the name of a PDO entry from the SubDevice's EEPROM, e.g. `"Statusword"` for
+    /// `0x6041:00`.
+    ///
+    /// Returns `Ok(None)` if the entry is not described in the EEPROM or has no name.
+    pub async fn pdo_entry_name(
+        &self,
+        index: u16,
+        sub_index: u8,
+    ) -> Result<Option<heapless::String<64>>, Error> {
+        let mut entries = heapless::Vec::<_, MAX_PDO_ENTRIES>::new();
+
+        self.eeprom()
+            .maindevice_read_pdo_entries(&mut entries)
+            .await?;
+        self.eeprom()
+            .maindevice_write_pdo_entries(&mut entries)
+            .await?;
+
+        let Some(entry) = entries
+            .iter()
+            .find(|entry| entry.index == index && entry.sub_index == sub_index)
+        else {
+            return Ok(None);
+        };
+
+        self.eeprom().find_string(entry.name_string_idx).await
+    }
+
     /// Get additional identifying details for the SubDevice.
     pub fn identity(&self) -> SubDeviceIdentity {
         self.state.identity
@@ -918,6 +955,11 @@ impl<'a, S> SubDeviceRef<'a, S> {
         }
     }
 
+    /// Take the SubDevice state back out of this reference.
+    pub(crate) fn into_state(self) -> S {
+        self.state
+    }
+
     /// Get the configured station address of the SubDevice.
     pub fn configured_address(&self) -> u16 {
         self.configured_address
@@ -961,6 +1003,217 @@ impl<'a, S> SubDeviceRef<'a, S> {
         futures_lite::future::try_zip(self.state(), code).await
     }
 
+    /// Read the ESC error counters, registers `0x0300` to `0x0313`.
+    ///
+    /// Counters that increase over time point to a bad cable or connector on the corresponding
+    /// port. Use [`SubDeviceGroup::error_counters`](crate::SubDeviceGroup::error_counters) to
+    /// locate it across a whole group.
+    pub async fn error_counters(&self) -> Result<ErrorCounters, Error> {
+        self.read(RegisterAddress::RxErrorCounter)
+            .receive::<ErrorCounters>(self.maindevice)
+            .await
+    }
+
+    /// Reset all ESC error counters to zero.
+    pub async fn reset_error_counters(&self) -> Result<(), Error> {
+        // Writing to a counter resets it, or for RX and lost link counters, all of them
+        let counters = self
+            .write(RegisterAddress::RxErrorCounter)
+            .send(self.maindevice, [0u8; 14]);
+        let lost_links = self
+            .write(RegisterAddress::LostLinkCounter)
+            .send(self.maindevice, [0u8; 4]);
+
+        futures_lite::future::try_zip(counters, lost_links).await?;
+
+        Ok(())
+    }
+
+    /// Set the sync manager and PDI watchdog timeouts.
+    ///
+    /// Watchdogs should be configured in PRE-OP, before the SubDevice's outputs are enabled. To
+    /// configure every SubDevice in a group, set
+    /// [`GroupConfig::watchdog`](crate::subdevice_group::GroupConfig::watchdog) instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::IntegerTypeConversion`] if a timeout is longer than the longest supported
+    /// watchdog time of roughly 171 seconds.
+    pub async fn configure_watchdogs(&self, config: WatchdogConfig) -> Result<(), Error> {
+        let registers = config.registers()?;
+
+        fmt::debug!(
+            "SubDevice {:#06x} watchdog divider {}, sync manager {}, PDI {}",
+            self.configured_address,
+            registers.divider,
+            registers.sync_manager,
+            registers.pdi
+        );
+
+        self.write(RegisterAddress::WatchdogDivider)
+            .send(self.maindevice, registers.divider)
+            .await?;
+
+        self.write(RegisterAddress::PdiWatchdog)
+            .send(self.maindevice, registers.pdi)
+            .await?;
+
+        self.write(RegisterAddress::SyncManagerWatchdog)
+            .send(self.maindevice, registers.sync_manager)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Read the sync manager watchdog status and the watchdog expiry counters.
+    pub async fn watchdog_status(&self) -> Result<WatchdogStatus, Error> {
+        self.read(RegisterAddress::SyncManagerWatchdogStatus)
+            .receive::<WatchdogStatus>(self.maindevice)
+            .await
+    }
+
+    /// Read the SubDevice's Explicit Device ID as defined in ETG.1020, e.g. the value of a rotary
+    /// switch used to tell identical SubDevices apart.
+    ///
+    /// The method the SubDevice supports is read from its EEPROM:
+    ///
+    /// 1. The ID is requested with the ID request bit of the AL control register and read from
+    ///    the AL status code register once the SubDevice sets the ID loaded bit in its AL status.
+    /// 2. The ID is read from the ESC memory address given in the EEPROM.
+    /// 3. Otherwise, the legacy method is used and the ID is read from the configured station
+    ///    alias register `0x0012`.
+    ///
+    /// The SubDevice stays in its current state.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ethercrab::{
+    ///     std::ethercat_now, MainDevice, MainDeviceConfig, PduStorage, Timeouts,
+    /// };
+    ///
+    /// static PDU_STORAGE: PduStorage<16, { PduStorage::element_size(1100) }> = PduStorage::new();
+    ///
+    /// let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    ///
+    /// let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+    ///
+    /// # async {
+    /// let mut group = maindevice
+    ///     .init_single_group::<16, 64>(ethercat_now)
+    ///     .await
+    ///     .expect("Init");
+    ///
+    /// for subdevice in group.iter(&maindevice) {
+    ///     let id = subdevice.explicit_device_id().await.expect("Read ID");
+    ///
+    ///     println!("{} has device ID {:#06x}", subdevice.name(), id);
+    /// }
+    /// # };
+    /// ```
+    pub async fn explicit_device_id(&self) -> Result<u16, Error> {
+        let general = match self.eeprom().general().await {
+            Ok(general) => Some(general),
+            Err(Error::Eeprom(EepromError::NoCategory)) => None,
+            Err(e) => return Err(e),
+        };
+
+        let id = match general {
+            Some(general) if general.flags.contains(Flags::IDENT_AL_STATUS) => {
+                self.request_device_id().await?
+            }
+            Some(general) if general.flags.contains(Flags::IDENT_PHY_M) => {
+                self.read(general.physical_memory_addr)
+                    .receive::<u16>(self.maindevice)
+                    .await?
+            }
+            _ => {
+                self.read(RegisterAddress::ConfiguredStationAlias)
+                    .receive::<u16>(self.maindevice)
+                    .await?
+            }
+        };
+
+        fmt::debug!(
+            "SubDevice {:#06x} Explicit Device ID {:#06x}",
+            self.configured_address,
+            id
+        );
+
+        Ok(id)
+    }
+
+    /// Return [`Error::DeviceIdMismatch`] if the SubDevice's Explicit Device ID is not `expected`.
+    pub(crate) async fn check_device_id(&self, expected: u16) -> Result<(), Error> {
+        let actual = self.explicit_device_id().await?;
+
+        if actual != expected {
+            fmt::error!(
+                "SubDevice {:#06x} has Explicit Device ID {:#06x}, expected {:#06x}",
+                self.configured_address,
+                actual,
+                expected
+            );
+
+            return Err(Error::DeviceIdMismatch {
+                configured_address: self.configured_address,
+                expected,
+                actual,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Request the Explicit Device ID using the ID request bit in the AL control register.
+    async fn request_device_id(&self) -> Result<u16, Error> {
+        let status = self
+            .read(RegisterAddress::AlStatus)
+            .receive::<AlControl>(self.maindevice)
+            .await?;
+
+        self.write(RegisterAddress::AlControl)
+            .send(self.maindevice, AlControl::id_request(status.state))
+            .await?;
+
+        let id = async {
+            loop {
+                let status = self
+                    .read(RegisterAddress::AlStatus)
+                    .receive::<AlControl>(self.maindevice)
+                    .await?;
+
+                if status.error {
+                    let code = self
+                        .read(RegisterAddress::AlStatusCode)
+                        .receive::<AlStatusCode>(self.maindevice)
+                        .await?;
+
+                    break Err(Error::SubDevice(code));
+                }
+
+                // The ID request bit in AL status is the ID loaded flag
+                if status.id_request {
+                    break self
+                        .read(RegisterAddress::AlStatusCode)
+                        .receive::<u16>(self.maindevice)
+                        .await;
+                }
+
+                self.maindevice.timeouts.loop_tick().await;
+            }
+        }
+        .timeout(self.maindevice.timeouts.state_transition)
+        .await;
+
+        // Clear the request so the AL status code register reports errors again
+        self.write(RegisterAddress::AlControl)
+            .send(self.maindevice, AlControl::new(status.state))
+            .await?;
+
+        id
+    }
+
     fn eeprom(&self) -> SubDeviceEeprom<DeviceEeprom> {
         SubDeviceEeprom::new(DeviceEeprom::new(self.maindevice, self.configured_address))
     }
@@ -1002,6 +1255,16 @@ impl<'a, S> SubDeviceRef<'a, S> {
                     break Ok(());
                 }
 
+                // The SubDevice refused the transition so will never reach the desired state
+                if status.error {
+                    let code = self
+                        .read(RegisterAddress::AlStatusCode)
+                        .receive::<AlStatusCode>(self.maindevice)
+                        .await?;
+
+                    break Err(Error::SubDevice(code));
+                }
+
                 self.maindevice.timeouts.loop_tick().await;
             }
         }
@@ -1035,7 +1298,7 @@ impl<'a, S> SubDeviceRef<'a, S> {
 
         if response.error {
             let error = self
-                .read(RegisterAddress::AlStatus)
+                .read(RegisterAddress::AlStatusCode)
                 .receive::<AlStatusCode>(self.maindevice)
                 .await?;
 
@@ -1056,9 +1319,150 @@ impl<'a, S> SubDeviceRef<'a, S> {
         &self,
         desired_state: SubDeviceState,
     ) -> Result<(), Error> {
-        self.request_subdevice_state_nowait(desired_state).await?;
+        self.request_state(desired_state, |_| ()).await
+    }
+
+    /// Request a new state and wait for the SubDevice to reach it.
+    ///
+    /// If the SubDevice refuses the transition, `on_failure` is called with the AL status code it
+    /// gave. The error is then handled according to
+    /// [`MainDevice::with_transition_retry`](crate::MainDevice::with_transition_retry): either
+    /// returned straight away, or acknowledged and the transition requested again, calling
+    /// `on_failure` for every failed attempt.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::SubDevice`] with the AL status code of the last attempt if the SubDevice
+    /// refuses the transition, or [`Error::Timeout`] if it does not reach the desired state in
+    /// time.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ethercrab::{
+    ///     std::ethercat_now, MainDevice, MainDeviceConfig, PduStorage, SubDeviceState,
+    ///     Timeouts, TransitionRetry,
+    /// };
+    ///
+    /// static PDU_STORAGE: PduStorage<16, { PduStorage::element_size(1100) }> = PduStorage::new();
+    ///
+    /// let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    ///
+    /// let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default())
+    ///     .with_transition_retry(TransitionRetry::Count(3));
+    ///
+    /// # async {
+    /// let group = maindevice
+    ///     .init_single_group::<16, 64>(ethercat_now)
+    ///     .await
+    ///     .expect("Init");
+    ///
+    /// let subdevice = group.subdevice(&maindevice, 0).expect("No SubDevice");
+    ///
+    /// subdevice
+    ///     .request_state(SubDeviceState::SafeOp, |failure| {
+    ///         eprintln!(
+    ///             "Attempt {} to go to {} failed: {}",
+    ///             failure.attempt, failure.state, failure.code
+    ///         );
+    ///     })
+    ///     .await
+    ///     .expect("PRE-OP -> SAFE-OP");
+    /// # };
+    /// ```
+    pub async fn request_state(
+        &self,
+        desired_state: SubDeviceState,
+        mut on_failure: impl FnMut(TransitionFailure),
+    ) -> Result<(), Error> {
+        let mut attempt = 0;
+
+        loop {
+            let result = match self.request_subdevice_state_nowait(desired_state).await {
+                Ok(()) => self.wait_for_state(desired_state).await,
+                Err(e) => Err(e),
+            };
 
-        self.wait_for_state(desired_state).await
+            let code = match result {
+                Err(Error::SubDevice(code)) => code,
+                result => break result,
+            };
+
+            fmt::warn!(
+                "SubDevice {:#06x} refused transition to {} (attempt {}): {}",
+                self.configured_address,
+                desired_state,
+                attempt,
+                code
+            );
+
+            on_failure(TransitionFailure {
+                configured_address: self.configured_address,
+                state: desired_state,
+                attempt,
+                code,
+            });
+
+            let TransitionRetry::Count(retries) = self.maindevice.transition_retry else {
+                break Err(Error::SubDevice(code));
+            };
+
+            self.acknowledge_error().await?;
+
+            if attempt >= retries {
+                break Err(Error::SubDevice(code));
+            }
+
+            attempt += 1;
+        }
+    }
+
+    /// Acknowledge an error reported by the SubDevice in its AL status register.
+    ///
+    /// The SubDevice stays in its current state. Returns the AL status code describing the error,
+    /// or [`AlStatusCode::NoError`] if no error was set.
+    pub async fn acknowledge_error(&self) -> Result<AlStatusCode, Error> {
+        let status = self
+            .read(RegisterAddress::AlStatus)
+            .receive::<AlControl>(self.maindevice)
+            .await?;
+
+        if !status.error {
+            return Ok(AlStatusCode::NoError);
+        }
+
+        let code = self
+            .read(RegisterAddress::AlStatusCode)
+            .receive::<AlStatusCode>(self.maindevice)
+            .await?;
+
+        fmt::debug!(
+            "Acknowledging SubDevice {:#06x} error in state {}: {}",
+            self.configured_address,
+            status.state,
+            code
+        );
+
+        self.write(RegisterAddress::AlControl)
+            .send(self.maindevice, AlControl::acknowledge(status.state))
+            .await?;
+
+        async {
+            loop {
+                let status = self
+                    .read(RegisterAddress::AlStatus)
+                    .receive::<AlControl>(self.maindevice)
+                    .await?;
+
+                if !status.error {
+                    break Ok(code);
+                }
+
+                self.maindevice.timeouts.loop_tick().await;
+            }
+        }
+        .timeout(self.maindevice.timeouts.state_transition)
+        .await
     }
 
     pub(crate) async fn set_eeprom_mode(&self, mode: SiiOwner) -> Result<(), Error> {
@@ -1074,4 +1478,11 @@ impl<'a, S> SubDeviceRef<'a, S> {
 
         Ok(())
     }
+
+    /// Read the SubDevice's identity from its EEPROM.
+    pub(crate) async fn read_identity(&self) -> Result<SubDeviceIdentity, Error> {
+        self.set_eeprom_mode(SiiOwner::Master).await?;
+
+        self.eeprom().identity().await
+    }
 }
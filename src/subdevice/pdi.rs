@@ -1,6 +1,19 @@
-use super::{SubDevice, SubDeviceRef};
+use super::{
+    configuration::PdoDirection,
+    pdo_entries::{GroupPdoEntry, PdoEntries, MAX_PDO_ENTRIES},
+    SubDevice, SubDeviceRef,
+};
+use crate::{
+    error::{Error, Item},
+    fmt,
+    pdi::PdiSegment,
+};
 use atomic_refcell::AtomicRefMut;
-use core::ops::Deref;
+use core::{
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicU8, Ordering},
+};
+use ethercrab_wire::{EtherCrabWireReadSized, EtherCrabWireSized, EtherCrabWireWrite};
 
 /// Process Data Image (PDI) segments for a given SubDevice.
 ///
@@ -13,6 +26,15 @@ pub struct SubDevicePdi<'group> {
     inputs: &'group [u8],
 
     outputs: &'group mut [u8],
+
+    /// Bit-packed inputs that share PDI bytes with other SubDevices.
+    packed_inputs: &'group [u8],
+
+    /// Bit-packed outputs that share PDI bytes with other SubDevices.
+    packed_outputs: &'group [AtomicU8],
+
+    /// The PDO entries mapped into every SubDevice in the group.
+    pdo_entries: &'group [GroupPdoEntry],
 }
 
 impl<'group> Deref for SubDevicePdi<'group> {
@@ -28,19 +50,371 @@ impl<'group> SubDevicePdi<'group> {
         subdevice: AtomicRefMut<'group, SubDevice>,
         inputs: &'group [u8],
         outputs: &'group mut [u8],
+        packed_inputs: &'group [u8],
+        packed_outputs: &'group [AtomicU8],
+        pdo_entries: &'group [GroupPdoEntry],
     ) -> Self {
         Self {
             subdevice,
             inputs,
             outputs,
+            packed_inputs,
+            packed_outputs,
+            pdo_entries,
         }
     }
 }
 
+/// A bit-accurate, read only view of a SubDevice's inputs in the Process Data Image (PDI).
+///
+/// Returned by [`SubDeviceRef::input_bits`].
+#[derive(Debug, Copy, Clone)]
+pub struct PdiBits<'a> {
+    data: &'a [u8],
+    start_bit: usize,
+    len: usize,
+}
+
+impl<'a> PdiBits<'a> {
+    fn new(data: &'a [u8], segment: &PdiSegment) -> Self {
+        Self {
+            data,
+            start_bit: usize::from(segment.start_bit),
+            len: segment.bit_len,
+        }
+    }
+
+    /// The number of bits in this view.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether this view contains no bits.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the value of a bit, or `None` if the index is out of range.
+    pub fn get(&self, index: usize) -> Option<bool> {
+        if index >= self.len {
+            return None;
+        }
+
+        let bit = self.start_bit + index;
+
+        self.data
+            .get(bit / 8)
+            .map(|byte| byte & (1 << (bit % 8)) != 0)
+    }
+
+    /// Iterate over every bit in this view.
+    pub fn iter(&self) -> impl Iterator<Item = bool> + 'a {
+        let bits = *self;
+
+        (0..bits.len).filter_map(move |index| bits.get(index))
+    }
+
+    /// Pack the bits in this view into a byte, starting at bit 0.
+    fn load_byte(&self) -> u8 {
+        let mut byte = [0u8; 1];
+
+        self.load(&mut byte);
+
+        byte[0]
+    }
+
+    /// Pack the bits in this view into `buf`, starting at bit 0 of the first byte.
+    fn load(&self, buf: &mut [u8]) {
+        for (i, bit) in self.iter().enumerate().take(buf.len() * 8) {
+            buf[i / 8] |= u8::from(bit) << (i % 8);
+        }
+    }
+
+    /// Get a view of `len` bits starting `offset` bits into this view.
+    fn slice(&self, offset: usize, len: usize) -> Self {
+        Self {
+            data: self.data,
+            start_bit: self.start_bit + offset,
+            len,
+        }
+    }
+}
+
+/// A bit-accurate, mutable view of a SubDevice's outputs in the Process Data Image (PDI).
+///
+/// Bits are written atomically, so SubDevices whose outputs share a byte in a bit-packed PDI can
+/// be updated independently.
+///
+/// Returned by [`SubDeviceRef::output_bits_mut`].
+#[derive(Debug)]
+pub struct PdiBitsMut<'a> {
+    data: &'a [AtomicU8],
+    start_bit: usize,
+    len: usize,
+}
+
+impl<'a> PdiBitsMut<'a> {
+    fn new(data: &'a [AtomicU8], segment: &PdiSegment) -> Self {
+        Self {
+            data,
+            start_bit: usize::from(segment.start_bit),
+            len: segment.bit_len,
+        }
+    }
+
+    /// The number of bits in this view.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether this view contains no bits.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the value of a bit, or `None` if the index is out of range.
+    pub fn get(&self, index: usize) -> Option<bool> {
+        let (byte, mask) = self.position(index)?;
+
+        Some(byte.load(Ordering::Relaxed) & mask != 0)
+    }
+
+    /// Set the value of a bit.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotFound`] if the index is out of range.
+    pub fn set(&mut self, index: usize, value: bool) -> Result<(), Error> {
+        let (byte, mask) = self.position(index).ok_or(Error::NotFound {
+            item: Item::PdoEntry,
+            index: Some(index),
+        })?;
+
+        if value {
+            byte.fetch_or(mask, Ordering::Relaxed);
+        } else {
+            byte.fetch_and(!mask, Ordering::Relaxed);
+        }
+
+        Ok(())
+    }
+
+    /// Iterate over every bit in this view.
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len).filter_map(move |index| self.get(index))
+    }
+
+    /// Pack the bits in this view into a byte, starting at bit 0.
+    fn load_byte(&self) -> u8 {
+        let mut byte = [0u8; 1];
+
+        self.load(&mut byte);
+
+        byte[0]
+    }
+
+    /// Pack the bits in this view into `buf`, starting at bit 0 of the first byte.
+    fn load(&self, buf: &mut [u8]) {
+        for (i, bit) in self.iter().enumerate().take(buf.len() * 8) {
+            buf[i / 8] |= u8::from(bit) << (i % 8);
+        }
+    }
+
+    /// Write the bits of a byte into this view, starting at bit 0.
+    fn store_byte(&mut self, byte: u8) {
+        self.store(&[byte]);
+    }
+
+    /// Write the bits of `buf` into this view, starting at bit 0 of the first byte.
+    fn store(&mut self, buf: &[u8]) {
+        for i in 0..self.len.min(buf.len() * 8) {
+            // Index is always in range
+            let _ = self.set(i, buf[i / 8] & (1 << (i % 8)) != 0);
+        }
+    }
+
+    /// Get a view of `len` bits starting `offset` bits into this view.
+    fn into_slice(self, offset: usize, len: usize) -> Self {
+        Self {
+            data: self.data,
+            start_bit: self.start_bit + offset,
+            len,
+        }
+    }
+
+    fn position(&self, index: usize) -> Option<(&AtomicU8, u8)> {
+        if index >= self.len {
+            return None;
+        }
+
+        let bit = self.start_bit + index;
+
+        self.data.get(bit / 8).map(|byte| (byte, 1 << (bit % 8)))
+    }
+}
+
+/// Maximum length of a PDO entry in bytes.
+const MAX_ENTRY_BYTES: usize = (u8::MAX as usize + 7) / 8;
+
+/// A single mapped PDO entry in a SubDevice's inputs.
+///
+/// Returned by [`SubDeviceRef::input_entry`].
+#[derive(Debug, Copy, Clone)]
+pub struct InputEntry<'a> {
+    bits: PdiBits<'a>,
+    index: u16,
+    sub_index: u8,
+}
+
+impl<'a> InputEntry<'a> {
+    /// Object index of this entry, e.g. `0x6041`.
+    pub fn index(&self) -> u16 {
+        self.index
+    }
+
+    /// Object sub-index of this entry.
+    pub fn sub_index(&self) -> u8 {
+        self.sub_index
+    }
+
+    /// Length of this entry in bits.
+    pub fn bit_len(&self) -> usize {
+        self.bits.len()
+    }
+
+    /// Offset of the first byte of this entry from the first PDI byte of the SubDevice's inputs.
+    pub fn byte_offset(&self) -> usize {
+        self.bits.start_bit / 8
+    }
+
+    /// The bit, 0-7, this entry starts at in the byte at [`byte_offset`](InputEntry::byte_offset).
+    pub fn bit_offset(&self) -> u8 {
+        (self.bits.start_bit % 8) as u8
+    }
+
+    /// Get a bit-accurate view of this entry.
+    pub fn bits(&self) -> PdiBits<'a> {
+        self.bits
+    }
+
+    /// Read this entry into a typed value.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::PdiLength`] if the packed length of `T` does not match the length of the
+    /// entry, or an [`Error::Wire`] if the data could not be decoded.
+    pub fn read<T>(&self) -> Result<T, Error>
+    where
+        T: EtherCrabWireReadSized,
+    {
+        read_entry(self.bits.len(), |buf| self.bits.load(buf))
+    }
+}
+
+/// A single mapped PDO entry in a SubDevice's outputs.
+///
+/// Returned by [`SubDeviceRef::output_entry_mut`].
+#[derive(Debug)]
+pub struct OutputEntryMut<'a> {
+    bits: PdiBitsMut<'a>,
+    index: u16,
+    sub_index: u8,
+}
+
+impl<'a> OutputEntryMut<'a> {
+    /// Object index of this entry, e.g. `0x6040`.
+    pub fn index(&self) -> u16 {
+        self.index
+    }
+
+    /// Object sub-index of this entry.
+    pub fn sub_index(&self) -> u8 {
+        self.sub_index
+    }
+
+    /// Length of this entry in bits.
+    pub fn bit_len(&self) -> usize {
+        self.bits.len()
+    }
+
+    /// Offset of the first byte of this entry from the first PDI byte of the SubDevice's outputs.
+    pub fn byte_offset(&self) -> usize {
+        self.bits.start_bit / 8
+    }
+
+    /// The bit, 0-7, this entry starts at in the byte at
+    /// [`byte_offset`](OutputEntryMut::byte_offset).
+    pub fn bit_offset(&self) -> u8 {
+        (self.bits.start_bit % 8) as u8
+    }
+
+    /// Get a bit-accurate, mutable view of this entry.
+    pub fn bits_mut(&mut self) -> &mut PdiBitsMut<'a> {
+        &mut self.bits
+    }
+
+    /// Read the current value of this entry into a typed value.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::PdiLength`] if the packed length of `T` does not match the length of the
+    /// entry, or an [`Error::Wire`] if the data could not be decoded.
+    pub fn read<T>(&self) -> Result<T, Error>
+    where
+        T: EtherCrabWireReadSized,
+    {
+        read_entry(self.bits.len(), |buf| self.bits.load(buf))
+    }
+
+    /// Write a typed value into this entry.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::PdiLength`] if the packed length of `T` does not match the length of the
+    /// entry.
+    pub fn write<T>(&mut self, value: T) -> Result<(), Error>
+    where
+        T: EtherCrabWireWrite + EtherCrabWireSized,
+    {
+        let len = (self.bits.len() + 7) / 8;
+
+        check_type_length::<T>(len)?;
+
+        let mut buf = [0u8; MAX_ENTRY_BYTES];
+        let buf = buf.get_mut(..len).ok_or(Error::Internal)?;
+
+        value.pack_to_slice_unchecked(buf);
+
+        self.bits.store(buf);
+
+        Ok(())
+    }
+}
+
+/// Read a PDO entry of `bit_len` bits into a typed value, using `load` to fill a byte buffer.
+fn read_entry<T>(bit_len: usize, load: impl FnOnce(&mut [u8])) -> Result<T, Error>
+where
+    T: EtherCrabWireReadSized,
+{
+    let len = (bit_len + 7) / 8;
+
+    check_type_length::<T>(len)?;
+
+    let mut buf = [0u8; MAX_ENTRY_BYTES];
+    let buf = buf.get_mut(..len).ok_or(Error::Internal)?;
+
+    load(buf);
+
+    T::unpack_from_slice(buf).map_err(Error::from)
+}
+
 /// Methods used when a SubDevice is part of a group and part of the PDI has been mapped to it.
 impl<'a, 'group> SubDeviceRef<'a, SubDevicePdi<'group>> {
     /// Get a tuple of (&I, &mut O) for this SubDevice in the Process Data Image (PDI).
     ///
+    /// Bit-packed inputs or outputs are returned as empty slices. See
+    /// [`inputs_raw`](SubDeviceRef::inputs_raw).
+    ///
     /// # Examples
     ///
     /// ## Disallow multiple mutable references
@@ -72,6 +446,9 @@ impl<'a, 'group> SubDeviceRef<'a, SubDevicePdi<'group>> {
 
     /// Get a tuple of (&I, &O) for this SubDevice in the Process Data Image (PDI).
     ///
+    /// Bit-packed inputs or outputs are returned as empty slices. See
+    /// [`inputs_raw`](SubDeviceRef::inputs_raw).
+    ///
     /// To get a mutable reference to the SubDevice outputs, see either
     /// [`io_raw_mut`](SubDeviceRef::io_raw_mut) or
     /// [`outputs_raw_mut`](SubDeviceRef::outputs_raw_mut).
@@ -107,18 +484,902 @@ impl<'a, 'group> SubDeviceRef<'a, SubDevicePdi<'group>> {
     }
 
     /// Get a reference to the raw input data for this SubDevice in the Process Data Image (PDI).
+    ///
+    /// When [`GroupConfig::bit_packed_pdi`](crate::subdevice_group::GroupConfig::bit_packed_pdi) is
+    /// enabled, SubDevices with fewer than 8 bits of inputs share PDI bytes with other SubDevices.
+    /// The bytes holding these inputs also hold other SubDevices' data, so an empty slice is
+    /// returned instead. Use [`input_bits`](SubDeviceRef::input_bits) or
+    /// [`inputs`](SubDeviceRef::inputs) to read them.
     pub fn inputs_raw(&self) -> &[u8] {
         self.state.inputs
     }
 
     /// Get a reference to the raw output data for this SubDevice in the Process Data Image (PDI).
+    ///
+    /// Bit-packed outputs are returned as an empty slice. Use
+    /// [`output_bits_mut`](SubDeviceRef::output_bits_mut) or
+    /// [`outputs_mut`](SubDeviceRef::outputs_mut) to access them.
     pub fn outputs_raw(&self) -> &[u8] {
         self.state.outputs
     }
 
     /// Get a mutable reference to the raw output data for this SubDevice in the Process Data Image
     /// (PDI).
+    ///
+    /// Bit-packed outputs are returned as an empty slice, as writing whole bytes would overwrite
+    /// other SubDevices' outputs. Use [`output_bits_mut`](SubDeviceRef::output_bits_mut) or
+    /// [`outputs_mut`](SubDeviceRef::outputs_mut) to write them.
     pub fn outputs_raw_mut(&mut self) -> &mut [u8] {
         self.state.outputs
     }
+
+    /// Get a bit-accurate view of this SubDevice's inputs.
+    ///
+    /// This works for all SubDevices, but is required to correctly read the inputs of SubDevices
+    /// that share PDI bytes with others when
+    /// [`GroupConfig::bit_packed_pdi`](crate::subdevice_group::GroupConfig::bit_packed_pdi) is
+    /// enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use ethercrab::{MainDevice, MainDeviceConfig, PduStorage, Timeouts};
+    /// # static PDU_STORAGE: PduStorage<8, 64> = PduStorage::new();
+    /// # let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    /// # let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+    /// # async {
+    /// # let group = maindevice.init_single_group::<8, 8>(ethercrab::std::ethercat_now).await.expect("Init");
+    /// # let group = group.into_op(&maindevice).await.expect("Op");
+    /// // E.g. an EL1004
+    /// let subdevice = group.subdevice(&maindevice, 1).expect("No device");
+    ///
+    /// for (channel, state) in subdevice.input_bits().iter().enumerate() {
+    ///     println!("Input {}: {}", channel + 1, state);
+    /// }
+    /// # };
+    /// ```
+    pub fn input_bits(&self) -> PdiBits<'_> {
+        let data = if self.state.inputs.is_empty() {
+            self.state.packed_inputs
+        } else {
+            self.state.inputs
+        };
+
+        PdiBits::new(data, &self.state.io_segments().input)
+    }
+
+    /// Get a bit-accurate, mutable view of this SubDevice's outputs.
+    ///
+    /// This works for all SubDevices, but is the only way to write the outputs of SubDevices that
+    /// share PDI bytes with others when
+    /// [`GroupConfig::bit_packed_pdi`](crate::subdevice_group::GroupConfig::bit_packed_pdi) is
+    /// enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use ethercrab::{MainDevice, MainDeviceConfig, PduStorage, Timeouts};
+    /// # static PDU_STORAGE: PduStorage<8, 64> = PduStorage::new();
+    /// # let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    /// # let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+    /// # async {
+    /// # let group = maindevice.init_single_group::<8, 8>(ethercrab::std::ethercat_now).await.expect("Init");
+    /// # let group = group.into_op(&maindevice).await.expect("Op");
+    /// // E.g. an EL2004
+    /// let mut subdevice = group.subdevice(&maindevice, 2).expect("No device");
+    ///
+    /// let mut outputs = subdevice.output_bits_mut();
+    ///
+    /// // Turn on the third output channel
+    /// outputs.set(2, true).expect("Out of range");
+    /// # };
+    /// ```
+    pub fn output_bits_mut(&mut self) -> PdiBitsMut<'_> {
+        let data = if self.state.outputs.is_empty() {
+            self.state.packed_outputs
+        } else {
+            let outputs: *mut [u8] = self.state.outputs;
+
+            // SAFETY: `AtomicU8` has the same layout as `u8`, and the outputs are mutably borrowed
+            // for the lifetime of the returned view so nothing else can access them.
+            unsafe { &*(outputs as *const [AtomicU8]) }
+        };
+
+        PdiBitsMut::new(data, &self.state.io_segments().output)
+    }
+
+    /// Read this SubDevice's inputs into a typed value.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::PdiLength`] if the packed length of `T` does not match the length of the
+    /// SubDevice's inputs, or an [`Error::Wire`] if the data could not be decoded.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use ethercrab::{MainDevice, MainDeviceConfig, PduStorage, Timeouts};
+    /// # static PDU_STORAGE: PduStorage<8, 64> = PduStorage::new();
+    /// # let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    /// # let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+    /// # async {
+    /// # let group = maindevice.init_single_group::<8, 8>(ethercrab::std::ethercat_now).await.expect("Init");
+    /// # let group = group.into_op(&maindevice).await.expect("Op");
+    /// /// EL3102 inputs.
+    /// #[derive(ethercrab_wire::EtherCrabWireRead)]
+    /// #[wire(bytes = 6)]
+    /// struct El3102Inputs {
+    ///     #[wire(bytes = 1)]
+    ///     status_1: u8,
+    ///     #[wire(bytes = 2)]
+    ///     value_1: i16,
+    ///     #[wire(bytes = 1)]
+    ///     status_2: u8,
+    ///     #[wire(bytes = 2)]
+    ///     value_2: i16,
+    /// }
+    ///
+    /// let subdevice = group.subdevice(&maindevice, 1).expect("No device");
+    ///
+    /// let inputs = subdevice.inputs::<El3102Inputs>().expect("Wrong inputs type");
+    ///
+    /// println!("Channel 1: {}", inputs.value_1);
+    /// # };
+    /// ```
+    pub fn inputs<T>(&self) -> Result<T, Error>
+    where
+        T: EtherCrabWireReadSized,
+    {
+        let segment = &self.state.io_segments().input;
+
+        check_length::<T>(segment)?;
+
+        if segment.bit_packed {
+            let byte = self.input_bits().load_byte();
+
+            T::unpack_from_slice(&[byte]).map_err(Error::from)
+        } else {
+            T::unpack_from_slice(self.state.inputs).map_err(Error::from)
+        }
+    }
+
+    /// Get a typed, mutable view of this SubDevice's outputs.
+    ///
+    /// The current outputs are decoded into a `T` held in the returned guard. Changes made through
+    /// the guard are written back to the PDI when it is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::PdiLength`] if the packed length of `T` does not match the length of the
+    /// SubDevice's outputs, or an [`Error::Wire`] if the current outputs could not be decoded.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use ethercrab::{MainDevice, MainDeviceConfig, PduStorage, Timeouts};
+    /// # static PDU_STORAGE: PduStorage<8, 64> = PduStorage::new();
+    /// # let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    /// # let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+    /// # async {
+    /// # let group = maindevice.init_single_group::<8, 8>(ethercrab::std::ethercat_now).await.expect("Init");
+    /// # let group = group.into_op(&maindevice).await.expect("Op");
+    /// /// EL2004 outputs.
+    /// #[derive(ethercrab_wire::EtherCrabWireReadWrite)]
+    /// #[wire(bits = 4)]
+    /// struct El2004Outputs {
+    ///     #[wire(bits = 1)]
+    ///     channel_1: bool,
+    ///     #[wire(bits = 1)]
+    ///     channel_2: bool,
+    ///     #[wire(bits = 1)]
+    ///     channel_3: bool,
+    ///     #[wire(bits = 1)]
+    ///     channel_4: bool,
+    /// }
+    ///
+    /// let mut subdevice = group.subdevice(&maindevice, 2).expect("No device");
+    ///
+    /// {
+    ///     let mut outputs = subdevice
+    ///         .outputs_mut::<El2004Outputs>()
+    ///         .expect("Wrong outputs type");
+    ///
+    ///     outputs.channel_2 = true;
+    /// }
+    ///
+    /// // Outputs are written back to the PDI when the guard is dropped
+    /// group.tx_rx(&maindevice).await.expect("TX/RX");
+    /// # };
+    /// ```
+    pub fn outputs_mut<T>(&mut self) -> Result<OutputsGuard<'_, T>, Error>
+    where
+        T: EtherCrabWireReadSized + EtherCrabWireWrite,
+    {
+        let segment = self.state.io_segments().output.clone();
+
+        check_length::<T>(&segment)?;
+
+        let target = if segment.bit_packed {
+            OutputsTarget::Bits(self.output_bits_mut())
+        } else {
+            OutputsTarget::Bytes(self.state.outputs)
+        };
+
+        let value = match &target {
+            OutputsTarget::Bytes(bytes) => T::unpack_from_slice(bytes)?,
+            OutputsTarget::Bits(bits) => T::unpack_from_slice(&[bits.load_byte()])?,
+        };
+
+        Ok(OutputsGuard { value, target })
+    }
+
+    /// Find a PDO entry mapped into this SubDevice's inputs by its object index and sub-index.
+    ///
+    /// The group's PDO entries must first be read with
+    /// [`SubDeviceGroup::read_pdo_entries`](crate::SubDeviceGroup::read_pdo_entries).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotFound`] if the entry is not mapped into the SubDevice's inputs, or the
+    /// group's PDO entries have not been read.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use ethercrab::{MainDevice, MainDeviceConfig, PduStorage, Timeouts};
+    /// # static PDU_STORAGE: PduStorage<8, 64> = PduStorage::new();
+    /// # let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    /// # let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+    /// # async {
+    /// # let group = maindevice.init_single_group::<8, 8>(ethercrab::std::ethercat_now).await.expect("Init");
+    /// # let mut group = group.into_op(&maindevice).await.expect("Op");
+    /// group.read_pdo_entries(&maindevice).await.expect("Read PDO entries");
+    ///
+    /// // A DS402 servo drive
+    /// let subdevice = group.subdevice(&maindevice, 0).expect("No device");
+    ///
+    /// let status_word = subdevice
+    ///     .input_entry(0x6041, 0)
+    ///     .expect("Status word not mapped")
+    ///     .read::<u16>()
+    ///     .expect("Read");
+    ///
+    /// println!("Status word {:#06x}", status_word);
+    /// # };
+    /// ```
+    pub fn input_entry(&self, index: u16, sub_index: u8) -> Result<InputEntry<'_>, Error> {
+        let (bit_offset, bit_len) = self.find_entry(PdoDirection::MasterRead, index, sub_index)?;
+
+        Ok(InputEntry {
+            bits: self.input_bits().slice(bit_offset, bit_len),
+            index,
+            sub_index,
+        })
+    }
+
+    /// Find a PDO entry mapped into this SubDevice's outputs by its object index and sub-index.
+    ///
+    /// See [`input_entry`](SubDeviceRef::input_entry) for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotFound`] if the entry is not mapped into the SubDevice's outputs, or the
+    /// group's PDO entries have not been read.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use ethercrab::{MainDevice, MainDeviceConfig, PduStorage, Timeouts};
+    /// # static PDU_STORAGE: PduStorage<8, 64> = PduStorage::new();
+    /// # let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    /// # let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+    /// # async {
+    /// # let group = maindevice.init_single_group::<8, 8>(ethercrab::std::ethercat_now).await.expect("Init");
+    /// # let mut group = group.into_op(&maindevice).await.expect("Op");
+    /// group.read_pdo_entries(&maindevice).await.expect("Read PDO entries");
+    ///
+    /// // A DS402 servo drive
+    /// let mut subdevice = group.subdevice(&maindevice, 0).expect("No device");
+    ///
+    /// // Control word: shutdown
+    /// subdevice
+    ///     .output_entry_mut(0x6040, 0)
+    ///     .expect("Control word not mapped")
+    ///     .write(0x0006u16)
+    ///     .expect("Write");
+    /// # };
+    /// ```
+    pub fn output_entry_mut(
+        &mut self,
+        index: u16,
+        sub_index: u8,
+    ) -> Result<OutputEntryMut<'_>, Error> {
+        let (bit_offset, bit_len) = self.find_entry(PdoDirection::MasterWrite, index, sub_index)?;
+
+        Ok(OutputEntryMut {
+            bits: self.output_bits_mut().into_slice(bit_offset, bit_len),
+            index,
+            sub_index,
+        })
+    }
+
+    /// Find a PDO entry mapped into this SubDevice's inputs by the name given to it in the
+    /// SubDevice's EEPROM, e.g. `"Statusword"`.
+    ///
+    /// Entry names are read from the EEPROM when they are looked up. If the group's PDO entries
+    /// have not been read with
+    /// [`SubDeviceGroup::read_pdo_entries`](crate::SubDeviceGroup::read_pdo_entries), this
+    /// SubDevice's entry layout is read too. This method should therefore be called during
+    /// startup, and the returned entry's [`index`](InputEntry::index) and
+    /// [`sub_index`](InputEntry::sub_index) passed to [`input_entry`](SubDeviceRef::input_entry)
+    /// in the cyclic task.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotFound`] if no entry with this name is mapped into the SubDevice's
+    /// inputs.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use ethercrab::{MainDevice, MainDeviceConfig, PduStorage, Timeouts};
+    /// # static PDU_STORAGE: PduStorage<8, 64> = PduStorage::new();
+    /// # let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    /// # let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+    /// # async {
+    /// # let group = maindevice.init_single_group::<8, 8>(ethercrab::std::ethercat_now).await.expect("Init");
+    /// # let group = group.into_op(&maindevice).await.expect("Op");
+    /// let subdevice = group.subdevice(&maindevice, 0).expect("No device");
+    ///
+    /// let status_word = subdevice
+    ///     .input_entry_by_name("Statusword")
+    ///     .await
+    ///     .expect("Status word not mapped");
+    ///
+    /// println!(
+    ///     "Status word is {:#06x}:{}",
+    ///     status_word.index(),
+    ///     status_word.sub_index()
+    /// );
+    /// # };
+    /// ```
+    pub async fn input_entry_by_name(&self, name: &str) -> Result<InputEntry<'_>, Error> {
+        let entry = self
+            .find_entry_by_name(PdoDirection::MasterRead, name)
+            .await?;
+
+        Ok(InputEntry {
+            bits: self
+                .input_bits()
+                .slice(usize::from(entry.bit_offset), usize::from(entry.bit_len)),
+            index: entry.index,
+            sub_index: entry.sub_index,
+        })
+    }
+
+    /// Find a PDO entry mapped into this SubDevice's outputs by the name given to it in the
+    /// SubDevice's EEPROM, e.g. `"Controlword"`.
+    ///
+    /// See [`input_entry_by_name`](SubDeviceRef::input_entry_by_name) for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotFound`] if no entry with this name is mapped into the SubDevice's
+    /// outputs.
+    pub async fn output_entry_by_name_mut(
+        &mut self,
+        name: &str,
+    ) -> Result<OutputEntryMut<'_>, Error> {
+        let entry = self
+            .find_entry_by_name(PdoDirection::MasterWrite, name)
+            .await?;
+
+        Ok(OutputEntryMut {
+            bits: self
+                .output_bits_mut()
+                .into_slice(usize::from(entry.bit_offset), usize::from(entry.bit_len)),
+            index: entry.index,
+            sub_index: entry.sub_index,
+        })
+    }
+
+    /// Iterate over every PDO entry mapped into this SubDevice's inputs.
+    ///
+    /// The group's PDO entries must first be read with
+    /// [`SubDeviceGroup::read_pdo_entries`](crate::SubDeviceGroup::read_pdo_entries), otherwise
+    /// no entries are returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use ethercrab::{MainDevice, MainDeviceConfig, PduStorage, Timeouts};
+    /// # static PDU_STORAGE: PduStorage<8, 64> = PduStorage::new();
+    /// # let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    /// # let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+    /// # async {
+    /// # let group = maindevice.init_single_group::<8, 8>(ethercrab::std::ethercat_now).await.expect("Init");
+    /// # let mut group = group.into_op(&maindevice).await.expect("Op");
+    /// group.read_pdo_entries(&maindevice).await.expect("Read PDO entries");
+    ///
+    /// let subdevice = group.subdevice(&maindevice, 0).expect("No device");
+    ///
+    /// for entry in subdevice.input_entries() {
+    ///     println!(
+    ///         "{:#06x}:{} at byte {} bit {}, {} bits",
+    ///         entry.index(),
+    ///         entry.sub_index(),
+    ///         entry.byte_offset(),
+    ///         entry.bit_offset(),
+    ///         entry.bit_len()
+    ///     );
+    /// }
+    /// # };
+    /// ```
+    pub fn input_entries(&self) -> impl Iterator<Item = InputEntry<'_>> {
+        let bits = self.input_bits();
+
+        self.entries(PdoDirection::MasterRead)
+            .map(move |entry| InputEntry {
+                bits: bits.slice(usize::from(entry.bit_offset), usize::from(entry.bit_len)),
+                index: entry.index,
+                sub_index: entry.sub_index,
+            })
+    }
+
+    /// Iterate over every PDO entry mapped into this SubDevice's outputs.
+    ///
+    /// See [`input_entries`](SubDeviceRef::input_entries) for details.
+    ///
+    /// Entries are written atomically, so every entry returned by the iterator can be held and
+    /// written at the same time.
+    pub fn output_entries_mut(&mut self) -> impl Iterator<Item = OutputEntryMut<'_>> {
+        let entries = self.entries(PdoDirection::MasterWrite);
+
+        let PdiBitsMut {
+            data,
+            start_bit,
+            len,
+        } = self.output_bits_mut();
+
+        entries.map(move |entry| OutputEntryMut {
+            bits: PdiBitsMut {
+                data,
+                start_bit,
+                len,
+            }
+            .into_slice(usize::from(entry.bit_offset), usize::from(entry.bit_len)),
+            index: entry.index,
+            sub_index: entry.sub_index,
+        })
+    }
+
+    /// The PDO entries mapped into this SubDevice's inputs or outputs.
+    fn entries(&self, direction: PdoDirection) -> impl Iterator<Item = &'group GroupPdoEntry> {
+        let configured_address = self.configured_address;
+
+        self.state.pdo_entries.iter().filter(move |entry| {
+            entry.configured_address == configured_address && entry.direction == direction
+        })
+    }
+
+    /// Find a mapped PDO entry by its EEPROM name.
+    ///
+    /// The SubDevice's entry layout is read if the group's PDO entries have not been read.
+    async fn find_entry_by_name(
+        &self,
+        direction: PdoDirection,
+        name: &str,
+    ) -> Result<GroupPdoEntry, Error> {
+        let mut layout = PdoEntries::default();
+
+        if self.entries(direction).next().is_none() {
+            self.read_pdo_entries(direction, &mut layout).await?;
+        }
+
+        let mut eeprom_entries = heapless::Vec::<_, MAX_PDO_ENTRIES>::new();
+
+        match direction {
+            PdoDirection::MasterRead => {
+                self.eeprom()
+                    .maindevice_read_pdo_entries(&mut eeprom_entries)
+                    .await?
+            }
+            PdoDirection::MasterWrite => {
+                self.eeprom()
+                    .maindevice_write_pdo_entries(&mut eeprom_entries)
+                    .await?
+            }
+        };
+
+        for entry in self.entries(direction).chain(layout.iter()) {
+            let Some(eeprom_entry) = eeprom_entries.iter().find(|eeprom_entry| {
+                eeprom_entry.index == entry.index && eeprom_entry.sub_index == entry.sub_index
+            }) else {
+                continue;
+            };
+
+            let entry_name = self
+                .eeprom()
+                .find_string::<64>(eeprom_entry.name_string_idx)
+                .await?;
+
+            if entry_name.as_deref() == Some(name) {
+                return Ok(*entry);
+            }
+        }
+
+        fmt::error!(
+            "SubDevice {:#06x} has no {:?} PDO entry named {}",
+            self.configured_address,
+            direction,
+            name
+        );
+
+        Err(Error::NotFound {
+            item: Item::PdoEntry,
+            index: None,
+        })
+    }
+
+    /// Find the bit offset and bit length of a mapped PDO entry.
+    fn find_entry(
+        &self,
+        direction: PdoDirection,
+        index: u16,
+        sub_index: u8,
+    ) -> Result<(usize, usize), Error> {
+        self.entries(direction)
+            .find(|entry| entry.index == index && entry.sub_index == sub_index)
+            .map(|entry| (usize::from(entry.bit_offset), usize::from(entry.bit_len)))
+            .ok_or_else(|| {
+                if self.state.pdo_entries.is_empty() {
+                    fmt::error!(
+                        "Group PDO entries have not been read. Call SubDeviceGroup::read_pdo_entries first"
+                    );
+                }
+
+                fmt::error!(
+                    "SubDevice {:#06x} has no {:?} PDO entry {:#06x}:{}",
+                    self.configured_address,
+                    direction,
+                    index,
+                    sub_index
+                );
+
+                Error::NotFound {
+                    item: Item::PdoEntry,
+                    index: Some(usize::from(index)),
+                }
+            })
+    }
+}
+
+/// Check that a type's packed length matches the length of a PDI segment.
+fn check_length<T>(segment: &PdiSegment) -> Result<(), Error>
+where
+    T: EtherCrabWireSized,
+{
+    let segment_length = if segment.bit_packed {
+        (segment.bit_len + 7) / 8
+    } else {
+        segment.len()
+    };
+
+    check_type_length::<T>(segment_length)
+}
+
+/// Check that a type's packed length matches a length in bytes.
+fn check_type_length<T>(segment_length: usize) -> Result<(), Error>
+where
+    T: EtherCrabWireSized,
+{
+    if T::PACKED_LEN != segment_length {
+        fmt::error!(
+            "Type of {} bytes does not match PDI segment of {} bytes",
+            T::PACKED_LEN,
+            segment_length
+        );
+
+        return Err(Error::PdiLength {
+            segment_length,
+            type_length: T::PACKED_LEN,
+        });
+    }
+
+    Ok(())
+}
+
+enum OutputsTarget<'a> {
+    Bytes(&'a mut [u8]),
+    Bits(PdiBitsMut<'a>),
+}
+
+/// A typed view of a SubDevice's outputs, returned by [`SubDeviceRef::outputs_mut`].
+///
+/// The value is written back to the SubDevice's outputs in the PDI when this guard is dropped.
+pub struct OutputsGuard<'a, T>
+where
+    T: EtherCrabWireWrite,
+{
+    value: T,
+    target: OutputsTarget<'a>,
+}
+
+impl<'a, T> core::fmt::Debug for OutputsGuard<'a, T>
+where
+    T: EtherCrabWireWrite + core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("OutputsGuard")
+            .field("value", &self.value)
+            .finish()
+    }
+}
+
+impl<'a, T> Deref for OutputsGuard<'a, T>
+where
+    T: EtherCrabWireWrite,
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<'a, T> DerefMut for OutputsGuard<'a, T>
+where
+    T: EtherCrabWireWrite,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
+
+impl<'a, T> Drop for OutputsGuard<'a, T>
+where
+    T: EtherCrabWireWrite,
+{
+    fn drop(&mut self) {
+        match &mut self.target {
+            // Length was checked when the guard was created
+            OutputsTarget::Bytes(bytes) => {
+                self.value.pack_to_slice_unchecked(bytes);
+            }
+            OutputsTarget::Bits(bits) => {
+                let mut byte = [0u8; 1];
+
+                self.value.pack_to_slice_unchecked(&mut byte);
+
+                bits.store_byte(byte[0]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethercrab_wire::EtherCrabWireRead;
+
+    #[test]
+    fn packed_bits() {
+        // Two EL1004s packed into one byte
+        let data = [0b0110_1001u8];
+
+        let first = PdiBits::new(
+            &data,
+            &PdiSegment {
+                bytes: 0..1,
+                bit_len: 4,
+                start_bit: 0,
+                bit_packed: true,
+            },
+        );
+
+        let second = PdiBits::new(
+            &data,
+            &PdiSegment {
+                bytes: 0..1,
+                bit_len: 4,
+                start_bit: 4,
+                bit_packed: true,
+            },
+        );
+
+        assert_eq!(
+            first.iter().collect::<heapless::Vec<_, 4>>(),
+            [true, false, false, true]
+        );
+        assert_eq!(
+            second.iter().collect::<heapless::Vec<_, 4>>(),
+            [false, true, true, false]
+        );
+        assert_eq!(second.get(4), None);
+    }
+
+    #[test]
+    fn packed_bits_mut() {
+        let data = [AtomicU8::new(0), AtomicU8::new(0)];
+
+        // 6 bits crossing into the second byte
+        let segment = PdiSegment {
+            bytes: 0..2,
+            bit_len: 6,
+            start_bit: 4,
+            bit_packed: true,
+        };
+
+        let mut bits = PdiBitsMut::new(&data, &segment);
+
+        bits.set(0, true).unwrap();
+        bits.set(5, true).unwrap();
+        bits.set(4, true).unwrap();
+        bits.set(4, false).unwrap();
+
+        assert_eq!(
+            bits.set(6, true),
+            Err(Error::NotFound {
+                item: Item::PdoEntry,
+                index: Some(6)
+            })
+        );
+
+        assert_eq!(data[0].load(Ordering::Relaxed), 0b0001_0000);
+        assert_eq!(data[1].load(Ordering::Relaxed), 0b0000_0010);
+        assert_eq!(bits.get(5), Some(true));
+        assert_eq!(bits.get(4), Some(false));
+    }
+
+    #[derive(Debug, PartialEq, ethercrab_wire::EtherCrabWireReadWrite)]
+    #[wire(bits = 4)]
+    struct El2004Outputs {
+        #[wire(bits = 1)]
+        channel_1: bool,
+        #[wire(bits = 1)]
+        channel_2: bool,
+        #[wire(bits = 1)]
+        channel_3: bool,
+        #[wire(bits = 1)]
+        channel_4: bool,
+    }
+
+    #[test]
+    fn typed_length_check() {
+        let segment = PdiSegment {
+            bytes: 0..2,
+            bit_len: 16,
+            start_bit: 0,
+            bit_packed: false,
+        };
+
+        assert_eq!(check_length::<u16>(&segment), Ok(()));
+        assert_eq!(
+            check_length::<u32>(&segment),
+            Err(Error::PdiLength {
+                segment_length: 2,
+                type_length: 4
+            })
+        );
+
+        let packed = PdiSegment {
+            bytes: 0..1,
+            bit_len: 4,
+            start_bit: 4,
+            bit_packed: true,
+        };
+
+        assert_eq!(check_length::<El2004Outputs>(&packed), Ok(()));
+    }
+
+    #[test]
+    fn entry_read_write() {
+        let segment = PdiSegment {
+            bytes: 0..4,
+            bit_len: 28,
+            start_bit: 4,
+            bit_packed: true,
+        };
+
+        // 4 bit entry, then a 16 bit entry crossing a byte boundary
+        let inputs = [0b1010_0000u8, 0x41, 0x06, 0x00];
+
+        let bits = PdiBits::new(&inputs, &segment);
+
+        let entry = InputEntry {
+            bits: bits.slice(4, 16),
+            index: 0x6041,
+            sub_index: 0,
+        };
+
+        assert_eq!(entry.byte_offset(), 1);
+        assert_eq!(entry.bit_offset(), 0);
+        assert_eq!(entry.read::<u16>(), Ok(0x0641));
+        assert_eq!(
+            entry.read::<u32>(),
+            Err(Error::PdiLength {
+                segment_length: 2,
+                type_length: 4
+            })
+        );
+
+        let outputs = [
+            AtomicU8::new(0b0000_0101),
+            AtomicU8::new(0),
+            AtomicU8::new(0),
+            AtomicU8::new(0),
+        ];
+
+        let mut entry = OutputEntryMut {
+            bits: PdiBitsMut::new(&outputs, &segment).into_slice(6, 16),
+            index: 0x6040,
+            sub_index: 0,
+        };
+
+        assert_eq!(entry.byte_offset(), 1);
+        assert_eq!(entry.bit_offset(), 2);
+
+        entry.write(0xffffu16).unwrap();
+
+        assert_eq!(entry.read::<u16>(), Ok(0xffff));
+        assert_eq!(outputs[0].load(Ordering::Relaxed), 0b0000_0101);
+        assert_eq!(outputs[1].load(Ordering::Relaxed), 0b1111_1100);
+        assert_eq!(outputs[2].load(Ordering::Relaxed), 0b1111_1111);
+        assert_eq!(outputs[3].load(Ordering::Relaxed), 0b0000_0011);
+    }
+
+    #[test]
+    fn outputs_guard_write_back() {
+        let mut data = [0x00u8, 0xff];
+
+        {
+            let mut guard = OutputsGuard {
+                value: 0u16,
+                target: OutputsTarget::Bytes(&mut data),
+            };
+
+            *guard = 0x1234;
+        }
+
+        assert_eq!(data, [0x34, 0x12]);
+    }
+
+    #[test]
+    fn packed_outputs_guard_write_back() {
+        // Another SubDevice owns the lower 4 bits
+        let data = [AtomicU8::new(0b0000_0101)];
+
+        let segment = PdiSegment {
+            bytes: 0..1,
+            bit_len: 4,
+            start_bit: 4,
+            bit_packed: true,
+        };
+
+        let bits = PdiBitsMut::new(&data, &segment);
+
+        let value = El2004Outputs::unpack_from_slice(&[bits.load_byte()]).unwrap();
+
+        assert_eq!(
+            value,
+            El2004Outputs {
+                channel_1: false,
+                channel_2: false,
+                channel_3: false,
+                channel_4: false,
+            }
+        );
+
+        {
+            let mut guard = OutputsGuard {
+                value,
+                target: OutputsTarget::Bits(bits),
+            };
+
+            guard.channel_2 = true;
+            guard.channel_4 = true;
+        }
+
+        assert_eq!(data[0].load(Ordering::Relaxed), 0b1010_0101);
+    }
 }
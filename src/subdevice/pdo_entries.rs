@@ -0,0 +1,99 @@
+//! Layout of mapped PDO entries in a SubDevice's process data.
+
+use super::configuration::PdoDirection;
+use crate::fmt;
+
+/// Maximum number of PDO entries read from a SubDevice's inputs or outputs. Padding entries are
+/// not stored.
+pub(crate) const MAX_PDO_ENTRIES: usize = 64;
+
+/// Maximum number of PDO entries a group with fixed storage keeps for all of its SubDevices.
+pub(crate) const MAX_GROUP_PDO_ENTRIES: usize = 64;
+
+/// A PDO entry mapped into the inputs or outputs of a SubDevice in a group.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct GroupPdoEntry {
+    /// Configured station address of the SubDevice the entry is mapped into.
+    pub(crate) configured_address: u16,
+    pub(crate) direction: PdoDirection,
+    pub(crate) index: u16,
+    pub(crate) sub_index: u8,
+    pub(crate) bit_len: u8,
+    /// Bit offset from the start of the SubDevice's inputs or outputs.
+    pub(crate) bit_offset: u16,
+}
+
+/// The PDO entries mapped into a SubDevice's inputs or outputs, read while they are being looked
+/// up.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct PdoEntries {
+    entries: heapless::Vec<GroupPdoEntry, MAX_PDO_ENTRIES>,
+}
+
+impl PdoEntries {
+    /// Add an entry. Padding entries and entries past the maximum capacity are ignored.
+    pub(crate) fn push(&mut self, entry: GroupPdoEntry) {
+        // Padding
+        if entry.index == 0 {
+            return;
+        }
+
+        if self.entries.push(entry).is_err() {
+            fmt::warn!(
+                "Too many PDO entries, max {}. Entry {:#06x}:{} will not be available",
+                MAX_PDO_ENTRIES,
+                entry.index,
+                entry.sub_index
+            );
+        }
+    }
+
+    /// Iterate over all entries.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &GroupPdoEntry> {
+        self.entries.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(index: u16, bit_offset: u16) -> GroupPdoEntry {
+        GroupPdoEntry {
+            configured_address: 0x1000,
+            direction: PdoDirection::MasterRead,
+            index,
+            sub_index: 0,
+            bit_len: 8,
+            bit_offset,
+        }
+    }
+
+    #[test]
+    fn skip_padding() {
+        let mut entries = PdoEntries::default();
+
+        entries.push(entry(0x6000, 0));
+        entries.push(entry(0x0000, 8));
+        entries.push(entry(0x6010, 16));
+
+        assert_eq!(
+            entries
+                .iter()
+                .map(|entry| (entry.index, entry.bit_offset))
+                .collect::<heapless::Vec<_, 3>>(),
+            [(0x6000, 0), (0x6010, 16)]
+        );
+    }
+
+    #[test]
+    fn full() {
+        let mut entries = PdoEntries::default();
+
+        for i in 0..=MAX_PDO_ENTRIES as u16 {
+            entries.push(entry(0x6000 + i, i * 8));
+        }
+
+        assert_eq!(entries.iter().count(), MAX_PDO_ENTRIES);
+    }
+}
@@ -0,0 +1,437 @@
+//! Declarative PDO mapping and sync manager assignment configuration.
+
+use super::{SubDevice, SubDeviceRef};
+use crate::{
+    error::{Error, Item},
+    fmt,
+    sync_manager_channel::SM_BASE_ADDRESS,
+};
+use core::ops::DerefMut;
+
+/// Maximum number of PDOs in a [`PdoMapping`].
+const MAX_PDOS: usize = 16;
+
+/// Maximum number of entries across all PDOs in a [`PdoMapping`].
+const MAX_ENTRIES: usize = 128;
+
+/// Sync manager PDO assignment object for SubDevice outputs (RxPDOs).
+const OUTPUTS_ASSIGNMENT: u16 = SM_BASE_ADDRESS + 2;
+
+/// Sync manager PDO assignment object for SubDevice inputs (TxPDOs).
+const INPUTS_ASSIGNMENT: u16 = SM_BASE_ADDRESS + 3;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum PdoKind {
+    /// MainDevice outputs, assigned to SM2.
+    Rx,
+    /// MainDevice inputs, assigned to SM3.
+    Tx,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct MappedPdo {
+    kind: PdoKind,
+    index: u16,
+    /// Start of this PDO's entries in [`PdoMapping::entries`].
+    start: usize,
+    /// Number of entries in this PDO.
+    len: usize,
+}
+
+/// PDO entry mapping value as written to a PDO mapping object, e.g. `0x6040_0010`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct MappedEntry {
+    index: u16,
+    sub_index: u8,
+    bit_len: u8,
+}
+
+impl MappedEntry {
+    fn mapping(&self) -> u32 {
+        u32::from(self.index) << 16 | u32::from(self.sub_index) << 8 | u32::from(self.bit_len)
+    }
+}
+
+/// A description of a SubDevice's PDO mappings (`0x1600`/`0x1a00`) and their sync manager
+/// assignments (`0x1c12`/`0x1c13`).
+///
+/// RxPDOs hold SubDevice outputs and are assigned to SM2 with `0x1c12`. TxPDOs hold SubDevice
+/// inputs and are assigned to SM3 with `0x1c13`. PDOs are assigned in the order they are added.
+///
+/// A PDO added with no entries is assigned without changing its mapping. This is useful for
+/// SubDevices with fixed PDOs.
+///
+/// Written to a SubDevice in PRE-OP with [`SubDeviceRef::configure_pdos`].
+///
+/// # Examples
+///
+/// ```rust
+/// use ethercrab::PdoMapping;
+///
+/// let mapping = PdoMapping::new()
+///     .rx_pdo(0x1600)
+///     // Control word, u16
+///     .entry(0x6040, 0, 16)
+///     // Target velocity, i32
+///     .entry(0x60ff, 0, 32)
+///     .tx_pdo(0x1a00)
+///     // Status word, u16
+///     .entry(0x6041, 0, 16)
+///     // Actual position, i32
+///     .entry(0x6064, 0, 32)
+///     // Actual velocity, i32
+///     .entry(0x606c, 0, 32);
+///
+/// assert_eq!(mapping.output_bit_len(), Some(48));
+/// assert_eq!(mapping.input_bit_len(), Some(80));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdoMapping {
+    pdos: heapless::Vec<MappedPdo, MAX_PDOS>,
+    entries: heapless::Vec<MappedEntry, MAX_ENTRIES>,
+    /// The first error encountered while building the mapping.
+    error: Option<Error>,
+}
+
+impl Default for PdoMapping {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PdoMapping {
+    /// Create a new, empty PDO mapping.
+    pub const fn new() -> Self {
+        Self {
+            pdos: heapless::Vec::new(),
+            entries: heapless::Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Add an RxPDO (SubDevice outputs), e.g. `0x1600`.
+    ///
+    /// Subsequent calls to [`entry`](PdoMapping::entry) add entries to this PDO.
+    pub fn rx_pdo(self, index: u16) -> Self {
+        self.pdo(PdoKind::Rx, index)
+    }
+
+    /// Add a TxPDO (SubDevice inputs), e.g. `0x1a00`.
+    ///
+    /// Subsequent calls to [`entry`](PdoMapping::entry) add entries to this PDO.
+    pub fn tx_pdo(self, index: u16) -> Self {
+        self.pdo(PdoKind::Tx, index)
+    }
+
+    /// Map an object dictionary entry into the most recently added PDO.
+    pub fn entry(mut self, index: u16, sub_index: u8, bit_len: u8) -> Self {
+        let Some(pdo) = self.pdos.last_mut() else {
+            fmt::error!(
+                "PDO entry {:#06x}:{} added before any PDO",
+                index,
+                sub_index
+            );
+
+            return self.with_error(Error::NotFound {
+                item: Item::Pdo,
+                index: None,
+            });
+        };
+
+        pdo.len += 1;
+
+        let entry = MappedEntry {
+            index,
+            sub_index,
+            bit_len,
+        };
+
+        if self.entries.push(entry).is_err() {
+            return self.with_error(Error::Capacity(Item::PdoEntry));
+        }
+
+        self
+    }
+
+    /// Add a gap of `bit_len` bits to the most recently added PDO.
+    pub fn padding(self, bit_len: u8) -> Self {
+        self.entry(0x0000, 0, bit_len)
+    }
+
+    /// The total bit length of all RxPDOs (SubDevice outputs).
+    ///
+    /// Returns `None` if no RxPDOs are mapped, or if any RxPDO has no entries and so has an
+    /// unknown length.
+    pub fn output_bit_len(&self) -> Option<u16> {
+        self.bit_len(PdoKind::Rx)
+    }
+
+    /// The total bit length of all TxPDOs (SubDevice inputs).
+    ///
+    /// Returns `None` if no TxPDOs are mapped, or if any TxPDO has no entries and so has an
+    /// unknown length.
+    pub fn input_bit_len(&self) -> Option<u16> {
+        self.bit_len(PdoKind::Tx)
+    }
+
+    fn pdo(mut self, kind: PdoKind, index: u16) -> Self {
+        let pdo = MappedPdo {
+            kind,
+            index,
+            start: self.entries.len(),
+            len: 0,
+        };
+
+        if self.pdos.push(pdo).is_err() {
+            return self.with_error(Error::Capacity(Item::Pdo));
+        }
+
+        self
+    }
+
+    fn with_error(mut self, error: Error) -> Self {
+        self.error.get_or_insert(error);
+
+        self
+    }
+
+    fn pdos(&self, kind: PdoKind) -> impl Iterator<Item = &MappedPdo> {
+        self.pdos.iter().filter(move |pdo| pdo.kind == kind)
+    }
+
+    fn entries(&self, pdo: &MappedPdo) -> &[MappedEntry] {
+        self.entries
+            .get(pdo.start..(pdo.start + pdo.len))
+            .unwrap_or(&[])
+    }
+
+    fn bit_len(&self, kind: PdoKind) -> Option<u16> {
+        let mut pdos = self.pdos(kind).peekable();
+
+        pdos.peek()?;
+
+        pdos.map(|pdo| {
+            let entries = self.entries(pdo);
+
+            (!entries.is_empty()).then(|| {
+                entries
+                    .iter()
+                    .map(|entry| u16::from(entry.bit_len))
+                    .sum::<u16>()
+            })
+        })
+        .sum()
+    }
+}
+
+impl<'a, S> SubDeviceRef<'a, S>
+where
+    S: DerefMut<Target = SubDevice>,
+{
+    /// Write PDO mappings and sync manager assignments to this SubDevice.
+    ///
+    /// The SubDevice must be in PRE-OP and support CoE. Objects are written in the order required
+    /// by ETG1000.6:
+    ///
+    /// 1. The sync manager assignments (`0x1c12`/`0x1c13`) for each direction with PDOs in the
+    ///    mapping are cleared.
+    /// 2. Each PDO with entries is written, e.g. `0x1600`.
+    /// 3. The PDOs are assigned to their sync managers.
+    ///
+    /// The total bit lengths of the mapped PDOs are then used when the group configures its PDI,
+    /// instead of being read back from the SubDevice.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the mapping is invalid, e.g. it has too many entries or an entry was
+    /// added before any PDOs, or if any SDO write fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ethercrab::{
+    ///     std::ethercat_now, MainDevice, MainDeviceConfig, PdoMapping, PduStorage, Timeouts,
+    /// };
+    ///
+    /// static PDU_STORAGE: PduStorage<16, { PduStorage::element_size(1100) }> = PduStorage::new();
+    ///
+    /// let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    ///
+    /// let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+    ///
+    /// # async {
+    /// let mut group = maindevice
+    ///     .init_single_group::<16, 64>(ethercat_now)
+    ///     .await
+    ///     .expect("Init");
+    ///
+    /// for mut subdevice in group.iter(&maindevice) {
+    ///     if subdevice.name() == "ELP-EC400S" {
+    ///         subdevice
+    ///             .configure_pdos(
+    ///                 &PdoMapping::new()
+    ///                     .rx_pdo(0x1600)
+    ///                     .entry(0x6040, 0, 16)
+    ///                     .entry(0x60ff, 0, 32)
+    ///                     .tx_pdo(0x1a00)
+    ///                     .entry(0x6041, 0, 16)
+    ///                     .entry(0x6064, 0, 32),
+    ///             )
+    ///             .await
+    ///             .expect("PDO mapping");
+    ///     }
+    /// }
+    ///
+    /// let group = group.into_op(&maindevice).await.expect("PRE-OP -> OP");
+    /// # };
+    /// ```
+    pub async fn configure_pdos(&mut self, mapping: &PdoMapping) -> Result<(), Error> {
+        if let Some(error) = mapping.error {
+            return Err(error);
+        }
+
+        let assignments = [
+            (PdoKind::Rx, OUTPUTS_ASSIGNMENT),
+            (PdoKind::Tx, INPUTS_ASSIGNMENT),
+        ];
+
+        // PDO mappings can only be changed while they are not assigned to a sync manager
+        for (kind, assignment) in assignments {
+            if mapping.pdos(kind).next().is_some() {
+                self.sdo_write(assignment, 0, 0u8).await?;
+            }
+        }
+
+        for pdo in mapping.pdos.iter() {
+            let entries = mapping.entries(pdo);
+
+            // Fixed PDOs are assigned but not remapped
+            if entries.is_empty() {
+                continue;
+            }
+
+            fmt::debug!(
+                "SubDevice {:#06x} PDO {:#06x}: {} entries",
+                self.configured_address,
+                pdo.index,
+                entries.len()
+            );
+
+            self.sdo_write(pdo.index, 0, 0u8).await?;
+
+            for (sub_index, entry) in (1..).zip(entries) {
+                self.sdo_write(pdo.index, sub_index, entry.mapping())
+                    .await?;
+            }
+
+            self.sdo_write(pdo.index, 0, entries.len() as u8).await?;
+        }
+
+        for (kind, assignment) in assignments {
+            let mut count = 0u8;
+
+            for pdo in mapping.pdos(kind) {
+                count += 1;
+
+                self.sdo_write(assignment, count, pdo.index).await?;
+            }
+
+            if count > 0 {
+                self.sdo_write(assignment, 0, count).await?;
+            }
+        }
+
+        self.state.config.assigned_pdos = AssignedPdos {
+            outputs: mapping.output_bit_len(),
+            inputs: mapping.input_bit_len(),
+        };
+
+        Ok(())
+    }
+}
+
+/// Total bit lengths of PDOs assigned by [`SubDeviceRef::configure_pdos`].
+///
+/// `None` means the length is unknown and must be read from the SubDevice.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct AssignedPdos {
+    /// PDOs assigned to SM2 with `0x1c12`.
+    outputs: Option<u16>,
+    /// PDOs assigned to SM3 with `0x1c13`.
+    inputs: Option<u16>,
+}
+
+impl AssignedPdos {
+    /// Get the known bit length of the PDOs assigned by a sync manager assignment object, e.g.
+    /// `0x1c12`.
+    pub(crate) fn bit_len(&self, assignment: u16) -> Option<u16> {
+        match assignment {
+            OUTPUTS_ASSIGNMENT => self.outputs,
+            INPUTS_ASSIGNMENT => self.inputs,
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bit_lengths() {
+        let mapping = PdoMapping::new()
+            .rx_pdo(0x1600)
+            .entry(0x6040, 0, 16)
+            .padding(8)
+            .rx_pdo(0x1601)
+            .entry(0x60ff, 0, 32);
+
+        assert_eq!(mapping.output_bit_len(), Some(56));
+        assert_eq!(mapping.input_bit_len(), None);
+        assert_eq!(mapping.error, None);
+    }
+
+    #[test]
+    fn fixed_pdo_unknown_length() {
+        let mapping = PdoMapping::new()
+            .rx_pdo(0x1702)
+            .tx_pdo(0x1a00)
+            .entry(0x6041, 0, 16)
+            .tx_pdo(0x1b01);
+
+        assert_eq!(mapping.output_bit_len(), None);
+        assert_eq!(mapping.input_bit_len(), None);
+    }
+
+    #[test]
+    fn mapping_value() {
+        let entry = MappedEntry {
+            index: 0x6064,
+            sub_index: 0x01,
+            bit_len: 32,
+        };
+
+        assert_eq!(entry.mapping(), 0x6064_0120);
+    }
+
+    #[test]
+    fn entry_without_pdo() {
+        let mapping = PdoMapping::new().entry(0x6040, 0, 16).rx_pdo(0x1600);
+
+        assert_eq!(
+            mapping.error,
+            Some(Error::NotFound {
+                item: Item::Pdo,
+                index: None
+            })
+        );
+    }
+
+    #[test]
+    fn too_many_pdos() {
+        let mapping =
+            (0..=MAX_PDOS as u16).fold(PdoMapping::new(), |mapping, i| mapping.tx_pdo(0x1a00 + i));
+
+        assert_eq!(mapping.error, Some(Error::Capacity(Item::Pdo)));
+    }
+}
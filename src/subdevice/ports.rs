@@ -108,6 +108,18 @@ impl Ports {
         self.0[3].dc_receive_time = time_p2;
     }
 
+    /// Close every port except port 0, e.g. for the last SubDevice in a redundant ring whose
+    /// outgoing port is connected back to the MainDevice instead of another SubDevice.
+    pub(crate) fn close_downstream_ports(&mut self) {
+        if !self.0[0].active {
+            return;
+        }
+
+        for port in self.0.iter_mut().skip(1) {
+            port.active = false;
+        }
+    }
+
     /// TEST ONLY: Set downstream ports.
     #[cfg(test)]
     pub(crate) fn set_downstreams(
@@ -279,6 +291,16 @@ pub mod tests {
         assert_eq!(passthrough.open_ports(), 2);
     }
 
+    #[test]
+    fn close_downstream_ports() {
+        let mut ring_end = make_ports(true, true, false, false);
+
+        ring_end.close_downstream_ports();
+
+        assert_eq!(ring_end.topology(), Topology::LineEnd);
+        assert_eq!(ring_end.entry_port().number, 0);
+    }
+
     #[test]
     fn topologies() {
         let passthrough = make_ports(true, true, false, false);
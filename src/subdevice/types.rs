@@ -1,12 +1,15 @@
 use crate::{
     eeprom::types::{MailboxProtocols, SyncManagerType},
     pdi::PdiSegment,
+    subdevice::pdo_mapping::AssignedPdos,
+    AlStatusCode, SubDeviceState,
 };
 use core::fmt::{self, Debug};
 
 /// SubDevice identity information (vendor ID, product ID, etc).
-#[derive(Default, Copy, Clone, PartialEq, ethercrab_wire::EtherCrabWireRead)]
+#[derive(Default, Copy, Clone, PartialEq, Eq, ethercrab_wire::EtherCrabWireRead)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[wire(bytes = 16)]
 #[doc(alias = "SlaveIdentity")]
 pub struct SubDeviceIdentity {
@@ -48,6 +51,7 @@ impl Debug for SubDeviceIdentity {
 pub struct SubDeviceConfig {
     pub io: IoRanges,
     pub mailbox: MailboxConfig,
+    pub(in crate::subdevice) assigned_pdos: AssignedPdos,
 }
 
 #[derive(Debug, Default, Clone, PartialEq)]
@@ -73,3 +77,19 @@ pub struct IoRanges {
     pub input: PdiSegment,
     pub output: PdiSegment,
 }
+
+/// A failed attempt to transition a SubDevice to a new state.
+///
+/// Passed to the callback given to [`SubDeviceRef::request_state`](crate::SubDeviceRef::request_state).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TransitionFailure {
+    /// SubDevice configured station address.
+    pub configured_address: u16,
+    /// The requested state.
+    pub state: SubDeviceState,
+    /// The attempt number, starting at 0 for the first request.
+    pub attempt: usize,
+    /// The reason the SubDevice gave for refusing the transition.
+    pub code: AlStatusCode,
+}
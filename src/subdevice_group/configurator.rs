@@ -3,6 +3,7 @@ use crate::{
     fmt,
     pdi::PdiOffset,
     subdevice::{SubDevice, SubDeviceRef},
+    subdevice_group::NoDc,
     MainDevice, SubDeviceGroup,
 };
 use atomic_refcell::AtomicRefCell;
@@ -11,6 +12,8 @@ use atomic_refcell::AtomicRefCell;
 struct GroupInnerRef<'a> {
     subdevices: &'a mut [AtomicRefCell<SubDevice>],
     pdi_start: &'a mut PdiOffset,
+    pdi_reserved: &'a mut bool,
+    hot_connect_alias: Option<u16>,
 }
 
 // TODO: Prove if this is safe. All this stuff is internal to the crate and short lived so I think
@@ -30,22 +33,72 @@ pub struct SubDeviceGroupRef<'a> {
 }
 
 impl<'a> SubDeviceGroupRef<'a> {
-    pub(in crate::subdevice_group) fn new<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S>(
-        group: &'a SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S>,
+    pub(in crate::subdevice_group) fn new<
+        const MAX_SUBDEVICES: usize,
+        const MAX_PDI: usize,
+        S,
+        M,
+    >(
+        group: &'a SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, NoDc, M>,
     ) -> Self {
         Self {
-            max_pdi_len: MAX_PDI,
+            max_pdi_len: group.max_pdi_len(),
             inner: {
                 let inner = unsafe { fmt::unwrap_opt!(group.inner.get().as_mut()) };
 
                 GroupInnerRef {
                     subdevices: &mut inner.subdevices,
                     pdi_start: &mut inner.pdi_start,
+                    pdi_reserved: &mut inner.pdi_reserved,
+                    hot_connect_alias: inner.hot_connect_alias,
                 }
             },
         }
     }
 
+    pub(in crate::subdevice_group) fn new_mut<
+        const MAX_SUBDEVICES: usize,
+        const MAX_PDI: usize,
+        S,
+        M,
+    >(
+        group: &'a mut SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, NoDc, M>,
+    ) -> Self {
+        let max_pdi_len = group.max_pdi_len();
+        let inner = group.inner.get_mut();
+
+        Self {
+            max_pdi_len,
+            inner: GroupInnerRef {
+                subdevices: &mut inner.subdevices,
+                pdi_start: &mut inner.pdi_start,
+                pdi_reserved: &mut inner.pdi_reserved,
+                hot_connect_alias: inner.hot_connect_alias,
+            },
+        }
+    }
+
+    /// The maximum PDI length of the group in bytes.
+    pub(crate) fn max_pdi_len(&self) -> usize {
+        self.max_pdi_len
+    }
+
+    /// Whether the group has no SubDevices.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.inner.subdevices.is_empty()
+    }
+
+    /// The alias address of the first SubDevice in the hot-connect segment this group holds, if
+    /// it is a hot-connect group.
+    pub(crate) fn hot_connect_alias(&self) -> Option<u16> {
+        self.inner.hot_connect_alias
+    }
+
+    /// The start of the group's segment of the PDI, if one has been reserved for it.
+    pub(crate) fn reserved_pdi_start(&self) -> Option<PdiOffset> {
+        (*self.inner.pdi_reserved).then_some(*self.inner.pdi_start)
+    }
+
     /// Initialise all SubDevices in the group and place them in PRE-OP.
     // Clippy: shush
     #[allow(clippy::wrong_self_convention)]
@@ -58,6 +111,7 @@ impl<'a> SubDeviceGroupRef<'a> {
 
         // Set the starting position in the PDI for this group's segment
         *inner.pdi_start = pdi_position;
+        *inner.pdi_reserved = true;
 
         fmt::debug!(
             "Going to configure group with {} SubDevice(s), starting PDI offset {:#08x}",
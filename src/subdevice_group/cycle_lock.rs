@@ -0,0 +1,388 @@
+//! Lock the host process data cycle to the DC SYNC0 pulse.
+
+use super::{CycleInfo, DcConfiguration};
+use crate::fmt;
+use core::time::Duration;
+
+/// Tuning parameters for a [`CycleLock`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CycleLockConfig {
+    /// Proportional gain.
+    ///
+    /// Defaults to `0.2`. A higher value reacts faster to phase errors but passes more of the host
+    /// timer's jitter through to the cycle time.
+    pub kp: f32,
+
+    /// Integral gain, used to remove the constant phase error caused by the host clock running at a
+    /// slightly different rate to DC system time.
+    ///
+    /// Defaults to `0.02`.
+    pub ki: f32,
+
+    /// The largest correction applied to a single cycle, as a fraction of
+    /// [`sync0_period`](DcConfiguration::sync0_period). Must be between `0.0` and `1.0`.
+    ///
+    /// The integral term stops growing while the correction is limited so that the controller does
+    /// not overshoot once the phase error is corrected.
+    ///
+    /// Defaults to `0.1`.
+    pub max_correction: f32,
+
+    /// The phase error below which the cycle is considered locked.
+    ///
+    /// Lock is lost if the phase error exceeds twice this value.
+    ///
+    /// Defaults to 50 us.
+    pub lock_threshold: Duration,
+
+    /// The number of consecutive cycles the phase error must be below
+    /// [`lock_threshold`](CycleLockConfig::lock_threshold) for before the cycle is locked.
+    ///
+    /// Defaults to 100.
+    pub lock_cycles: u32,
+}
+
+impl Default for CycleLockConfig {
+    fn default() -> Self {
+        Self {
+            kp: 0.2,
+            ki: 0.02,
+            max_correction: 0.1,
+            lock_threshold: Duration::from_micros(50),
+            lock_cycles: 100,
+        }
+    }
+}
+
+/// Statistics recorded by a [`CycleLock`].
+///
+/// Phase errors are in nanoseconds. A positive error means the process data arrived at the DC
+/// reference SubDevice later than [`sync0_shift`](DcConfiguration::sync0_shift) after SYNC0.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CycleLockStats {
+    /// The number of cycles recorded.
+    pub cycles: u64,
+    /// The phase error of the most recent cycle.
+    pub last_error_ns: i64,
+    /// The most negative phase error.
+    pub min_error_ns: i64,
+    /// The most positive phase error.
+    pub max_error_ns: i64,
+    /// The number of cycles where the correction was limited by
+    /// [`max_correction`](CycleLockConfig::max_correction).
+    pub saturated_cycles: u64,
+    /// The number of times lock was lost after being acquired.
+    pub lock_losses: u32,
+}
+
+/// A PI controller that locks the host process data cycle to the DC SYNC0 pulse.
+///
+/// [`CycleInfo::next_cycle_wait`] on its own corrects the whole phase error of each cycle at once,
+/// so any jitter in the host's timer or network stack is passed straight into the next cycle.
+/// `CycleLock` filters the phase error measured by every call to
+/// [`tx_rx_dc`](super::SubDeviceGroup::tx_rx_dc) and returns a smoothed wait time instead, with
+/// anti-windup, lock detection and statistics.
+///
+/// `CycleLock` does not depend on any async runtime or timer. Measure the start of each cycle
+/// before calling `tx_rx_dc` and wait until the start time plus the duration returned by
+/// [`update`](CycleLock::update).
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ethercrab::{
+///     std::ethercat_now,
+///     subdevice_group::{CycleLock, CycleLockConfig, DcConfiguration},
+///     MainDevice, MainDeviceConfig, PduStorage, Timeouts,
+/// };
+/// use std::time::{Duration, Instant};
+///
+/// static PDU_STORAGE: PduStorage<16, { PduStorage::element_size(1100) }> = PduStorage::new();
+///
+/// let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+///
+/// let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+///
+/// let dc_config = DcConfiguration {
+///     start_delay: Duration::from_millis(100),
+///     sync0_period: Duration::from_millis(1),
+///     sync0_shift: Duration::from_micros(500),
+/// };
+///
+/// # async {
+/// let group = maindevice
+///     .init_single_group::<16, 64>(ethercat_now)
+///     .await
+///     .expect("Init")
+///     .into_pre_op_pdi(&maindevice)
+///     .await
+///     .expect("PRE-OP -> PRE-OP with PDI")
+///     .configure_dc_sync(&maindevice, dc_config)
+///     .await
+///     .expect("DC configuration")
+///     .into_op(&maindevice)
+///     .await
+///     .expect("SAFE-OP -> OP");
+///
+/// let mut cycle_lock = CycleLock::new(dc_config, CycleLockConfig::default());
+///
+/// loop {
+///     let now = Instant::now();
+///
+///     let (_wkc, cycle_info) = group.tx_rx_dc(&maindevice).await.expect("TX/RX");
+///
+///     let wait = cycle_lock.update(&cycle_info);
+///
+///     if !cycle_lock.is_locked() {
+///         eprintln!("Not locked, stats {:?}", cycle_lock.stats());
+///     }
+///
+///     smol::Timer::at(now + wait).await;
+/// }
+/// # };
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct CycleLock {
+    config: CycleLockConfig,
+    /// SYNC0 period in nanoseconds.
+    period: i64,
+    /// Target offset from SYNC0 in nanoseconds.
+    shift: i64,
+    /// Sum of phase errors in nanoseconds.
+    integral: i64,
+    /// Consecutive cycles below the lock threshold.
+    settled_cycles: u32,
+    locked: bool,
+    stats: CycleLockStats,
+}
+
+impl CycleLock {
+    /// Create a new controller for a group configured with the given DC configuration.
+    pub fn new(dc_config: DcConfiguration, config: CycleLockConfig) -> Self {
+        Self {
+            config,
+            period: i64::try_from(dc_config.sync0_period.as_nanos()).unwrap_or(i64::MAX),
+            shift: i64::try_from(dc_config.sync0_shift.as_nanos()).unwrap_or(i64::MAX),
+            integral: 0,
+            settled_cycles: 0,
+            locked: false,
+            stats: CycleLockStats::default(),
+        }
+    }
+
+    /// Record the cycle timing returned by [`tx_rx_dc`](super::SubDeviceGroup::tx_rx_dc) and
+    /// compute how long to wait from the start of this cycle to the start of the next.
+    pub fn update(&mut self, cycle_info: &CycleInfo) -> Duration {
+        if self.period == 0 {
+            return cycle_info.next_cycle_wait;
+        }
+
+        let offset = i64::try_from(cycle_info.cycle_start_offset.as_nanos()).unwrap_or(i64::MAX);
+
+        let error = self.phase_error(offset);
+
+        self.record(error);
+
+        let max_correction = (self.period as f32 * self.config.max_correction) as i64;
+
+        let integral = self.integral.saturating_add(error);
+
+        let correction = (self.config.kp * error as f32 + self.config.ki * integral as f32) as i64;
+
+        let saturated = correction.abs() > max_correction;
+
+        if saturated {
+            self.stats.saturated_cycles += 1;
+        }
+
+        // Anti-windup: stop integrating while the correction is limited, unless the error would
+        // reduce the integral.
+        if !saturated || error.signum() != correction.signum() {
+            self.integral = integral;
+        }
+
+        let correction = correction.clamp(-max_correction, max_correction);
+
+        // A late cycle is corrected by waiting for less than a full period
+        Duration::from_nanos(u64::try_from(self.period - correction).unwrap_or(0))
+    }
+
+    /// Whether the phase error has settled below
+    /// [`lock_threshold`](CycleLockConfig::lock_threshold).
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Statistics recorded since the controller was created or last [`reset`](CycleLock::reset).
+    pub fn stats(&self) -> CycleLockStats {
+        self.stats
+    }
+
+    /// Reset the controller state and statistics, e.g. after the cyclic application has been
+    /// paused.
+    pub fn reset(&mut self) {
+        self.integral = 0;
+        self.settled_cycles = 0;
+        self.locked = false;
+        self.stats = CycleLockStats::default();
+    }
+
+    /// The signed distance in nanoseconds from the target offset to the measured offset, wrapped
+    /// into half a period either side.
+    fn phase_error(&self, offset: i64) -> i64 {
+        let error = (offset - self.shift).rem_euclid(self.period);
+
+        if error > self.period / 2 {
+            error - self.period
+        } else {
+            error
+        }
+    }
+
+    /// Update statistics and lock state.
+    fn record(&mut self, error: i64) {
+        let stats = &mut self.stats;
+
+        if stats.cycles == 0 {
+            stats.min_error_ns = error;
+            stats.max_error_ns = error;
+        } else {
+            stats.min_error_ns = stats.min_error_ns.min(error);
+            stats.max_error_ns = stats.max_error_ns.max(error);
+        }
+
+        stats.cycles += 1;
+        stats.last_error_ns = error;
+
+        let threshold = i64::try_from(self.config.lock_threshold.as_nanos()).unwrap_or(i64::MAX);
+
+        if error.abs() < threshold {
+            self.settled_cycles = self.settled_cycles.saturating_add(1);
+        } else {
+            self.settled_cycles = 0;
+        }
+
+        if self.locked && error.abs() > threshold.saturating_mul(2) {
+            fmt::debug!("Cycle lock lost, phase error {} ns", error);
+
+            self.locked = false;
+            stats.lock_losses += 1;
+        } else if !self.locked && self.settled_cycles >= self.config.lock_cycles {
+            fmt::debug!("Cycle locked after {} cycles", stats.cycles);
+
+            self.locked = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PERIOD: u64 = 1_000_000;
+    const SHIFT: u64 = 500_000;
+
+    fn cycle_lock() -> CycleLock {
+        CycleLock::new(
+            DcConfiguration {
+                start_delay: Duration::ZERO,
+                sync0_period: Duration::from_nanos(PERIOD),
+                sync0_shift: Duration::from_nanos(SHIFT),
+            },
+            CycleLockConfig::default(),
+        )
+    }
+
+    /// Simulate a host clock running `drift` nanoseconds fast per cycle, starting at `offset` into
+    /// the DC cycle. Returns the final offset.
+    fn simulate(lock: &mut CycleLock, mut offset: u64, drift: i64, cycles: usize) -> u64 {
+        for _ in 0..cycles {
+            let wait = lock.update(&CycleInfo {
+                dc_system_time: 0,
+                next_cycle_wait: Duration::ZERO,
+                cycle_start_offset: Duration::from_nanos(offset),
+            });
+
+            let elapsed = wait.as_nanos() as i64 - drift;
+
+            offset = (offset as i64 + elapsed).rem_euclid(PERIOD as i64) as u64;
+        }
+
+        offset
+    }
+
+    #[test]
+    fn phase_error_wraps() {
+        let lock = cycle_lock();
+
+        assert_eq!(lock.phase_error(SHIFT as i64 + 10), 10);
+        assert_eq!(lock.phase_error(SHIFT as i64 - 10), -10);
+        // Just after SYNC0 is closer to the previous cycle's target
+        assert_eq!(lock.phase_error(1), -499_999);
+        assert_eq!(lock.phase_error(SHIFT as i64 + 600_000), -400_000);
+    }
+
+    #[test]
+    fn locks_with_drift() {
+        let mut lock = cycle_lock();
+
+        let offset = simulate(&mut lock, 10_000, 2_000, 1000);
+
+        assert!(lock.is_locked());
+        // Integral term removes the error caused by drift
+        assert!(offset.abs_diff(SHIFT) < 10, "{}", offset);
+
+        let stats = lock.stats();
+
+        assert_eq!(stats.cycles, 1000);
+        assert_eq!(stats.min_error_ns, -490_000);
+        assert!(stats.saturated_cycles > 0);
+        assert_eq!(stats.lock_losses, 0);
+    }
+
+    #[test]
+    fn no_windup() {
+        let mut lock = cycle_lock();
+
+        // The host is unable to correct a large error for a few cycles, e.g. while busy
+        for _ in 0..10 {
+            lock.update(&CycleInfo {
+                dc_system_time: 0,
+                next_cycle_wait: Duration::ZERO,
+                cycle_start_offset: Duration::from_nanos(10_000),
+            });
+        }
+
+        assert_eq!(lock.stats().saturated_cycles, 10);
+
+        simulate(&mut lock, SHIFT, 0, 200);
+
+        let stats = lock.stats();
+
+        assert!(lock.is_locked());
+        // The phase would overshoot well past the lock threshold if the integral had kept growing
+        // during the saturated cycles
+        assert_eq!(stats.max_error_ns, 0, "{:?}", stats);
+    }
+
+    #[test]
+    fn lose_lock() {
+        let mut lock = cycle_lock();
+
+        simulate(&mut lock, SHIFT, 0, 100);
+
+        assert!(lock.is_locked());
+
+        // A host stall
+        simulate(&mut lock, SHIFT + 200_000, 0, 1);
+
+        assert!(!lock.is_locked());
+        assert_eq!(lock.stats().lock_losses, 1);
+
+        lock.reset();
+
+        assert_eq!(lock.stats(), CycleLockStats::default());
+    }
+}
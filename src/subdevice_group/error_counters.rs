@@ -0,0 +1,283 @@
+//! Group-wide ESC error counter diagnostics.
+
+use super::{storage::GroupList, SubDeviceGroup};
+use crate::{error::Error, fmt, subdevice::SubDeviceRef, ErrorCounters, MainDevice};
+
+/// A cable segment with errors, identified by the SubDevice port it is connected to.
+///
+/// Port 0 is usually connected to the previous SubDevice in the network, or the MainDevice for the
+/// first SubDevice. Other ports lead to the following SubDevices.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CableSegment {
+    /// Configured station address of the SubDevice that detected the errors.
+    pub configured_address: u16,
+    /// The port that detected the errors, from 0 to 3.
+    pub port: u8,
+    /// The number of errors detected, not including errors forwarded from previous SubDevices.
+    pub errors: u16,
+}
+
+/// ESC error counters of every SubDevice in a group, returned by
+/// [`SubDeviceGroup::error_counters`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorCounterReport<const MAX_SUBDEVICES: usize> {
+    counters: GroupList<(u16, ErrorCounters), MAX_SUBDEVICES>,
+}
+
+impl<const MAX_SUBDEVICES: usize> ErrorCounterReport<MAX_SUBDEVICES> {
+    /// The error counters of each SubDevice, paired with its configured station address.
+    pub fn counters(&self) -> &[(u16, ErrorCounters)] {
+        &self.counters
+    }
+
+    /// Get the error counters of the SubDevice with the given configured station address.
+    pub fn get(&self, configured_address: u16) -> Option<&ErrorCounters> {
+        self.counters
+            .iter()
+            .find(|(address, _)| *address == configured_address)
+            .map(|(_, counters)| counters)
+    }
+
+    /// The increase of every counter since a `previous` report.
+    ///
+    /// SubDevices missing from `previous` keep their current counts.
+    pub fn since(&self, previous: &Self) -> Self {
+        let mut counters = self.counters.clone();
+
+        for (address, current) in counters.iter_mut() {
+            if let Some(previous) = previous.get(*address) {
+                *current = current.since(previous);
+            }
+        }
+
+        Self { counters }
+    }
+
+    /// Every cable segment with at least one error, in network order.
+    pub fn segments(&self) -> impl Iterator<Item = CableSegment> + '_ {
+        self.counters
+            .iter()
+            .flat_map(|(configured_address, counters)| {
+                counters
+                    .ports
+                    .iter()
+                    .zip(0u8..)
+                    .map(|(port, number)| CableSegment {
+                        configured_address: *configured_address,
+                        port: number,
+                        errors: port.local_errors(),
+                    })
+            })
+            .filter(|segment| segment.errors > 0)
+    }
+
+    /// The cable segment with the most errors.
+    ///
+    /// Call this on the result of [`since`](ErrorCounterReport::since) to find the segment whose
+    /// errors are growing the fastest.
+    pub fn worst_segment(&self) -> Option<CableSegment> {
+        self.segments()
+            // Pick the first segment in network order if several have the same count
+            .fold(None, |worst: Option<CableSegment>, segment| match worst {
+                Some(worst) if worst.errors >= segment.errors => Some(worst),
+                _ => Some(segment),
+            })
+    }
+}
+
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC, M>
+    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, DC, M>
+{
+    /// Read the ESC error counters of every SubDevice in the group.
+    ///
+    /// SubDevices are not borrowed, so this method can be called while SubDevices are held by
+    /// e.g. [`SubDeviceGroup::subdevice`] in another task.
+    ///
+    /// # Examples
+    ///
+    /// Find the cable segment with the fastest growing error count.
+    ///
+    /// ```rust,no_run
+    /// use ethercrab::{
+    ///     std::ethercat_now, MainDevice, MainDeviceConfig, PduStorage, Timeouts,
+    /// };
+    /// use std::time::Duration;
+    ///
+    /// static PDU_STORAGE: PduStorage<16, { PduStorage::element_size(1100) }> = PduStorage::new();
+    ///
+    /// let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    ///
+    /// let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+    ///
+    /// # async {
+    /// let group = maindevice
+    ///     .init_single_group::<16, 64>(ethercat_now)
+    ///     .await
+    ///     .expect("Init");
+    ///
+    /// let mut previous = group.error_counters(&maindevice).await.expect("Read counters");
+    ///
+    /// loop {
+    ///     smol::Timer::after(Duration::from_secs(10)).await;
+    ///
+    ///     let current = group.error_counters(&maindevice).await.expect("Read counters");
+    ///
+    ///     if let Some(segment) = current.since(&previous).worst_segment() {
+    ///         eprintln!(
+    ///             "{} new errors on port {} of SubDevice {:#06x}",
+    ///             segment.errors, segment.port, segment.configured_address
+    ///         );
+    ///     }
+    ///
+    ///     previous = current;
+    /// }
+    /// # };
+    /// ```
+    pub async fn error_counters(
+        &self,
+        maindevice: &MainDevice<'_>,
+    ) -> Result<ErrorCounterReport<MAX_SUBDEVICES>, Error> {
+        let addresses = &self.inner().addresses;
+
+        let mut counters = addresses.empty_like();
+
+        for &configured_address in addresses.iter() {
+            let subdevice_counters = SubDeviceRef::new(maindevice, configured_address, ())
+                .error_counters()
+                .await?;
+
+            if let Some((port, errors)) = subdevice_counters.worst_port() {
+                fmt::trace!(
+                    "SubDevice {:#06x} has {} errors on port {}",
+                    configured_address,
+                    errors,
+                    port
+                );
+            }
+
+            // The report holds as many SubDevices as the group so will never be full
+            let _ = counters.push((configured_address, subdevice_counters));
+        }
+
+        Ok(ErrorCounterReport { counters })
+    }
+
+    /// Reset the ESC error counters of every SubDevice in the group.
+    ///
+    /// Like [`error_counters`](SubDeviceGroup::error_counters), SubDevices are not borrowed.
+    pub async fn reset_error_counters(&self, maindevice: &MainDevice<'_>) -> Result<(), Error> {
+        for &configured_address in self.inner().addresses.iter() {
+            SubDeviceRef::new(maindevice, configured_address, ())
+                .reset_error_counters()
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        sim::{SimNetwork, SimSubDevice},
+        subdevice_group::Op,
+        PduStorage, PortErrorCounters,
+    };
+
+    fn counters(port: usize, invalid_frames: u8) -> ErrorCounters {
+        let mut counters = ErrorCounters::default();
+
+        counters.ports[port] = PortErrorCounters {
+            invalid_frames,
+            ..PortErrorCounters::default()
+        };
+
+        counters
+    }
+
+    fn report(counters: &[(u16, ErrorCounters)]) -> ErrorCounterReport<4> {
+        let mut report = ErrorCounterReport {
+            counters: GroupList::default(),
+        };
+
+        for item in counters {
+            report.counters.push(*item).unwrap();
+        }
+
+        report
+    }
+
+    #[test]
+    fn worst_growing_segment() {
+        let previous = report(&[(0x1000, counters(1, 100)), (0x1001, counters(0, 3))]);
+        let current = report(&[
+            (0x1000, counters(1, 101)),
+            (0x1001, counters(0, 8)),
+            (0x1002, counters(0, 2)),
+        ]);
+
+        // Most errors overall
+        assert_eq!(
+            current.worst_segment(),
+            Some(CableSegment {
+                configured_address: 0x1000,
+                port: 1,
+                errors: 101
+            })
+        );
+
+        let growth = current.since(&previous);
+
+        assert_eq!(
+            growth.worst_segment(),
+            Some(CableSegment {
+                configured_address: 0x1001,
+                port: 0,
+                errors: 5
+            })
+        );
+        assert_eq!(growth.segments().count(), 3);
+    }
+
+    #[test]
+    fn no_errors() {
+        let report = report(&[(0x1000, ErrorCounters::default())]);
+
+        assert_eq!(report.worst_segment(), None);
+        assert_eq!(report.segments().count(), 0);
+    }
+
+    #[test]
+    fn borrowed_subdevice() {
+        let storage = PduStorage::<16, { PduStorage::element_size(1100) }>::new();
+        let (maindevice, mut network) = SimNetwork::new(
+            &storage,
+            vec![SimSubDevice::new(1, 0), SimSubDevice::new(1, 1)],
+        );
+
+        let group = network
+            .run(maindevice.init_single_group::<4, 64>(|| 0))
+            .expect("init");
+        let group: SubDeviceGroup<4, 64, Op> = network.run(group.into_op(&maindevice)).expect("OP");
+
+        let borrowed = group.subdevice(&maindevice, 1).expect("SubDevice");
+
+        let report = network
+            .run(group.error_counters(&maindevice))
+            .expect("error counters");
+
+        // Borrowed SubDevices are still read
+        assert_eq!(report.counters().len(), 2);
+        assert!(report.get(0x1000).is_some());
+        assert!(report.get(0x1001).is_some());
+
+        assert_eq!(network.run(group.reset_error_counters(&maindevice)), Ok(()));
+
+        // Reading counters doesn't borrow SubDevices
+        drop(borrowed);
+
+        let _subdevice = group.subdevice(&maindevice, 1).expect("SubDevice");
+    }
+}
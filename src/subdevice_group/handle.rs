@@ -1,5 +1,8 @@
-use crate::{error::Error, subdevice_group::SubDeviceGroupRef, GroupId, SubDevice, SubDeviceGroup};
-use atomic_refcell::AtomicRefCell;
+use crate::{
+    error::Error,
+    subdevice_group::{NoDc, SubDeviceGroupRef},
+    GroupId, SubDevice, SubDeviceGroup, SubDeviceIdentity,
+};
 
 /// A trait implemented only by [`SubDeviceGroup`] so multiple groups with different const params
 /// can be stored in a hashmap, `Vec`, etc.
@@ -12,26 +15,53 @@ pub trait SubDeviceGroupHandle {
     /// Add a SubDevice device to this group.
     unsafe fn push(&self, subdevice: SubDevice) -> Result<(), Error>;
 
+    /// Add a SubDevice device to this group through a unique reference.
+    fn push_mut(&mut self, subdevice: SubDevice) -> Result<(), Error>;
+
     /// Get a reference to the group with const generic params erased.
     fn as_ref(&self) -> SubDeviceGroupRef<'_>;
+
+    /// Get a reference to the group with const generic params erased through a unique reference.
+    fn as_mut(&mut self) -> SubDeviceGroupRef<'_>;
+
+    /// Get the configured address and identity of the SubDevice in this group that was discovered
+    /// at the given network position.
+    fn find_position(&self, position: u16) -> Result<Option<(u16, SubDeviceIdentity)>, Error>;
 }
 
 #[sealed::sealed]
-impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S> SubDeviceGroupHandle
-    for SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S>
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, M> SubDeviceGroupHandle
+    for SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, NoDc, M>
 {
     fn id(&self) -> GroupId {
         self.id
     }
 
     unsafe fn push(&self, subdevice: SubDevice) -> Result<(), Error> {
-        (*self.inner.get())
-            .subdevices
-            .push(AtomicRefCell::new(subdevice))
-            .map_err(|_| Error::Capacity(crate::error::Item::SubDevice))
+        (*self.inner.get()).push_subdevice(subdevice)
+    }
+
+    fn push_mut(&mut self, subdevice: SubDevice) -> Result<(), Error> {
+        self.inner.get_mut().push_subdevice(subdevice)
     }
 
     fn as_ref(&self) -> SubDeviceGroupRef<'_> {
         SubDeviceGroupRef::new(self)
     }
+
+    fn as_mut(&mut self) -> SubDeviceGroupRef<'_> {
+        SubDeviceGroupRef::new_mut(self)
+    }
+
+    fn find_position(&self, position: u16) -> Result<Option<(u16, SubDeviceIdentity)>, Error> {
+        for subdevice in self.inner().subdevices.iter() {
+            let subdevice = subdevice.try_borrow().map_err(|_| Error::Borrow)?;
+
+            if subdevice.index == position {
+                return Ok(Some((subdevice.configured_address(), subdevice.identity)));
+            }
+        }
+
+        Ok(None)
+    }
 }
@@ -0,0 +1,593 @@
+//! Periodically check the health of a group's SubDevices.
+
+use super::SubDeviceGroup;
+use crate::{
+    al_control::AlControl, command::Command, dl_status::DlStatus, error::Error, fmt,
+    timer_factory::timer, AlStatusCode, MainDevice, RegisterAddress, SubDeviceState,
+    WatchdogStatus,
+};
+use core::{
+    sync::atomic::{AtomicU16, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+/// Working counter statistics recorded by a group's `tx_rx*` methods and zero-copy frames.
+#[derive(Debug, Default)]
+pub(crate) struct WkcStats {
+    /// The number of exchanges whose working counter did not match the expected value.
+    mismatches: AtomicUsize,
+    /// The working counter received in the most recent mismatched exchange.
+    last_received: AtomicU16,
+    /// The working counter expected in the most recent mismatched exchange.
+    last_expected: AtomicU16,
+}
+
+impl WkcStats {
+    /// Record the result of a PDI exchange.
+    pub(crate) fn record(&self, received: u16, expected: u16) {
+        if received != expected {
+            self.last_received.store(received, Ordering::Relaxed);
+            self.last_expected.store(expected, Ordering::Relaxed);
+            self.mismatches.fetch_add(1, Ordering::Release);
+        }
+    }
+}
+
+/// An event emitted by a [`HealthMonitor`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum HealthEvent {
+    /// A SubDevice changed state, e.g. left OP.
+    StateChanged {
+        /// SubDevice configured station address.
+        configured_address: u16,
+        /// The state the SubDevice was in when last checked.
+        previous: SubDeviceState,
+        /// The state the SubDevice is in now.
+        current: SubDeviceState,
+    },
+    /// A SubDevice set an AL status code, i.e. it has an error.
+    AlStatusCode {
+        /// SubDevice configured station address.
+        configured_address: u16,
+        /// The AL status code read from the SubDevice.
+        code: AlStatusCode,
+    },
+    /// A SubDevice lost the physical link on one of its ports.
+    LinkLost {
+        /// SubDevice configured station address.
+        configured_address: u16,
+        /// Port number, from 0 to 3.
+        port: u8,
+    },
+    /// A SubDevice regained the physical link on one of its ports.
+    LinkRestored {
+        /// SubDevice configured station address.
+        configured_address: u16,
+        /// Port number, from 0 to 3.
+        port: u8,
+    },
+    /// A port's loop changed.
+    ///
+    /// A closed port forwards frames back to itself instead of to the next SubDevice.
+    LoopChanged {
+        /// SubDevice configured station address.
+        configured_address: u16,
+        /// Port number, from 0 to 3.
+        port: u8,
+        /// Whether the port's loop is now closed.
+        closed: bool,
+    },
+    /// A SubDevice's sync manager watchdog expired because its outputs were not written within
+    /// the watchdog timeout, e.g. because the cyclic application stalled.
+    ///
+    /// The SubDevice drops its outputs to a safe state and leaves OP.
+    SyncManagerWatchdogExpired {
+        /// SubDevice configured station address.
+        configured_address: u16,
+        /// The number of expirations since the last check.
+        ///
+        /// Once the SubDevice's expiry counter has saturated at 255, this is 1 for every check
+        /// where the watchdog is still expired.
+        count: u8,
+    },
+    /// A SubDevice did not respond to status reads, e.g. because it was disconnected or lost
+    /// power.
+    NotResponding {
+        /// SubDevice configured station address.
+        configured_address: u16,
+    },
+    /// A SubDevice that previously did not respond is responding again.
+    Responding {
+        /// SubDevice configured station address.
+        configured_address: u16,
+    },
+    /// Cyclic PDI exchanges returned an unexpected working counter since the last check.
+    WorkingCounter {
+        /// The number of mismatched exchanges since the last check.
+        count: usize,
+        /// The working counter expected in the most recent mismatched exchange.
+        expected: u16,
+        /// The working counter received in the most recent mismatched exchange.
+        received: u16,
+    },
+}
+
+/// The last observed status of a single SubDevice.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct SubDeviceHealth {
+    state: SubDeviceState,
+    code: AlStatusCode,
+    /// One bit per port, set if the port has a physical link.
+    links: u8,
+    /// One bit per port, set if the port's loop is closed.
+    loops: u8,
+    watchdog: WatchdogStatus,
+}
+
+impl SubDeviceHealth {
+    fn new(
+        al_status: AlControl,
+        code: AlStatusCode,
+        dl_status: DlStatus,
+        watchdog: WatchdogStatus,
+    ) -> Self {
+        let bits = |values: [bool; 4]| {
+            values
+                .iter()
+                .enumerate()
+                .fold(0u8, |acc, (port, set)| acc | (u8::from(*set) << port))
+        };
+
+        Self {
+            state: al_status.state,
+            code,
+            links: bits([
+                dl_status.link_port0,
+                dl_status.link_port1,
+                dl_status.link_port2,
+                dl_status.link_port3,
+            ]),
+            loops: bits([
+                dl_status.loopback_port0,
+                dl_status.loopback_port1,
+                dl_status.loopback_port2,
+                dl_status.loopback_port3,
+            ]),
+            watchdog,
+        }
+    }
+
+    /// Emit events for everything that changed between `self` and `current`.
+    fn diff(
+        &self,
+        current: &Self,
+        configured_address: u16,
+        on_event: &mut impl FnMut(HealthEvent),
+    ) {
+        if current.state != self.state {
+            on_event(HealthEvent::StateChanged {
+                configured_address,
+                previous: self.state,
+                current: current.state,
+            });
+        }
+
+        if current.code != self.code && current.code != AlStatusCode::NoError {
+            on_event(HealthEvent::AlStatusCode {
+                configured_address,
+                code: current.code,
+            });
+        }
+
+        for port in 0..4u8 {
+            let mask = 1 << port;
+
+            if self.links & mask != current.links & mask {
+                on_event(if current.links & mask == 0 {
+                    HealthEvent::LinkLost {
+                        configured_address,
+                        port,
+                    }
+                } else {
+                    HealthEvent::LinkRestored {
+                        configured_address,
+                        port,
+                    }
+                });
+            }
+
+            if self.loops & mask != current.loops & mask {
+                on_event(HealthEvent::LoopChanged {
+                    configured_address,
+                    port,
+                    closed: current.loops & mask != 0,
+                });
+            }
+        }
+
+        // The counter is cleared if the SubDevice lost power
+        let expirations = current
+            .watchdog
+            .sync_manager_expirations
+            .checked_sub(self.watchdog.sync_manager_expirations)
+            .unwrap_or(current.watchdog.sync_manager_expirations);
+
+        // New expirations can't be counted once the counter has saturated, so report the watchdog
+        // for as long as it stays expired.
+        let saturated = self.watchdog.sync_manager_expirations == u8::MAX
+            && current.watchdog.sync_manager_expirations == u8::MAX;
+
+        if expirations > 0
+            || (self.watchdog.sync_manager_ok && !current.watchdog.sync_manager_ok)
+            || (saturated && !current.watchdog.sync_manager_ok)
+        {
+            on_event(HealthEvent::SyncManagerWatchdogExpired {
+                configured_address,
+                count: expirations.max(1),
+            });
+        }
+    }
+}
+
+/// The status of a SubDevice as seen by the previous check.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Observed {
+    /// The SubDevice has not been checked yet.
+    Unknown,
+    Responding(SubDeviceHealth),
+    NotResponding(Option<SubDeviceHealth>),
+}
+
+/// Check AL status, AL status codes, DL status, sync manager watchdogs and working counters of all
+/// SubDevices in a group, emitting a [`HealthEvent`] when something changes.
+///
+/// A monitor only reads SubDevice registers and never borrows SubDevices, so it can be run
+/// in a separate task from the one calling the group's `tx_rx*` methods. Working counter
+/// mismatches are recorded by [`tx_rx`](SubDeviceGroup::tx_rx) and friends and reported on the
+/// next check.
+///
+/// Up to `MAX_SUBDEVICES` SubDevices at the start of the group are monitored, so
+/// `MAX_SUBDEVICES` should be at least the number of SubDevices in the group.
+///
+/// The first check records the baseline status of each SubDevice, only emitting events for AL
+/// status codes that are already set.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ethercrab::{
+///     std::ethercat_now, subdevice_group::HealthMonitor, MainDevice, MainDeviceConfig,
+///     PduStorage, Timeouts,
+/// };
+/// use std::time::Duration;
+///
+/// static PDU_STORAGE: PduStorage<16, { PduStorage::element_size(1100) }> = PduStorage::new();
+///
+/// let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+///
+/// let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+///
+/// # async {
+/// let group = maindevice
+///     .init_single_group::<16, 64>(ethercat_now)
+///     .await
+///     .expect("Init")
+///     .into_op(&maindevice)
+///     .await
+///     .expect("PRE-OP -> OP");
+///
+/// let monitor = async {
+///     HealthMonitor::<16>::new()
+///         .run(&group, &maindevice, Duration::from_millis(100), |event| {
+///             println!("Network health: {:?}", event);
+///         })
+///         .await
+/// };
+///
+/// let cyclic = async {
+///     loop {
+///         group.tx_rx(&maindevice).await.expect("TX/RX");
+///
+///         // Process data here
+///     }
+/// };
+///
+/// futures_lite::future::race(monitor, cyclic).await;
+/// # };
+/// ```
+#[derive(Debug)]
+pub struct HealthMonitor<const MAX_SUBDEVICES: usize> {
+    observed: [Observed; MAX_SUBDEVICES],
+    wkc_mismatches: usize,
+}
+
+impl<const MAX_SUBDEVICES: usize> Default for HealthMonitor<MAX_SUBDEVICES> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const MAX_SUBDEVICES: usize> HealthMonitor<MAX_SUBDEVICES> {
+    /// Create a new monitor.
+    pub const fn new() -> Self {
+        Self {
+            observed: [Observed::Unknown; MAX_SUBDEVICES],
+            wkc_mismatches: 0,
+        }
+    }
+
+    /// Check every SubDevice in the group once, calling `on_event` for each change since the
+    /// previous check.
+    ///
+    /// # Errors
+    ///
+    /// SubDevices that do not respond are reported with [`HealthEvent::NotResponding`]. Any other
+    /// error, e.g. the PDU storage being full, is returned.
+    pub async fn check<const GROUP_MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC, M>(
+        &mut self,
+        group: &SubDeviceGroup<GROUP_MAX_SUBDEVICES, MAX_PDI, S, DC, M>,
+        maindevice: &MainDevice<'_>,
+        mut on_event: impl FnMut(HealthEvent),
+    ) -> Result<(), Error> {
+        let inner = group.inner();
+
+        for (&configured_address, observed) in inner.addresses.iter().zip(self.observed.iter_mut())
+        {
+            let current = match Self::read(maindevice, configured_address).await {
+                Ok(current) => current,
+                Err(Error::WorkingCounter { .. }) | Err(Error::Timeout) => {
+                    if !matches!(observed, Observed::NotResponding(_)) {
+                        fmt::debug!("SubDevice {:#06x} is not responding", configured_address);
+
+                        on_event(HealthEvent::NotResponding { configured_address });
+
+                        *observed = Observed::NotResponding(match observed {
+                            Observed::Responding(previous) => Some(*previous),
+                            _ => None,
+                        });
+                    }
+
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            let previous = match observed {
+                Observed::Unknown => None,
+                Observed::Responding(previous) => Some(*previous),
+                Observed::NotResponding(previous) => {
+                    on_event(HealthEvent::Responding { configured_address });
+
+                    *previous
+                }
+            };
+
+            match previous {
+                Some(previous) => previous.diff(&current, configured_address, &mut on_event),
+                None if current.code != AlStatusCode::NoError => {
+                    on_event(HealthEvent::AlStatusCode {
+                        configured_address,
+                        code: current.code,
+                    })
+                }
+                None => (),
+            }
+
+            *observed = Observed::Responding(current);
+        }
+
+        let stats = &inner.wkc_stats;
+
+        let mismatches = stats.mismatches.load(Ordering::Acquire);
+
+        if mismatches != self.wkc_mismatches {
+            on_event(HealthEvent::WorkingCounter {
+                count: mismatches.wrapping_sub(self.wkc_mismatches),
+                expected: stats.last_expected.load(Ordering::Relaxed),
+                received: stats.last_received.load(Ordering::Relaxed),
+            });
+
+            self.wkc_mismatches = mismatches;
+        }
+
+        Ok(())
+    }
+
+    /// Call [`check`](HealthMonitor::check) every `interval` until an error occurs.
+    pub async fn run<const GROUP_MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC, M>(
+        &mut self,
+        group: &SubDeviceGroup<GROUP_MAX_SUBDEVICES, MAX_PDI, S, DC, M>,
+        maindevice: &MainDevice<'_>,
+        interval: Duration,
+        mut on_event: impl FnMut(HealthEvent),
+    ) -> Result<(), Error> {
+        loop {
+            self.check(group, maindevice, &mut on_event).await?;
+
+            timer(interval).await;
+        }
+    }
+
+    async fn read(
+        maindevice: &MainDevice<'_>,
+        configured_address: u16,
+    ) -> Result<SubDeviceHealth, Error> {
+        let ((al_status, code), (dl_status, watchdog)) = futures_lite::future::try_zip(
+            futures_lite::future::try_zip(
+                Command::fprd(configured_address, RegisterAddress::AlStatus.into())
+                    .receive::<AlControl>(maindevice),
+                Command::fprd(configured_address, RegisterAddress::AlStatusCode.into())
+                    .receive::<AlStatusCode>(maindevice),
+            ),
+            futures_lite::future::try_zip(
+                Command::fprd(configured_address, RegisterAddress::DlStatus.into())
+                    .receive::<DlStatus>(maindevice),
+                Command::fprd(
+                    configured_address,
+                    RegisterAddress::SyncManagerWatchdogStatus.into(),
+                )
+                .receive::<WatchdogStatus>(maindevice),
+            ),
+        )
+        .await?;
+
+        Ok(SubDeviceHealth::new(al_status, code, dl_status, watchdog))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        sim::{SimNetwork, SimSubDevice},
+        subdevice_group::Op,
+        PduStorage,
+    };
+
+    fn health(state: SubDeviceState, code: AlStatusCode, links: u8, loops: u8) -> SubDeviceHealth {
+        SubDeviceHealth {
+            state,
+            code,
+            links,
+            loops,
+            watchdog: WatchdogStatus {
+                sync_manager_ok: true,
+                sync_manager_expirations: 0,
+                pdi_expirations: 0,
+            },
+        }
+    }
+
+    #[test]
+    fn diff_events() {
+        let previous = health(SubDeviceState::Op, AlStatusCode::NoError, 0b0011, 0b1100);
+        let current = health(
+            SubDeviceState::SafeOp,
+            AlStatusCode::SyncManagerWatchdog,
+            0b0001,
+            0b1110,
+        );
+
+        let mut events = Vec::new();
+
+        previous.diff(&current, 0x1001, &mut |event| events.push(event));
+
+        assert_eq!(
+            events,
+            [
+                HealthEvent::StateChanged {
+                    configured_address: 0x1001,
+                    previous: SubDeviceState::Op,
+                    current: SubDeviceState::SafeOp,
+                },
+                HealthEvent::AlStatusCode {
+                    configured_address: 0x1001,
+                    code: AlStatusCode::SyncManagerWatchdog
+                },
+                HealthEvent::LinkLost {
+                    configured_address: 0x1001,
+                    port: 1
+                },
+                HealthEvent::LoopChanged {
+                    configured_address: 0x1001,
+                    port: 1,
+                    closed: true
+                },
+            ]
+        );
+
+        let mut events = Vec::new();
+
+        current.diff(&current, 0x1001, &mut |event| events.push(event));
+
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn watchdog_expired() {
+        let previous = health(SubDeviceState::Op, AlStatusCode::NoError, 0b0011, 0);
+
+        let mut current = previous;
+        current.watchdog.sync_manager_expirations = 2;
+
+        let mut expired_and_recovered = current;
+        expired_and_recovered.watchdog.sync_manager_ok = false;
+        expired_and_recovered.watchdog.sync_manager_expirations = 255;
+
+        let mut events = Vec::new();
+
+        previous.diff(&current, 0x1001, &mut |event| events.push(event));
+        current.diff(&expired_and_recovered, 0x1001, &mut |event| {
+            events.push(event)
+        });
+        // Saturated counter, still expired
+        expired_and_recovered.diff(&expired_and_recovered, 0x1001, &mut |event| {
+            events.push(event)
+        });
+
+        // Saturated counter, no longer expired
+        let mut saturated_ok = expired_and_recovered;
+        saturated_ok.watchdog.sync_manager_ok = true;
+
+        saturated_ok.diff(&saturated_ok, 0x1001, &mut |event| events.push(event));
+
+        assert_eq!(
+            events,
+            [
+                HealthEvent::SyncManagerWatchdogExpired {
+                    configured_address: 0x1001,
+                    count: 2
+                },
+                HealthEvent::SyncManagerWatchdogExpired {
+                    configured_address: 0x1001,
+                    count: 253
+                },
+                HealthEvent::SyncManagerWatchdogExpired {
+                    configured_address: 0x1001,
+                    count: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn wkc_stats() {
+        let stats = WkcStats::default();
+
+        stats.record(6, 6);
+        stats.record(4, 6);
+        stats.record(3, 6);
+
+        assert_eq!(stats.mismatches.load(Ordering::Relaxed), 2);
+        assert_eq!(stats.last_received.load(Ordering::Relaxed), 3);
+        assert_eq!(stats.last_expected.load(Ordering::Relaxed), 6);
+    }
+
+    #[test]
+    fn borrowed_subdevice() {
+        let storage = PduStorage::<16, { PduStorage::element_size(1100) }>::new();
+        let (maindevice, mut network) = SimNetwork::new(
+            &storage,
+            vec![SimSubDevice::new(1, 0), SimSubDevice::new(1, 1)],
+        );
+
+        let group = network
+            .run(maindevice.init_single_group::<4, 64>(|| 0))
+            .expect("init");
+        let group: SubDeviceGroup<4, 64, Op> = network.run(group.into_op(&maindevice)).expect("OP");
+
+        let mut monitor = HealthMonitor::<4>::new();
+
+        // Held by e.g. the cyclic task
+        let _borrowed = group.subdevice(&maindevice, 1).expect("SubDevice");
+
+        network
+            .run(monitor.check(&group, &maindevice, |_event| ()))
+            .expect("check");
+
+        assert!(matches!(monitor.observed[0], Observed::Responding(_)));
+        assert!(matches!(monitor.observed[1], Observed::Responding(_)));
+        assert!(matches!(monitor.observed[2], Observed::Unknown));
+    }
+}
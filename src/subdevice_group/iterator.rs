@@ -1,4 +1,4 @@
-use super::{HasPdi, PreOp};
+use super::{Fixed, HasPdi, PreOp};
 use crate::{fmt, MainDevice, SubDevice, SubDeviceGroup, SubDevicePdi, SubDeviceRef};
 use atomic_refcell::AtomicRefMut;
 
@@ -13,18 +13,19 @@ pub struct GroupSubDeviceIterator<
     const MAX_PDI: usize,
     S,
     DC,
+    M = Fixed,
 > {
-    group: &'group SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, DC>,
+    group: &'group SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, DC, M>,
     idx: usize,
     maindevice: &'maindevice MainDevice<'maindevice>,
 }
 
-impl<'group, 'maindevice, const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC>
-    GroupSubDeviceIterator<'group, 'maindevice, MAX_SUBDEVICES, MAX_PDI, S, DC>
+impl<'group, 'maindevice, const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC, M>
+    GroupSubDeviceIterator<'group, 'maindevice, MAX_SUBDEVICES, MAX_PDI, S, DC, M>
 {
     pub(in crate::subdevice_group) fn new(
         maindevice: &'maindevice MainDevice<'maindevice>,
-        group: &'group SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, DC>,
+        group: &'group SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, DC, M>,
     ) -> Self {
         Self {
             group,
@@ -35,8 +36,8 @@ impl<'group, 'maindevice, const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S,
 }
 
 // Impl for SubDevices that don't have a PDI yet
-impl<'group, 'maindevice, const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC> Iterator
-    for GroupSubDeviceIterator<'group, 'maindevice, MAX_SUBDEVICES, MAX_PDI, PreOp, DC>
+impl<'group, 'maindevice, const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC, M> Iterator
+    for GroupSubDeviceIterator<'group, 'maindevice, MAX_SUBDEVICES, MAX_PDI, PreOp, DC, M>
 where
     'maindevice: 'group,
 {
@@ -60,8 +61,8 @@ where
 }
 
 // Impl for SubDevices with PDI
-impl<'group, 'maindevice, const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC> Iterator
-    for GroupSubDeviceIterator<'group, 'maindevice, MAX_SUBDEVICES, MAX_PDI, S, DC>
+impl<'group, 'maindevice, const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC, M> Iterator
+    for GroupSubDeviceIterator<'group, 'maindevice, MAX_SUBDEVICES, MAX_PDI, S, DC, M>
 where
     'maindevice: 'group,
     S: HasPdi,
@@ -4,35 +4,136 @@
 //! potentially at different tick rates.
 
 mod configurator;
+mod cycle_lock;
+mod error_counters;
 mod group_id;
 mod handle;
+mod health;
 mod iterator;
-
+mod recovery;
+mod shutdown;
+mod snapshot;
+mod storage;
+mod zero_copy;
+
+use self::health::WkcStats;
+use self::storage::PdiStorage;
 use crate::{
     al_control::AlControl,
     command::Command,
     error::{DistributedClockError, Error, Item, PduError},
     fmt,
+    hooks::HookTransition,
     pdi::PdiOffset,
+    pdu_loop::{PduResponseHandle, ReceiveFrameFut, ReceivedFrame},
     subdevice::{
-        configuration::PdoDirection, pdi::SubDevicePdi, IoRanges, SubDevice, SubDeviceRef,
+        configuration::PdoDirection,
+        pdi::SubDevicePdi,
+        pdo_entries::{GroupPdoEntry, PdoEntries, MAX_GROUP_PDO_ENTRIES},
+        IoRanges, SubDevice, SubDeviceRef, TransitionFailure,
     },
     timer_factory::IntoTimeout,
-    DcSync, MainDevice, RegisterAddress, SubDeviceState,
+    DcSync, MainDevice, RegisterAddress, SubDeviceState, WatchdogConfig,
 };
 use atomic_refcell::{AtomicRefCell, AtomicRefMut};
 use core::{
-    cell::UnsafeCell, marker::PhantomData, slice, sync::atomic::AtomicUsize, time::Duration,
+    cell::UnsafeCell,
+    future::{poll_fn, Future},
+    marker::PhantomData,
+    ops::{Deref, Range},
+    pin::Pin,
+    slice,
+    sync::atomic::{AtomicU8, AtomicUsize},
+    task::Poll,
+    time::Duration,
 };
 use ethercrab_wire::{EtherCrabWireRead, EtherCrabWireSized};
 
+pub use self::cycle_lock::{CycleLock, CycleLockConfig, CycleLockStats};
+pub use self::error_counters::{CableSegment, ErrorCounterReport};
 pub use self::group_id::GroupId;
 pub use self::handle::SubDeviceGroupHandle;
+pub use self::health::{HealthEvent, HealthMonitor};
 pub use self::iterator::GroupSubDeviceIterator;
+pub use self::shutdown::{ShutdownFailure, ShutdownReport};
+pub use self::snapshot::{PdiSnapshot, SnapshotReader, SnapshotWriter};
+pub use self::zero_copy::{PdiRxFrame, PdiTxFrame};
 pub use configurator::SubDeviceGroupRef;
+pub(crate) use storage::GroupList;
 
 static GROUP_ID: AtomicUsize = AtomicUsize::new(0);
 
+/// The maximum number of frames sent at once when a group PDI is too long to fit in one frame.
+///
+/// If more frames are required, they are sent in batches of this size.
+const MAX_IN_FLIGHT_FRAMES: usize = 8;
+
+/// One chunk of a group's PDI, sent in its own frame.
+struct PdiFrame<'sto> {
+    state: PdiFrameState<'sto>,
+    /// Handle to the `FRMW` used to read DC system time. Only present in the first frame.
+    dc_handle: Option<PduResponseHandle>,
+    /// One `LRW`, or up to one each of `LRD` and `LWR` if the chunk spans inputs and outputs.
+    pdus: heapless::Vec<PdiPdu, 2>,
+}
+
+/// A PDU holding part of a group's PDI.
+struct PdiPdu {
+    handle: PduResponseHandle,
+    /// The position of this PDU's data in the group PDI.
+    range: Range<usize>,
+    /// The position in the group PDI the PDU's response is copied into.
+    inputs: Range<usize>,
+    /// Set if this PDU is an `LWR`.
+    lwr: bool,
+}
+
+enum PdiFrameState<'sto> {
+    Sent(ReceiveFrameFut<'sto>),
+    Received(ReceivedFrame<'sto>),
+}
+
+/// The state of a group's SubDevices during a transition.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum GroupState {
+    /// All SubDevices are in the desired state.
+    Reached,
+    /// Some SubDevices are still transitioning.
+    Pending,
+    /// At least one SubDevice refused the transition and set its AL status error flag.
+    Refused,
+}
+
+/// The result of exchanging a group's PDI with the network.
+struct PdiExchange {
+    /// The sum of all working counters, scaled to match `LRW`.
+    wkc: u16,
+    /// The working counter expected if every SubDevice processed its part of the PDI.
+    expected_wkc: u16,
+    /// DC system time, if requested.
+    time: Option<u64>,
+}
+
+impl PdiExchange {
+    /// Check the received working counter against the expected value.
+    fn check_wkc(&self) -> Result<u16, Error> {
+        if self.wkc != self.expected_wkc {
+            fmt::warn!(
+                "Group working counter expected {}, got {}",
+                self.expected_wkc,
+                self.wkc
+            );
+
+            return Err(Error::WorkingCounter {
+                expected: self.expected_wkc,
+                received: self.wkc,
+            });
+        }
+
+        Ok(self.wkc)
+    }
+}
+
 /// A typestate for [`SubDeviceGroup`] representing a group that is shut down.
 ///
 /// This corresponds to the EtherCAT states INIT.
@@ -67,19 +168,50 @@ pub struct NoDc;
 /// This typestate can be entered by calling [`SubDeviceGroup::configure_dc_sync`].
 #[derive(Copy, Clone, Debug)]
 pub struct HasDc {
+    start_delay: u64,
     sync0_period: u64,
     sync0_shift: u64,
     /// Configured address of the DC reference SubDevice.
     reference: u16,
+    /// Host clock the DC reference SubDevice is steered towards, if any.
+    host_clock: Option<fn() -> u64>,
 }
 
 /// Marker trait for `SubDeviceGroup` typestates where all SubDevices have a PDI.
 #[doc(hidden)]
-pub trait HasPdi {}
+pub trait HasPdi {
+    /// The state all SubDevices in the group are in.
+    const STATE: SubDeviceState;
+}
+
+impl HasPdi for PreOpPdi {
+    const STATE: SubDeviceState = SubDeviceState::PreOp;
+}
+impl HasPdi for SafeOp {
+    const STATE: SubDeviceState = SubDeviceState::SafeOp;
+}
+impl HasPdi for Op {
+    const STATE: SubDeviceState = SubDeviceState::Op;
+}
+
+/// Trait for `SubDeviceGroup` Distributed Clock typestates.
+#[doc(hidden)]
+pub trait DcState {
+    /// The group's DC configuration, if any.
+    fn dc_conf(&self) -> Option<&HasDc>;
+}
 
-impl HasPdi for PreOpPdi {}
-impl HasPdi for SafeOp {}
-impl HasPdi for Op {}
+impl DcState for NoDc {
+    fn dc_conf(&self) -> Option<&HasDc> {
+        None
+    }
+}
+
+impl DcState for HasDc {
+    fn dc_conf(&self) -> Option<&HasDc> {
+        Some(self)
+    }
+}
 
 #[doc(hidden)]
 pub trait IsPreOp {}
@@ -89,8 +221,118 @@ impl IsPreOp for PreOpPdi {}
 
 #[derive(Default)]
 struct GroupInner<const MAX_SUBDEVICES: usize> {
-    subdevices: heapless::Vec<AtomicRefCell<SubDevice>, MAX_SUBDEVICES>,
+    subdevices: GroupList<AtomicRefCell<SubDevice>, MAX_SUBDEVICES>,
+    /// The configured station address of each SubDevice in `subdevices`, so SubDevices can be
+    /// addressed without borrowing them.
+    addresses: GroupList<u16, MAX_SUBDEVICES>,
     pdi_start: PdiOffset,
+    config: GroupConfig,
+    /// The PDO entries mapped into every SubDevice's inputs and outputs, read by
+    /// [`SubDeviceGroup::read_pdo_entries`].
+    pdo_entries: GroupList<GroupPdoEntry, MAX_GROUP_PDO_ENTRIES>,
+    /// Exchange the PDI with separate `LRD` and `LWR` commands instead of `LRW`.
+    ///
+    /// Resolved from [`GroupConfig::cyclic_mode`] when the group's PDI is configured.
+    lrd_lwr: bool,
+    /// The position of each SubDevice's inputs and outputs in the group PDI, used to compute
+    /// expected working counters.
+    io_ranges: GroupList<(Range<usize>, Range<usize>), MAX_SUBDEVICES>,
+    /// The expected Explicit Device ID of each SubDevice, set with
+    /// [`SubDeviceGroup::set_device_ids`].
+    device_ids: GroupList<u16, MAX_SUBDEVICES>,
+    /// Working counter mismatches seen by `tx_rx*`, reported by [`HealthMonitor`].
+    wkc_stats: WkcStats,
+    /// Set once `pdi_start` has been assigned, so a detached hot-connect group keeps its segment
+    /// of the PDI when attached again.
+    pdi_reserved: bool,
+    /// The alias address identifying this group's hot-connect segment, if it is a hot-connect
+    /// group.
+    hot_connect_alias: Option<u16>,
+}
+
+impl<const MAX_SUBDEVICES: usize> GroupInner<MAX_SUBDEVICES> {
+    /// Add a SubDevice to the end of the group.
+    fn push_subdevice(&mut self, subdevice: SubDevice) -> Result<(), Error> {
+        let configured_address = subdevice.configured_address();
+
+        self.subdevices
+            .push(AtomicRefCell::new(subdevice))
+            .map_err(|_| Error::Capacity(Item::SubDevice))?;
+
+        // Same capacity as `subdevices`, so this push can't fail
+        let _ = self.addresses.push(configured_address);
+
+        Ok(())
+    }
+}
+
+/// Group process data configuration.
+///
+/// Set with [`SubDeviceGroup::set_config`] before the group's PDI is configured.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GroupConfig {
+    /// Map SubDevices with less than 8 bits of inputs or outputs onto shared PDI bytes.
+    ///
+    /// By default, every SubDevice's inputs and outputs start at a new byte in the PDI, so e.g. an
+    /// EL2004 with 4 output bits uses a whole byte. When this option is enabled, consecutive
+    /// SubDevices like this are packed into the same byte using bit-oriented FMMU mappings.
+    ///
+    /// Only SubDevices whose ESC supports bit-oriented FMMU operation and that use a single sync
+    /// manager for their inputs or outputs are packed. All others remain byte-aligned.
+    ///
+    /// The raw input slice of a packed SubDevice contains the whole byte, including other
+    /// SubDevices' bits, and the raw output slice is empty. Use
+    /// [`SubDeviceRef::input_bits`](crate::SubDeviceRef::input_bits) and
+    /// [`SubDeviceRef::output_bits_mut`](crate::SubDeviceRef::output_bits_mut) to access
+    /// packed SubDevice process data.
+    ///
+    /// Defaults to `false`.
+    pub bit_packed_pdi: bool,
+
+    /// The commands used to exchange the group's PDI with SubDevices every cycle.
+    ///
+    /// Defaults to [`CyclicMode::Auto`].
+    pub cyclic_mode: CyclicMode,
+
+    /// Map each SubDevice's inputs and outputs to the same logical addresses.
+    ///
+    /// By default, all inputs in the group are mapped before all outputs, so every `LRW` carries
+    /// both. When this option is enabled, each SubDevice's input and output FMMUs start at the same
+    /// logical address, and the SubDevice replaces its outputs in the frame with its inputs as it
+    /// processes them. The frame length is then the sum of each SubDevice's larger mapping, roughly
+    /// halving it for SubDevices with similar amounts of inputs and outputs.
+    ///
+    /// The group's in-memory PDI still keeps inputs and outputs separate, so
+    /// [`MAX_PDI`](SubDeviceGroup) must be at least twice the frame length. If it is not,
+    /// [`Error::PdiTooLong`] is returned while the group's PDI is configured, with the total memory
+    /// length required.
+    ///
+    /// Defaults to `false`.
+    pub overlapping_pdi: bool,
+
+    /// Sync manager and PDI watchdog timeouts written to every SubDevice in the group before it
+    /// goes to SAFE-OP.
+    ///
+    /// Defaults to `None`, leaving each SubDevice's watchdogs at their power-on or EEPROM
+    /// configured values.
+    pub watchdog: Option<WatchdogConfig>,
+}
+
+/// The commands used to exchange a group's process data.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CyclicMode {
+    /// Use [`Lrw`](CyclicMode::Lrw), unless any SubDevice in the group reports that it does not
+    /// support `LRW`, in which case use [`LrdLwr`](CyclicMode::LrdLwr).
+    #[default]
+    Auto,
+    /// Read inputs and write outputs in a single `LRW` (Logical Read Write).
+    Lrw,
+    /// Read inputs with an `LRD` (Logical Read) and write outputs with an `LWR` (Logical Write).
+    ///
+    /// The working counter returned by the group's `tx_rx*` methods is the same as it would be
+    /// using `LRW`.
+    LrdLwr,
 }
 
 const CYCLIC_OP_ENABLE: u8 = 0b0000_0001;
@@ -132,38 +374,96 @@ pub struct CycleInfo {
     pub cycle_start_offset: Duration,
 }
 
+/// A storage marker for [`SubDeviceGroup`]s whose SubDevices and PDI are stored inline, with
+/// capacities set by the group's const parameters.
+#[derive(Copy, Clone, Debug)]
+pub struct Fixed;
+
+/// A storage marker for [`SubDeviceGroup`]s whose SubDevices and PDI are stored on the heap, with
+/// capacities chosen at runtime.
+#[cfg(feature = "std")]
+#[derive(Copy, Clone, Debug)]
+pub struct Heap;
+
+/// A [`SubDeviceGroup`] with a SubDevice and PDI capacity chosen at runtime.
+///
+/// Created with [`HeapSubDeviceGroup::with_capacity`] or
+/// [`HeapSubDeviceGroup::hot_connect_with_capacity`] and initialised with
+/// [`MainDevice::init_heap`]. The group's SubDevices and PDI are stored on the heap so the number
+/// of groups and their sizes can be decided at runtime, e.g. from a configuration file.
+///
+/// The const parameters of a heap-backed group are unused.
+#[cfg(feature = "std")]
+pub type HeapSubDeviceGroup<S = PreOp, DC = NoDc> = SubDeviceGroup<0, 0, S, DC, Heap>;
+
 /// A group of one or more EtherCAT SubDevices.
 ///
 /// Groups are created during EtherCrab initialisation, and are the only way to access individual
 /// SubDevice PDI sections.
 #[doc(alias = "SlaveGroup")]
-pub struct SubDeviceGroup<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S = PreOp, DC = NoDc> {
+pub struct SubDeviceGroup<
+    const MAX_SUBDEVICES: usize,
+    const MAX_PDI: usize,
+    S = PreOp,
+    DC = NoDc,
+    M = Fixed,
+> {
     id: GroupId,
-    pdi: UnsafeCell<[u8; MAX_PDI]>,
+    pdi: UnsafeCell<PdiStorage<MAX_PDI>>,
     /// The number of bytes at the beginning of the PDI reserved for SubDevice inputs.
     read_pdi_len: usize,
     /// The total length (I and O) of the PDI for this group.
     pdi_len: usize,
     inner: UnsafeCell<GroupInner<MAX_SUBDEVICES>>,
     dc_conf: DC,
-    _state: PhantomData<S>,
+    /// Group state and storage markers.
+    _state: PhantomData<(S, M)>,
 }
 
-impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
-    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, PreOp, DC>
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC, M>
+    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, PreOp, DC, M>
 {
     /// Configure read/write FMMUs and PDI for this group.
     async fn configure_fmmus(&mut self, maindevice: &MainDevice<'_>) -> Result<(), Error> {
         let inner = self.inner.get_mut();
 
+        for (i, subdevice) in inner
+            .subdevices
+            .iter_mut()
+            .map(AtomicRefCell::get_mut)
+            .enumerate()
+        {
+            if let Some(expected) = inner.device_ids.get(i) {
+                SubDeviceRef::new(maindevice, subdevice.configured_address(), ())
+                    .check_device_id(*expected)
+                    .await?;
+            }
+
+            if let Some(watchdog) = inner.config.watchdog {
+                SubDeviceRef::new(maindevice, subdevice.configured_address(), ())
+                    .configure_watchdogs(watchdog)
+                    .await?;
+            }
+
+            maindevice
+                .run_hooks(HookTransition::PreOpToSafeOp, subdevice)
+                .await?;
+        }
+
         let mut pdi_position = inner.pdi_start;
 
         fmt::debug!(
-            "Going to configure group with {} SubDevice(s), starting PDI offset {:#010x}",
+            "Going to configure group with {} SubDevice(s), starting PDI offset {:#010x}, bit packing {:?}, overlapping {:?}",
             inner.subdevices.len(),
-            inner.pdi_start.start_address
+            inner.pdi_start.start_address,
+            inner.config.bit_packed_pdi,
+            inner.config.overlapping_pdi
         );
 
+        if inner.config.overlapping_pdi {
+            return self.configure_fmmus_overlapping(maindevice).await;
+        }
+
         // Configure master read PDI mappings in the first section of the PDI
         for subdevice in inner.subdevices.iter_mut().map(AtomicRefCell::get_mut) {
             // We're in PRE-OP at this point
@@ -172,10 +472,14 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
                     pdi_position,
                     inner.pdi_start.start_address,
                     PdoDirection::MasterRead,
+                    inner.config.bit_packed_pdi,
                 )
                 .await?;
         }
 
+        // Outputs start on a new byte, even if the last input mapping was bit-packed
+        pdi_position = pdi_position.align();
+
         self.read_pdi_len = (pdi_position.start_address - inner.pdi_start.start_address) as usize;
 
         fmt::debug!("SubDevice mailboxes configured and init hooks called");
@@ -194,10 +498,13 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
                     pdi_position,
                     inner.pdi_start.start_address,
                     PdoDirection::MasterWrite,
+                    inner.config.bit_packed_pdi,
                 )
                 .await?;
         }
 
+        pdi_position = pdi_position.align();
+
         fmt::debug!("SubDevice FMMUs configured for group. Able to move to SAFE-OP");
 
         self.pdi_len = (pdi_position.start_address - inner.pdi_start.start_address) as usize;
@@ -209,13 +516,236 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
             self.read_pdi_len
         );
 
-        if self.pdi_len > MAX_PDI {
+        self.finish_pdi_config()
+    }
+
+    /// Configure read/write FMMUs for this group so each SubDevice's inputs and outputs share the
+    /// same logical addresses.
+    ///
+    /// The logical PDI is mirrored in the group's memory as IIIIOOOO, with each SubDevice's inputs
+    /// and outputs at the same offset into their half.
+    ///
+    /// The group's memory must hold twice the logical length. This is checked after each SubDevice
+    /// is mapped, so [`Error::PdiTooLong`] is returned before any further FMMUs are written.
+    async fn configure_fmmus_overlapping(
+        &mut self,
+        maindevice: &MainDevice<'_>,
+    ) -> Result<(), Error> {
+        let inner = self.inner.get_mut();
+        let max_length = self.pdi.get_mut().len();
+
+        let mut pdi_position = inner.pdi_start;
+
+        for subdevice in inner.subdevices.iter_mut().map(AtomicRefCell::get_mut) {
+            let addr = subdevice.configured_address();
+
+            let mut subdevice_config = SubDeviceRef::new(maindevice, addr, subdevice);
+
+            let inputs_end = subdevice_config
+                .configure_fmmus(
+                    pdi_position,
+                    inner.pdi_start.start_address,
+                    PdoDirection::MasterRead,
+                    inner.config.bit_packed_pdi,
+                )
+                .await?;
+
+            let outputs_end = subdevice_config
+                .configure_fmmus(
+                    pdi_position,
+                    inner.pdi_start.start_address,
+                    PdoDirection::MasterWrite,
+                    inner.config.bit_packed_pdi,
+                )
+                .await?;
+
+            // The next SubDevice starts after whichever of this SubDevice's mappings is longer
+            pdi_position = if (outputs_end.start_address, outputs_end.start_bit)
+                > (inputs_end.start_address, inputs_end.start_bit)
+            {
+                outputs_end
+            } else {
+                inputs_end
+            };
+
+            let desired_length =
+                (pdi_position.align().start_address - inner.pdi_start.start_address) as usize * 2;
+
+            if desired_length > max_length {
+                fmt::error!(
+                    "Overlapping PDI needs {} bytes of group memory for {} logical bytes, but MAX_PDI is {}",
+                    desired_length,
+                    desired_length / 2,
+                    max_length
+                );
+
+                return Err(Error::PdiTooLong {
+                    max_length,
+                    desired_length,
+                });
+            }
+        }
+
+        pdi_position = pdi_position.align();
+
+        let logical_len = (pdi_position.start_address - inner.pdi_start.start_address) as usize;
+
+        // Outputs are stored after all inputs in the group's memory, but are sent at the same
+        // logical addresses as the inputs.
+        for subdevice in inner.subdevices.iter_mut().map(AtomicRefCell::get_mut) {
+            let output = &mut subdevice.config.io.output.bytes;
+
+            *output = (output.start + logical_len)..(output.end + logical_len);
+        }
+
+        fmt::debug!("SubDevice FMMUs configured for group. Able to move to SAFE-OP");
+
+        self.read_pdi_len = logical_len;
+        self.pdi_len = logical_len * 2;
+
+        fmt::debug!(
+            "Group PDI length: start {:#010x}, {} logical bytes, {} total bytes",
+            inner.pdi_start.start_address,
+            logical_len,
+            self.pdi_len
+        );
+
+        self.finish_pdi_config()
+    }
+
+    /// Check the configured PDI length and resolve the group's cyclic exchange settings.
+    fn finish_pdi_config(&mut self) -> Result<(), Error> {
+        let inner = self.inner.get_mut();
+
+        let max_length = self.pdi.get_mut().len();
+
+        if self.pdi_len > max_length {
             return Err(Error::PdiTooLong {
-                max_length: MAX_PDI,
+                max_length,
                 desired_length: self.pdi_len,
             });
         }
 
+        inner.lrd_lwr = match inner.config.cyclic_mode {
+            CyclicMode::Auto => inner
+                .subdevices
+                .iter_mut()
+                .map(AtomicRefCell::get_mut)
+                .any(|subdevice| subdevice.flags.lrw_not_supported),
+            CyclicMode::Lrw => false,
+            CyclicMode::LrdLwr => true,
+        };
+
+        fmt::debug!(
+            "Group cyclic mode {:?}, using LRD/LWR: {:?}",
+            inner.config.cyclic_mode,
+            inner.lrd_lwr
+        );
+
+        inner.io_ranges = inner.subdevices.empty_like();
+
+        for subdevice in inner.subdevices.iter_mut().map(AtomicRefCell::get_mut) {
+            let io = subdevice.io_segments();
+
+            // `io_ranges` was created from `subdevices`, so there is room for every SubDevice
+            let _ = inner
+                .io_ranges
+                .push((io.input.bytes.clone(), io.output.bytes.clone()));
+        }
+
+        Ok(())
+    }
+
+    /// Set the group's process data configuration.
+    ///
+    /// This must be called before the group's PDI is configured by e.g.
+    /// [`into_pre_op_pdi`](SubDeviceGroup::into_pre_op_pdi) or
+    /// [`into_op`](SubDeviceGroup::into_op).
+    ///
+    /// # Examples
+    ///
+    /// Pack SubDevices with only a few bits of I/O into shared PDI bytes.
+    ///
+    /// ```rust,no_run
+    /// use ethercrab::{
+    ///     std::ethercat_now, subdevice_group::GroupConfig, MainDevice, MainDeviceConfig,
+    ///     PduStorage, Timeouts,
+    /// };
+    ///
+    /// static PDU_STORAGE: PduStorage<16, { PduStorage::element_size(1100) }> = PduStorage::new();
+    ///
+    /// let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    ///
+    /// let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+    ///
+    /// # async {
+    /// let mut group = maindevice
+    ///     .init_single_group::<16, 32>(ethercat_now)
+    ///     .await
+    ///     .expect("Init");
+    ///
+    /// group.set_config(GroupConfig {
+    ///     bit_packed_pdi: true,
+    ///     ..GroupConfig::default()
+    /// });
+    ///
+    /// let group = group.into_op(&maindevice).await.expect("PRE-OP -> OP");
+    /// # };
+    /// ```
+    pub fn set_config(&mut self, config: GroupConfig) {
+        self.inner.get_mut().config = config;
+    }
+
+    /// Set the expected [Explicit Device ID](crate::SubDeviceRef::explicit_device_id) of each
+    /// SubDevice in the group, in group order.
+    ///
+    /// IDs are read and checked before the group goes to SAFE-OP, and when a SubDevice is
+    /// [recovered](SubDeviceGroup::recover_subdevice), returning [`Error::DeviceIdMismatch`] for
+    /// the first SubDevice with a different ID. SubDevices past the end of the list are not
+    /// checked. The IDs are copied into the group, so they can e.g. be loaded from a
+    /// configuration file at runtime.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Capacity`] if more IDs are given than the group can hold SubDevices.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ethercrab::{std::ethercat_now, MainDevice, MainDeviceConfig, PduStorage, Timeouts};
+    ///
+    /// static PDU_STORAGE: PduStorage<16, { PduStorage::element_size(1100) }> = PduStorage::new();
+    ///
+    /// let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    ///
+    /// let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+    ///
+    /// # async {
+    /// let mut group = maindevice
+    ///     .init_single_group::<16, 32>(ethercat_now)
+    ///     .await
+    ///     .expect("Init");
+    ///
+    /// let ids = vec![0x0001, 0x0002];
+    ///
+    /// group.set_device_ids(&ids).expect("Too many IDs");
+    ///
+    /// let group = group.into_op(&maindevice).await.expect("PRE-OP -> OP");
+    /// # };
+    /// ```
+    pub fn set_device_ids(&mut self, ids: &[u16]) -> Result<(), Error> {
+        let inner = self.inner.get_mut();
+
+        let mut device_ids = inner.subdevices.empty_like();
+
+        for id in ids {
+            device_ids
+                .push(*id)
+                .map_err(|_| Error::Capacity(Item::SubDevice))?;
+        }
+
+        inner.device_ids = device_ids;
+
         Ok(())
     }
 
@@ -265,10 +795,22 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
     pub async fn into_op(
         self,
         maindevice: &MainDevice<'_>,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC>, Error> {
-        let self_ = self.into_safe_op(maindevice).await?;
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC, M>, Error> {
+        self.into_op_with(maindevice, |_| ()).await
+    }
+
+    /// Like [`into_op`](SubDeviceGroup::into_op), but calls `on_failure` for every refused
+    /// transition attempt of every SubDevice.
+    ///
+    /// See [`SubDeviceRef::request_state`] for how refused transitions are retried.
+    pub async fn into_op_with(
+        self,
+        maindevice: &MainDevice<'_>,
+        mut on_failure: impl FnMut(TransitionFailure),
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC, M>, Error> {
+        let self_ = self.into_safe_op_with(maindevice, &mut on_failure).await?;
 
-        self_.into_op(maindevice).await
+        self_.into_op_with(maindevice, on_failure).await
     }
 
     /// Configure FMMUs, but leave the group in [`PreOp`] state.
@@ -279,7 +821,7 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
     pub async fn into_pre_op_pdi(
         mut self,
         maindevice: &MainDevice<'_>,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, PreOpPdi, DC>, Error> {
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, PreOpPdi, DC, M>, Error> {
         self.configure_fmmus(maindevice).await?;
 
         Ok(SubDeviceGroup {
@@ -297,13 +839,23 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
     pub async fn into_safe_op(
         self,
         maindevice: &MainDevice<'_>,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, SafeOp, DC>, Error> {
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, SafeOp, DC, M>, Error> {
+        self.into_safe_op_with(maindevice, |_| ()).await
+    }
+
+    /// Like [`into_safe_op`](SubDeviceGroup::into_safe_op), but calls `on_failure` for every
+    /// refused transition attempt of every SubDevice.
+    pub async fn into_safe_op_with(
+        self,
+        maindevice: &MainDevice<'_>,
+        on_failure: impl FnMut(TransitionFailure),
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, SafeOp, DC, M>, Error> {
         let self_ = self.into_pre_op_pdi(maindevice).await?;
 
         // We're done configuring FMMUs, etc, now we can request all SubDevices in this group go into
         // SAFE-OP
         self_
-            .transition_to(maindevice, SubDeviceState::SafeOp)
+            .transition_to(maindevice, SubDeviceState::SafeOp, on_failure)
             .await
     }
 
@@ -311,21 +863,22 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
     pub async fn into_init(
         self,
         maindevice: &MainDevice<'_>,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Init, DC>, Error> {
-        self.transition_to(maindevice, SubDeviceState::Init).await
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Init, DC, M>, Error> {
+        self.transition_to(maindevice, SubDeviceState::Init, |_| ())
+            .await
     }
 
     /// Get an iterator over all SubDevices in this group.
     pub fn iter<'group, 'maindevice>(
         &'group mut self,
         maindevice: &'maindevice MainDevice<'maindevice>,
-    ) -> GroupSubDeviceIterator<'group, 'maindevice, MAX_SUBDEVICES, MAX_PDI, PreOp, DC> {
+    ) -> GroupSubDeviceIterator<'group, 'maindevice, MAX_SUBDEVICES, MAX_PDI, PreOp, DC, M> {
         GroupSubDeviceIterator::new(maindevice, self)
     }
 }
 
-impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC>
-    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, DC>
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC, M>
+    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, DC, M>
 where
     S: IsPreOp,
 {
@@ -340,7 +893,7 @@ where
         self,
         maindevice: &MainDevice<'_>,
         dc_conf: DcConfiguration,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, PreOpPdi, HasDc>, Error> {
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, PreOpPdi, HasDc, M>, Error> {
         fmt::debug!("Configuring distributed clocks for group");
 
         let Some(reference) = maindevice.dc_ref_address() else {
@@ -363,7 +916,7 @@ where
             pdi_len: self.pdi_len,
             inner: UnsafeCell::new(self.inner.into_inner()),
             dc_conf: NoDc,
-            _state: PhantomData::<PreOp>,
+            _state: PhantomData::<(PreOp, M)>,
         };
 
         // Only configure DC for those devices that want and support it
@@ -372,69 +925,13 @@ where
         });
 
         for subdevice in dc_devices {
-            fmt::debug!(
-                "--> Configuring SubDevice {:#06x} {} DC mode {}",
-                subdevice.configured_address(),
-                subdevice.name(),
-                subdevice.dc_sync()
-            );
-
-            // Disable cyclic op, ignore WKC
-            subdevice
-                .write(RegisterAddress::DcSyncActive)
-                .ignore_wkc()
-                .send(maindevice, 0u8)
-                .await?;
-
-            // Write access to EtherCAT
-            subdevice
-                .write(RegisterAddress::DcCyclicUnitControl)
-                .send(maindevice, 0u8)
-                .await?;
-
-            let device_time: u64 = subdevice
-                .read(RegisterAddress::DcSystemTime)
-                .ignore_wkc()
-                .receive(maindevice)
-                .await?;
-
-            fmt::debug!("--> Device time {} ns", device_time);
-
-            let sync0_period = sync0_period.as_nanos() as u64;
-
-            let first_pulse_delay = start_delay.as_nanos() as u64;
-
-            // Round first pulse time to a whole number of cycles
-            let start_time = (device_time + first_pulse_delay) / sync0_period * sync0_period;
-
-            fmt::debug!("--> Computed DC sync start time: {}", start_time);
-
-            subdevice
-                .write(RegisterAddress::DcSyncStartTime)
-                .send(maindevice, start_time)
-                .await?;
-
-            // Cycle time in nanoseconds
-            subdevice
-                .write(RegisterAddress::DcSync0CycleTime)
-                .send(maindevice, sync0_period)
-                .await?;
-
-            let flags = if let DcSync::Sync01 { sync1_period } = subdevice.dc_sync() {
-                subdevice
-                    .write(RegisterAddress::DcSync1CycleTime)
-                    .send(maindevice, sync1_period.as_nanos() as u64)
-                    .await?;
-
-                SYNC1_ACTIVATE | SYNC0_ACTIVATE | CYCLIC_OP_ENABLE
-            } else {
-                SYNC0_ACTIVATE | CYCLIC_OP_ENABLE
-            };
-
-            subdevice
-                .write(RegisterAddress::DcSyncActive)
-                .send(maindevice, flags)
-                .await?;
+            configure_subdevice_dc_sync(
+                &subdevice,
+                maindevice,
+                start_delay.as_nanos() as u64,
+                sync0_period.as_nanos() as u64,
+            )
+            .await?;
         }
 
         Ok(SubDeviceGroup {
@@ -444,24 +941,37 @@ where
             pdi_len: self_.pdi_len,
             inner: UnsafeCell::new(self_.inner.into_inner()),
             dc_conf: HasDc {
+                start_delay: start_delay.as_nanos() as u64,
                 sync0_period: sync0_period.as_nanos() as u64,
                 sync0_shift: sync0_shift.as_nanos() as u64,
                 reference,
+                host_clock: None,
             },
             _state: PhantomData,
         })
     }
 }
 
-impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
-    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, PreOpPdi, DC>
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC, M>
+    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, PreOpPdi, DC, M>
 {
     /// Transition the SubDevice group from PRE-OP to SAFE-OP.
     pub async fn into_safe_op(
         self,
         maindevice: &MainDevice<'_>,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, SafeOp, DC>, Error> {
-        self.transition_to(maindevice, SubDeviceState::SafeOp).await
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, SafeOp, DC, M>, Error> {
+        self.into_safe_op_with(maindevice, |_| ()).await
+    }
+
+    /// Like [`into_safe_op`](SubDeviceGroup::into_safe_op), but calls `on_failure` for every
+    /// refused transition attempt of every SubDevice.
+    pub async fn into_safe_op_with(
+        self,
+        maindevice: &MainDevice<'_>,
+        on_failure: impl FnMut(TransitionFailure),
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, SafeOp, DC, M>, Error> {
+        self.transition_to(maindevice, SubDeviceState::SafeOp, on_failure)
+            .await
     }
 
     /// Transition all SubDevices in the group from PRE-OP to SAFE-OP, then to OP.
@@ -471,10 +981,22 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
     pub async fn into_op(
         self,
         maindevice: &MainDevice<'_>,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC>, Error> {
-        let self_ = self.into_safe_op(maindevice).await?;
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC, M>, Error> {
+        self.into_op_with(maindevice, |_| ()).await
+    }
 
-        self_.transition_to(maindevice, SubDeviceState::Op).await
+    /// Like [`into_op`](SubDeviceGroup::into_op), but calls `on_failure` for every refused
+    /// transition attempt of every SubDevice.
+    pub async fn into_op_with(
+        self,
+        maindevice: &MainDevice<'_>,
+        mut on_failure: impl FnMut(TransitionFailure),
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC, M>, Error> {
+        let self_ = self.into_safe_op_with(maindevice, &mut on_failure).await?;
+
+        self_
+            .transition_to(maindevice, SubDeviceState::Op, on_failure)
+            .await
     }
 
     /// Like [`into_op`](SubDeviceGroup::into_op), however does not wait for all SubDevices to enter OP
@@ -488,7 +1010,7 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
     pub async fn request_into_op(
         self,
         maindevice: &MainDevice<'_>,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC>, Error> {
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC, M>, Error> {
         let self_ = self.into_safe_op(maindevice).await?;
 
         self_.request_into_op(maindevice).await
@@ -498,28 +1020,73 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
     pub async fn into_init(
         self,
         maindevice: &MainDevice<'_>,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Init, DC>, Error> {
-        self.transition_to(maindevice, SubDeviceState::Init).await
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Init, DC, M>, Error> {
+        self.transition_to(maindevice, SubDeviceState::Init, |_| ())
+            .await
     }
 }
 
-impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
-    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, SafeOp, DC>
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC, M>
+    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, SafeOp, DC, M>
 {
     /// Transition all SubDevices in the group from SAFE-OP to OP.
     pub async fn into_op(
         self,
         maindevice: &MainDevice<'_>,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC>, Error> {
-        self.transition_to(maindevice, SubDeviceState::Op).await
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC, M>, Error> {
+        self.into_op_with(maindevice, |_| ()).await
     }
 
-    /// Transition all SubDevices in the group from SAFE-OP to PRE-OP.
-    pub async fn into_pre_op(
+    /// Like [`into_op`](SubDeviceGroup::into_op), but calls `on_failure` for every refused
+    /// transition attempt of every SubDevice.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ethercrab::{std::ethercat_now, MainDevice, MainDeviceConfig, PduStorage, Timeouts};
+    ///
+    /// static PDU_STORAGE: PduStorage<16, { PduStorage::element_size(1100) }> = PduStorage::new();
+    ///
+    /// let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    ///
+    /// let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+    ///
+    /// # async {
+    /// let group = maindevice
+    ///     .init_single_group::<16, 64>(ethercat_now)
+    ///     .await
+    ///     .expect("Init")
+    ///     .into_safe_op(&maindevice)
+    ///     .await
+    ///     .expect("PRE-OP -> SAFE-OP");
+    ///
+    /// let group = group
+    ///     .into_op_with(&maindevice, |failure| {
+    ///         eprintln!(
+    ///             "SubDevice {:#06x} refused OP (attempt {}): {}",
+    ///             failure.configured_address, failure.attempt, failure.code
+    ///         );
+    ///     })
+    ///     .await
+    ///     .expect("SAFE-OP -> OP");
+    /// # };
+    /// ```
+    pub async fn into_op_with(
+        self,
+        maindevice: &MainDevice<'_>,
+        on_failure: impl FnMut(TransitionFailure),
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC, M>, Error> {
+        self.transition_to(maindevice, SubDeviceState::Op, on_failure)
+            .await
+    }
+
+    /// Transition all SubDevices in the group from SAFE-OP to PRE-OP.
+    pub async fn into_pre_op(
         self,
         maindevice: &MainDevice<'_>,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, PreOp, DC>, Error> {
-        self.transition_to(maindevice, SubDeviceState::PreOp).await
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, PreOp, DC, M>, Error> {
+        self.transition_to(maindevice, SubDeviceState::PreOp, |_| ())
+            .await
     }
 
     /// Like [`into_op`](SubDeviceGroup::into_op), however does not wait for all SubDevices to enter OP
@@ -533,7 +1100,7 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
     pub async fn request_into_op(
         mut self,
         maindevice: &MainDevice<'_>,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC>, Error> {
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC, M>, Error> {
         for subdevice in self
             .inner
             .get_mut()
@@ -541,6 +1108,10 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
             .iter_mut()
             .map(|subdevice| subdevice.get_mut())
         {
+            maindevice
+                .run_hooks(HookTransition::SafeOpToOp, subdevice)
+                .await?;
+
             SubDeviceRef::new(maindevice, subdevice.configured_address(), subdevice)
                 .request_subdevice_state_nowait(SubDeviceState::Op)
                 .await?;
@@ -558,15 +1129,26 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
     }
 }
 
-impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
-    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC>
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC, M>
+    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Op, DC, M>
 {
     /// Transition all SubDevices in the group from OP to SAFE-OP.
     pub async fn into_safe_op(
         self,
         maindevice: &MainDevice<'_>,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, SafeOp, DC>, Error> {
-        self.transition_to(maindevice, SubDeviceState::SafeOp).await
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, SafeOp, DC, M>, Error> {
+        self.into_safe_op_with(maindevice, |_| ()).await
+    }
+
+    /// Like [`into_safe_op`](SubDeviceGroup::into_safe_op), but calls `on_failure` for every
+    /// refused transition attempt of every SubDevice.
+    pub async fn into_safe_op_with(
+        self,
+        maindevice: &MainDevice<'_>,
+        on_failure: impl FnMut(TransitionFailure),
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, SafeOp, DC, M>, Error> {
+        self.transition_to(maindevice, SubDeviceState::SafeOp, on_failure)
+            .await
     }
 
     /// Returns true if all SubDevices in the group are in OP state
@@ -575,12 +1157,12 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC>
     }
 }
 
-unsafe impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S> Sync
-    for SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S>
+unsafe impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, M> Sync
+    for SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, NoDc, M>
 {
 }
-unsafe impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC> Send
-    for SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, DC>
+unsafe impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC, M> Send
+    for SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, DC, M>
 {
 }
 
@@ -588,9 +1170,18 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S> Default
     for SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S>
 {
     fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, M>
+    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, NoDc, M>
+{
+    /// Create an empty group with a new ID.
+    fn empty() -> Self {
         Self {
             id: GroupId(GROUP_ID.fetch_add(1, core::sync::atomic::Ordering::Relaxed)),
-            pdi: UnsafeCell::new([0u8; MAX_PDI]),
+            pdi: UnsafeCell::new(PdiStorage::default()),
             read_pdi_len: Default::default(),
             pdi_len: Default::default(),
             inner: UnsafeCell::new(GroupInner::default()),
@@ -600,16 +1191,107 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S> Default
     }
 }
 
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize>
+    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, PreOp>
+{
+    /// Create an empty hot-connect group.
+    ///
+    /// A hot-connect group holds an optional segment of the network which may be absent during
+    /// initialisation and connected or disconnected at runtime, e.g. an I/O module on a tool
+    /// changer. The segment is identified by the configured station alias of its first SubDevice,
+    /// and contains every newly connected SubDevice after it up to the next segment or already
+    /// configured SubDevice.
+    ///
+    /// SubDevices present during initialisation are assigned to groups by the closure passed to
+    /// [`MainDevice::init`], so a hot-connect group can be returned from there if its segment is
+    /// already connected. Otherwise, the group is left empty and is filled when the segment is
+    /// found by [`MainDevice::rescan`].
+    ///
+    /// When the segment is disconnected, call [`detach`](SubDeviceGroup::detach) to get an empty
+    /// group back that can be attached again.
+    pub fn hot_connect(alias: u16) -> Self {
+        let mut group = Self::default();
+
+        group.inner.get_mut().hot_connect_alias = Some(alias);
+
+        group
+    }
+}
+
+#[cfg(feature = "std")]
+impl HeapSubDeviceGroup {
+    /// Create an empty group that can hold up to `max_subdevices` SubDevices with a PDI of up to
+    /// `max_pdi` bytes.
+    pub fn with_capacity(max_subdevices: usize, max_pdi: usize) -> Self {
+        Self {
+            pdi: UnsafeCell::new(PdiStorage::heap(max_pdi)),
+            inner: UnsafeCell::new(GroupInner {
+                subdevices: GroupList::heap(max_subdevices),
+                addresses: GroupList::heap(max_subdevices),
+                // Room for every input and output entry of every SubDevice
+                pdo_entries: GroupList::heap(
+                    max_subdevices * 2 * crate::subdevice::pdo_entries::MAX_PDO_ENTRIES,
+                ),
+                ..GroupInner::default()
+            }),
+            ..Self::empty()
+        }
+    }
+
+    /// Create an empty [hot-connect](SubDeviceGroup::hot_connect) group that can hold up to
+    /// `max_subdevices` SubDevices with a PDI of up to `max_pdi` bytes.
+    pub fn hot_connect_with_capacity(alias: u16, max_subdevices: usize, max_pdi: usize) -> Self {
+        let mut group = Self::with_capacity(max_subdevices, max_pdi);
+
+        group.inner.get_mut().hot_connect_alias = Some(alias);
+
+        group
+    }
+}
+
 /// Returned when a SubDevice's input or output PDI segment is empty.
 static EMPTY_PDI_SLICE: &[u8] = &[];
 
-impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC>
-    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, DC>
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC, M>
+    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, DC, M>
 {
     fn inner(&self) -> &GroupInner<MAX_SUBDEVICES> {
         unsafe { &*self.inner.get() }
     }
 
+    /// Remove all SubDevices from the group, returning an empty group in PRE-OP.
+    ///
+    /// This is used when a [hot-connect](SubDeviceGroup::hot_connect) segment has been
+    /// disconnected. The returned group keeps its ID, configuration and segment of the PDI, and
+    /// can be passed to [`MainDevice::rescan`] to attach the segment again once it is reconnected.
+    ///
+    /// No commands are sent to the SubDevices in the group.
+    pub fn detach(self) -> SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, PreOp, NoDc, M> {
+        let Self { id, pdi, inner, .. } = self;
+
+        let mut pdi = pdi.into_inner();
+        let mut inner = inner.into_inner();
+
+        pdi.fill(0);
+
+        inner.subdevices.clear();
+        inner.addresses.clear();
+        inner.io_ranges.clear();
+        inner.pdo_entries.clear();
+        inner.lrd_lwr = false;
+        inner.wkc_stats = WkcStats::default();
+
+        SubDeviceGroup {
+            id,
+            pdi: UnsafeCell::new(pdi),
+            read_pdi_len: 0,
+            pdi_len: 0,
+            inner: UnsafeCell::new(inner),
+            dc_conf: NoDc,
+            _state: PhantomData,
+        }
+    }
+
     /// Get the number of SubDevices in this group.
     pub fn len(&self) -> usize {
         self.inner().subdevices.len()
@@ -633,14 +1315,31 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC>
         &all_buf[0..self.pdi_len]
     }
 
+    /// The capacity of the group's PDI in bytes.
+    fn max_pdi_len(&self) -> usize {
+        unsafe { &*self.pdi.get() }.len()
+    }
+
     /// Check if all SubDevices in the group are the given desired state.
     async fn is_state(
         &self,
         maindevice: &MainDevice<'_>,
         desired_state: SubDeviceState,
     ) -> Result<bool, Error> {
+        Ok(self.check_state(maindevice, desired_state).await? == GroupState::Reached)
+    }
+
+    /// Check whether all SubDevices in the group are in the given desired state, or whether any
+    /// SubDevice has refused to transition to it.
+    async fn check_state(
+        &self,
+        maindevice: &MainDevice<'_>,
+        desired_state: SubDeviceState,
+    ) -> Result<GroupState, Error> {
         fmt::trace!("Check group state");
 
+        let mut group_state = GroupState::Reached;
+
         let mut subdevices = self.inner().subdevices.iter();
 
         let mut frame_idx = 0;
@@ -709,14 +1408,19 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC>
                 let result = AlControl::unpack_from_slice(&pdu)?;
 
                 if result.state != desired_state {
-                    return Ok(false);
+                    // A SubDevice that refused the transition will never reach the desired state
+                    if result.error {
+                        return Ok(GroupState::Refused);
+                    }
+
+                    group_state = GroupState::Pending;
                 }
             }
 
             frame_idx += 1;
         }
 
-        Ok(true)
+        Ok(group_state)
     }
 
     /// Wait for all SubDevices in this group to transition to the given state.
@@ -727,8 +1431,10 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC>
     ) -> Result<(), Error> {
         async {
             loop {
-                if self.is_state(maindevice, desired_state).await? {
-                    break Ok(());
+                match self.check_state(maindevice, desired_state).await? {
+                    GroupState::Reached => break Ok(()),
+                    GroupState::Refused => break Err(Error::StateTransition),
+                    GroupState::Pending => (),
                 }
 
                 maindevice.timeouts.loop_tick().await;
@@ -738,12 +1444,13 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC>
         .await
     }
 
-    /// Transition to a new state.
+    /// Transition to a new state, calling `on_failure` for every refused attempt.
     async fn transition_to<TO>(
         mut self,
         maindevice: &MainDevice<'_>,
         desired_state: SubDeviceState,
-    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, TO, DC>, Error> {
+        mut on_failure: impl FnMut(TransitionFailure),
+    ) -> Result<SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, TO, DC, M>, Error> {
         // We're done configuring FMMUs, etc, now we can request all SubDevices in this group go into
         // SAFE-OP
         for subdevice in self
@@ -753,6 +1460,12 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC>
             .iter_mut()
             .map(AtomicRefCell::get_mut)
         {
+            if desired_state == SubDeviceState::Op {
+                maindevice
+                    .run_hooks(HookTransition::SafeOpToOp, subdevice)
+                    .await?;
+            }
+
             SubDeviceRef::new(maindevice, subdevice.configured_address(), subdevice)
                 .request_subdevice_state_nowait(desired_state)
                 .await?;
@@ -760,7 +1473,23 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC>
 
         fmt::debug!("Waiting for group state {}", desired_state);
 
-        self.wait_for_state(maindevice, desired_state).await?;
+        match self.wait_for_state(maindevice, desired_state).await {
+            // Acknowledge and retry refused transitions one SubDevice at a time
+            Err(Error::StateTransition) => {
+                for subdevice in self
+                    .inner
+                    .get_mut()
+                    .subdevices
+                    .iter_mut()
+                    .map(AtomicRefCell::get_mut)
+                {
+                    SubDeviceRef::new(maindevice, subdevice.configured_address(), subdevice)
+                        .request_state(desired_state, &mut on_failure)
+                        .await?;
+                }
+            }
+            result => result?,
+        }
 
         fmt::debug!("--> Group reached state {}", desired_state);
 
@@ -777,8 +1506,8 @@ impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC>
 }
 
 // Methods for any state where a PDI has been configured.
-impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC>
-    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, DC>
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC, M>
+    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, DC, M>
 where
     S: HasPdi,
 {
@@ -828,26 +1557,48 @@ where
             "--> Group PDI: {:?} ({} byte subset of {} max)",
             i_data,
             self.pdi_len,
-            MAX_PDI
+            self.max_pdi_len()
         );
 
         // NOTE: Using panicking `[]` indexing as the indices and arrays should all be correct by
         // this point. If something isn't right, that's a bug.
-        let inputs = if input_range.is_empty() {
-            EMPTY_PDI_SLICE
+        let (inputs, packed_inputs) = if input_range.is_empty() {
+            (EMPTY_PDI_SLICE, EMPTY_PDI_SLICE)
         } else {
-            i_data
+            let data = i_data
                 .get(input_range.bytes.clone())
-                .ok_or(Error::Internal)?
+                .ok_or(Error::Internal)?;
+
+            // Bit-packed input bytes may hold other SubDevices' inputs, so are only read by
+            // `PdiBits`.
+            if input_range.bit_packed {
+                (EMPTY_PDI_SLICE, data)
+            } else {
+                (data, EMPTY_PDI_SLICE)
+            }
         };
 
-        let outputs = if output_range.is_empty() {
-            // SAFETY: Slice is empty so can never be mutated
-            unsafe { slice::from_raw_parts_mut(EMPTY_PDI_SLICE.as_ptr().cast_mut(), 0) }
-        } else {
-            o_data
+        // SAFETY: Slice is empty so can never be mutated
+        let empty_outputs =
+            unsafe { slice::from_raw_parts_mut(EMPTY_PDI_SLICE.as_ptr().cast_mut(), 0) };
+
+        let (outputs, packed_outputs) = if output_range.is_empty() {
+            (empty_outputs, &[][..])
+        } else if output_range.bit_packed {
+            let packed: *mut [u8] = o_data
                 .get_mut(output_range.bytes.clone())
-                .ok_or(Error::Internal)?
+                .ok_or(Error::Internal)?;
+
+            // SAFETY: `AtomicU8` has the same layout as `u8`. Bit-packed output bytes may be shared
+            // with other SubDevices, so they are only ever written atomically by `PdiBitsMut`.
+            (empty_outputs, unsafe { &*(packed as *const [AtomicU8]) })
+        } else {
+            (
+                o_data
+                    .get_mut(output_range.bytes.clone())
+                    .ok_or(Error::Internal)?,
+                &[][..],
+            )
         };
 
         Ok(SubDeviceRef::new(
@@ -856,35 +1607,118 @@ where
             // SAFETY: A given SubDevice contained in a `SubDevicePdi` MUST only be borrowed once
             // (currently enforced by `AtomicRefCell`). If it is borrowed more than once, immutable
             // APIs in `SubDeviceRef<SubDevicePdi>` will be unsound.
-            SubDevicePdi::new(subdevice, inputs, outputs),
+            SubDevicePdi::new(
+                subdevice,
+                inputs,
+                outputs,
+                packed_inputs,
+                packed_outputs,
+                &self.inner().pdo_entries,
+            ),
         ))
     }
 
+    /// Read the layout of the PDO entries mapped into every SubDevice's inputs and outputs, so
+    /// they can be accessed with [`SubDeviceRef::input_entry`] and
+    /// [`SubDeviceRef::output_entry_mut`].
+    ///
+    /// Entries are not read when the group's PDI is configured. The layout is read back from each
+    /// SubDevice's object dictionary if it supports CoE, or from its EEPROM otherwise, so this
+    /// should be called once during startup and not in the cyclic task.
+    ///
+    /// A group with fixed storage holds up to 64 entries across all of its SubDevices. A group
+    /// created with [`with_capacity`](SubDeviceGroup::with_capacity) holds every entry.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Capacity`] if the group cannot hold every mapped entry.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use ethercrab::{MainDevice, MainDeviceConfig, PduStorage, Timeouts};
+    /// # static PDU_STORAGE: PduStorage<8, 64> = PduStorage::new();
+    /// # let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    /// # let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+    /// # async {
+    /// # let group = maindevice.init_single_group::<8, 8>(ethercrab::std::ethercat_now).await.expect("Init");
+    /// let mut group = group.into_op(&maindevice).await.expect("PRE-OP -> OP");
+    ///
+    /// group
+    ///     .read_pdo_entries(&maindevice)
+    ///     .await
+    ///     .expect("Read PDO entries");
+    ///
+    /// // A DS402 servo drive
+    /// let subdevice = group.subdevice(&maindevice, 0).expect("No device");
+    ///
+    /// let status_word = subdevice
+    ///     .input_entry(0x6041, 0)
+    ///     .expect("Status word not mapped")
+    ///     .read::<u16>()
+    ///     .expect("Read");
+    /// # };
+    /// ```
+    pub async fn read_pdo_entries(&mut self, maindevice: &MainDevice<'_>) -> Result<(), Error> {
+        let inner = self.inner.get_mut();
+
+        inner.pdo_entries.clear();
+
+        for subdevice in inner.subdevices.iter_mut().map(AtomicRefCell::get_mut) {
+            let subdevice =
+                SubDeviceRef::new(maindevice, subdevice.configured_address(), &*subdevice);
+
+            for direction in [PdoDirection::MasterRead, PdoDirection::MasterWrite] {
+                let mut entries = PdoEntries::default();
+
+                subdevice.read_pdo_entries(direction, &mut entries).await?;
+
+                for entry in entries.iter() {
+                    inner.pdo_entries.push(*entry).map_err(|entry| {
+                        fmt::error!(
+                            "Too many PDO entries in group. SubDevice {:#06x} entry {:#06x}:{} does not fit",
+                            entry.configured_address,
+                            entry.index,
+                            entry.sub_index
+                        );
+
+                        Error::Capacity(Item::PdoEntry)
+                    })?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Get an iterator over all SubDevices in this group.
     pub fn iter<'group, 'maindevice>(
         &'group mut self,
         maindevice: &'maindevice MainDevice<'maindevice>,
-    ) -> GroupSubDeviceIterator<'group, 'maindevice, MAX_SUBDEVICES, MAX_PDI, S, DC> {
+    ) -> GroupSubDeviceIterator<'group, 'maindevice, MAX_SUBDEVICES, MAX_PDI, S, DC, M> {
         GroupSubDeviceIterator::new(maindevice, self)
     }
 
     /// Drive the SubDevice group's inputs and outputs.
     ///
     /// A `SubDeviceGroup` will not process any inputs or outputs unless this method is called
-    /// periodically. It will send an `LRW` to update SubDevice outputs and read SubDevice inputs.
+    /// periodically. It will send an `LRW` to update SubDevice outputs and read SubDevice inputs,
+    /// or an `LRD` and `LWR` depending on the group's [`CyclicMode`].
+    ///
+    /// If the group PDI is too long to fit in a single frame, it is split into chunks which are sent
+    /// in multiple frames at the same time. Each chunk is exchanged with one `LRW`, or with up to
+    /// one each of `LRD` and `LWR` when using [`CyclicMode::LrdLwr`]. If the group was configured
+    /// with [`overlapping_pdi`](GroupConfig::overlapping_pdi), each SubDevice's inputs and outputs
+    /// share logical addresses, so less data is sent than the length of the group's in-memory PDI.
     ///
-    /// This method returns the working counter on success.
+    /// This method returns the working counter on success. If the PDI was split, this is the sum
+    /// of the working counters of every chunk, with `LRD` and `LWR` working counters scaled to
+    /// match `LRW`.
     ///
     /// # Errors
     ///
     /// This method will return with an error if the PDU could not be sent over the network, or the
     /// response times out.
-    ///
-    /// # Panics
-    ///
-    /// This method will panic if the frame data length of the group is too large to fit in the
-    /// configured maximum PDU length set by the `DATA` const generic of
-    /// [`PduStorage`](crate::PduStorage).
     pub async fn tx_rx<'sto>(&self, maindevice: &'sto MainDevice<'sto>) -> Result<u16, Error> {
         fmt::trace!(
             "Group TX/RX, start address {:#010x}, data len {}, of which read bytes: {}",
@@ -893,51 +1727,214 @@ where
             self.read_pdi_len
         );
 
-        assert!(
-            self.len() <= maindevice.max_frame_data(),
-            "Chunked sends not yet supported. Buffer len {} B too long to send in {} B frame",
-            self.len(),
-            maindevice.max_frame_data()
-        );
+        self.tx_rx_chunked(maindevice, None, None)
+            .await
+            .map(|exchange| exchange.wkc)
+    }
 
-        let data = Command::lrw(self.inner().pdi_start.start_address)
-            .ignore_wkc()
-            .send_receive_slice(maindevice, self.pdi())
-            .await?;
+    /// Drive the SubDevice group's inputs and outputs, checking that every SubDevice responded.
+    ///
+    /// This method is the same as [`tx_rx`](SubDeviceGroup::tx_rx), but the working counter is
+    /// checked against the value expected from the SubDevices with inputs and outputs mapped into
+    /// the group PDI. See [`expected_wkc`](SubDeviceGroup::expected_wkc).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::WorkingCounter`] if the working counter does not match the expected value,
+    /// e.g. if a SubDevice has left OP or been disconnected. Any received inputs will still have
+    /// been written into the PDI.
+    ///
+    /// This method will also return with an error if the PDU could not be sent over the network,
+    /// or the response times out.
+    pub async fn tx_rx_checked<'sto>(
+        &self,
+        maindevice: &'sto MainDevice<'sto>,
+    ) -> Result<u16, Error> {
+        self.tx_rx_chunked(maindevice, None, None)
+            .await?
+            .check_wkc()
+    }
+
+    /// The working counter expected from [`tx_rx`](SubDeviceGroup::tx_rx) when every SubDevice in
+    /// the group processes its inputs and outputs.
+    ///
+    /// Each SubDevice with inputs adds 1 and each SubDevice with outputs adds 2. This is computed
+    /// when the group's PDI is configured on the way to SAFE-OP.
+    ///
+    /// If the PDI is split across multiple frames, it is split between SubDevices so this value
+    /// still matches the sum of the working counters of every chunk. The only exception is a
+    /// SubDevice whose inputs or outputs alone do not fit in one frame, which is counted once per
+    /// frame. [`tx_rx_checked`](SubDeviceGroup::tx_rx_checked) takes this into account.
+    pub fn expected_wkc(&self) -> u16 {
+        self.expected_pdu_wkc(&(0..self.read_pdi_len), &(self.read_pdi_len..self.pdi_len))
+    }
+
+    /// Allocate a frame to exchange the group's process data without copying it to or from the
+    /// group PDI.
+    ///
+    /// Outputs are written directly into the frame with [`PdiTxFrame::outputs_mut`] or
+    /// [`PdiTxFrame::subdevice_outputs_mut`], and inputs are read directly from the response with
+    /// [`PdiRxFrame::inputs`] or [`PdiRxFrame::subdevice_inputs`]. The group PDI is not used, so
+    /// [`SubDeviceRef`] inputs and outputs are not updated or sent.
+    ///
+    /// The group's [`CyclicMode`] and [`GroupConfig::overlapping_pdi`] settings are respected. DC
+    /// system time is not distributed, so groups using DC should still call e.g.
+    /// [`tx_rx_sync_system_time`](SubDeviceGroup::tx_rx_sync_system_time) periodically.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Pdu`] with [`PduError::TooLong`] if the group PDI does not fit in a single
+    /// frame. [`tx_rx`](SubDeviceGroup::tx_rx) must be used for these groups.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ethercrab::{std::ethercat_now, MainDevice, MainDeviceConfig, PduStorage, Timeouts};
+    ///
+    /// static PDU_STORAGE: PduStorage<16, { PduStorage::element_size(1100) }> = PduStorage::new();
+    ///
+    /// let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    ///
+    /// let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+    ///
+    /// # async {
+    /// let group = maindevice
+    ///     .init_single_group::<16, 64>(ethercat_now)
+    ///     .await
+    ///     .expect("Init")
+    ///     .into_op(&maindevice)
+    ///     .await
+    ///     .expect("PRE-OP -> OP");
+    ///
+    /// loop {
+    ///     let mut frame = group.pdi_tx_frame(&maindevice).expect("Frame");
+    ///
+    ///     frame.subdevice_outputs_mut(0).expect("SubDevice 0")[0] = 0xaa;
+    ///
+    ///     let response = frame.send().await.expect("TX/RX");
+    ///
+    ///     response.check_wkc().expect("Working counter");
+    ///
+    ///     println!("Inputs: {:?}", response.subdevice_inputs(1).expect("SubDevice 1"));
+    /// }
+    /// # };
+    /// ```
+    pub fn pdi_tx_frame<'sto>(
+        &self,
+        maindevice: &'sto MainDevice<'sto>,
+    ) -> Result<PdiTxFrame<'sto, '_>, Error> {
+        let inner = self.inner();
+
+        PdiTxFrame::new(
+            maindevice,
+            inner.pdi_start.start_address,
+            self.read_pdi_len,
+            self.pdi_len,
+            inner.config.overlapping_pdi,
+            inner.lrd_lwr,
+            &inner.io_ranges,
+            self.expected_wkc(),
+            &inner.wkc_stats,
+        )
+    }
+
+    /// Copy the group's current inputs into a [`PdiSnapshot`] so they can be read by another task
+    /// without blocking this one.
+    ///
+    /// This is usually called after every [`tx_rx`](SubDeviceGroup::tx_rx). The published data
+    /// starts at the beginning of the group PDI, so each SubDevice's inputs are at the same offsets
+    /// as in the group.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::PdiTooLong`] if the group's inputs do not fit in the snapshot.
+    pub fn publish_inputs<const N: usize>(
+        &self,
+        writer: &mut SnapshotWriter<'_, N>,
+    ) -> Result<(), Error> {
+        writer.publish(
+            self.pdi()
+                .get(0..self.read_pdi_len)
+                .ok_or(Error::Internal)?,
+        )
+    }
+
+    /// Copy outputs staged by another task into the group, if any new outputs have been published
+    /// since the last call.
+    ///
+    /// This is usually called before every [`tx_rx`](SubDeviceGroup::tx_rx). The staged data must
+    /// hold all of the group's outputs, i.e. the part of the group PDI after the inputs.
+    ///
+    /// Returns `true` if new outputs were applied.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::PdiLength`] if the staged data is not the same length as the group's
+    /// outputs. The group's outputs are left unchanged.
+    pub fn apply_staged_outputs<const N: usize>(
+        &self,
+        reader: &mut SnapshotReader<'_, N>,
+    ) -> Result<bool, Error> {
+        let Some(staged) = reader.take_new() else {
+            return Ok(false);
+        };
+
+        let outputs = self
+            .pdi_mut()
+            .get_mut(self.read_pdi_len..self.pdi_len)
+            .ok_or(Error::Internal)?;
+
+        if staged.len() != outputs.len() {
+            return Err(Error::PdiLength {
+                segment_length: outputs.len(),
+                type_length: staged.len(),
+            });
+        }
+
+        outputs.copy_from_slice(staged);
 
-        self.process_pdi_response(&data)
+        Ok(true)
+    }
+
+    /// Compute the working counter expected from a PDU that reads the given part of the PDI's
+    /// inputs and writes the given part of its outputs.
+    fn expected_pdu_wkc(&self, inputs: &Range<usize>, outputs: &Range<usize>) -> u16 {
+        fn overlaps(a: &Range<usize>, b: &Range<usize>) -> bool {
+            !a.is_empty() && !b.is_empty() && a.start < b.end && b.start < a.end
+        }
+
+        self.inner()
+            .io_ranges
+            .iter()
+            .map(|(subdevice_inputs, subdevice_outputs)| {
+                u16::from(overlaps(subdevice_inputs, inputs))
+                    + u16::from(overlaps(subdevice_outputs, outputs)) * 2
+            })
+            .sum()
     }
 
     /// Drive the SubDevice group's inputs and outputs and synchronise EtherCAT system time with
     /// `FRMW`.
     ///
     /// A `SubDeviceGroup` will not process any inputs or outputs unless this method is called
-    /// periodically. It will send an `LRW` to update SubDevice outputs and read SubDevice inputs.
+    /// periodically. It will send an `LRW` to update SubDevice outputs and read SubDevice inputs,
+    /// or an `LRD` and `LWR` depending on the group's [`CyclicMode`].
+    ///
+    /// Large PDIs are split across multiple frames as described in
+    /// [`tx_rx`](SubDeviceGroup::tx_rx).
     ///
     /// This method returns the working counter and the current EtherCAT system time in nanoseconds
-    /// on success.
+    /// on success. If the PDI was split, the working counter is the sum of the working counters of
+    /// every chunk, with `LRD` and `LWR` working counters scaled to match `LRW`.
     ///
     /// # Errors
     ///
     /// This method will return with an error if the PDU could not be sent over the network, or the
     /// response times out.
-    ///
-    /// # Panics
-    ///
-    /// This method will panic if the frame data length of the group is too large to fit in the
-    /// configured maximum PDU length set by the `DATA` const generic of
-    /// [`PduStorage`](crate::PduStorage).
     pub async fn tx_rx_sync_system_time<'sto>(
         &self,
         maindevice: &'sto MainDevice<'sto>,
     ) -> Result<(u16, Option<u64>), Error> {
-        assert!(
-            self.len() <= maindevice.max_frame_data(),
-            "Chunked sends not yet supported. Buffer len {} B too long to send in {} B frame",
-            self.len(),
-            maindevice.max_frame_data()
-        );
-
         fmt::trace!(
             "Group TX/RX with DC sync, start address {:#010x}, data len {}, of which read bytes: {}",
             self.inner().pdi_start.start_address,
@@ -945,103 +1942,347 @@ where
             self.read_pdi_len
         );
 
-        if let Some(dc_ref) = maindevice.dc_ref_address() {
-            let mut frame = maindevice.pdu_loop.alloc_frame()?;
+        self.tx_rx_chunked(maindevice, maindevice.dc_ref_address(), None)
+            .await
+            .map(|exchange| (exchange.wkc, exchange.time))
+    }
 
-            let dc_handle = frame.push_pdu(
-                Command::frmw(dc_ref, RegisterAddress::DcSystemTime.into()).into(),
-                0u64,
-                None,
-            )?;
+    /// Send the group PDI in as many `LRW`s, or `LRD`s and `LWR`s, as required to fit it into
+    /// frames.
+    ///
+    /// Frames are sent in batches of up to `MAX_IN_FLIGHT_FRAMES`, or as many as can be allocated
+    /// from the PDU storage if fewer are free.
+    ///
+    /// If `dc_reference` is given, an `FRMW` is sent in the first frame to read and distribute the
+    /// DC system time from the given SubDevice. If `host_clock` is also given, the current host time
+    /// is first written to the reference SubDevice's system time register to steer its clock
+    /// towards the host clock.
+    ///
+    /// Returns the sum of all working counters, scaled to match `LRW`, the expected working
+    /// counter for the PDUs that were sent, and the DC system time if it was requested.
+    async fn tx_rx_chunked<'sto>(
+        &self,
+        maindevice: &'sto MainDevice<'sto>,
+        mut dc_reference: Option<u16>,
+        host_clock: Option<fn() -> u64>,
+    ) -> Result<PdiExchange, Error> {
+        let start_address = self.inner().pdi_start.start_address;
+        let lrd_lwr = self.inner().lrd_lwr;
+        let overlapping = self.inner().config.overlapping_pdi;
+        // Responses are written into the PDI while this loop runs, so only short-lived borrows of
+        // it may be taken.
+        let pdi_len = self.pdi_len;
+
+        let mut wkc = 0u16;
+        let mut expected_wkc = 0u16;
+        let mut time = None;
+        // With overlapping mappings, an `LRW` sends only the outputs. The SubDevices replace them
+        // with their inputs.
+        let mut pos = if overlapping && !lrd_lwr {
+            self.read_pdi_len
+        } else {
+            0
+        };
 
-            let pdu_handle = frame.push_pdu(
-                Command::lrw(self.inner().pdi_start.start_address).into(),
-                self.pdi(),
-                None,
-            )?;
+        loop {
+            let mut frames = heapless::Vec::<PdiFrame<'sto>, MAX_IN_FLIGHT_FRAMES>::new();
+
+            // The first frame is always sent, even for an empty PDI, so DC time is still
+            // distributed.
+            while frames.is_empty() || (pos < pdi_len && !frames.is_full()) {
+                let mut frame = match maindevice.pdu_loop.alloc_frame() {
+                    Ok(frame) => frame,
+                    // Other frames may be in flight, e.g. from a `HealthMonitor`. Send what we
+                    // have and continue with the rest of the PDI in the next batch.
+                    Err(_) if !frames.is_empty() => break,
+                    Err(e) => return Err(e),
+                };
+
+                let mut host_time = None;
+
+                let dc_handle = dc_reference
+                    .take()
+                    .map(|reference| {
+                        if host_clock.is_some() {
+                            // The ESC compares the written time with its own system time and
+                            // adjusts its clock speed to reduce the difference. Only the lower 32
+                            // bits are compared. The time is filled in just before the frame is
+                            // marked as sendable.
+                            let (_, payload) = frame.push_pdu_zeroed(
+                                Command::fpwr(reference, RegisterAddress::DcSystemTime.into())
+                                    .into(),
+                                u32::PACKED_LEN,
+                            )?;
+
+                            host_time = Some(payload);
+                        }
+
+                        frame.push_pdu(
+                            Command::frmw(reference, RegisterAddress::DcSystemTime.into()).into(),
+                            0u64,
+                            None,
+                        )
+                    })
+                    .transpose()?;
+
+                let mut pdus = heapless::Vec::new();
+
+                // An `LRW` exchanges the whole PDI. Otherwise, inputs at the start of the PDI are
+                // read with `LRD`, then outputs are written with `LWR`, possibly in the same frame.
+                loop {
+                    // Overlapping outputs are sent to the same logical addresses as the inputs
+                    let logical_pos = if overlapping && pos >= self.read_pdi_len {
+                        pos - self.read_pdi_len
+                    } else {
+                        pos
+                    };
+
+                    let address = start_address + logical_pos as u32;
+
+                    let (command, end, lwr) = if !lrd_lwr {
+                        (Command::lrw(address).into(), pdi_len, false)
+                    } else if pos < self.read_pdi_len {
+                        (Command::lrd(address).into(), self.read_pdi_len, false)
+                    } else {
+                        (Command::lwr(address).into(), pdi_len, true)
+                    };
+
+                    let len = self.chunk_len(pos, frame.max_pdu_data_len().min(end - pos), end);
+
+                    if len == 0 && pos < pdi_len {
+                        // Frame is too small to hold anything, so we'll never make progress
+                        if pdus.is_empty() {
+                            return Err(Error::Pdu(PduError::TooLong));
+                        }
 
-            let frame = frame.mark_sendable(
-                &maindevice.pdu_loop,
-                maindevice.timeouts.pdu,
-                maindevice.config.retry_behaviour.retry_count(),
-            );
+                        break;
+                    }
 
-            maindevice.pdu_loop.wake_sender();
+                    let range = pos..(pos + len);
 
-            let received = frame.await?;
+                    let inputs = if lwr {
+                        pos..pos
+                    } else if overlapping {
+                        logical_pos..(logical_pos + len)
+                    } else {
+                        // Only the first `read_pdi_len` bytes of the PDI hold inputs
+                        pos..(pos + len).min(self.read_pdi_len).max(pos)
+                    };
 
-            let (time, wkc) = self.process_pdi_response_with_time(
-                &received.pdu(dc_handle)?,
-                &received.pdu(pdu_handle)?,
-            )?;
+                    let handle = frame.push_pdu(command, &self.pdi()[range.clone()], None)?;
 
-            Ok((wkc, Some(time)))
-        } else {
-            self.tx_rx(maindevice).await.map(|wkc| (wkc, None))
-        }
-    }
+                    fmt::unwrap!(pdus
+                        .push(PdiPdu {
+                            handle,
+                            range,
+                            inputs,
+                            lwr
+                        })
+                        .map_err(|_| ()));
 
-    fn process_pdi_response_with_time(
-        &self,
-        dc: &crate::pdu_loop::ReceivedPdu,
-        data: &crate::pdu_loop::ReceivedPdu,
-    ) -> Result<(u64, u16), Error> {
-        let time = u64::unpack_from_slice(dc)?;
+                    pos += len;
 
-        Ok((time, self.process_pdi_response(data)?))
-    }
+                    if !lrd_lwr || pos >= pdi_len || pdus.is_full() {
+                        break;
+                    }
+                }
 
-    /// Take a received PDI and copy its inputs into the group's memory.
-    ///
-    /// Returns working counter on success.
-    fn process_pdi_response(&self, data: &crate::pdu_loop::ReceivedPdu) -> Result<u16, Error> {
-        if data.len() != self.pdi().len() {
-            fmt::error!(
-                "Data length {} does not match value length {}",
-                data.len(),
-                self.pdi().len()
-            );
+                // Sample the host clock as late as possible so the time spent building the frame
+                // doesn't add to the lag.
+                if let (Some(payload), Some(now)) = (host_time, host_clock) {
+                    if let Some(buf) = frame.pdu_payload_mut(payload) {
+                        buf.copy_from_slice(&(now() as u32).to_le_bytes());
+                    }
+                }
 
-            return Err(Error::Pdu(PduError::Decode));
-        }
+                let frame = frame.mark_sendable(
+                    &maindevice.pdu_loop,
+                    maindevice.timeouts.pdu,
+                    maindevice.config.retry_behaviour.retry_count(),
+                );
+
+                maindevice.pdu_loop.wake_sender();
+
+                fmt::unwrap!(frames
+                    .push(PdiFrame {
+                        state: PdiFrameState::Sent(frame),
+                        dc_handle,
+                        pdus,
+                    })
+                    .map_err(|_| ()));
+            }
 
-        let wkc = data.working_counter;
+            // All frames must be polled together. A response is discarded if its frame has not
+            // been polled by the time it arrives.
+            poll_fn(|cx| {
+                let mut pending = false;
 
-        self.pdi_mut()
-            .get_mut(0..self.read_pdi_len)
-            .ok_or(Error::Internal)?
-            .copy_from_slice(data.get(0..self.read_pdi_len).ok_or(Error::Internal)?);
+                for frame in frames.iter_mut() {
+                    let PdiFrameState::Sent(fut) = &mut frame.state else {
+                        continue;
+                    };
 
-        Ok(wkc)
-    }
-}
+                    match Pin::new(fut).poll(cx) {
+                        Poll::Ready(received) => frame.state = PdiFrameState::Received(received?),
+                        Poll::Pending => pending = true,
+                    }
+                }
 
-// Methods for when the group has a PDI AND has Distributed Clocks configured
-impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S>
-    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, HasDc>
-where
-    S: HasPdi,
-{
-    /// Drive the SubDevice group's inputs and outputs, synchronise EtherCAT system time with `FRMW`,
-    /// and return cycle timing information.
-    ///
+                if pending {
+                    Poll::Pending
+                } else {
+                    Poll::Ready(Ok::<_, Error>(()))
+                }
+            })
+            .await?;
+
+            for frame in frames {
+                let PdiFrameState::Received(received) = frame.state else {
+                    return Err(Error::Internal);
+                };
+
+                if let Some(dc_handle) = frame.dc_handle {
+                    time = Some(u64::unpack_from_slice(&received.pdu(dc_handle)?)?);
+                }
+
+                for pdu in frame.pdus {
+                    expected_wkc =
+                        expected_wkc.wrapping_add(self.expected_pdu_wkc(&pdu.inputs, &pdu.range));
+
+                    let chunk_wkc = self.process_pdi_response(
+                        pdu.range.len(),
+                        pdu.inputs,
+                        &received.pdu(pdu.handle)?,
+                    )?;
+
+                    // `LRW` increments the working counter by 2 for every SubDevice written to, but
+                    // `LWR` only increments it by 1, so scale it to match.
+                    let chunk_wkc = if pdu.lwr {
+                        chunk_wkc.wrapping_mul(2)
+                    } else {
+                        chunk_wkc
+                    };
+
+                    wkc = wkc.wrapping_add(chunk_wkc);
+                }
+            }
+
+            if pos >= pdi_len {
+                self.inner().wkc_stats.record(wkc, expected_wkc);
+
+                break Ok(PdiExchange {
+                    wkc,
+                    expected_wkc,
+                    time,
+                });
+            }
+        }
+    }
+
+    /// Shorten a chunk of `len` bytes starting at `pos` in the group PDI so it ends between two
+    /// SubDevices, unless it already reaches `end`.
+    ///
+    /// Every SubDevice's inputs and outputs are then exchanged in a single PDU, so each SubDevice
+    /// adds to the working counter once, as computed by [`expected_wkc`](Self::expected_wkc). If a
+    /// single SubDevice's mapping is longer than `len`, the chunk is left as is.
+    fn chunk_len(&self, pos: usize, len: usize, end: usize) -> usize {
+        if pos + len >= end {
+            return len;
+        }
+
+        // Overlapping inputs and outputs share logical addresses, so a SubDevice's inputs are sent
+        // at the same place in the frame as its outputs.
+        let offset = if self.inner().config.overlapping_pdi {
+            self.read_pdi_len
+        } else {
+            0
+        };
+
+        let mut chunk_end = pos + len;
+
+        // Move the end back to the start of any mapping it splits until none are split
+        while let Some(start) = self
+            .inner()
+            .io_ranges
+            .iter()
+            .flat_map(|(inputs, outputs)| {
+                [
+                    inputs.clone(),
+                    outputs.clone(),
+                    (inputs.start + offset)..(inputs.end + offset),
+                    outputs.start.saturating_sub(offset)..outputs.end.saturating_sub(offset),
+                ]
+            })
+            .find(|range| range.start < chunk_end && chunk_end < range.end)
+            .map(|range| range.start)
+        {
+            if start <= pos {
+                return len;
+            }
+
+            chunk_end = start;
+        }
+
+        chunk_end - pos
+    }
+
+    /// Take a received PDI chunk and copy its inputs into the group's memory.
+    ///
+    /// `len` is the length of the chunk that was sent, and `inputs` is the position in the group
+    /// PDI that the start of the response is copied to.
+    ///
+    /// Returns working counter on success.
+    fn process_pdi_response(
+        &self,
+        len: usize,
+        inputs: Range<usize>,
+        data: &crate::pdu_loop::ReceivedPdu,
+    ) -> Result<u16, Error> {
+        if data.len() != len {
+            fmt::error!(
+                "Data length {} does not match value length {}",
+                data.len(),
+                len
+            );
+
+            return Err(Error::Pdu(PduError::Decode));
+        }
+
+        self.pdi_mut()
+            .get_mut(inputs.clone())
+            .ok_or(Error::Internal)?
+            .copy_from_slice(data.get(0..inputs.len()).ok_or(Error::Internal)?);
+
+        Ok(data.working_counter)
+    }
+}
+
+// Methods for when the group has a PDI AND has Distributed Clocks configured
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, M>
+    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, HasDc, M>
+where
+    S: HasPdi,
+{
+    /// Drive the SubDevice group's inputs and outputs, synchronise EtherCAT system time with `FRMW`,
+    /// and return cycle timing information.
+    ///
     /// A `SubDeviceGroup` will not process any inputs or outputs unless this method is called
-    /// periodically. It will send an `LRW` to update SubDevice outputs and read SubDevice inputs.
+    /// periodically. It will send an `LRW` to update SubDevice outputs and read SubDevice inputs,
+    /// or an `LRD` and `LWR` depending on the group's [`CyclicMode`].
+    ///
+    /// Large PDIs are split across multiple frames as described in
+    /// [`tx_rx`](SubDeviceGroup::tx_rx).
     ///
     /// This method returns the working counter and a [`CycleInfo`], containing values that can be
-    /// used to synchronise the MainDevice to the network SYNC0 event.
+    /// used to synchronise the MainDevice to the network SYNC0 event. If the PDI was split, the
+    /// working counter is the sum of the working counters of every chunk, with `LRD` and `LWR`
+    /// working counters scaled to match `LRW`.
     ///
     /// # Errors
     ///
     /// This method will return with an error if the PDU could not be sent over the network, or the
     /// response times out.
     ///
-    /// # Panics
-    ///
-    /// This method will panic if the frame data length of the group is too large to fit in the
-    /// configured maximum PDU length set by the `DATA` const generic of
-    /// [`PduStorage`](crate::PduStorage).
-    ///
     /// # Examples
     ///
     /// This example sends process data at 2.5ms offset into a 5ms cycle.
@@ -1137,13 +2378,6 @@ where
         &self,
         maindevice: &'sto MainDevice<'sto>,
     ) -> Result<(u16, CycleInfo), Error> {
-        assert!(
-            self.len() <= maindevice.max_frame_data(),
-            "Chunked sends not yet supported. Buffer len {} B too long to send in {} B frame",
-            self.len(),
-            maindevice.max_frame_data()
-        );
-
         fmt::trace!(
             "Group TX/RX with DC sync, start address {:#010x}, data len {}, of which read bytes: {}",
             self.inner().pdi_start.start_address,
@@ -1151,34 +2385,81 @@ where
             self.read_pdi_len
         );
 
-        let mut frame = maindevice.pdu_loop.alloc_frame()?;
+        let exchange = self
+            .tx_rx_chunked(
+                maindevice,
+                Some(self.dc_conf.reference),
+                self.dc_conf.host_clock,
+            )
+            .await?;
 
-        let dc_handle = frame.push_pdu(
-            Command::frmw(self.dc_conf.reference, RegisterAddress::DcSystemTime.into()).into(),
-            0u64,
-            None,
-        )?;
+        self.cycle_info(exchange.wkc, exchange.time)
+    }
 
-        let pdu_handle = frame.push_pdu(
-            Command::lrw(self.inner().pdi_start.start_address).into(),
-            self.pdi(),
-            None,
-        )?;
+    /// Drive the SubDevice group's inputs and outputs, synchronise EtherCAT system time with
+    /// `FRMW`, and check that every SubDevice responded.
+    ///
+    /// This method is the same as [`tx_rx_dc`](SubDeviceGroup::tx_rx_dc), but the working counter
+    /// is checked in the same way as [`tx_rx_checked`](SubDeviceGroup::tx_rx_checked).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::WorkingCounter`] if the working counter does not match the expected value,
+    /// e.g. if a SubDevice has left OP or been disconnected. Any received inputs will still have
+    /// been written into the PDI.
+    ///
+    /// This method will also return with an error if the PDU could not be sent over the network,
+    /// or the response times out.
+    pub async fn tx_rx_dc_checked<'sto>(
+        &self,
+        maindevice: &'sto MainDevice<'sto>,
+    ) -> Result<(u16, CycleInfo), Error> {
+        let exchange = self
+            .tx_rx_chunked(
+                maindevice,
+                Some(self.dc_conf.reference),
+                self.dc_conf.host_clock,
+            )
+            .await?;
 
-        let frame = frame.mark_sendable(
-            &maindevice.pdu_loop,
-            maindevice.timeouts.pdu,
-            maindevice.config.retry_behaviour.retry_count(),
-        );
+        let wkc = exchange.check_wkc()?;
 
-        maindevice.pdu_loop.wake_sender();
+        self.cycle_info(wkc, exchange.time)
+    }
 
-        let received = frame.await?;
+    /// Steer the DC reference SubDevice's clock towards a host clock.
+    ///
+    /// By default, the DC reference SubDevice runs freely and every other SubDevice follows it.
+    /// Once a host clock is set, every call to [`tx_rx_dc`](SubDeviceGroup::tx_rx_dc) or
+    /// [`tx_rx_dc_checked`](SubDeviceGroup::tx_rx_dc_checked) also writes the current host time to
+    /// the reference SubDevice. Its drift compensation then continuously adjusts its clock speed so
+    /// that EtherCAT system time tracks the host clock, e.g. to line up timestamps from several
+    /// EtherCAT networks synchronised to the same PTP clock.
+    ///
+    /// `now` should be the same function passed to
+    /// [`init`](crate::MainDevice::init), e.g. `ethercrab::std::ethercat_now`. It
+    /// must return nanoseconds since the EtherCAT epoch of 2000-01-01.
+    ///
+    /// The host time is sampled just before the frame is queued for sending, so DC time lags the
+    /// host clock by the delay until the frame reaches the reference SubDevice. This delay is made
+    /// up of the time taken for the TX task to pick up and send the frame, which varies from cycle
+    /// to cycle with OS scheduling, and the time on the wire, which is roughly constant. The
+    /// average delay shows up as a fixed offset between DC time and the host clock. The variation
+    /// shows up as noise in the time difference measured by the reference SubDevice. Its drift
+    /// compensation only adjusts the clock speed by a small amount per write, so occasional
+    /// latency spikes are averaged out, but consistently high jitter, e.g. from a TX task without
+    /// realtime priority, makes the reference clock wander around the host clock instead of
+    /// tracking it closely. Running the TX/RX task on a realtime thread keeps this to a minimum.
+    ///
+    /// The drift compensation only slews the clock slowly, so large steps in the host clock will
+    /// take a long time to be followed.
+    pub fn set_host_clock(&mut self, now: fn() -> u64) {
+        self.dc_conf.host_clock = Some(now);
+    }
 
-        let (time, wkc) = self.process_pdi_response_with_time(
-            &received.pdu(dc_handle)?,
-            &received.pdu(pdu_handle)?,
-        )?;
+    /// Compute cycle timing information from the current DC system time.
+    fn cycle_info(&self, wkc: u16, time: Option<u64>) -> Result<(u16, CycleInfo), Error> {
+        let time = time.ok_or(Error::Internal)?;
 
         // Nanoseconds from the start of the cycle. This works because the first SYNC0 pulse
         // time is rounded to a whole number of `sync0_period`-length cycles.
@@ -1197,3 +2478,975 @@ where
         ))
     }
 }
+
+/// Configure SYNC0, and SYNC1 if required, pulse generation for a single SubDevice.
+///
+/// `start_delay` and `sync0_period` are in nanoseconds.
+async fn configure_subdevice_dc_sync<S>(
+    subdevice: &SubDeviceRef<'_, S>,
+    maindevice: &MainDevice<'_>,
+    start_delay: u64,
+    sync0_period: u64,
+) -> Result<(), Error>
+where
+    S: Deref<Target = SubDevice>,
+{
+    fmt::debug!(
+        "--> Configuring SubDevice {:#06x} {} DC mode {}",
+        subdevice.configured_address(),
+        subdevice.name(),
+        subdevice.dc_sync()
+    );
+
+    // Disable cyclic op, ignore WKC
+    subdevice
+        .write(RegisterAddress::DcSyncActive)
+        .ignore_wkc()
+        .send(maindevice, 0u8)
+        .await?;
+
+    // Write access to EtherCAT
+    subdevice
+        .write(RegisterAddress::DcCyclicUnitControl)
+        .send(maindevice, 0u8)
+        .await?;
+
+    let device_time: u64 = subdevice
+        .read(RegisterAddress::DcSystemTime)
+        .ignore_wkc()
+        .receive(maindevice)
+        .await?;
+
+    fmt::debug!("--> Device time {} ns", device_time);
+
+    // Round first pulse time to a whole number of cycles
+    let start_time = (device_time + start_delay) / sync0_period * sync0_period;
+
+    fmt::debug!("--> Computed DC sync start time: {}", start_time);
+
+    subdevice
+        .write(RegisterAddress::DcSyncStartTime)
+        .send(maindevice, start_time)
+        .await?;
+
+    // Cycle time in nanoseconds
+    subdevice
+        .write(RegisterAddress::DcSync0CycleTime)
+        .send(maindevice, sync0_period)
+        .await?;
+
+    let flags = if let DcSync::Sync01 { sync1_period } = subdevice.dc_sync() {
+        subdevice
+            .write(RegisterAddress::DcSync1CycleTime)
+            .send(maindevice, sync1_period.as_nanos() as u64)
+            .await?;
+
+        SYNC1_ACTIVATE | SYNC0_ACTIVATE | CYCLIC_OP_ENABLE
+    } else {
+        SYNC0_ACTIVATE | CYCLIC_OP_ENABLE
+    };
+
+    subdevice
+        .write(RegisterAddress::DcSyncActive)
+        .send(maindevice, flags)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        sim::{SimNetwork, SimSubDevice, FPRD, FPWR, LRD, LRW, LWR},
+        AlStatusCode, PduStorage, TransitionRetry,
+    };
+
+    const DC_REFERENCE: u16 = 0x1000;
+
+    /// Frames small enough that the PDI of a few SubDevices is split across them, but large enough
+    /// to clear FMMU registers during init.
+    type SmallStorage = PduStorage<4, { PduStorage::element_size(300) }>;
+
+    type TestGroup = SubDeviceGroup<8, 512, Op>;
+
+    /// The inputs of the simulated SubDevice at `position`.
+    fn inputs(position: usize) -> Vec<u8> {
+        (0..20).map(|i| (position * 20 + i) as u8 ^ 0x5a).collect()
+    }
+
+    /// The outputs written to the SubDevice at `position`.
+    fn outputs(position: usize) -> Vec<u8> {
+        (0..30).map(|i| (position * 30 + i) as u8).collect()
+    }
+
+    /// Eight SubDevices with 20 bytes of inputs and 30 bytes of outputs each, for a 400 byte PDI
+    /// with 160 bytes of inputs.
+    fn subdevices() -> Vec<SimSubDevice> {
+        (0..8)
+            .map(|position| {
+                let mut subdevice = SimSubDevice::new(1, position as u32)
+                    .inputs(160)
+                    .outputs(240);
+
+                subdevice.set_inputs(&inputs(position));
+
+                subdevice
+            })
+            .collect()
+    }
+
+    /// Initialise all SubDevices into one group and take it to OP.
+    fn op_group<'sto>(
+        maindevice: &'sto MainDevice<'sto>,
+        network: &mut SimNetwork<'_>,
+        config: GroupConfig,
+    ) -> TestGroup {
+        let mut group = network
+            .run(maindevice.init_single_group::<8, 512>(|| 0))
+            .expect("init");
+
+        group.set_config(config);
+
+        let group = network.run(group.into_op(maindevice)).expect("OP");
+
+        for position in 0..group.len() {
+            let mut subdevice = group.subdevice(maindevice, position).expect("SubDevice");
+            let pdi_outputs = subdevice.outputs_raw_mut();
+            let len = pdi_outputs.len();
+
+            pdi_outputs.copy_from_slice(&outputs(position)[..len]);
+        }
+
+        network.clear_log();
+
+        group
+    }
+
+    fn lrd_lwr() -> GroupConfig {
+        GroupConfig {
+            cyclic_mode: CyclicMode::LrdLwr,
+            ..GroupConfig::default()
+        }
+    }
+
+    /// Exchange the group PDI with the simulated network.
+    fn exchange<'sto>(
+        maindevice: &'sto MainDevice<'sto>,
+        network: &mut SimNetwork<'_>,
+        group: &TestGroup,
+        host_clock: Option<fn() -> u64>,
+    ) -> PdiExchange {
+        network
+            .run(group.tx_rx_chunked(maindevice, host_clock.map(|_| DC_REFERENCE), host_clock))
+            .expect("exchange")
+    }
+
+    /// Check inputs were read into the PDI and outputs were written to the network.
+    fn assert_pdi(maindevice: &MainDevice<'_>, network: &SimNetwork<'_>, group: &TestGroup) {
+        for (position, subdevice) in network.subdevices.iter().enumerate() {
+            let pdi = group.subdevice(maindevice, position).expect("SubDevice");
+
+            assert_eq!(pdi.inputs_raw(), inputs(position), "inputs of {}", position);
+            assert_eq!(
+                subdevice.outputs_memory(),
+                outputs(position),
+                "outputs of {}",
+                position
+            );
+        }
+    }
+
+    /// Check that `ranges` cover `expected` with no gaps or overlaps.
+    fn assert_contiguous(ranges: &[Range<u32>], expected: Range<u32>) {
+        assert_eq!(ranges.first().map(|r| r.start), Some(expected.start));
+        assert_eq!(ranges.last().map(|r| r.end), Some(expected.end));
+
+        for pair in ranges.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start, "{:?}", ranges);
+        }
+    }
+
+    #[test]
+    fn lrw_split_across_frames() {
+        let storage = SmallStorage::new();
+        let (maindevice, mut network) = SimNetwork::new(&storage, subdevices());
+        let group = op_group(&maindevice, &mut network, GroupConfig::default());
+
+        let exchange = exchange(&maindevice, &mut network, &group, None);
+
+        let lrws = network.ranges(LRW);
+
+        assert!(network.frames > 1, "PDI should be split");
+        assert_eq!(lrws.len(), network.pdus.len());
+        assert_contiguous(&lrws, 0..400);
+
+        // One LRW per frame
+        assert_eq!(lrws.len(), network.frames);
+        assert_eq!(exchange.wkc, exchange.expected_wkc);
+        assert_eq!(exchange.wkc, group.expected_wkc());
+
+        assert_pdi(&maindevice, &network, &group);
+    }
+
+    #[test]
+    fn lrd_lwr_split_across_frames() {
+        let storage = SmallStorage::new();
+        let (maindevice, mut network) = SimNetwork::new(&storage, subdevices());
+        let group = op_group(&maindevice, &mut network, lrd_lwr());
+
+        let exchange = exchange(&maindevice, &mut network, &group, None);
+
+        let lrds = network.ranges(LRD);
+        let lwrs = network.ranges(LWR);
+
+        assert!(network.frames > 1, "PDI should be split");
+        assert_contiguous(&lrds, 0..160);
+        assert_contiguous(&lwrs, 160..400);
+
+        // The last inputs and first outputs share a frame
+        let last_lrd_frame = network.pdus.iter().rfind(|pdu| pdu.command == LRD);
+        let first_lwr_frame = network.pdus.iter().find(|pdu| pdu.command == LWR);
+
+        assert_eq!(
+            last_lrd_frame.map(|pdu| pdu.frame),
+            first_lwr_frame.map(|pdu| pdu.frame)
+        );
+
+        // LWR working counters are scaled to match LRW
+        assert_eq!(exchange.wkc, exchange.expected_wkc);
+        assert_eq!(exchange.wkc, group.expected_wkc());
+
+        assert_pdi(&maindevice, &network, &group);
+    }
+
+    #[test]
+    fn fewer_free_frames_than_chunks() {
+        let storage = SmallStorage::new();
+        let (maindevice, mut network) = SimNetwork::new(&storage, subdevices());
+        let group = op_group(&maindevice, &mut network, GroupConfig::default());
+
+        // Claim every frame, e.g. as if other tasks had frames in flight, then release one
+        let mut held =
+            core::iter::from_fn(|| maindevice.pdu_loop.alloc_frame().ok()).collect::<Vec<_>>();
+
+        let mut frame = held.pop().expect("free frame");
+
+        frame
+            .push_pdu(Command::brd(0).into(), 0u8, None)
+            .expect("push");
+
+        network
+            .run(frame.mark_sendable(&maindevice.pdu_loop, maindevice.timeouts.pdu, 0))
+            .expect("release");
+
+        network.clear_log();
+
+        let exchange = exchange(&maindevice, &mut network, &group, None);
+
+        assert!(network.frames > 1);
+        assert_contiguous(&network.ranges(LRW), 0..400);
+        assert_eq!(exchange.wkc, exchange.expected_wkc);
+        assert_eq!(exchange.wkc, group.expected_wkc());
+
+        assert_pdi(&maindevice, &network, &group);
+    }
+
+    #[test]
+    fn host_clock_written_to_reference() {
+        fn now() -> u64 {
+            0x0012_3456_789a_bcde
+        }
+
+        let storage = SmallStorage::new();
+        let (maindevice, mut network) = SimNetwork::new(&storage, subdevices());
+        let group = op_group(&maindevice, &mut network, GroupConfig::default());
+
+        exchange(&maindevice, &mut network, &group, Some(now));
+
+        let address =
+            u32::from(DC_REFERENCE) | u32::from(u16::from(RegisterAddress::DcSystemTime)) << 16;
+
+        let fpwrs = network
+            .pdus
+            .iter()
+            .filter(|pdu| pdu.command == FPWR)
+            .map(|pdu| (pdu.address, pdu.data.clone(), pdu.frame))
+            .collect::<Vec<_>>();
+
+        // Lower 32 bits of the host time, in the first frame only
+        assert_eq!(
+            fpwrs,
+            &[(address, 0x789a_bcdeu32.to_le_bytes().to_vec(), 0)]
+        );
+
+        assert_pdi(&maindevice, &network, &group);
+    }
+
+    #[test]
+    fn bit_packed_raw_slices() {
+        let storage = PduStorage::<4, { PduStorage::element_size(1100) }>::new();
+        let (maindevice, mut network) = SimNetwork::new(
+            &storage,
+            vec![
+                SimSubDevice::new(1, 0).inputs(4),
+                SimSubDevice::new(1, 1).inputs(4),
+                SimSubDevice::new(1, 2).outputs(4),
+                SimSubDevice::new(1, 3).outputs(4),
+            ],
+        );
+
+        network.subdevices[0].set_inputs(&[0b1001]);
+        network.subdevices[1].set_inputs(&[0b0110]);
+
+        let group = op_group(
+            &maindevice,
+            &mut network,
+            GroupConfig {
+                bit_packed_pdi: true,
+                ..GroupConfig::default()
+            },
+        );
+
+        // Each pair of SubDevices shares a byte
+        assert_eq!(group.read_pdi_len, 1);
+        assert_eq!(group.pdi_len, 2);
+
+        for position in 0..group.len() {
+            let mut subdevice = group.subdevice(&maindevice, position).expect("SubDevice");
+
+            // Whole bytes would include the other SubDevice's bits
+            assert_eq!(subdevice.inputs_raw(), &[], "inputs of {}", position);
+            assert_eq!(subdevice.outputs_raw_mut(), &[], "outputs of {}", position);
+        }
+
+        for (position, bit) in [(2, 0), (3, 3)] {
+            let mut subdevice = group.subdevice(&maindevice, position).expect("SubDevice");
+
+            subdevice
+                .output_bits_mut()
+                .set(bit, true)
+                .expect("set output");
+        }
+
+        assert_eq!(network.run(group.tx_rx(&maindevice)), Ok(6));
+
+        let input_bits = |position| {
+            group
+                .subdevice(&maindevice, position)
+                .expect("SubDevice")
+                .input_bits()
+                .iter()
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(input_bits(0), [true, false, false, true]);
+        assert_eq!(input_bits(1), [false, true, true, false]);
+
+        assert_eq!(network.subdevices[2].outputs_memory(), &[0b0001]);
+        assert_eq!(network.subdevices[3].outputs_memory(), &[0b1000]);
+    }
+
+    /// A SubDevice with only inputs, one with only outputs, and one with both.
+    fn mixed_subdevices() -> Vec<SimSubDevice> {
+        vec![
+            SimSubDevice::new(1, 0).inputs(16),
+            SimSubDevice::new(1, 1).outputs(8),
+            SimSubDevice::new(1, 2).inputs(8).outputs(8),
+        ]
+    }
+
+    #[test]
+    fn expected_wkc_lrw() {
+        let storage = PduStorage::<4, { PduStorage::element_size(1100) }>::new();
+        let (maindevice, mut network) = SimNetwork::new(&storage, mixed_subdevices());
+        let group = op_group(&maindevice, &mut network, GroupConfig::default());
+
+        assert_eq!(group.expected_wkc(), 1 + 2 + 3);
+        assert_eq!(network.run(group.tx_rx(&maindevice)), Ok(6));
+        assert_eq!(network.run(group.tx_rx_checked(&maindevice)), Ok(6));
+        assert_eq!(network.ranges(LRW).len(), 2);
+    }
+
+    #[test]
+    fn expected_wkc_lrd_lwr() {
+        let storage = PduStorage::<4, { PduStorage::element_size(1100) }>::new();
+        let (maindevice, mut network) = SimNetwork::new(&storage, mixed_subdevices());
+        let group = op_group(&maindevice, &mut network, lrd_lwr());
+
+        assert_eq!(group.expected_wkc(), 1 + 2 + 3);
+        assert_eq!(network.run(group.tx_rx(&maindevice)), Ok(6));
+        assert_eq!(network.run(group.tx_rx_checked(&maindevice)), Ok(6));
+        assert_eq!(network.ranges(LRD).len(), 2);
+        assert_eq!(network.ranges(LWR).len(), 2);
+    }
+
+    #[test]
+    fn tx_rx_checked_missing_subdevice() {
+        let storage = PduStorage::<4, { PduStorage::element_size(1100) }>::new();
+        let (maindevice, mut network) = SimNetwork::new(&storage, mixed_subdevices());
+        let group = op_group(&maindevice, &mut network, GroupConfig::default());
+
+        // The SubDevice with only outputs is disconnected
+        network.subdevices.remove(1);
+
+        assert_eq!(network.run(group.tx_rx(&maindevice)), Ok(4));
+        assert_eq!(
+            network.run(group.tx_rx_checked(&maindevice)),
+            Err(Error::WorkingCounter {
+                expected: 6,
+                received: 4
+            })
+        );
+    }
+
+    #[test]
+    fn tx_rx_checked_split_across_frames() {
+        let storage = SmallStorage::new();
+        let (maindevice, mut network) = SimNetwork::new(&storage, subdevices());
+        let group = op_group(&maindevice, &mut network, GroupConfig::default());
+
+        assert_eq!(network.run(group.tx_rx_checked(&maindevice)), Ok(24));
+        assert!(network.frames > 1, "PDI should be split");
+
+        // The last SubDevice is disconnected
+        network.subdevices.truncate(7);
+
+        assert_eq!(
+            network.run(group.tx_rx_checked(&maindevice)),
+            Err(Error::WorkingCounter {
+                expected: 24,
+                received: 21
+            })
+        );
+    }
+
+    fn overlapping(cyclic_mode: CyclicMode) -> GroupConfig {
+        GroupConfig {
+            overlapping_pdi: true,
+            cyclic_mode,
+            ..GroupConfig::default()
+        }
+    }
+
+    /// Mixed SubDevices with inputs set, in an overlapping group in OP.
+    fn overlapping_group<'sto>(
+        maindevice: &'sto MainDevice<'sto>,
+        network: &mut SimNetwork<'_>,
+        cyclic_mode: CyclicMode,
+    ) -> TestGroup {
+        network.subdevices[0].set_inputs(&[0xaa, 0xbb]);
+        network.subdevices[2].set_inputs(&[0xcc]);
+
+        op_group(maindevice, network, overlapping(cyclic_mode))
+    }
+
+    /// Check the inputs and outputs of the mixed SubDevices were exchanged.
+    fn assert_mixed_pdi(maindevice: &MainDevice<'_>, network: &SimNetwork<'_>, group: &TestGroup) {
+        let inputs = |position| {
+            group
+                .subdevice(maindevice, position)
+                .map(|subdevice| subdevice.inputs_raw().to_vec())
+        };
+
+        assert_eq!(inputs(0), Ok(vec![0xaa, 0xbb]));
+        assert_eq!(inputs(2), Ok(vec![0xcc]));
+
+        assert_eq!(network.subdevices[1].outputs_memory(), &outputs(1)[..1]);
+        assert_eq!(network.subdevices[2].outputs_memory(), &outputs(2)[..1]);
+    }
+
+    #[test]
+    fn overlapping_fmmu_layout() {
+        let storage = PduStorage::<4, { PduStorage::element_size(1100) }>::new();
+        let (maindevice, mut network) = SimNetwork::new(&storage, mixed_subdevices());
+        let group = op_group(&maindevice, &mut network, overlapping(CyclicMode::Lrw));
+
+        let fmmus = network
+            .subdevices
+            .iter()
+            .map(|subdevice| {
+                let mapped = |kind| subdevice.fmmu(kind).filter(|range| !range.is_empty());
+
+                (mapped(0x01), mapped(0x02))
+            })
+            .collect::<Vec<_>>();
+
+        // Each SubDevice starts after the longer mapping of the one before it, and its inputs and
+        // outputs start at the same logical address.
+        assert_eq!(
+            fmmus,
+            &[
+                (Some(0..2), None),
+                (None, Some(2..3)),
+                (Some(3..4), Some(3..4))
+            ]
+        );
+
+        // Inputs then outputs in group memory, each taking the logical length
+        assert_eq!(group.read_pdi_len, 4);
+        assert_eq!(group.pdi_len, 8);
+    }
+
+    #[test]
+    fn overlapping_lrw() {
+        let storage = PduStorage::<4, { PduStorage::element_size(1100) }>::new();
+        let (maindevice, mut network) = SimNetwork::new(&storage, mixed_subdevices());
+        let group = overlapping_group(&maindevice, &mut network, CyclicMode::Lrw);
+
+        assert_eq!(network.run(group.tx_rx_checked(&maindevice)), Ok(6));
+
+        // Only the logical length is sent
+        assert_eq!(network.ranges(LRW), &[0..4]);
+
+        assert_mixed_pdi(&maindevice, &network, &group);
+    }
+
+    #[test]
+    fn overlapping_lrd_lwr() {
+        let storage = PduStorage::<4, { PduStorage::element_size(1100) }>::new();
+        let (maindevice, mut network) = SimNetwork::new(&storage, mixed_subdevices());
+        let group = overlapping_group(&maindevice, &mut network, CyclicMode::LrdLwr);
+
+        assert_eq!(network.run(group.tx_rx_checked(&maindevice)), Ok(6));
+
+        assert_eq!(network.ranges(LRD), &[0..4]);
+        assert_eq!(network.ranges(LWR), &[0..4]);
+
+        assert_mixed_pdi(&maindevice, &network, &group);
+    }
+
+    #[test]
+    fn overlapping_needs_twice_max_pdi() {
+        let storage = PduStorage::<4, { PduStorage::element_size(1100) }>::new();
+        let (maindevice, mut network) = SimNetwork::new(&storage, subdevices());
+
+        // 8 SubDevices need 240 logical bytes, which fits in MAX_PDI but twice that doesn't
+        let mut group = network
+            .run(maindevice.init_single_group::<8, 256>(|| 0))
+            .expect("init");
+
+        group.set_config(overlapping(CyclicMode::Lrw));
+
+        assert_eq!(
+            network.run(group.into_safe_op(&maindevice)).map(|_| ()),
+            Err(Error::PdiTooLong {
+                max_length: 256,
+                desired_length: 300,
+            })
+        );
+
+        // Configuration stopped at the first SubDevice that didn't fit
+        assert!(network.subdevices[4].fmmu(0x01).is_some());
+        assert!(network.subdevices[5].fmmu(0x01).is_none());
+    }
+
+    /// Exchange the mixed SubDevices' PDI in place with a zero-copy frame.
+    fn zero_copy_round_trip(config: GroupConfig) {
+        let storage = PduStorage::<16, { PduStorage::element_size(1100) }>::new();
+        let (maindevice, mut network) = SimNetwork::new(&storage, mixed_subdevices());
+
+        network.subdevices[0].set_inputs(&[0xaa, 0xbb]);
+        network.subdevices[2].set_inputs(&[0xcc]);
+
+        let group = op_group(&maindevice, &mut network, config);
+
+        let mut frame = group.pdi_tx_frame(&maindevice).expect("frame");
+
+        frame.subdevice_outputs_mut(1).expect("SubDevice 1")[0] = 0x11;
+        frame.subdevice_outputs_mut(2).expect("SubDevice 2")[0] = 0x22;
+
+        let response = network.run(frame.send()).expect("send");
+
+        assert_eq!(response.check_wkc(), Ok(6));
+        assert_eq!(response.subdevice_inputs(0), Ok([0xaa, 0xbb].as_slice()));
+        assert_eq!(response.subdevice_inputs(2), Ok([0xcc].as_slice()));
+
+        drop(response);
+
+        assert_eq!(network.subdevices[1].outputs_memory(), &[0x11]);
+        assert_eq!(network.subdevices[2].outputs_memory(), &[0x22]);
+
+        // The SubDevice with only outputs is disconnected
+        network.subdevices.remove(1);
+
+        let frame = group.pdi_tx_frame(&maindevice).expect("frame");
+        let response = network.run(frame.send()).expect("send");
+
+        assert_eq!(response.wkc(), 4);
+
+        drop(response);
+
+        // The mismatch is recorded like it is for `tx_rx`
+        let mut events = Vec::new();
+
+        network
+            .run(HealthMonitor::<8>::new().check(&group, &maindevice, |event| events.push(event)))
+            .expect("check");
+
+        assert!(events.contains(&HealthEvent::WorkingCounter {
+            count: 1,
+            expected: 6,
+            received: 4
+        }));
+    }
+
+    #[test]
+    fn zero_copy_lrw() {
+        zero_copy_round_trip(GroupConfig::default());
+    }
+
+    #[test]
+    fn zero_copy_lrd_lwr() {
+        zero_copy_round_trip(lrd_lwr());
+    }
+
+    /// The result of moving a group of three SubDevices to SAFE-OP when the second refuses with
+    /// `refusals`, the refused attempts reported, and the values written to its AL control
+    /// register.
+    fn refused_safe_op(
+        transition_retry: TransitionRetry,
+        refusals: &[u16],
+    ) -> (
+        Result<(), Error>,
+        Vec<TransitionFailure>,
+        Vec<u8>,
+        SimSubDevice,
+    ) {
+        let storage = PduStorage::<16, { PduStorage::element_size(1100) }>::new();
+        let (maindevice, mut network) = SimNetwork::new(&storage, mixed_subdevices());
+        let maindevice = maindevice.with_transition_retry(transition_retry);
+
+        let group = network
+            .run(maindevice.init_single_group::<8, 512>(|| 0))
+            .expect("init");
+
+        network.subdevices[1].refusals = refusals.iter().map(|code| (0x04, *code)).collect();
+        network.clear_log();
+
+        let mut failures = Vec::new();
+
+        let result = network
+            .run(group.into_safe_op_with(&maindevice, |failure| failures.push(failure)))
+            .map(|_group| ());
+
+        let al_control = al_control_writes(&network, 0x1001);
+
+        (result, failures, al_control, network.subdevices.remove(1))
+    }
+
+    fn failure(attempt: usize, code: AlStatusCode) -> TransitionFailure {
+        TransitionFailure {
+            configured_address: 0x1001,
+            state: SubDeviceState::SafeOp,
+            attempt,
+            code,
+        }
+    }
+
+    #[test]
+    fn refused_transition_retried() {
+        let (result, failures, al_control, subdevice) =
+            refused_safe_op(TransitionRetry::Count(2), &[0x001d, 0x001e]);
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            failures,
+            [
+                failure(0, AlStatusCode::InvalidOutputConfiguration),
+                failure(1, AlStatusCode::InvalidInputConfiguration)
+            ]
+        );
+
+        // The group request is refused, then the error is acknowledged in PRE-OP before every retry
+        assert_eq!(al_control, [0x04, 0x04, 0x12, 0x04, 0x12, 0x04]);
+        assert_eq!(subdevice.state(), 0x04);
+        assert!(!subdevice.al_error());
+    }
+
+    #[test]
+    fn refused_transition_retries_exhausted() {
+        let (result, failures, al_control, subdevice) =
+            refused_safe_op(TransitionRetry::Count(1), &[0x001d, 0x001e, 0x001f]);
+
+        assert_eq!(
+            result,
+            Err(Error::SubDevice(AlStatusCode::InvalidInputConfiguration))
+        );
+        assert_eq!(
+            failures,
+            [
+                failure(0, AlStatusCode::InvalidOutputConfiguration),
+                failure(1, AlStatusCode::InvalidInputConfiguration)
+            ]
+        );
+        assert_eq!(al_control, [0x04, 0x04, 0x12, 0x04, 0x12]);
+        assert_eq!(subdevice.state(), 0x02);
+        assert!(!subdevice.al_error());
+    }
+
+    #[test]
+    fn refused_transition_count_zero() {
+        let (result, failures, al_control, subdevice) =
+            refused_safe_op(TransitionRetry::Count(0), &[0x001d]);
+
+        assert_eq!(
+            result,
+            Err(Error::SubDevice(AlStatusCode::InvalidOutputConfiguration))
+        );
+        assert_eq!(
+            failures,
+            [failure(0, AlStatusCode::InvalidOutputConfiguration)]
+        );
+
+        // Acknowledged but not retried
+        assert_eq!(al_control, [0x04, 0x04, 0x12]);
+        assert_eq!(subdevice.state(), 0x02);
+        assert!(!subdevice.al_error());
+    }
+
+    #[test]
+    fn refused_transition_not_retried() {
+        let (result, failures, al_control, subdevice) =
+            refused_safe_op(TransitionRetry::None, &[0x001d]);
+
+        assert_eq!(
+            result,
+            Err(Error::SubDevice(AlStatusCode::InvalidOutputConfiguration))
+        );
+        assert_eq!(
+            failures,
+            [failure(0, AlStatusCode::InvalidOutputConfiguration)]
+        );
+
+        // The error is left for the application to acknowledge
+        assert_eq!(al_control, [0x04, 0x04]);
+        assert!(subdevice.al_error());
+    }
+
+    #[test]
+    fn pdo_entries() {
+        let storage = PduStorage::<4, { PduStorage::element_size(1100) }>::new();
+        let (maindevice, mut network) = SimNetwork::new(&storage, mixed_subdevices());
+
+        network.subdevices[0].set_inputs(&[0x34, 0x12]);
+        network.subdevices[2].set_inputs(&[0xab]);
+
+        let mut group = op_group(&maindevice, &mut network, GroupConfig::default());
+
+        // Entries are only read when asked for
+        assert!(group.inner().pdo_entries.is_empty());
+
+        {
+            let subdevice = group.subdevice(&maindevice, 0).expect("SubDevice");
+
+            assert!(matches!(
+                subdevice.input_entry(0x6000, 1),
+                Err(Error::NotFound {
+                    item: Item::PdoEntry,
+                    index: Some(0x6000)
+                })
+            ));
+
+            // Looking up a name reads the SubDevice's layout on demand
+            let entry = network
+                .run(subdevice.input_entry_by_name("Input"))
+                .expect("by name");
+
+            assert_eq!(
+                (entry.index(), entry.sub_index(), entry.bit_len()),
+                (0x6000, 1, 16)
+            );
+        }
+
+        network
+            .run(group.read_pdo_entries(&maindevice))
+            .expect("read PDO entries");
+
+        // One input entry for each SubDevice with inputs, one output entry for each with outputs
+        assert_eq!(group.inner().pdo_entries.len(), 4);
+
+        {
+            let mut subdevice = group.subdevice(&maindevice, 2).expect("SubDevice");
+
+            let entries = subdevice
+                .input_entries()
+                .map(|entry| (entry.index(), entry.sub_index(), entry.bit_len()))
+                .collect::<Vec<_>>();
+
+            assert_eq!(entries, [(0x6000, 1, 8)]);
+
+            let mut outputs = subdevice.output_entries_mut().collect::<Vec<_>>();
+
+            assert_eq!(outputs.len(), 1);
+            assert_eq!(outputs[0].index(), 0x7000);
+
+            outputs[0].write(0x5au8).expect("write");
+        }
+
+        assert_eq!(network.run(group.tx_rx(&maindevice)), Ok(6));
+        assert_eq!(network.subdevices[2].outputs_memory(), &[0x5a]);
+
+        let mut subdevice = group.subdevice(&maindevice, 0).expect("SubDevice");
+
+        assert_eq!(subdevice.output_entries_mut().count(), 0);
+
+        let entry = network
+            .run(subdevice.input_entry_by_name("Input"))
+            .expect("by name");
+
+        assert_eq!((entry.index(), entry.sub_index()), (0x6000, 1));
+        assert_eq!(entry.read::<u16>(), Ok(0x1234));
+
+        assert!(matches!(
+            network.run(subdevice.input_entry_by_name("Output")),
+            Err(Error::NotFound {
+                item: Item::PdoEntry,
+                index: None
+            })
+        ));
+
+        drop(subdevice);
+
+        let mut subdevice = group.subdevice(&maindevice, 1).expect("SubDevice");
+
+        assert!(network
+            .run(subdevice.output_entry_by_name_mut("Output"))
+            .is_ok());
+    }
+
+    /// The values written to the AL control register of the SubDevice at `configured_address`.
+    fn al_control_writes(network: &SimNetwork<'_>, configured_address: u16) -> Vec<u8> {
+        network
+            .pdus
+            .iter()
+            .filter(|pdu| {
+                pdu.command == FPWR && pdu.address == (0x0120 << 16 | u32::from(configured_address))
+            })
+            .map(|pdu| pdu.data[0])
+            .collect()
+    }
+
+    #[test]
+    fn device_ids_requested() {
+        let storage = PduStorage::<16, { PduStorage::element_size(1100) }>::new();
+        let (maindevice, mut network) = SimNetwork::new(
+            &storage,
+            vec![
+                SimSubDevice::new(1, 0).inputs(8).device_id(0x0100),
+                SimSubDevice::new(1, 1).outputs(8).device_id(0x0101),
+            ],
+        );
+
+        let mut group = network
+            .run(maindevice.init_single_group::<8, 512>(|| 0))
+            .expect("init");
+
+        // IDs are copied into the group, so e.g. a list loaded at runtime can be dropped
+        let ids = vec![0x0100, 0x0101];
+
+        group.set_device_ids(&ids).expect("IDs");
+
+        drop(ids);
+
+        network.clear_log();
+
+        let group = network
+            .run(group.into_safe_op(&maindevice))
+            .expect("SAFE-OP");
+
+        // ID requested in PRE-OP and the request cleared before going to SAFE-OP
+        assert_eq!(al_control_writes(&network, 0x1001), [0x22, 0x02, 0x04]);
+        assert_eq!(network.subdevices[1].state(), 0x04);
+
+        let id = network.run(async { group.subdevice(&maindevice, 1)?.explicit_device_id().await });
+
+        assert_eq!(id, Ok(0x0101));
+    }
+
+    #[test]
+    fn device_ids_mismatch() {
+        let storage = PduStorage::<16, { PduStorage::element_size(1100) }>::new();
+        let (maindevice, mut network) = SimNetwork::new(
+            &storage,
+            vec![
+                SimSubDevice::new(1, 0).inputs(8).device_id(0x0100),
+                SimSubDevice::new(1, 1).outputs(8).device_id(0x0101),
+            ],
+        );
+
+        let mut group = network
+            .run(maindevice.init_single_group::<8, 512>(|| 0))
+            .expect("init");
+
+        group.set_device_ids(&[0x0100, 0x0999]).expect("IDs");
+
+        let result = network
+            .run(group.into_safe_op(&maindevice))
+            .map(|_group| ());
+
+        assert_eq!(
+            result,
+            Err(Error::DeviceIdMismatch {
+                configured_address: 0x1001,
+                expected: 0x0999,
+                actual: 0x0101
+            })
+        );
+        assert_eq!(network.subdevices[1].state(), 0x02);
+        assert!(!network.subdevices[1].al_error());
+    }
+
+    #[test]
+    fn device_ids_alias_fallback() {
+        let storage = PduStorage::<16, { PduStorage::element_size(1100) }>::new();
+        let (maindevice, mut network) = SimNetwork::new(
+            &storage,
+            vec![SimSubDevice::new(1, 0).inputs(8).alias(0x0042)],
+        );
+
+        let mut group = network
+            .run(maindevice.init_single_group::<8, 512>(|| 0))
+            .expect("init");
+
+        group.set_device_ids(&[0x0042]).expect("IDs");
+
+        network.clear_log();
+
+        let group = network.run(group.into_safe_op(&maindevice));
+
+        assert!(group.is_ok());
+
+        // ID read from the station alias register without an ID request
+        assert_eq!(al_control_writes(&network, 0x1000), [0x04]);
+        assert!(network
+            .pdus
+            .iter()
+            .any(|pdu| pdu.command == FPRD && pdu.address == (0x0012 << 16 | 0x1000)));
+    }
+
+    #[test]
+    fn device_ids_capacity() {
+        let mut group = SubDeviceGroup::<2, 64>::default();
+
+        assert_eq!(group.set_device_ids(&[1, 2]), Ok(()));
+        assert_eq!(
+            group.set_device_ids(&[1, 2, 3]),
+            Err(Error::Capacity(Item::SubDevice))
+        );
+    }
+
+    #[test]
+    fn heap_hot_connect_group() {
+        let group = HeapSubDeviceGroup::hot_connect_with_capacity(0x1234, 3, 64);
+
+        assert_eq!(group.inner().hot_connect_alias, Some(0x1234));
+        assert_eq!(group.max_pdi_len(), 64);
+
+        // Detaching keeps the heap capacity
+        let group = group.detach();
+
+        assert_eq!(group.inner().hot_connect_alias, Some(0x1234));
+        assert_eq!(group.max_pdi_len(), 64);
+    }
+}
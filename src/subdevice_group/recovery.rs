@@ -0,0 +1,566 @@
+//! Recover individual SubDevices in a running group.
+
+use super::{configure_subdevice_dc_sync, DcState, HasPdi, SubDeviceGroup};
+use crate::{
+    command::Command,
+    error::{Error, Item},
+    fmt,
+    hooks::HookTransition,
+    pdi::PdiOffset,
+    subdevice::{
+        configuration::{identity_mismatch, PdoDirection},
+        SubDevice, SubDeviceRef,
+    },
+    DcSync, MainDevice, RegisterAddress, SubDeviceState,
+};
+use atomic_refcell::AtomicRefMut;
+use core::future::Future;
+
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC, M>
+    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, DC, M>
+where
+    S: HasPdi,
+    DC: DcState,
+{
+    /// Reconfigure a single SubDevice that has dropped out of the network, e.g. after losing power,
+    /// and bring it back to the group's current state.
+    ///
+    /// If the SubDevice lost its configured address, it is first looked for at the network
+    /// position it was discovered at during initialisation. SubDevices before it may have been
+    /// removed or power cycled since, so if another SubDevice is found there, every other SubDevice
+    /// without a configured address is checked until one with the same identity is found. Its
+    /// configured address, mailbox sync managers, DC system time offset and propagation delay,
+    /// watchdogs, FMMUs and DC sync configuration are then written again.
+    /// If the DC reference SubDevice is available, static drift compensation is then run for
+    /// [`MainDeviceConfig::dc_static_sync_iterations`](crate::MainDeviceConfig::dc_static_sync_iterations)
+    /// iterations, as during initialisation.
+    ///
+    /// Any PDO configuration done in PRE-OP, e.g. with
+    /// [`SubDeviceRef::configure_pdos`](crate::SubDeviceRef::configure_pdos) or SDO writes, is lost
+    /// when a SubDevice loses power. The `configure` closure is called once the SubDevice is back
+    /// in PRE-OP to repeat this configuration, and must return the SubDevice it was given so
+    /// recovery can continue. The resulting process data layout must be the same as before, so the
+    /// SubDevice's inputs and outputs are mapped to the same place in the group PDI.
+    ///
+    /// Hooks registered with `MainDevice::add_hook` are run as they would be during
+    /// initialisation. [`InitToPreOp`](crate::HookTransition::InitToPreOp) hooks run once the
+    /// SubDevice is back in PRE-OP, before `configure` is called.
+    /// [`PreOpToSafeOp`](crate::HookTransition::PreOpToSafeOp) hooks run after `configure`, and
+    /// [`SafeOpToOp`](crate::HookTransition::SafeOpToOp) hooks run before the SubDevice is
+    /// requested to go to OP.
+    ///
+    /// The SubDevice is borrowed for the whole recovery sequence, so
+    /// [`SubDeviceGroup::subdevice`] returns [`Error::Borrow`] for it until recovery finishes.
+    /// Other SubDevices in the group are not touched, so the group's `tx_rx*` methods may continue
+    /// to be called from another task while recovery is in progress. The working counter will not
+    /// match the expected value until the SubDevice is back in the group's state.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::NotFound`] if `index` is out of range of the group, or the SubDevice is not found
+    ///   on the network.
+    /// - [`Error::Borrow`] if the SubDevice is currently borrowed.
+    /// - [`Error::TopologyMismatch`] if a different product or revision is found at the SubDevice's
+    ///   network position and the SubDevice is not found anywhere else.
+    /// - [`Error::Internal`] if `configure` returns a different SubDevice to the one it was given.
+    /// - [`Error::DeviceIdMismatch`] if an Explicit Device ID was set with
+    ///   [`set_device_ids`](SubDeviceGroup::set_device_ids) and a different ID is found.
+    /// - [`Error::PdiLayoutChanged`] if the SubDevice's inputs or outputs are a different size
+    ///   after reconfiguration. The SubDevice's FMMUs are cleared so it does not overwrite other
+    ///   SubDevices' process data.
+    ///
+    /// # Examples
+    ///
+    /// Recover a SubDevice, repeating its PDO configuration.
+    ///
+    /// ```rust,no_run
+    /// use ethercrab::{
+    ///     error::Error, std::ethercat_now, MainDevice, MainDeviceConfig, PduStorage, PdoMapping,
+    ///     Timeouts,
+    /// };
+    ///
+    /// static PDU_STORAGE: PduStorage<16, { PduStorage::element_size(1100) }> = PduStorage::new();
+    ///
+    /// let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    ///
+    /// let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+    ///
+    /// let mapping = PdoMapping::new()
+    ///     .rx_pdo(0x1600)
+    ///     .entry(0x6040, 0, 16)
+    ///     .tx_pdo(0x1a00)
+    ///     .entry(0x6041, 0, 16);
+    ///
+    /// # async {
+    /// let group = maindevice
+    ///     .init_single_group::<16, 64>(ethercat_now)
+    ///     .await
+    ///     .expect("Init")
+    ///     .into_op(&maindevice)
+    ///     .await
+    ///     .expect("PRE-OP -> OP");
+    ///
+    /// // ...SubDevice 2 drops out...
+    ///
+    /// group
+    ///     .recover_subdevice(&maindevice, 2, |mut subdevice| async move {
+    ///         subdevice.configure_pdos(&mapping).await?;
+    ///
+    ///         Ok(subdevice)
+    ///     })
+    ///     .await?;
+    /// # Ok::<(), Error>(())
+    /// # };
+    /// ```
+    pub async fn recover_subdevice<'maindevice, 'group, F, Fut>(
+        &'group self,
+        maindevice: &'maindevice MainDevice<'maindevice>,
+        index: usize,
+        configure: F,
+    ) -> Result<(), Error>
+    where
+        F: FnOnce(SubDeviceRef<'maindevice, AtomicRefMut<'group, SubDevice>>) -> Fut,
+        Fut: Future<
+            Output = Result<SubDeviceRef<'maindevice, AtomicRefMut<'group, SubDevice>>, Error>,
+        >,
+    {
+        let inner = self.inner();
+
+        let cell = inner.subdevices.get(index).ok_or(Error::NotFound {
+            item: Item::SubDevice,
+            index: Some(index),
+        })?;
+
+        let mut subdevice = cell.try_borrow_mut().map_err(|_e| {
+            fmt::error!("SubDevice index {} already borrowed", index);
+
+            Error::Borrow
+        })?;
+
+        let configured_address = subdevice.configured_address();
+        let previous_io = subdevice.io_segments().clone();
+
+        fmt::info!(
+            "Recovering SubDevice {:#06x} at position {}",
+            configured_address,
+            subdevice.index
+        );
+
+        restore_address(maindevice, &mut subdevice, index).await?;
+
+        let dc_time_source = self.dc_time_source(maindevice, configured_address);
+
+        SubDeviceRef::new(maindevice, configured_address, &mut *subdevice)
+            .reinit(dc_time_source)
+            .await?;
+
+        if let Some(expected) = inner.device_ids.get(index) {
+            SubDeviceRef::new(maindevice, configured_address, ())
+                .check_device_id(*expected)
+                .await?;
+        }
+
+        let configured =
+            configure(SubDeviceRef::new(maindevice, configured_address, subdevice)).await?;
+
+        if configured.configured_address() != configured_address {
+            fmt::error!(
+                "Recovery of SubDevice {:#06x} was given SubDevice {:#06x} back",
+                configured_address,
+                configured.configured_address()
+            );
+
+            return Err(Error::Internal);
+        }
+
+        let mut subdevice = configured.into_state();
+
+        if let Some(watchdog) = inner.config.watchdog {
+            SubDeviceRef::new(maindevice, configured_address, ())
+                .configure_watchdogs(watchdog)
+                .await?;
+        }
+
+        maindevice
+            .run_hooks(HookTransition::PreOpToSafeOp, &mut subdevice)
+            .await?;
+
+        // Map inputs and outputs back to the same logical addresses as before
+        let group_start = inner.pdi_start.start_address;
+
+        let output_offset = if inner.config.overlapping_pdi {
+            self.read_pdi_len
+        } else {
+            0
+        };
+
+        let input_start = PdiOffset {
+            start_address: group_start + previous_io.input.bytes.start as u32,
+            start_bit: previous_io.input.start_bit,
+        };
+
+        let output_start = PdiOffset {
+            start_address: group_start + (previous_io.output.bytes.start - output_offset) as u32,
+            start_bit: previous_io.output.start_bit,
+        };
+
+        let remapped = async {
+            let mut subdevice_ref =
+                SubDeviceRef::new(maindevice, configured_address, &mut *subdevice);
+
+            // The group's PDO entries are kept, as the layout must not change
+            subdevice_ref
+                .configure_fmmus(
+                    input_start,
+                    group_start,
+                    PdoDirection::MasterRead,
+                    inner.config.bit_packed_pdi,
+                )
+                .await?;
+
+            subdevice_ref
+                .configure_fmmus(
+                    output_start,
+                    group_start,
+                    PdoDirection::MasterWrite,
+                    inner.config.bit_packed_pdi,
+                )
+                .await?;
+
+            let output = &mut subdevice.config.io.output.bytes;
+
+            *output = (output.start + output_offset)..(output.end + output_offset);
+
+            if subdevice.io_segments() != &previous_io {
+                fmt::error!(
+                    "SubDevice {:#06x} process data layout changed. Was I: {}, O: {}, now I: {}, O: {}",
+                    configured_address,
+                    previous_io.input,
+                    previous_io.output,
+                    subdevice.io_segments().input,
+                    subdevice.io_segments().output
+                );
+
+                return Err(Error::PdiLayoutChanged { configured_address });
+            }
+
+            Ok(())
+        }
+        .await;
+
+        if let Err(e) = remapped {
+            // The FMMUs may now map this SubDevice over other SubDevices' process data, so clear
+            // them and leave the SubDevice out of the PDI exchange.
+            subdevice.config.io = previous_io;
+
+            SubDeviceRef::new(maindevice, configured_address, ())
+                .write(RegisterAddress::Fmmu0)
+                .send(maindevice, [0u8; 256])
+                .await?;
+
+            return Err(e);
+        }
+
+        let subdevice_ref = SubDeviceRef::new(maindevice, configured_address, &*subdevice);
+
+        if let Some(dc_conf) = self.dc_conf.dc_conf() {
+            if subdevice_ref.dc_support().any()
+                && !matches!(subdevice_ref.dc_sync(), DcSync::Disabled)
+            {
+                configure_subdevice_dc_sync(
+                    &subdevice_ref,
+                    maindevice,
+                    dc_conf.start_delay,
+                    dc_conf.sync0_period,
+                )
+                .await?;
+            }
+        }
+
+        if matches!(S::STATE, SubDeviceState::SafeOp | SubDeviceState::Op) {
+            subdevice_ref
+                .request_subdevice_state(SubDeviceState::SafeOp)
+                .await?;
+        }
+
+        if S::STATE == SubDeviceState::Op {
+            maindevice
+                .run_hooks(HookTransition::SafeOpToOp, &mut subdevice)
+                .await?;
+
+            let subdevice_ref = SubDeviceRef::new(maindevice, configured_address, &*subdevice);
+
+            subdevice_ref
+                .request_subdevice_state(SubDeviceState::Op)
+                .await?;
+        }
+
+        fmt::info!(
+            "SubDevice {:#06x} recovered into {}",
+            configured_address,
+            S::STATE
+        );
+
+        Ok(())
+    }
+
+    /// Find a SubDevice to read DC system time from when restoring a recovered SubDevice's system
+    /// time offset.
+    ///
+    /// This is the DC reference SubDevice, unless it is the SubDevice being recovered, in which
+    /// case any other SubDevice in the group that supports DC is used.
+    fn dc_time_source(&self, maindevice: &MainDevice<'_>, recovering: u16) -> Option<u16> {
+        maindevice
+            .dc_ref_address()
+            .filter(|reference| *reference != recovering)
+            .or_else(|| {
+                self.inner()
+                    .subdevices
+                    .iter()
+                    .filter_map(|subdevice| subdevice.try_borrow().ok())
+                    .find(|subdevice| {
+                        subdevice.configured_address() != recovering && subdevice.dc_support().any()
+                    })
+                    .map(|subdevice| subdevice.configured_address())
+            })
+    }
+}
+
+/// Give a SubDevice that lost power its configured address back, updating its network position if
+/// it has moved.
+///
+/// The position recorded during initialisation is checked first, then every other SubDevice
+/// without a configured address. A SubDevice is only given the address if its identity matches.
+async fn restore_address(
+    maindevice: &MainDevice<'_>,
+    subdevice: &mut SubDevice,
+    index: usize,
+) -> Result<(), Error> {
+    let configured_address = subdevice.configured_address();
+    let recorded = subdevice.index;
+
+    // SubDevices lose their configured address when they lose power
+    let has_address = Command::fprd(
+        configured_address,
+        RegisterAddress::ConfiguredStationAddress.into(),
+    )
+    .receive_wkc::<u16>(maindevice)
+    .await?
+        > 0;
+
+    if has_address {
+        return Ok(());
+    }
+
+    let num_subdevices = maindevice.count_subdevices().await?;
+
+    let positions = core::iter::once(recorded)
+        .chain((0..num_subdevices).filter(|position| *position != recorded))
+        .filter(|position| *position < num_subdevices);
+
+    // Identity of an unknown SubDevice without an address at the recorded position
+    let mut replaced_by = None;
+
+    for position in positions {
+        let found_address =
+            Command::aprd(position, RegisterAddress::ConfiguredStationAddress.into())
+                .receive::<u16>(maindevice)
+                .await?;
+
+        // A SubDevice that is still configured cannot be the one that lost power
+        if found_address != 0 {
+            continue;
+        }
+
+        Command::apwr(position, RegisterAddress::ConfiguredStationAddress.into())
+            .send(maindevice, configured_address)
+            .await?;
+
+        let found = SubDeviceRef::new(maindevice, configured_address, ())
+            .read_identity()
+            .await?;
+
+        if found == subdevice.identity {
+            if position != recorded {
+                fmt::info!(
+                    "SubDevice {:#06x} moved from position {} to {}",
+                    configured_address,
+                    recorded,
+                    position
+                );
+            }
+
+            subdevice.index = position;
+
+            return Ok(());
+        }
+
+        // Another SubDevice that lost power, so leave it unconfigured
+        Command::apwr(position, RegisterAddress::ConfiguredStationAddress.into())
+            .send(maindevice, 0u16)
+            .await?;
+
+        if position == recorded {
+            replaced_by = Some(found);
+        }
+    }
+
+    if let Some(found) = replaced_by {
+        fmt::error!(
+            "SubDevice {:#06x} at position {} was replaced by {}",
+            configured_address,
+            recorded,
+            found
+        );
+
+        return Err(identity_mismatch(recorded, subdevice.identity, found));
+    }
+
+    fmt::error!(
+        "SubDevice {:#06x} not found on the network",
+        configured_address
+    );
+
+    Err(Error::NotFound {
+        item: Item::SubDevice,
+        index: Some(index),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        sim::{SimNetwork, SimSubDevice},
+        subdevice_group::Op,
+        PduStorage, TopologyMismatch,
+    };
+
+    type Storage = PduStorage<16, { PduStorage::element_size(1100) }>;
+
+    fn subdevices() -> Vec<SimSubDevice> {
+        vec![
+            SimSubDevice::new(1, 0).inputs(8),
+            SimSubDevice::new(1, 1).inputs(8).outputs(8),
+            SimSubDevice::new(1, 2).inputs(8),
+        ]
+    }
+
+    fn op_group<'sto>(
+        maindevice: &'sto MainDevice<'sto>,
+        network: &mut SimNetwork<'_>,
+    ) -> SubDeviceGroup<4, 64, Op> {
+        let group = network
+            .run(maindevice.init_single_group::<4, 64>(|| 0))
+            .expect("init");
+
+        network.run(group.into_op(maindevice)).expect("OP")
+    }
+
+    fn recover(
+        network: &mut SimNetwork<'_>,
+        maindevice: &MainDevice<'_>,
+        group: &SubDeviceGroup<4, 64, Op>,
+        index: usize,
+    ) -> Result<(), Error> {
+        network.run(group.recover_subdevice(maindevice, index, |subdevice| async { Ok(subdevice) }))
+    }
+
+    #[test]
+    fn power_cycle() {
+        let storage = Storage::new();
+        let (maindevice, mut network) = SimNetwork::new(&storage, subdevices());
+        let group = op_group(&maindevice, &mut network);
+
+        network.subdevices[1].power_cycle();
+        network.subdevices[1].set_inputs(&[0xa5]);
+
+        assert_eq!(network.subdevices[1].station_address(), 0);
+
+        group
+            .subdevice(&maindevice, 1)
+            .expect("SubDevice")
+            .outputs_raw_mut()
+            .copy_from_slice(&[0x3c]);
+
+        assert_eq!(recover(&mut network, &maindevice, &group, 1), Ok(()));
+
+        assert_eq!(network.subdevices[1].station_address(), 0x1001);
+        assert_eq!(network.subdevices[1].state(), 0x08);
+
+        assert_eq!(
+            network.run(group.tx_rx(&maindevice)),
+            Ok(group.expected_wkc())
+        );
+
+        let subdevice = group.subdevice(&maindevice, 1).expect("SubDevice");
+
+        assert_eq!(subdevice.inputs_raw(), &[0xa5]);
+        assert_eq!(network.subdevices[1].outputs_memory(), &[0x3c]);
+    }
+
+    #[test]
+    fn moved_subdevice() {
+        let storage = Storage::new();
+        let (maindevice, mut network) = SimNetwork::new(&storage, subdevices());
+        let group = op_group(&maindevice, &mut network);
+
+        // Unplug the second SubDevice, moving the third to its position, then power cycle the
+        // third.
+        network.subdevices.remove(1);
+        network.subdevices[1].power_cycle();
+
+        assert_eq!(recover(&mut network, &maindevice, &group, 2), Ok(()));
+
+        assert_eq!(network.subdevices[1].station_address(), 0x1002);
+        assert_eq!(network.subdevices[1].state(), 0x08);
+        assert_eq!(group.inner().subdevices[2].borrow().index, 1);
+    }
+
+    #[test]
+    fn identity_changed() {
+        let storage = Storage::new();
+        let (maindevice, mut network) = SimNetwork::new(&storage, subdevices());
+        let group = op_group(&maindevice, &mut network);
+
+        network.subdevices[1] = SimSubDevice::new(9, 1).inputs(8).outputs(8);
+
+        assert!(matches!(
+            recover(&mut network, &maindevice, &group, 1),
+            Err(Error::TopologyMismatch {
+                first: TopologyMismatch::WrongProduct { expected, found },
+                count: 1,
+            }) if expected.position == 1 && expected.product_id == 1 && found.product_id == 9
+        ));
+
+        // The replacement SubDevice is not given the recovered SubDevice's address
+        assert_eq!(network.subdevices[1].station_address(), 0);
+        assert_eq!(network.subdevices[1].state(), 0x01);
+    }
+
+    #[test]
+    fn layout_changed() {
+        let storage = Storage::new();
+        let (maindevice, mut network) = SimNetwork::new(&storage, subdevices());
+        let group = op_group(&maindevice, &mut network);
+
+        network.subdevices[1].power_cycle();
+        network.subdevices[1].input_bits = 16;
+
+        assert_eq!(
+            recover(&mut network, &maindevice, &group, 1),
+            Err(Error::PdiLayoutChanged {
+                configured_address: 0x1001
+            })
+        );
+
+        // FMMUs are cleared so the SubDevice does not overwrite its neighbours' inputs
+        assert_eq!(network.subdevices[1].fmmu(0x01), None);
+        assert_eq!(network.subdevices[1].fmmu(0x02), None);
+
+        // The SubDevice's previous layout is kept
+        let subdevice = group.subdevice(&maindevice, 1).expect("SubDevice");
+
+        assert_eq!(subdevice.inputs_raw().len(), 1);
+    }
+}
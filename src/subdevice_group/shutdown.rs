@@ -0,0 +1,627 @@
+//! Step SubDevices down to INIT, collecting per-SubDevice failures.
+
+use super::{storage::GroupList, HasPdi, Init, PreOp, SubDeviceGroup};
+use crate::{
+    al_control::AlControl,
+    error::{Error, Item},
+    fmt,
+    subdevice::{SubDevice, SubDeviceRef},
+    timer_factory::IntoTimeout,
+    DcSync, MainDevice, RegisterAddress, SubDeviceState,
+};
+use core::future::Future;
+
+/// A SubDevice that could not be shut down cleanly.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ShutdownFailure {
+    /// SubDevice configured station address.
+    pub configured_address: u16,
+    /// The state the SubDevice failed to reach.
+    pub state: SubDeviceState,
+    /// The error encountered.
+    pub error: Error,
+}
+
+/// The SubDevices that could not be shut down cleanly.
+///
+/// Only the first failure of each SubDevice is recorded. A SubDevice that fails to reach one state
+/// is still requested to go to the following states.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShutdownReport<const MAX_SUBDEVICES: usize> {
+    failures: GroupList<ShutdownFailure, MAX_SUBDEVICES>,
+}
+
+impl<const MAX_SUBDEVICES: usize> ShutdownReport<MAX_SUBDEVICES> {
+    pub(crate) fn new() -> Self {
+        Self {
+            failures: GroupList::default(),
+        }
+    }
+
+    /// A report with room for every SubDevice in `subdevices`.
+    fn for_subdevices<T>(subdevices: &GroupList<T, MAX_SUBDEVICES>) -> Self {
+        Self {
+            failures: subdevices.empty_like(),
+        }
+    }
+
+    /// Returns `true` if every SubDevice reached INIT without error.
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// SubDevices that failed to shut down cleanly.
+    pub fn failures(&self) -> &[ShutdownFailure] {
+        &self.failures
+    }
+
+    fn record(&mut self, configured_address: u16, state: SubDeviceState, error: Error) {
+        fmt::error!(
+            "SubDevice {:#06x} failed to shut down to {}: {}",
+            configured_address,
+            state,
+            error
+        );
+
+        if self
+            .failures
+            .iter()
+            .any(|failure| failure.configured_address == configured_address)
+        {
+            return;
+        }
+
+        // A report holds as many SubDevices as are being shut down so will never be full
+        let _ = self.failures.push(ShutdownFailure {
+            configured_address,
+            state,
+            error,
+        });
+    }
+
+    /// Request every SubDevice above `state` to go to `state` and wait for them to reach it,
+    /// calling `cycle` while waiting.
+    ///
+    /// SubDevices already in or below `state` are left alone. If `skip_missing` is true,
+    /// addresses that no SubDevice responds to are skipped instead of being recorded as failures.
+    pub(crate) async fn step_down<F, Fut>(
+        &mut self,
+        maindevice: &MainDevice<'_>,
+        addresses: impl Iterator<Item = u16> + Clone,
+        state: SubDeviceState,
+        skip_missing: bool,
+        mut cycle: F,
+    ) where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        fmt::debug!("Shutting down into {}", state);
+
+        let mut pending = self.failures.empty_like::<(u16, Error)>();
+
+        for configured_address in addresses {
+            let subdevice = SubDeviceRef::new(maindevice, configured_address, ());
+
+            let current = match subdevice
+                .read(RegisterAddress::AlStatus)
+                .receive::<AlControl>(maindevice)
+                .await
+            {
+                Ok(status) => status.state,
+                Err(Error::WorkingCounter { received: 0, .. }) if skip_missing => {
+                    fmt::debug!("--> No SubDevice at {:#06x}", configured_address);
+
+                    continue;
+                }
+                Err(e) => {
+                    self.record(configured_address, state, e);
+
+                    continue;
+                }
+            };
+
+            if !is_above(current, state) {
+                continue;
+            }
+
+            if let Err(e) = subdevice.request_subdevice_state_nowait(state).await {
+                self.record(configured_address, state, e);
+
+                continue;
+            }
+
+            if pending.push((configured_address, Error::Timeout)).is_err() {
+                self.record(configured_address, state, Error::Capacity(Item::SubDevice));
+            }
+        }
+
+        let wait = async {
+            while !pending.is_empty() {
+                cycle().await;
+
+                let mut idx = 0;
+
+                while let Some((configured_address, last_error)) = pending.get_mut(idx) {
+                    match SubDeviceRef::new(maindevice, *configured_address, ())
+                        .state()
+                        .await
+                    {
+                        Ok(current) if current == state => {
+                            pending.swap_remove(idx);
+
+                            continue;
+                        }
+                        Ok(_) => (),
+                        Err(e) => *last_error = e,
+                    }
+
+                    idx += 1;
+                }
+
+                maindevice.timeouts.loop_tick().await;
+            }
+
+            Ok::<(), Error>(())
+        };
+
+        if wait
+            .timeout(maindevice.timeouts.state_transition)
+            .await
+            .is_err()
+        {
+            for (configured_address, error) in pending {
+                self.record(configured_address, state, error);
+            }
+        }
+    }
+}
+
+/// Whether a SubDevice in `current` must be requested to go down to `target`.
+fn is_above(current: SubDeviceState, target: SubDeviceState) -> bool {
+    fn level(state: SubDeviceState) -> u8 {
+        match state {
+            SubDeviceState::Init => 0,
+            SubDeviceState::PreOp => 1,
+            SubDeviceState::SafeOp => 2,
+            SubDeviceState::Op => 3,
+            // Unknown states are always stepped down
+            _ => u8::MAX,
+        }
+    }
+
+    match current {
+        // BOOT can only go back to INIT
+        SubDeviceState::Bootstrap => target == SubDeviceState::Init,
+        current => level(current) > level(target),
+    }
+}
+
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC, M>
+    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, DC, M>
+{
+    /// Configured station addresses of every SubDevice in the group.
+    fn addresses(&self) -> impl Iterator<Item = u16> + Clone + '_ {
+        self.inner()
+            .subdevices
+            .iter()
+            .map(|subdevice| subdevice.borrow().configured_address())
+    }
+
+    /// Step every SubDevice down to PRE-OP, deactivate DC SYNC signal generation, then step every
+    /// SubDevice down to INIT, returning the group in INIT.
+    async fn shutdown_from_pre_op(
+        self,
+        maindevice: &MainDevice<'_>,
+        mut report: ShutdownReport<MAX_SUBDEVICES>,
+    ) -> (
+        SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Init, DC, M>,
+        ShutdownReport<MAX_SUBDEVICES>,
+    ) {
+        report
+            .step_down(
+                maindevice,
+                self.addresses(),
+                SubDeviceState::PreOp,
+                false,
+                || async {},
+            )
+            .await;
+
+        for subdevice in self.inner().subdevices.iter() {
+            let subdevice = subdevice.borrow();
+
+            let subdevice =
+                SubDeviceRef::new(maindevice, subdevice.configured_address(), &*subdevice);
+
+            if !subdevice.dc_support().any() || matches!(subdevice.dc_sync(), DcSync::Disabled) {
+                continue;
+            }
+
+            fmt::debug!(
+                "Deactivating DC sync for SubDevice {:#06x}",
+                subdevice.configured_address()
+            );
+
+            if let Err(e) = subdevice
+                .write(RegisterAddress::DcSyncActive)
+                .send(maindevice, 0u8)
+                .await
+            {
+                report.record(subdevice.configured_address(), SubDeviceState::PreOp, e);
+            }
+        }
+
+        report
+            .step_down(
+                maindevice,
+                self.addresses(),
+                SubDeviceState::Init,
+                false,
+                || async {},
+            )
+            .await;
+
+        fmt::debug!(
+            "Group shutdown complete with {} failure(s)",
+            report.failures().len()
+        );
+
+        let SubDeviceGroup {
+            id,
+            pdi,
+            read_pdi_len,
+            pdi_len,
+            inner,
+            dc_conf,
+            ..
+        } = self;
+
+        (
+            SubDeviceGroup {
+                id,
+                pdi,
+                read_pdi_len,
+                pdi_len,
+                inner,
+                dc_conf,
+                _state: core::marker::PhantomData,
+            },
+            report,
+        )
+    }
+}
+
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, DC, M>
+    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, PreOp, DC, M>
+{
+    /// Gracefully step all SubDevices in the group down to INIT.
+    ///
+    /// SubDevices are requested to go to PRE-OP in case any were moved out of it individually, DC
+    /// SYNC signal generation is deactivated for all SubDevices that use it, then SubDevices are
+    /// requested to go to INIT.
+    ///
+    /// A SubDevice that fails any step is recorded in the returned [`ShutdownReport`], but the
+    /// sequence continues for every SubDevice.
+    ///
+    /// Groups in SAFE-OP or OP have their own `shutdown` method, which also sets outputs to safe
+    /// values and steps SubDevices down through SAFE-OP first.
+    pub async fn shutdown(
+        self,
+        maindevice: &MainDevice<'_>,
+    ) -> (
+        SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Init, DC, M>,
+        ShutdownReport<MAX_SUBDEVICES>,
+    ) {
+        let report = ShutdownReport::for_subdevices(&self.inner().subdevices);
+
+        self.shutdown_from_pre_op(maindevice, report).await
+    }
+}
+
+impl<const MAX_SUBDEVICES: usize, const MAX_PDI: usize, S, DC, M>
+    SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, S, DC, M>
+where
+    S: HasPdi,
+{
+    /// Gracefully step all SubDevices in the group down to INIT, from whichever state the group is
+    /// in.
+    ///
+    /// The shutdown sequence is:
+    ///
+    /// 1. If the group is in SAFE-OP or OP, `safe_outputs` is called for every SubDevice with its
+    ///    outputs so they can be set to safe values, e.g. zeroed, and the group's process data is
+    ///    exchanged once.
+    /// 2. If the group is in OP, SubDevices are requested to go to SAFE-OP. Process data keeps
+    ///    being exchanged until every SubDevice has left OP so outputs stay valid and SubDevice
+    ///    watchdogs do not trip.
+    /// 3. SubDevices are requested to go to PRE-OP.
+    /// 4. DC SYNC signal generation is deactivated for all SubDevices that use it.
+    /// 5. SubDevices are requested to go to INIT.
+    ///
+    /// A SubDevice that fails any step is recorded in the returned [`ShutdownReport`], but the
+    /// sequence continues for every SubDevice.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use ethercrab::{
+    ///     std::ethercat_now, MainDevice, MainDeviceConfig, PduStorage, Timeouts,
+    /// };
+    ///
+    /// static PDU_STORAGE: PduStorage<16, { PduStorage::element_size(1100) }> = PduStorage::new();
+    ///
+    /// let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+    ///
+    /// let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+    ///
+    /// # async {
+    /// let group = maindevice
+    ///     .init_single_group::<16, 64>(ethercat_now)
+    ///     .await
+    ///     .expect("Init")
+    ///     .into_op(&maindevice)
+    ///     .await
+    ///     .expect("PRE-OP -> OP");
+    ///
+    /// // Cyclic application runs here
+    ///
+    /// let (_group, report) = group
+    ///     .shutdown(&maindevice, |_subdevice, outputs| outputs.fill(0))
+    ///     .await;
+    ///
+    /// for failure in report.failures() {
+    ///     eprintln!(
+    ///         "SubDevice {:#06x} failed to go to {}: {}",
+    ///         failure.configured_address, failure.state, failure.error
+    ///     );
+    /// }
+    /// # };
+    /// ```
+    pub async fn shutdown(
+        self,
+        maindevice: &MainDevice<'_>,
+        mut safe_outputs: impl FnMut(&SubDevice, &mut [u8]),
+    ) -> (
+        SubDeviceGroup<MAX_SUBDEVICES, MAX_PDI, Init, DC, M>,
+        ShutdownReport<MAX_SUBDEVICES>,
+    ) {
+        let mut report = ShutdownReport::for_subdevices(&self.inner().subdevices);
+
+        if matches!(S::STATE, SubDeviceState::SafeOp | SubDeviceState::Op) {
+            let pdi = self.pdi_mut();
+
+            for subdevice in self.inner().subdevices.iter() {
+                let subdevice = subdevice.borrow();
+
+                let outputs = &subdevice.io_segments().output.bytes;
+
+                if let Some(outputs) = pdi.get_mut(outputs.clone()) {
+                    safe_outputs(&subdevice, outputs);
+                }
+            }
+
+            self.shutdown_cycle(maindevice).await;
+        }
+
+        if S::STATE == SubDeviceState::Op {
+            report
+                .step_down(
+                    maindevice,
+                    self.addresses(),
+                    SubDeviceState::SafeOp,
+                    false,
+                    || self.shutdown_cycle(maindevice),
+                )
+                .await;
+        }
+
+        self.shutdown_from_pre_op(maindevice, report).await
+    }
+
+    /// Exchange process data during shutdown. Errors are ignored as failing SubDevices are
+    /// detected by their state.
+    async fn shutdown_cycle(&self, maindevice: &MainDevice<'_>) {
+        if let Err(e) = self.tx_rx_chunked(maindevice, None, None).await {
+            fmt::warn!("Process data exchange failed during shutdown: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        sim::{SimNetwork, SimSubDevice, FPWR, LRW},
+        subdevice_group::Op,
+        PduStorage,
+    };
+
+    type Storage = PduStorage<16, { PduStorage::element_size(1100) }>;
+
+    const AL_CONTROL: u32 = 0x0120;
+    const DC_SYNC_ACTIVE: u32 = 0x0981;
+
+    fn subdevices() -> Vec<SimSubDevice> {
+        vec![
+            SimSubDevice::new(1, 0).outputs(8),
+            SimSubDevice::new(1, 1).inputs(8),
+            SimSubDevice::new(1, 2).outputs(8),
+        ]
+    }
+
+    /// The index of the first FPWR to `register` of the SubDevice at `configured_address` with
+    /// `value` as its first byte.
+    fn fpwr(network: &SimNetwork<'_>, configured_address: u16, register: u32, value: u8) -> usize {
+        network
+            .pdus
+            .iter()
+            .position(|pdu| {
+                pdu.command == FPWR
+                    && pdu.address == (register << 16 | u32::from(configured_address))
+                    && pdu.data[0] == value
+            })
+            .expect("write not found")
+    }
+
+    #[test]
+    fn sequence() {
+        let storage = Storage::new();
+        let (maindevice, mut network) = SimNetwork::new(&storage, subdevices());
+
+        let group = network
+            .run(maindevice.init_single_group::<4, 64>(|| 0))
+            .expect("init");
+
+        // The first SubDevice has DC SYNC0 active
+        {
+            let mut subdevice = group.inner().subdevices[0].borrow_mut();
+
+            subdevice.flags.dc_supported = true;
+            subdevice.dc_sync = DcSync::Sync0;
+        }
+
+        network.subdevices[0].dc = true;
+        network.subdevices[0].memory[DC_SYNC_ACTIVE as usize] = 0x03;
+
+        let group: SubDeviceGroup<4, 64, Op> = network.run(group.into_op(&maindevice)).expect("OP");
+
+        for position in [0, 2] {
+            group
+                .subdevice(&maindevice, position)
+                .expect("SubDevice")
+                .outputs_raw_mut()
+                .fill(0x55);
+        }
+
+        network.run(group.tx_rx(&maindevice)).expect("tx_rx");
+
+        // The last SubDevice never leaves OP
+        network.subdevices[2].stuck = true;
+
+        for subdevice in network.subdevices.iter_mut() {
+            subdevice.requested_states.clear();
+        }
+
+        network.clear_log();
+
+        let (_group, report) =
+            network.run(group.shutdown(&maindevice, |_subdevice, outputs| outputs.fill(0)));
+
+        assert_eq!(
+            report.failures(),
+            &[ShutdownFailure {
+                configured_address: 0x1002,
+                state: SubDeviceState::SafeOp,
+                error: Error::Timeout
+            }]
+        );
+
+        // Safe outputs are written before leaving OP
+        assert_eq!(network.subdevices[0].outputs_memory(), &[0]);
+        assert_eq!(network.subdevices[2].outputs_memory(), &[0]);
+        assert_eq!(network.pdus[0].command, LRW);
+
+        // The stuck SubDevice is still requested to go to every state
+        for subdevice in network.subdevices.iter() {
+            assert_eq!(subdevice.requested_states, [0x04, 0x02, 0x01]);
+        }
+
+        assert_eq!(network.subdevices[0].state(), 0x01);
+        assert_eq!(network.subdevices[1].state(), 0x01);
+        assert_eq!(network.subdevices[2].state(), 0x08);
+
+        // DC SYNC is deactivated between PRE-OP and INIT, only for the SubDevice that uses it
+        let pre_op = fpwr(&network, 0x1000, AL_CONTROL, 0x02);
+        let dc_off = fpwr(&network, 0x1000, DC_SYNC_ACTIVE, 0x00);
+        let init = fpwr(&network, 0x1000, AL_CONTROL, 0x01);
+
+        assert!(pre_op < dc_off && dc_off < init);
+        assert_eq!(network.subdevices[0].memory[DC_SYNC_ACTIVE as usize], 0x00);
+        assert_eq!(
+            network
+                .pdus
+                .iter()
+                .filter(|pdu| pdu.address >> 16 == DC_SYNC_ACTIVE)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn pre_op_group() {
+        let storage = Storage::new();
+        let (maindevice, mut network) = SimNetwork::new(&storage, subdevices());
+
+        let group = network
+            .run(maindevice.init_single_group::<4, 64>(|| 0))
+            .expect("init");
+
+        network.subdevices[1].stuck = true;
+
+        let (_group, report) = network.run(group.shutdown(&maindevice));
+
+        assert_eq!(
+            report.failures(),
+            &[ShutdownFailure {
+                configured_address: 0x1001,
+                state: SubDeviceState::Init,
+                error: Error::Timeout
+            }]
+        );
+
+        assert_eq!(network.subdevices[0].state(), 0x01);
+        assert_eq!(network.subdevices[1].state(), 0x02);
+        assert_eq!(network.subdevices[2].state(), 0x01);
+    }
+
+    #[test]
+    fn only_step_down() {
+        assert!(is_above(SubDeviceState::Op, SubDeviceState::SafeOp));
+        assert!(is_above(SubDeviceState::SafeOp, SubDeviceState::PreOp));
+        assert!(is_above(SubDeviceState::Other(0x05), SubDeviceState::Init));
+
+        // Never step up
+        assert!(!is_above(SubDeviceState::PreOp, SubDeviceState::SafeOp));
+        assert!(!is_above(SubDeviceState::Init, SubDeviceState::SafeOp));
+        assert!(!is_above(SubDeviceState::Init, SubDeviceState::Init));
+
+        assert!(!is_above(SubDeviceState::Bootstrap, SubDeviceState::PreOp));
+        assert!(is_above(SubDeviceState::Bootstrap, SubDeviceState::Init));
+    }
+
+    #[test]
+    fn first_failure_per_subdevice() {
+        let mut report = ShutdownReport::<4>::new();
+
+        assert!(report.is_ok());
+
+        report.record(0x1000, SubDeviceState::SafeOp, Error::Timeout);
+        report.record(0x1001, SubDeviceState::PreOp, Error::Timeout);
+        report.record(
+            0x1000,
+            SubDeviceState::Init,
+            Error::WorkingCounter {
+                expected: 1,
+                received: 0,
+            },
+        );
+
+        assert!(!report.is_ok());
+        assert_eq!(
+            report.failures(),
+            &[
+                ShutdownFailure {
+                    configured_address: 0x1000,
+                    state: SubDeviceState::SafeOp,
+                    error: Error::Timeout
+                },
+                ShutdownFailure {
+                    configured_address: 0x1001,
+                    state: SubDeviceState::PreOp,
+                    error: Error::Timeout
+                }
+            ]
+        );
+    }
+}
@@ -0,0 +1,300 @@
+//! Lock-free triple-buffered copies of a group's process data.
+
+use core::{
+    cell::UnsafeCell,
+    sync::atomic::{AtomicBool, AtomicU8, Ordering},
+};
+
+/// Set in the shared buffer index when the buffer holds data the reader has not seen yet.
+const FRESH: u8 = 0b1000_0000;
+
+/// Mask to get the buffer index out of the shared buffer index.
+const INDEX_MASK: u8 = 0b0000_0011;
+
+/// One buffer of a [`PdiSnapshot`].
+struct Slot<const N: usize> {
+    data: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> Slot<N> {
+    const fn new() -> Self {
+        Self {
+            data: [0u8; N],
+            len: 0,
+        }
+    }
+}
+
+/// Triple-buffered storage used to pass process data between a cyclic task and non-realtime
+/// tasks without blocking or tearing.
+///
+/// A snapshot is split into a single [`SnapshotWriter`] and a single [`SnapshotReader`] which may
+/// be used from different threads. The writer always has a buffer to write into, and the reader
+/// always sees the most recently published complete buffer. Older buffers that were never read
+/// are overwritten.
+///
+/// Snapshots are used in two directions:
+///
+/// - Latest inputs: the cyclic task calls
+///   [`SubDeviceGroup::publish_inputs`](crate::SubDeviceGroup::publish_inputs) after each
+///   `tx_rx*` and e.g. a UI thread reads them with [`SnapshotReader::latest`].
+/// - Next outputs: e.g. a UI thread stages outputs with [`SnapshotWriter::publish`] and the cyclic
+///   task copies them into the group before its next `tx_rx*` with
+///   [`SubDeviceGroup::apply_staged_outputs`](crate::SubDeviceGroup::apply_staged_outputs).
+///
+/// `N` must be at least the length of the data being passed, e.g. the group's `MAX_PDI`.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use ethercrab::{
+///     std::ethercat_now, subdevice_group::PdiSnapshot, MainDevice, MainDeviceConfig, PduStorage,
+///     Timeouts,
+/// };
+///
+/// static PDU_STORAGE: PduStorage<16, { PduStorage::element_size(1100) }> = PduStorage::new();
+/// static INPUTS: PdiSnapshot<64> = PdiSnapshot::new();
+///
+/// let (_tx, _rx, pdu_loop) = PDU_STORAGE.try_split().expect("can only split once");
+/// let (mut inputs_writer, mut inputs_reader) = INPUTS.try_split().expect("can only split once");
+///
+/// let maindevice = MainDevice::new(pdu_loop, Timeouts::default(), MainDeviceConfig::default());
+///
+/// std::thread::spawn(move || loop {
+///     println!("Inputs: {:?}", inputs_reader.latest());
+///
+///     std::thread::sleep(std::time::Duration::from_millis(100));
+/// });
+///
+/// # async {
+/// let group = maindevice
+///     .init_single_group::<16, 64>(ethercat_now)
+///     .await
+///     .expect("Init")
+///     .into_op(&maindevice)
+///     .await
+///     .expect("PRE-OP -> OP");
+///
+/// loop {
+///     group.tx_rx(&maindevice).await.expect("TX/RX");
+///
+///     group
+///         .publish_inputs(&mut inputs_writer)
+///         .expect("Snapshot too small");
+/// }
+/// # };
+/// ```
+pub struct PdiSnapshot<const N: usize> {
+    slots: [UnsafeCell<Slot<N>>; 3],
+    /// The index of the buffer shared between the writer and reader, and the [`FRESH`] flag.
+    shared: AtomicU8,
+    is_split: AtomicBool,
+}
+
+// SAFETY: The writer and reader only ever access the buffer they own. Ownership of the shared
+// buffer is exchanged atomically.
+unsafe impl<const N: usize> Sync for PdiSnapshot<N> {}
+
+impl<const N: usize> Default for PdiSnapshot<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> PdiSnapshot<N> {
+    /// Create new, empty snapshot storage.
+    pub const fn new() -> Self {
+        Self {
+            slots: [
+                UnsafeCell::new(Slot::new()),
+                UnsafeCell::new(Slot::new()),
+                UnsafeCell::new(Slot::new()),
+            ],
+            // The writer starts with buffer 0 and the reader with buffer 2
+            shared: AtomicU8::new(1),
+            is_split: AtomicBool::new(false),
+        }
+    }
+
+    /// Split the snapshot into its writer and reader halves.
+    ///
+    /// # Errors
+    ///
+    /// To make sure there is only ever one writer and one reader, `try_split` will return an error
+    /// if called more than once on any given `PdiSnapshot`.
+    #[allow(clippy::result_unit_err)]
+    pub fn try_split(&self) -> Result<(SnapshotWriter<'_, N>, SnapshotReader<'_, N>), ()> {
+        self.is_split
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Relaxed)
+            .map_err(|_| ())?;
+
+        Ok((
+            SnapshotWriter {
+                snapshot: self,
+                back: 0,
+            },
+            SnapshotReader {
+                snapshot: self,
+                front: 2,
+            },
+        ))
+    }
+}
+
+/// The writing half of a [`PdiSnapshot`].
+pub struct SnapshotWriter<'snapshot, const N: usize> {
+    snapshot: &'snapshot PdiSnapshot<N>,
+    /// The buffer currently owned by the writer.
+    back: u8,
+}
+
+impl<'snapshot, const N: usize> SnapshotWriter<'snapshot, N> {
+    /// Copy `data` into the snapshot and make it available to the reader.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::PdiTooLong`](crate::error::Error::PdiTooLong) if `data` is longer than `N`.
+    pub fn publish(&mut self, data: &[u8]) -> Result<(), crate::error::Error> {
+        if data.len() > N {
+            return Err(crate::error::Error::PdiTooLong {
+                max_length: N,
+                desired_length: data.len(),
+            });
+        }
+
+        // SAFETY: The back buffer is only ever accessed by this writer.
+        let slot = unsafe { &mut *self.snapshot.slots[usize::from(self.back)].get() };
+
+        slot.data[0..data.len()].copy_from_slice(data);
+        slot.len = data.len();
+
+        let previous = self
+            .snapshot
+            .shared
+            .swap(self.back | FRESH, Ordering::AcqRel);
+
+        self.back = previous & INDEX_MASK;
+
+        Ok(())
+    }
+}
+
+/// The reading half of a [`PdiSnapshot`].
+pub struct SnapshotReader<'snapshot, const N: usize> {
+    snapshot: &'snapshot PdiSnapshot<N>,
+    /// The buffer currently owned by the reader.
+    front: u8,
+}
+
+impl<'snapshot, const N: usize> SnapshotReader<'snapshot, N> {
+    /// Get the most recently published data.
+    ///
+    /// The returned slice is empty if nothing has been published yet.
+    pub fn latest(&mut self) -> &[u8] {
+        self.update();
+
+        self.current()
+    }
+
+    /// Get data published since the last call to this method or [`latest`](SnapshotReader::latest),
+    /// or `None` if nothing new has been published.
+    pub fn take_new(&mut self) -> Option<&[u8]> {
+        if self.update() {
+            Some(self.current())
+        } else {
+            None
+        }
+    }
+
+    /// Swap the reader's buffer with the shared buffer if it holds fresh data.
+    ///
+    /// Returns `true` if new data is now available to the reader.
+    fn update(&mut self) -> bool {
+        if self.snapshot.shared.load(Ordering::Acquire) & FRESH == 0 {
+            return false;
+        }
+
+        let previous = self.snapshot.shared.swap(self.front, Ordering::AcqRel);
+
+        self.front = previous & INDEX_MASK;
+
+        true
+    }
+
+    fn current(&self) -> &[u8] {
+        // SAFETY: The front buffer is only ever accessed by this reader.
+        let slot = unsafe { &*self.snapshot.slots[usize::from(self.front)].get() };
+
+        &slot.data[0..slot.len]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_once() {
+        let snapshot = PdiSnapshot::<4>::new();
+
+        assert!(snapshot.try_split().is_ok());
+        assert!(snapshot.try_split().is_err());
+    }
+
+    #[test]
+    fn latest_wins() {
+        let snapshot = PdiSnapshot::<4>::new();
+
+        let (mut writer, mut reader) = snapshot.try_split().unwrap();
+
+        assert!(reader.latest().is_empty());
+        assert_eq!(reader.take_new(), None);
+
+        writer.publish(&[1, 2]).unwrap();
+        writer.publish(&[3, 4, 5]).unwrap();
+
+        assert_eq!(reader.take_new(), Some(&[3u8, 4, 5][..]));
+        assert_eq!(reader.take_new(), None);
+        assert_eq!(reader.latest(), &[3, 4, 5]);
+
+        writer.publish(&[6]).unwrap();
+        writer.publish(&[7]).unwrap();
+        writer.publish(&[8, 9]).unwrap();
+
+        assert_eq!(reader.latest(), &[8, 9]);
+
+        assert_eq!(
+            writer.publish(&[0; 5]),
+            Err(crate::error::Error::PdiTooLong {
+                max_length: 4,
+                desired_length: 5
+            })
+        );
+    }
+
+    #[test]
+    fn no_tearing() {
+        static SNAPSHOT: PdiSnapshot<64> = PdiSnapshot::new();
+
+        let (mut writer, mut reader) = SNAPSHOT.try_split().unwrap();
+
+        let writer = std::thread::spawn(move || {
+            for i in 0..10_000u32 {
+                writer.publish(&[i as u8; 64]).unwrap();
+            }
+        });
+
+        while !writer.is_finished() {
+            let latest = reader.latest();
+
+            if let Some(first) = latest.first() {
+                assert!(latest.iter().all(|b| b == first), "torn read");
+            }
+        }
+
+        writer.join().unwrap();
+
+        assert_eq!(reader.latest(), &[(9_999u32 as u8); 64]);
+    }
+}
@@ -0,0 +1,205 @@
+//! Backing storage for a group's SubDevices and process data.
+
+use core::ops::{Deref, DerefMut};
+
+/// A list with either a fixed capacity of `N`, or with `std`, a capacity chosen at runtime.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum GroupList<T, const N: usize> {
+    Fixed(heapless::Vec<T, N>),
+    #[cfg(feature = "std")]
+    Heap {
+        items: std::vec::Vec<T>,
+        capacity: usize,
+    },
+}
+
+impl<T, const N: usize> Default for GroupList<T, N> {
+    fn default() -> Self {
+        Self::Fixed(heapless::Vec::new())
+    }
+}
+
+impl<T, const N: usize> GroupList<T, N> {
+    /// A heap allocated list that can hold up to `capacity` items.
+    #[cfg(feature = "std")]
+    pub fn heap(capacity: usize) -> Self {
+        Self::Heap {
+            items: std::vec::Vec::new(),
+            capacity,
+        }
+    }
+
+    /// An empty list with the same kind of storage and capacity as this one.
+    pub fn empty_like<U>(&self) -> GroupList<U, N> {
+        match self {
+            Self::Fixed(_) => GroupList::Fixed(heapless::Vec::new()),
+            #[cfg(feature = "std")]
+            Self::Heap { capacity, .. } => GroupList::heap(*capacity),
+        }
+    }
+
+    /// Add an item to the end of the list, returning it back if the list is full.
+    pub fn push(&mut self, item: T) -> Result<(), T> {
+        match self {
+            Self::Fixed(items) => items.push(item),
+            #[cfg(feature = "std")]
+            Self::Heap { items, capacity } => {
+                if items.len() >= *capacity {
+                    return Err(item);
+                }
+
+                items.push(item);
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Remove the item at `index`, replacing it with the last item in the list.
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        match self {
+            Self::Fixed(items) => items.swap_remove(index),
+            #[cfg(feature = "std")]
+            Self::Heap { items, .. } => items.swap_remove(index),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        match self {
+            Self::Fixed(items) => items.clear(),
+            #[cfg(feature = "std")]
+            Self::Heap { items, .. } => items.clear(),
+        }
+    }
+}
+
+impl<T, const N: usize> Deref for GroupList<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Fixed(items) => items,
+            #[cfg(feature = "std")]
+            Self::Heap { items, .. } => items,
+        }
+    }
+}
+
+impl<T, const N: usize> DerefMut for GroupList<T, N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Self::Fixed(items) => items,
+            #[cfg(feature = "std")]
+            Self::Heap { items, .. } => items,
+        }
+    }
+}
+
+impl<T, const N: usize> IntoIterator for GroupList<T, N> {
+    type Item = T;
+    type IntoIter = IntoIter<T, N>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self {
+            Self::Fixed(items) => IntoIter::Fixed(items.into_iter()),
+            #[cfg(feature = "std")]
+            Self::Heap { items, .. } => IntoIter::Heap(items.into_iter()),
+        }
+    }
+}
+
+pub(crate) enum IntoIter<T, const N: usize> {
+    Fixed(<heapless::Vec<T, N> as IntoIterator>::IntoIter),
+    #[cfg(feature = "std")]
+    Heap(std::vec::IntoIter<T>),
+}
+
+impl<T, const N: usize> Iterator for IntoIter<T, N> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Fixed(items) => items.next(),
+            #[cfg(feature = "std")]
+            Self::Heap(items) => items.next(),
+        }
+    }
+}
+
+/// Group process data image, either an array of `N` bytes or with `std`, a heap allocated buffer
+/// with a length chosen at runtime.
+#[derive(Debug)]
+pub(crate) enum PdiStorage<const N: usize> {
+    Fixed([u8; N]),
+    #[cfg(feature = "std")]
+    Heap(std::boxed::Box<[u8]>),
+}
+
+impl<const N: usize> Default for PdiStorage<N> {
+    fn default() -> Self {
+        Self::Fixed([0u8; N])
+    }
+}
+
+impl<const N: usize> PdiStorage<N> {
+    /// A zeroed, heap allocated buffer of `len` bytes.
+    #[cfg(feature = "std")]
+    pub fn heap(len: usize) -> Self {
+        Self::Heap(std::vec![0u8; len].into_boxed_slice())
+    }
+}
+
+impl<const N: usize> Deref for PdiStorage<N> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Fixed(buf) => buf,
+            #[cfg(feature = "std")]
+            Self::Heap(buf) => buf,
+        }
+    }
+}
+
+impl<const N: usize> DerefMut for PdiStorage<N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Self::Fixed(buf) => buf,
+            #[cfg(feature = "std")]
+            Self::Heap(buf) => buf,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn heap_capacity() {
+        let mut list = GroupList::<u8, 0>::heap(2);
+
+        assert_eq!(list.push(1), Ok(()));
+        assert_eq!(list.push(2), Ok(()));
+        assert_eq!(list.push(3), Err(3));
+        assert_eq!(&*list, &[1, 2]);
+
+        let other = list.empty_like::<u16>();
+
+        assert!(matches!(other, GroupList::Heap { capacity: 2, .. }));
+        assert_eq!(list.into_iter().collect::<std::vec::Vec<_>>(), [1, 2]);
+    }
+
+    #[test]
+    fn fixed_capacity() {
+        let mut list = GroupList::<u8, 1>::default();
+
+        assert_eq!(list.push(1), Ok(()));
+        assert_eq!(list.push(2), Err(2));
+
+        list.clear();
+
+        assert!(list.is_empty());
+    }
+}
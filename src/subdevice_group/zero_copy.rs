@@ -0,0 +1,281 @@
+//! Exchange a group's process data by reading and writing frame payloads in place.
+
+use super::health::WkcStats;
+use crate::{
+    error::{Error, Item, PduError},
+    fmt,
+    pdu_loop::{CreatedFrame, PduResponseHandle, ReceivedFrame},
+    Command, MainDevice,
+};
+use core::ops::Range;
+
+/// A PDU in a [`PdiTxFrame`] holding a group's inputs or outputs.
+#[derive(Debug, Clone)]
+struct ZeroCopyPdu {
+    handle: PduResponseHandle,
+    /// The position of the PDU's payload in the frame's PDU buffer.
+    payload: Range<usize>,
+    /// Set if this PDU is an `LWR`.
+    lwr: bool,
+}
+
+/// The positions of a group's process data in a frame.
+#[derive(Debug, Clone)]
+struct ZeroCopyLayout<'group> {
+    /// The `LRW` or `LRD` PDU holding inputs. When using `LRW`, outputs are also in this PDU.
+    inputs: ZeroCopyPdu,
+    /// The PDU holding outputs, if they are written with a separate `LWR`.
+    outputs: Option<ZeroCopyPdu>,
+    /// The position of the group's inputs in the frame's PDU buffer.
+    input_data: Range<usize>,
+    /// The position of the group's outputs in the frame's PDU buffer.
+    output_data: Range<usize>,
+    /// The number of input bytes in the group PDI. SubDevice outputs are stored after this.
+    read_pdi_len: usize,
+    /// The position of each SubDevice's inputs and outputs in the group PDI.
+    io_ranges: &'group [(Range<usize>, Range<usize>)],
+    expected_wkc: u16,
+    /// The group's working counter statistics, updated when the response is received.
+    wkc_stats: &'group WkcStats,
+}
+
+impl<'group> ZeroCopyLayout<'group> {
+    /// Get the position of a SubDevice's inputs or outputs in the frame's PDU buffer.
+    fn subdevice_range(&self, index: usize, outputs: bool) -> Result<Range<usize>, Error> {
+        let (inputs_range, outputs_range) = self.io_ranges.get(index).ok_or(Error::NotFound {
+            item: Item::SubDevice,
+            index: Some(index),
+        })?;
+
+        let (range, data_start) = if outputs {
+            (
+                (outputs_range.start - self.read_pdi_len)..(outputs_range.end - self.read_pdi_len),
+                self.output_data.start,
+            )
+        } else {
+            (inputs_range.clone(), self.input_data.start)
+        };
+
+        Ok((data_start + range.start)..(data_start + range.end))
+    }
+}
+
+/// A frame holding a group's outputs, ready to be written in place and sent.
+///
+/// Created by [`SubDeviceGroup::pdi_tx_frame`](crate::SubDeviceGroup::pdi_tx_frame).
+#[derive(Debug)]
+pub struct PdiTxFrame<'sto, 'group> {
+    frame: CreatedFrame<'sto>,
+    maindevice: &'sto MainDevice<'sto>,
+    layout: ZeroCopyLayout<'group>,
+}
+
+impl<'sto, 'group> PdiTxFrame<'sto, 'group> {
+    /// Allocate a frame and push the PDUs used to exchange a group's PDI into it.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        maindevice: &'sto MainDevice<'sto>,
+        start_address: u32,
+        read_pdi_len: usize,
+        pdi_len: usize,
+        overlapping: bool,
+        lrd_lwr: bool,
+        io_ranges: &'group [(Range<usize>, Range<usize>)],
+        expected_wkc: u16,
+        wkc_stats: &'group WkcStats,
+    ) -> Result<Self, Error> {
+        let mut frame = maindevice.pdu_loop.alloc_frame()?;
+
+        let outputs_len = pdi_len - read_pdi_len;
+
+        let layout = if !lrd_lwr {
+            // Overlapping outputs share the same logical addresses as the inputs
+            let len = if overlapping { read_pdi_len } else { pdi_len };
+
+            let (handle, payload) =
+                frame.push_pdu_zeroed(Command::lrw(start_address).into(), len)?;
+
+            let output_start = if overlapping {
+                payload.start
+            } else {
+                payload.start + read_pdi_len
+            };
+
+            ZeroCopyLayout {
+                input_data: payload.start..(payload.start + read_pdi_len),
+                output_data: output_start..(output_start + outputs_len),
+                inputs: ZeroCopyPdu {
+                    handle,
+                    payload,
+                    lwr: false,
+                },
+                outputs: None,
+                read_pdi_len,
+                io_ranges,
+                expected_wkc,
+                wkc_stats,
+            }
+        } else {
+            let output_address = if overlapping {
+                start_address
+            } else {
+                start_address + read_pdi_len as u32
+            };
+
+            let (input_handle, input_payload) =
+                frame.push_pdu_zeroed(Command::lrd(start_address).into(), read_pdi_len)?;
+            let (output_handle, output_payload) =
+                frame.push_pdu_zeroed(Command::lwr(output_address).into(), outputs_len)?;
+
+            ZeroCopyLayout {
+                input_data: input_payload.clone(),
+                output_data: output_payload.clone(),
+                inputs: ZeroCopyPdu {
+                    handle: input_handle,
+                    payload: input_payload,
+                    lwr: false,
+                },
+                outputs: Some(ZeroCopyPdu {
+                    handle: output_handle,
+                    payload: output_payload,
+                    lwr: true,
+                }),
+                read_pdi_len,
+                io_ranges,
+                expected_wkc,
+                wkc_stats,
+            }
+        };
+
+        Ok(Self {
+            frame,
+            maindevice,
+            layout,
+        })
+    }
+
+    /// All of the group's outputs, in the same layout as the outputs part of the group PDI.
+    ///
+    /// Outputs are zeroed when the frame is allocated.
+    pub fn outputs_mut(&mut self) -> &mut [u8] {
+        fmt::unwrap_opt!(self.frame.pdu_payload_mut(self.layout.output_data.clone()))
+    }
+
+    /// The outputs of the SubDevice at the given index in the group.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotFound`] if the index is out of bounds.
+    pub fn subdevice_outputs_mut(&mut self, index: usize) -> Result<&mut [u8], Error> {
+        let range = self.layout.subdevice_range(index, true)?;
+
+        self.frame.pdu_payload_mut(range).ok_or(Error::Internal)
+    }
+
+    /// Send the frame and wait for the response.
+    ///
+    /// The working counter of the response is recorded in the group's statistics, as with
+    /// [`tx_rx`](crate::SubDeviceGroup::tx_rx).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the frame could not be sent over the network, or the response times
+    /// out.
+    pub async fn send(self) -> Result<PdiRxFrame<'sto, 'group>, Error> {
+        let maindevice = self.maindevice;
+
+        let frame = self.frame.mark_sendable(
+            &maindevice.pdu_loop,
+            maindevice.timeouts.pdu,
+            maindevice.config.retry_behaviour.retry_count(),
+        );
+
+        maindevice.pdu_loop.wake_sender();
+
+        let frame = frame.await?;
+
+        let mut wkc = 0u16;
+
+        for pdu in core::iter::once(&self.layout.inputs).chain(self.layout.outputs.as_ref()) {
+            let received = frame.pdu(pdu.handle)?;
+
+            if received.len() != pdu.payload.len() {
+                fmt::error!(
+                    "Data length {} does not match value length {}",
+                    received.len(),
+                    pdu.payload.len()
+                );
+
+                return Err(Error::Pdu(PduError::Decode));
+            }
+
+            // `LRW` increments the working counter by 2 for every SubDevice written to, but `LWR`
+            // only increments it by 1, so scale it to match.
+            wkc = wkc.wrapping_add(if pdu.lwr {
+                received.working_counter.wrapping_mul(2)
+            } else {
+                received.working_counter
+            });
+        }
+
+        self.layout.wkc_stats.record(wkc, self.layout.expected_wkc);
+
+        Ok(PdiRxFrame {
+            frame,
+            layout: self.layout,
+            wkc,
+        })
+    }
+}
+
+/// A received frame holding a group's inputs.
+///
+/// Created by [`PdiTxFrame::send`]. The frame is released back to the PDU storage when this is
+/// dropped, so it should not be held for longer than necessary.
+#[derive(Debug)]
+pub struct PdiRxFrame<'sto, 'group> {
+    frame: ReceivedFrame<'sto>,
+    layout: ZeroCopyLayout<'group>,
+    wkc: u16,
+}
+
+impl<'sto, 'group> PdiRxFrame<'sto, 'group> {
+    /// All of the group's inputs, in the same layout as the inputs part of the group PDI.
+    pub fn inputs(&self) -> &[u8] {
+        fmt::unwrap_opt!(self.frame.pdu_buf().get(self.layout.input_data.clone()))
+    }
+
+    /// The inputs of the SubDevice at the given index in the group.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotFound`] if the index is out of bounds.
+    pub fn subdevice_inputs(&self, index: usize) -> Result<&[u8], Error> {
+        let range = self.layout.subdevice_range(index, false)?;
+
+        self.frame.pdu_buf().get(range).ok_or(Error::Internal)
+    }
+
+    /// The working counter of the exchange, scaled to match `LRW`.
+    pub fn wkc(&self) -> u16 {
+        self.wkc
+    }
+
+    /// Check the working counter against the value expected from the group.
+    ///
+    /// See [`SubDeviceGroup::expected_wkc`](crate::SubDeviceGroup::expected_wkc).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::WorkingCounter`] if the working counter does not match.
+    pub fn check_wkc(&self) -> Result<u16, Error> {
+        if self.wkc != self.layout.expected_wkc {
+            return Err(Error::WorkingCounter {
+                expected: self.layout.expected_wkc,
+                received: self.wkc,
+            });
+        }
+
+        Ok(self.wkc)
+    }
+}
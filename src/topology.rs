@@ -0,0 +1,435 @@
+//! Verification of discovered SubDevices against an expected network layout.
+
+use crate::{error::Error, fmt, SubDeviceIdentity};
+
+/// A SubDevice that is expected to be present at a given position in the EtherCAT network.
+///
+/// Used with [`MainDevice::init_with_topology`](crate::MainDevice::init_with_topology).
+///
+/// # Examples
+///
+/// ```rust
+/// use ethercrab::ExpectedSubDevice;
+///
+/// let expected = [
+///     // EK1100 coupler, any revision
+///     ExpectedSubDevice::new(0, 0x2, 0x044c2c52),
+///     // EL2004 at position 1 with a specific revision and alias address
+///     ExpectedSubDevice::new(1, 0x2, 0x07d43052)
+///         .with_revision(0x00100000)
+///         .with_alias(0x0010),
+/// ];
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ExpectedSubDevice {
+    /// Position of the SubDevice in the network, starting at `0` for the SubDevice closest to the
+    /// MainDevice.
+    pub position: u16,
+    /// Vendor ID.
+    pub vendor_id: u32,
+    /// Product ID.
+    pub product_id: u32,
+    /// Product revision. If `None`, any revision is accepted.
+    pub revision: Option<u32>,
+    /// Configured station alias. If `None`, any alias is accepted.
+    pub alias: Option<u16>,
+}
+
+impl ExpectedSubDevice {
+    /// Create a new expected SubDevice at the given position, accepting any revision or alias.
+    pub const fn new(position: u16, vendor_id: u32, product_id: u32) -> Self {
+        Self {
+            position,
+            vendor_id,
+            product_id,
+            revision: None,
+            alias: None,
+        }
+    }
+
+    /// Require a specific product revision.
+    pub const fn with_revision(self, revision: u32) -> Self {
+        Self {
+            revision: Some(revision),
+            ..self
+        }
+    }
+
+    /// Require a specific configured station alias.
+    pub const fn with_alias(self, alias: u16) -> Self {
+        Self {
+            alias: Some(alias),
+            ..self
+        }
+    }
+
+    pub(crate) fn is_product(&self, identity: &SubDeviceIdentity) -> bool {
+        self.vendor_id == identity.vendor_id && self.product_id == identity.product_id
+    }
+}
+
+/// A difference between the expected and discovered network topology.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum TopologyMismatch {
+    /// An expected SubDevice was not found on the network.
+    Missing {
+        /// The expected SubDevice.
+        expected: ExpectedSubDevice,
+    },
+    /// A SubDevice was found that is not in the expected list.
+    Extra {
+        /// Network position of the unexpected SubDevice.
+        position: u16,
+        /// The identity of the unexpected SubDevice.
+        found: SubDeviceIdentity,
+    },
+    /// A different product was found at the expected position.
+    WrongProduct {
+        /// The expected SubDevice.
+        expected: ExpectedSubDevice,
+        /// The identity of the SubDevice found at the expected position.
+        found: SubDeviceIdentity,
+    },
+    /// The expected product was found at the expected position but with a different revision.
+    WrongRevision {
+        /// The expected SubDevice.
+        expected: ExpectedSubDevice,
+        /// The revision of the discovered SubDevice.
+        found: u32,
+    },
+    /// The expected product was found at the expected position but with a different alias
+    /// address.
+    WrongAlias {
+        /// The expected SubDevice.
+        expected: ExpectedSubDevice,
+        /// The alias address of the discovered SubDevice.
+        found: u16,
+    },
+    /// The expected SubDevice was found at a different position in the network.
+    WrongPosition {
+        /// The expected SubDevice.
+        expected: ExpectedSubDevice,
+        /// The position the SubDevice was found at.
+        found_position: u16,
+    },
+}
+
+impl core::fmt::Display for TopologyMismatch {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Missing { expected } => write!(
+                f,
+                "position {}: expected vendor {:#010x} product {:#010x}, found nothing",
+                expected.position, expected.vendor_id, expected.product_id
+            ),
+            Self::Extra { position, found } => {
+                write!(f, "position {}: unexpected SubDevice ({})", position, found)
+            }
+            Self::WrongProduct { expected, found } => write!(
+                f,
+                "position {}: expected vendor {:#010x} product {:#010x}, found vendor {:#010x} product {:#010x}",
+                expected.position,
+                expected.vendor_id,
+                expected.product_id,
+                found.vendor_id,
+                found.product_id
+            ),
+            Self::WrongRevision { expected, found } => write!(
+                f,
+                "position {}: expected revision {:#010x}, found {:#010x}",
+                expected.position,
+                expected.revision.unwrap_or_default(),
+                found
+            ),
+            Self::WrongAlias { expected, found } => write!(
+                f,
+                "position {}: expected alias {:#06x}, found {:#06x}",
+                expected.position,
+                expected.alias.unwrap_or_default(),
+                found
+            ),
+            Self::WrongPosition {
+                expected,
+                found_position,
+            } => write!(
+                f,
+                "position {}: vendor {:#010x} product {:#010x} found at position {}",
+                expected.position, expected.vendor_id, expected.product_id, found_position
+            ),
+        }
+    }
+}
+
+/// A SubDevice as discovered on the network, used for topology comparison.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct DiscoveredSubDevice {
+    pub position: u16,
+    pub identity: SubDeviceIdentity,
+    pub alias: u16,
+}
+
+/// Compare discovered SubDevices against the expected list, calling `report` for every mismatch.
+fn compare(
+    expected: &[ExpectedSubDevice],
+    found: &[DiscoveredSubDevice],
+    mut report: impl FnMut(TopologyMismatch),
+) {
+    for exp in expected {
+        let Some(at_position) = found.iter().find(|f| f.position == exp.position) else {
+            report(TopologyMismatch::Missing { expected: *exp });
+
+            continue;
+        };
+
+        if exp.is_product(&at_position.identity) {
+            if let Some(revision) = exp.revision {
+                if revision != at_position.identity.revision {
+                    report(TopologyMismatch::WrongRevision {
+                        expected: *exp,
+                        found: at_position.identity.revision,
+                    });
+                }
+            }
+
+            if let Some(alias) = exp.alias {
+                if alias != at_position.alias {
+                    report(TopologyMismatch::WrongAlias {
+                        expected: *exp,
+                        found: at_position.alias,
+                    });
+                }
+            }
+
+            continue;
+        }
+
+        // Look for the expected product somewhere else in the network, in a position that
+        // doesn't hold the correct product already.
+        let elsewhere = found.iter().find(|f| {
+            exp.is_product(&f.identity)
+                && exp.alias.map_or(true, |alias| alias == f.alias)
+                && !expected
+                    .iter()
+                    .any(|e| e.position == f.position && e.is_product(&f.identity))
+        });
+
+        match elsewhere {
+            Some(elsewhere) => report(TopologyMismatch::WrongPosition {
+                expected: *exp,
+                found_position: elsewhere.position,
+            }),
+            None => report(TopologyMismatch::WrongProduct {
+                expected: *exp,
+                found: at_position.identity,
+            }),
+        }
+    }
+
+    for f in found {
+        if !expected.iter().any(|e| e.position == f.position) {
+            report(TopologyMismatch::Extra {
+                position: f.position,
+                found: f.identity,
+            });
+        }
+    }
+}
+
+/// Check discovered SubDevices against the expected topology.
+///
+/// Every mismatch is logged and passed to `on_mismatch`, and the first one is returned as an
+/// error.
+pub(crate) fn verify(
+    expected: &[ExpectedSubDevice],
+    found: &[DiscoveredSubDevice],
+    mut on_mismatch: impl FnMut(TopologyMismatch),
+) -> Result<(), Error> {
+    let mut first = None;
+    let mut count = 0;
+
+    compare(expected, found, |mismatch| {
+        fmt::error!("Topology mismatch: {}", mismatch);
+
+        on_mismatch(mismatch);
+
+        first.get_or_insert(mismatch);
+        count += 1;
+    });
+
+    match first {
+        Some(first) => Err(Error::TopologyMismatch { first, count }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EK1100: (u32, u32) = (0x2, 0x044c2c52);
+    const EL2004: (u32, u32) = (0x2, 0x07d43052);
+    const EL1004: (u32, u32) = (0x2, 0x03ec3052);
+
+    fn found(
+        position: u16,
+        (vendor_id, product_id): (u32, u32),
+        revision: u32,
+    ) -> DiscoveredSubDevice {
+        DiscoveredSubDevice {
+            position,
+            identity: SubDeviceIdentity {
+                vendor_id,
+                product_id,
+                revision,
+                serial: 0,
+            },
+            alias: 0,
+        }
+    }
+
+    fn expected(position: u16, (vendor_id, product_id): (u32, u32)) -> ExpectedSubDevice {
+        ExpectedSubDevice::new(position, vendor_id, product_id)
+    }
+
+    fn mismatches(
+        expected: &[ExpectedSubDevice],
+        found: &[DiscoveredSubDevice],
+    ) -> Vec<TopologyMismatch> {
+        let mut mismatches = Vec::new();
+
+        compare(expected, found, |m| mismatches.push(m));
+
+        mismatches
+    }
+
+    #[test]
+    fn matching() {
+        let report = mismatches(
+            &[expected(0, EK1100).with_revision(1), expected(1, EL2004)],
+            &[found(0, EK1100, 1), found(1, EL2004, 5)],
+        );
+
+        assert_eq!(report, []);
+    }
+
+    #[test]
+    fn missing_and_extra() {
+        let report = mismatches(
+            &[expected(0, EK1100), expected(1, EL2004)],
+            &[found(0, EK1100, 1)],
+        );
+
+        assert_eq!(
+            report,
+            [TopologyMismatch::Missing {
+                expected: expected(1, EL2004)
+            }]
+        );
+
+        let report = mismatches(
+            &[expected(0, EK1100)],
+            &[found(0, EK1100, 1), found(1, EL2004, 1)],
+        );
+
+        assert_eq!(
+            report,
+            [TopologyMismatch::Extra {
+                position: 1,
+                found: found(1, EL2004, 1).identity
+            }]
+        );
+    }
+
+    #[test]
+    fn swapped_terminals() {
+        let report = mismatches(
+            &[
+                expected(0, EK1100),
+                expected(1, EL2004),
+                expected(2, EL1004),
+            ],
+            &[
+                found(0, EK1100, 1),
+                found(1, EL1004, 1),
+                found(2, EL2004, 1),
+            ],
+        );
+
+        assert_eq!(
+            report,
+            [
+                TopologyMismatch::WrongPosition {
+                    expected: expected(1, EL2004),
+                    found_position: 2
+                },
+                TopologyMismatch::WrongPosition {
+                    expected: expected(2, EL1004),
+                    found_position: 1
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn wrong_product_revision_alias() {
+        let report = mismatches(
+            &[
+                expected(0, EK1100).with_revision(2).with_alias(0x10),
+                expected(1, EL2004),
+            ],
+            &[found(0, EK1100, 1), found(1, EL1004, 1)],
+        );
+
+        assert_eq!(
+            report,
+            [
+                TopologyMismatch::WrongRevision {
+                    expected: expected(0, EK1100).with_revision(2).with_alias(0x10),
+                    found: 1
+                },
+                TopologyMismatch::WrongAlias {
+                    expected: expected(0, EK1100).with_revision(2).with_alias(0x10),
+                    found: 0
+                },
+                TopologyMismatch::WrongProduct {
+                    expected: expected(1, EL2004),
+                    found: found(1, EL1004, 1).identity
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn first_mismatch_error() {
+        let mut reported = Vec::new();
+
+        assert_eq!(
+            verify(&[expected(0, EK1100), expected(1, EL2004)], &[], |m| {
+                reported.push(m)
+            }),
+            Err(Error::TopologyMismatch {
+                first: TopologyMismatch::Missing {
+                    expected: expected(0, EK1100)
+                },
+                count: 2
+            })
+        );
+
+        // Every mismatch is reported, not just the first
+        assert_eq!(
+            reported,
+            [
+                TopologyMismatch::Missing {
+                    expected: expected(0, EK1100)
+                },
+                TopologyMismatch::Missing {
+                    expected: expected(1, EL2004)
+                }
+            ]
+        );
+    }
+}
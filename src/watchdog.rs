@@ -0,0 +1,192 @@
+//! Sync manager and PDI watchdogs.
+
+use crate::error::Error;
+use core::time::Duration;
+use ethercrab_wire::{EtherCrabWireRead, EtherCrabWireSized, WireError};
+
+/// The length of one watchdog divider increment.
+const DIVIDER_STEP_NS: u64 = 40;
+
+/// Sync manager and PDI watchdog timeouts.
+///
+/// The sync manager watchdog expires if the SubDevice's outputs are not written for longer than
+/// its timeout, e.g. because the cyclic application has stalled. The SubDevice then drops its
+/// outputs to a safe state and leaves OP. The PDI watchdog expires if the SubDevice's
+/// microcontroller stops accessing the ESC.
+///
+/// A timeout of `None` disables the watchdog. Both timeouts share the same time base, chosen to
+/// represent the longest timeout with the highest resolution possible.
+///
+/// The default timeouts are 100 ms for each watchdog, the same as the ESC power-on default.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct WatchdogConfig {
+    /// Sync manager (process data) watchdog timeout.
+    pub sync_manager: Option<Duration>,
+    /// PDI watchdog timeout.
+    pub pdi: Option<Duration>,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            sync_manager: Some(Duration::from_millis(100)),
+            pdi: Some(Duration::from_millis(100)),
+        }
+    }
+}
+
+/// Raw watchdog register values.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct WatchdogRegisters {
+    /// Watchdog divider, `0x0400`.
+    pub divider: u16,
+    /// PDI watchdog time, `0x0410`.
+    pub pdi: u16,
+    /// Sync manager watchdog time, `0x0420`.
+    pub sync_manager: u16,
+}
+
+impl WatchdogConfig {
+    /// Convert the timeouts into register values.
+    ///
+    /// Returns [`Error::IntegerTypeConversion`] if a timeout is longer than the longest
+    /// supported watchdog time of roughly 171 seconds.
+    pub(crate) fn registers(&self) -> Result<WatchdogRegisters, Error> {
+        let nanos = |timeout: Option<Duration>| {
+            timeout
+                .map(|timeout| u64::try_from(timeout.as_nanos()))
+                .transpose()
+                .map_err(|_| Error::IntegerTypeConversion)
+        };
+
+        let sync_manager = nanos(self.sync_manager)?;
+        let pdi = nanos(self.pdi)?;
+
+        let longest = sync_manager.max(pdi).unwrap_or(0);
+
+        // Smallest time base that can count up to the longest timeout, with a minimum of 2 steps
+        let steps = longest
+            .div_ceil(u64::from(u16::MAX))
+            .div_ceil(DIVIDER_STEP_NS)
+            .max(2);
+
+        let divider = u16::try_from(steps - 2).map_err(|_| Error::IntegerTypeConversion)?;
+
+        let base = steps * DIVIDER_STEP_NS;
+
+        let count = |nanos: Option<u64>| {
+            nanos.map_or(Ok(0), |nanos| {
+                // Round to the nearest count. A count of 0 would disable the watchdog.
+                u16::try_from(((nanos + base / 2) / base).max(1))
+                    .map_err(|_| Error::IntegerTypeConversion)
+            })
+        };
+
+        Ok(WatchdogRegisters {
+            divider,
+            pdi: count(pdi)?,
+            sync_manager: count(sync_manager)?,
+        })
+    }
+}
+
+/// The watchdog status of a SubDevice, read by
+/// [`SubDeviceRef::watchdog_status`](crate::SubDeviceRef::watchdog_status).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct WatchdogStatus {
+    /// `false` if the sync manager watchdog has expired, i.e. outputs have not been written within
+    /// the watchdog timeout. `true` if the watchdog is running or disabled.
+    pub sync_manager_ok: bool,
+    /// The number of times the sync manager watchdog has expired, saturating at 255.
+    pub sync_manager_expirations: u8,
+    /// The number of times the PDI watchdog has expired, saturating at 255.
+    pub pdi_expirations: u8,
+}
+
+impl EtherCrabWireSized for WatchdogStatus {
+    // 0x0440 to 0x0443 inclusive
+    const PACKED_LEN: usize = 4;
+
+    type Buffer = [u8; Self::PACKED_LEN];
+
+    fn buffer() -> Self::Buffer {
+        [0u8; Self::PACKED_LEN]
+    }
+}
+
+impl EtherCrabWireRead for WatchdogStatus {
+    fn unpack_from_slice(buf: &[u8]) -> Result<Self, WireError> {
+        let Some(&[status, _reserved, sync_manager_expirations, pdi_expirations]) =
+            buf.get(0..Self::PACKED_LEN)
+        else {
+            return Err(WireError::ReadBufferTooShort);
+        };
+
+        Ok(Self {
+            sync_manager_ok: status & 0x01 != 0,
+            sync_manager_expirations,
+            pdi_expirations,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_registers() {
+        let registers = WatchdogConfig::default().registers().unwrap();
+
+        let base = (u64::from(registers.divider) + 2) * DIVIDER_STEP_NS;
+
+        // Highest resolution base that fits 100 ms, rather than the ESC's default 100 us
+        assert_eq!(registers.divider, 37);
+        assert_eq!(registers.pdi, registers.sync_manager);
+        assert!(
+            (u64::from(registers.sync_manager) * base).abs_diff(100_000_000) <= base / 2,
+            "{:?}",
+            registers
+        );
+    }
+
+    #[test]
+    fn short_and_disabled() {
+        let registers = WatchdogConfig {
+            sync_manager: Some(Duration::from_micros(10)),
+            pdi: None,
+        }
+        .registers()
+        .unwrap();
+
+        assert_eq!(
+            registers,
+            WatchdogRegisters {
+                divider: 0,
+                pdi: 0,
+                sync_manager: 125
+            }
+        );
+    }
+
+    #[test]
+    fn too_long() {
+        assert_eq!(
+            WatchdogConfig {
+                sync_manager: Some(Duration::from_secs(200)),
+                pdi: None,
+            }
+            .registers(),
+            Err(Error::IntegerTypeConversion)
+        );
+
+        assert!(WatchdogConfig {
+            sync_manager: Some(Duration::from_secs(171)),
+            pdi: Some(Duration::from_micros(1)),
+        }
+        .registers()
+        .is_ok());
+    }
+}